@@ -0,0 +1,31 @@
+//! Copy a Postgres table into a namespace.
+//!
+//! Run with `DATABASE_URL=postgres://... cargo run --example postgres_ingest --features postgres`.
+
+use rs_puff::postgres::{ColumnMapping, copy_query};
+use rs_puff::{BulkWriter, Client, DistanceMetric};
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let client = Client::from_env()?;
+    let ns = client.namespace("rust-example-postgres");
+
+    let (pg, connection) = tokio_postgres::connect(&std::env::var("DATABASE_URL")?, tokio_postgres::NoTls).await?;
+    tokio::spawn(connection);
+
+    // Expects e.g. `CREATE TABLE documents (doc_id bigint, body text, embedding vector(4), updated_at timestamptz)`
+    let mapping = ColumnMapping::new()
+        .id_column("doc_id")
+        .rename("body", "text")
+        .vector("embedding");
+
+    let mut writer = BulkWriter::new(&ns)
+        .batch_size(500)
+        .distance_metric(DistanceMetric::CosineDistance);
+
+    let rows = copy_query(&pg, "SELECT doc_id, body, embedding, updated_at FROM documents", &mapping, &mut writer).await?;
+    let report = writer.finish().await?;
+
+    println!("Read {} rows, wrote {} batches", rows, report.batches);
+    Ok(())
+}
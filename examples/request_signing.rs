@@ -0,0 +1,66 @@
+//! Sign every request for a gateway that rejects unsigned or replayed traffic.
+//!
+//! Each attempt gets a timestamp, a nonce, and an HMAC-SHA256 over
+//! `timestamp \n nonce \n method \n path \n body`. The gateway recomputes the
+//! MAC with the shared secret, rejects stale timestamps, and remembers nonces
+//! inside its window. Retries are re-signed, so they carry a fresh timestamp.
+//!
+//! Run with `SIGNING_SECRET=... cargo run --example request_signing`.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use hmac::{Hmac, KeyInit, Mac};
+use reqwest::header::HeaderValue;
+use rs_puff::{Client, Middleware, QueryParams, RankBy, RequestParts};
+use sha2::Sha256;
+
+struct RequestSigner {
+    secret: Vec<u8>,
+    counter: AtomicU64,
+}
+
+impl RequestSigner {
+    fn new(secret: impl Into<Vec<u8>>) -> Self {
+        Self { secret: secret.into(), counter: AtomicU64::new(0) }
+    }
+}
+
+impl Middleware for RequestSigner {
+    fn on_request(&self, request: &mut RequestParts) -> rs_puff::Result<()> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        let timestamp = now.as_secs().to_string();
+        let nonce = format!("{:x}-{:x}", now.as_nanos(), self.counter.fetch_add(1, Ordering::Relaxed));
+
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.secret).map_err(|e| rs_puff::Error::External(Box::new(e)))?;
+        for part in [timestamp.as_bytes(), nonce.as_bytes(), request.method.as_str().as_bytes(), request.path.as_bytes()] {
+            mac.update(part);
+            mac.update(b"\n");
+        }
+        mac.update(request.body.as_deref().unwrap_or_default());
+        let signature: String = mac.finalize().into_bytes().iter().map(|b| format!("{:02x}", b)).collect();
+
+        for (name, value) in [("x-signature-timestamp", timestamp), ("x-signature-nonce", nonce), ("x-signature", signature)] {
+            let value = HeaderValue::from_str(&value).map_err(|e| rs_puff::Error::External(Box::new(e)))?;
+            request.headers.insert(name, value);
+        }
+        Ok(())
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let client = Client::from_env()?.with_middleware(RequestSigner::new(std::env::var("SIGNING_SECRET")?));
+    let ns = client.namespace("rust-example");
+
+    let results = ns
+        .query(QueryParams {
+            rank_by: Some(RankBy::vector("vector", vec![0.1, 0.2, 0.3, 0.4])),
+            top_k: Some(5),
+            ..Default::default()
+        })
+        .await?;
+    println!("{} rows", results.rows.len());
+
+    Ok(())
+}
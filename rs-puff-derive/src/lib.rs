@@ -0,0 +1,170 @@
+//! Derive macros for [rs-puff](https://docs.rs/rs-puff). Use them through the
+//! `derive` feature of `rs-puff` rather than depending on this crate directly.
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{Data, DeriveInput, Fields, LitStr, parse_macro_input};
+
+/// Implements `rs_puff::Document`, listing the attribute names the struct
+/// deserializes from.
+///
+/// Honors `#[serde(rename = "...")]`, `#[serde(rename_all = "...")]` and
+/// `#[serde(skip)]`. `#[serde(flatten)]` is rejected because the attributes
+/// of a flattened field aren't known at compile time.
+#[proc_macro_derive(Document)]
+pub fn derive_document(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new_spanned(input, "Document can only be derived for structs"));
+    };
+    let Fields::Named(fields) = &data.fields else {
+        return Err(syn::Error::new_spanned(input, "Document requires named fields"));
+    };
+
+    let rename_all = container_rename_all(input)?;
+    let mut attributes = Vec::new();
+    for field in &fields.named {
+        let serde = field_serde(field)?;
+        if serde.skip {
+            continue;
+        }
+        if serde.flatten {
+            return Err(syn::Error::new_spanned(
+                field,
+                "Document can't list the attributes of a #[serde(flatten)] field; implement the trait manually",
+            ));
+        }
+        let ident = field.ident.as_ref().expect("named field");
+        let name = match serde.rename {
+            Some(name) => name,
+            None => {
+                let name = ident.to_string();
+                let name = name.strip_prefix("r#").unwrap_or(&name).to_string();
+                match &rename_all {
+                    Some(rule) => apply_rename_all(rule, &name).map_err(|msg| syn::Error::new_spanned(input, msg))?,
+                    None => name,
+                }
+            }
+        };
+        attributes.push(name);
+    }
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::rs_puff::Document for #ident #ty_generics #where_clause {
+            const ATTRIBUTES: &'static [&'static str] = &[#(#attributes),*];
+        }
+    })
+}
+
+#[derive(Default)]
+struct FieldSerde {
+    rename: Option<String>,
+    skip: bool,
+    flatten: bool,
+}
+
+fn field_serde(field: &syn::Field) -> syn::Result<FieldSerde> {
+    let mut out = FieldSerde::default();
+    for attr in field.attrs.iter().filter(|a| a.path().is_ident("serde")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                if meta.input.peek(syn::Token![=]) {
+                    out.rename = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else {
+                    // rename(serialize = "...", deserialize = "...")
+                    meta.parse_nested_meta(|inner| {
+                        let value = inner.value()?.parse::<LitStr>()?.value();
+                        if inner.path.is_ident("deserialize") {
+                            out.rename = Some(value);
+                        }
+                        Ok(())
+                    })?;
+                }
+            } else if meta.path.is_ident("skip") || meta.path.is_ident("skip_deserializing") {
+                out.skip = true;
+            } else if meta.path.is_ident("flatten") {
+                out.flatten = true;
+            } else if meta.input.peek(syn::Token![=]) {
+                meta.value()?.parse::<syn::Expr>()?;
+            } else if meta.input.peek(syn::token::Paren) {
+                meta.parse_nested_meta(|inner| {
+                    if inner.input.peek(syn::Token![=]) {
+                        inner.value()?.parse::<syn::Expr>()?;
+                    }
+                    Ok(())
+                })?;
+            }
+            Ok(())
+        })?;
+    }
+    Ok(out)
+}
+
+fn container_rename_all(input: &DeriveInput) -> syn::Result<Option<String>> {
+    let mut rule = None;
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("serde")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename_all") {
+                if meta.input.peek(syn::Token![=]) {
+                    rule = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else {
+                    meta.parse_nested_meta(|inner| {
+                        let value = inner.value()?.parse::<LitStr>()?.value();
+                        if inner.path.is_ident("deserialize") {
+                            rule = Some(value);
+                        }
+                        Ok(())
+                    })?;
+                }
+            } else if meta.input.peek(syn::Token![=]) {
+                meta.value()?.parse::<syn::Expr>()?;
+            } else if meta.input.peek(syn::token::Paren) {
+                meta.parse_nested_meta(|inner| {
+                    if inner.input.peek(syn::Token![=]) {
+                        inner.value()?.parse::<syn::Expr>()?;
+                    }
+                    Ok(())
+                })?;
+            }
+            Ok(())
+        })?;
+    }
+    Ok(rule)
+}
+
+// Same rules as serde's `rename_all`, applied to snake_case field names.
+fn apply_rename_all(rule: &str, field: &str) -> Result<String, String> {
+    let words: Vec<&str> = field.split('_').collect();
+    let capitalize = |w: &str| {
+        let mut chars = w.chars();
+        chars
+            .next()
+            .map(|c| c.to_uppercase().chain(chars).collect::<String>())
+            .unwrap_or_default()
+    };
+    Ok(match rule {
+        "lowercase" | "snake_case" => field.to_string(),
+        "UPPERCASE" | "SCREAMING_SNAKE_CASE" => field.to_uppercase(),
+        "PascalCase" => words.iter().map(|w| capitalize(w)).collect(),
+        "camelCase" => {
+            let pascal: String = words.iter().map(|w| capitalize(w)).collect();
+            let mut chars = pascal.chars();
+            chars
+                .next()
+                .map(|c| c.to_lowercase().chain(chars).collect())
+                .unwrap_or_default()
+        }
+        "kebab-case" => field.replace('_', "-"),
+        "SCREAMING-KEBAB-CASE" => field.replace('_', "-").to_uppercase(),
+        other => return Err(format!("unknown rename_all rule {:?}", other)),
+    })
+}
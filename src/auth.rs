@@ -0,0 +1,40 @@
+use std::fmt;
+
+use crate::Result;
+use crate::rt::BoxFuture;
+
+/// Supplies the API key sent with each request; register with
+/// [`Client::with_api_key_provider`](crate::Client::with_api_key_provider).
+///
+/// Asked before every attempt, retries included, so a long-lived client
+/// picks up rotated keys without being rebuilt. Providers that fetch keys
+/// from a secret store should cache them and refresh in the background or
+/// on expiry, as the call is on the request path. An error fails the
+/// attempt without retrying.
+///
+/// Closures returning a `String` are providers, e.g. one reading a key that
+/// another task rotates.
+pub trait ApiKeyProvider: Send + Sync {
+    fn api_key(&self) -> BoxFuture<'_, Result<String>>;
+}
+
+impl<F: Fn() -> String + Send + Sync> ApiKeyProvider for F {
+    fn api_key(&self) -> BoxFuture<'_, Result<String>> {
+        Box::pin(std::future::ready(Ok(self())))
+    }
+}
+
+impl fmt::Debug for dyn ApiKeyProvider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ApiKeyProvider")
+    }
+}
+
+/// The key a client was constructed with.
+pub(crate) struct StaticKey(pub String);
+
+impl ApiKeyProvider for StaticKey {
+    fn api_key(&self) -> BoxFuture<'_, Result<String>> {
+        Box::pin(std::future::ready(Ok(self.0.clone())))
+    }
+}
@@ -0,0 +1,261 @@
+//! A blocking client for code that isn't async, e.g. command-line ingestion
+//! tools.
+//!
+//! Like `reqwest::blocking`, each [`Client`] runs the async client on a
+//! private Tokio runtime driven by its own thread, so it shares its retries,
+//! middleware and metrics, and background work such as an
+//! [`IngestQueue`](crate::IngestQueue) worker keeps running between calls.
+//! Calls block the current thread and panic if made from within an async
+//! runtime.
+//!
+//! ```no_run
+//! # fn example() -> rs_puff::Result<()> {
+//! use rs_puff::blocking::Client;
+//! use rs_puff::{DistanceMetric, Row, WriteParams};
+//!
+//! let client = Client::from_env()?;
+//! let ns = client.namespace("products");
+//! let row: Row = serde_json::from_value(serde_json::json!({"id": 1, "vector": [0.1, 0.2]}))?;
+//! ns.write(WriteParams {
+//!     upsert_rows: Some(vec![row]),
+//!     distance_metric: Some(DistanceMetric::CosineDistance),
+//!     ..Default::default()
+//! })?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::de::DeserializeOwned;
+use tokio::runtime::Handle;
+use tokio::sync::oneshot;
+
+use crate::{
+    CallOptions, DeleteAllResponse, DeleteAllStatus, GetManyResult, HintCacheWarmResponse, Id, MultiQueryParams,
+    MultiQueryResponse, NamespaceMetadata, NamespacesParams, NamespacesResponse, QueryParams, QueryResponse, Result,
+    Row, Schema, SchemaResponse, WriteParams, WriteResponse,
+};
+
+/// Blocking counterpart of [`crate::Client`]. Clones share the connection
+/// pool and runtime.
+#[derive(Clone)]
+pub struct Client {
+    inner: crate::Client,
+    runtime: Arc<Driver>,
+}
+
+/// A current-thread runtime driven by a dedicated thread, which runs its IO,
+/// timers and spawned tasks; callers' futures are polled on their own thread.
+struct Driver {
+    handle: Handle,
+    shutdown: Option<oneshot::Sender<()>>,
+    thread: Option<std::thread::JoinHandle<()>>,
+}
+
+impl Driver {
+    fn start() -> std::io::Result<Self> {
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+        let handle = runtime.handle().clone();
+        let (shutdown, stopped) = oneshot::channel::<()>();
+        let thread = std::thread::Builder::new()
+            .name("rs-puff-blocking".to_string())
+            .spawn(move || {
+                let _ = runtime.block_on(stopped);
+            })?;
+        Ok(Self { handle, shutdown: Some(shutdown), thread: Some(thread) })
+    }
+
+    fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.handle.block_on(future)
+    }
+}
+
+impl Drop for Driver {
+    fn drop(&mut self) {
+        drop(self.shutdown.take());
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Client {
+    pub fn new(api_key: impl Into<String>) -> Self {
+        Self::from_async(crate::Client::new(api_key))
+    }
+
+    pub fn with_region(api_key: impl Into<String>, region: &str) -> Self {
+        Self::from_async(crate::Client::with_region(api_key, region))
+    }
+
+    pub fn with_base_url(api_key: impl Into<String>, base_url: impl Into<String>) -> Self {
+        Self::from_async(crate::Client::with_base_url(api_key, base_url))
+    }
+
+    pub fn from_env() -> Result<Self> {
+        Ok(Self::from_async(crate::Client::from_env()?))
+    }
+
+    /// Wrap a configured async client, e.g. one from [`crate::Client::builder`].
+    ///
+    /// Panics if the runtime can't be started.
+    pub fn from_async(client: crate::Client) -> Self {
+        let runtime = Driver::start().expect("failed to start the blocking client's runtime");
+        Self { inner: client, runtime: Arc::new(runtime) }
+    }
+
+    /// The async client this one drives.
+    pub fn as_async(&self) -> &crate::Client {
+        &self.inner
+    }
+
+    pub fn namespace(&self, name: impl Into<String>) -> Namespace {
+        Namespace { inner: self.inner.namespace(name), runtime: self.runtime.clone() }
+    }
+
+    pub fn namespaces(&self, params: NamespacesParams) -> Result<NamespacesResponse> {
+        self.runtime.block_on(self.inner.namespaces(params))
+    }
+
+    /// See [`crate::Client::ping`].
+    pub fn ping(&self) -> Result<Duration> {
+        self.runtime.block_on(self.inner.ping())
+    }
+
+    /// See [`crate::Client::raw_request`].
+    pub fn raw_request(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+        body: Option<&serde_json::Value>,
+    ) -> Result<serde_json::Value> {
+        self.runtime.block_on(self.inner.raw_request(method, path, body))
+    }
+}
+
+/// Blocking counterpart of [`crate::Namespace`].
+pub struct Namespace {
+    inner: crate::Namespace,
+    runtime: Arc<Driver>,
+}
+
+impl Namespace {
+    pub fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    /// The async handle, for operations without a blocking counterpart; run
+    /// them with [`block_on`](Self::block_on).
+    pub fn as_async(&self) -> &crate::Namespace {
+        &self.inner
+    }
+
+    /// Run a future on this client's runtime, e.g.
+    /// `ns.block_on(ns.as_async().query_page(params, None))`.
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.runtime.block_on(future)
+    }
+
+    pub fn write(&self, params: WriteParams) -> Result<WriteResponse> {
+        self.block_on(self.inner.write(params))
+    }
+
+    pub fn write_with_options(&self, params: WriteParams, opts: &CallOptions) -> Result<WriteResponse> {
+        self.block_on(self.inner.write_with_options(params, opts))
+    }
+
+    pub fn query(&self, params: QueryParams) -> Result<QueryResponse> {
+        self.block_on(self.inner.query(params))
+    }
+
+    pub fn query_with_options(&self, params: QueryParams, opts: &CallOptions) -> Result<QueryResponse> {
+        self.block_on(self.inner.query_with_options(params, opts))
+    }
+
+    pub fn query_as<T: DeserializeOwned>(&self, params: QueryParams) -> Result<Vec<T>> {
+        self.block_on(self.inner.query_as(params))
+    }
+
+    pub fn multi_query(&self, params: MultiQueryParams) -> Result<MultiQueryResponse> {
+        self.block_on(self.inner.multi_query(params))
+    }
+
+    pub fn get_many<I: Into<Id>>(&self, ids: impl IntoIterator<Item = I>) -> Result<GetManyResult> {
+        self.block_on(self.inner.get_many(ids))
+    }
+
+    pub fn get(&self, id: impl Into<Id>) -> Result<Option<Row>> {
+        self.block_on(self.inner.get(id))
+    }
+
+    pub fn delete_all(&self) -> Result<DeleteAllResponse> {
+        self.block_on(self.inner.delete_all())
+    }
+
+    pub fn delete_all_if_exists(&self) -> Result<DeleteAllStatus> {
+        self.block_on(self.inner.delete_all_if_exists())
+    }
+
+    pub fn metadata(&self) -> Result<NamespaceMetadata> {
+        self.block_on(self.inner.metadata())
+    }
+
+    pub fn schema(&self) -> Result<SchemaResponse> {
+        self.block_on(self.inner.schema())
+    }
+
+    pub fn update_schema(&self, schema: Schema) -> Result<SchemaResponse> {
+        self.block_on(self.inner.update_schema(schema))
+    }
+
+    pub fn hint_cache_warm(&self) -> Result<HintCacheWarmResponse> {
+        self.block_on(self.inner.hint_cache_warm())
+    }
+
+    pub fn exists(&self) -> Result<bool> {
+        self.block_on(self.inner.exists())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blocking_client_sends_requests() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            use std::io::{Read, Write};
+            let (mut stream, _) = listener.accept().unwrap();
+            let _ = stream.read(&mut [0; 4096]);
+            let body = r#"{"namespaces": [], "next_cursor": "c"}"#;
+            let head = format!("HTTP/1.1 200 OK\r\nconnection: close\r\ncontent-length: {}\r\n\r\n", body.len());
+            stream.write_all(head.as_bytes()).unwrap();
+            stream.write_all(body.as_bytes()).unwrap();
+        });
+
+        let client = Client::with_base_url("key", format!("http://{}", addr));
+        let resp = client.namespaces(NamespacesParams::default()).unwrap();
+        assert_eq!(resp.next_cursor.as_deref(), Some("c"));
+        assert_eq!(client.namespace("docs").name(), "docs");
+    }
+
+    #[test]
+    fn test_spawned_tasks_run_between_calls() {
+        let client = Client::new("key");
+        let ns = client.namespace("docs");
+        let (done, finished) = std::sync::mpsc::channel();
+        ns.block_on(async move {
+            tokio::spawn(async move {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                done.send(()).unwrap();
+            });
+        });
+        // No call is blocking on the runtime while the task sleeps.
+        finished.recv_timeout(Duration::from_secs(5)).unwrap();
+    }
+}
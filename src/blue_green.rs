@@ -0,0 +1,192 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use futures_util::{Stream, TryStreamExt};
+
+use crate::{BulkWriteReport, BulkWriter, DistanceMetric, Error, Namespace, QueryParams, Result, Row};
+
+const DEFAULT_MIN_RECALL: f64 = 0.9;
+
+/// Checks a standby namespace must pass before
+/// [`BlueGreenIndex::promote`] makes it active.
+#[derive(Debug, Clone)]
+pub struct StandbyChecks {
+    /// Rows the standby must hold exactly, e.g. the source's row count.
+    pub expected_rows: Option<u64>,
+    /// Vector queries run on the standby with both ANN and exhaustive kNN
+    /// ranking; see [`Namespace::compare_ann_knn`].
+    pub recall_queries: Vec<QueryParams>,
+    /// Mean recall the recall queries must reach. Defaults to 0.9.
+    pub min_recall: f64,
+}
+
+impl Default for StandbyChecks {
+    fn default() -> Self {
+        Self { expected_rows: None, recall_queries: Vec::new(), min_recall: DEFAULT_MIN_RECALL }
+    }
+}
+
+/// Outcome of [`BlueGreenIndex::validate`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct StandbyReport {
+    pub namespace: String,
+    pub rows: u64,
+    /// Mean recall of [`StandbyChecks::recall_queries`], if there were any.
+    pub recall: Option<f64>,
+    /// Checks that failed; empty when the standby can be promoted.
+    pub failures: Vec<String>,
+}
+
+impl StandbyReport {
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+/// Two namespaces serving the same data, one active and one on standby, for
+/// reindexing without downtime: [`rebuild`](Self::rebuild) the standby,
+/// [`promote`](Self::promote) it once it passes its checks, and keep querying
+/// [`active`](Self::active).
+///
+/// The flip is an atomic swap inside this process; share one index (e.g. in
+/// an `Arc`) between everything that queries it, and store
+/// [`active_name`](Self::active_name) if other processes need to follow.
+/// The previously active namespace becomes the standby, so
+/// [`flip`](Self::flip) again rolls back until the next rebuild.
+///
+/// ```no_run
+/// # async fn example(client: rs_puff::Client, rows: Vec<rs_puff::Row>) -> rs_puff::Result<()> {
+/// use rs_puff::{BlueGreenIndex, DistanceMetric, StandbyChecks};
+///
+/// let index = BlueGreenIndex::new(client.namespace("docs-blue"), client.namespace("docs-green"))
+///     .distance_metric(DistanceMetric::CosineDistance);
+/// let expected_rows = rows.len() as u64;
+/// index.rebuild(futures_util::stream::iter(rows.into_iter().map(Ok))).await?;
+/// index.promote(&StandbyChecks { expected_rows: Some(expected_rows), ..Default::default() }).await?;
+/// let results = index.active().query(Default::default()).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct BlueGreenIndex {
+    namespaces: [Namespace; 2],
+    active: AtomicUsize,
+    distance_metric: Option<DistanceMetric>,
+    schema: Option<HashMap<String, serde_json::Value>>,
+}
+
+impl BlueGreenIndex {
+    /// `blue` starts out active.
+    pub fn new(blue: Namespace, green: Namespace) -> Self {
+        Self { namespaces: [blue, green], active: AtomicUsize::new(0), distance_metric: None, schema: None }
+    }
+
+    /// Start with the namespace named `name` active, e.g. as stored from
+    /// [`active_name`](Self::active_name) by an earlier run.
+    pub fn with_active(self, name: &str) -> Result<Self> {
+        let Some(index) = self.namespaces.iter().position(|ns| ns.name() == name) else {
+            return Err(Error::InvalidParams(format!("{} is neither namespace of the index", name)));
+        };
+        self.active.store(index, Ordering::SeqCst);
+        Ok(self)
+    }
+
+    /// Distance metric sent with rebuilds; required as the standby is
+    /// recreated from scratch.
+    pub fn distance_metric(mut self, metric: DistanceMetric) -> Self {
+        self.distance_metric = Some(metric);
+        self
+    }
+
+    /// Schema sent with rebuilds.
+    pub fn schema(mut self, schema: HashMap<String, serde_json::Value>) -> Self {
+        self.schema = Some(schema);
+        self
+    }
+
+    /// The namespace to query.
+    pub fn active(&self) -> &Namespace {
+        &self.namespaces[self.active.load(Ordering::SeqCst)]
+    }
+
+    pub fn standby(&self) -> &Namespace {
+        &self.namespaces[1 - self.active.load(Ordering::SeqCst)]
+    }
+
+    pub fn active_name(&self) -> &str {
+        self.active().name()
+    }
+
+    /// Delete everything in the standby and upsert `rows` into it.
+    ///
+    /// The active namespace is untouched; writes to it while the rebuild runs
+    /// only reach the standby if `rows` includes them.
+    pub async fn rebuild<S>(&self, rows: S) -> Result<BulkWriteReport>
+    where
+        S: Stream<Item = Result<Row>>,
+    {
+        let standby = self.standby();
+        standby.delete_all_if_exists().await?;
+        let mut writer = BulkWriter::new(standby);
+        if let Some(metric) = self.distance_metric {
+            writer = writer.distance_metric(metric);
+        }
+        if let Some(schema) = &self.schema {
+            writer = writer.schema(schema.clone());
+        }
+        let mut rows = std::pin::pin!(rows);
+        while let Some(row) = rows.try_next().await? {
+            writer.push(row).await?;
+        }
+        writer.finish().await
+    }
+
+    /// Run `checks` against the standby without promoting it.
+    pub async fn validate(&self, checks: &StandbyChecks) -> Result<StandbyReport> {
+        check(self.standby(), checks).await
+    }
+
+    /// Validate the standby and make it active if it passes; otherwise fail
+    /// with [`Error::Verification`] and leave the active namespace as is.
+    pub async fn promote(&self, checks: &StandbyChecks) -> Result<StandbyReport> {
+        let standby = 1 - self.active.load(Ordering::SeqCst);
+        let report = check(&self.namespaces[standby], checks).await?;
+        if !report.passed() {
+            return Err(Error::Verification(report.failures.join("; ")));
+        }
+        if self.active.compare_exchange(1 - standby, standby, Ordering::SeqCst, Ordering::SeqCst).is_err() {
+            return Err(Error::Verification(format!("the index was flipped while {} was validated", report.namespace)));
+        }
+        Ok(report)
+    }
+
+    /// Swap the active and standby namespaces without checks, e.g. to roll
+    /// back a promotion. Returns the newly active namespace.
+    pub fn flip(&self) -> &Namespace {
+        &self.namespaces[self.active.fetch_xor(1, Ordering::SeqCst) ^ 1]
+    }
+}
+
+async fn check(standby: &Namespace, checks: &StandbyChecks) -> Result<StandbyReport> {
+    let rows = standby.count_rows().await?;
+    let mut failures = Vec::new();
+    if let Some(expected) = checks.expected_rows
+        && rows != expected
+    {
+        failures.push(format!("{} has {} rows, expected {}", standby.name(), rows, expected));
+    }
+
+    let mut recall = None;
+    if !checks.recall_queries.is_empty() {
+        let mut total = 0.0;
+        for query in &checks.recall_queries {
+            total += standby.compare_ann_knn(query.clone()).await?.recall;
+        }
+        let mean = total / checks.recall_queries.len() as f64;
+        if mean < checks.min_recall {
+            failures.push(format!("{} has recall {:.3}, below {:.3}", standby.name(), mean, checks.min_recall));
+        }
+        recall = Some(mean);
+    }
+
+    Ok(StandbyReport { namespace: standby.name().to_string(), rows, recall, failures })
+}
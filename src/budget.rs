@@ -0,0 +1,160 @@
+use std::time::Duration;
+
+use crate::rt;
+use crate::{
+    Consistency, ConsistencyLevel, Error, MultiQueryParams, MultiQueryResponse, Namespace, QueryParams, QueryResponse,
+    Result,
+};
+
+/// What to return when a query overruns its [`LatencyBudget`].
+#[derive(Debug, Clone)]
+pub enum Fallback {
+    /// Re-run the query with cheaper settings.
+    Cheaper {
+        /// Replacement `top_k`; `None` keeps the original.
+        top_k: Option<u64>,
+        /// Read with eventual consistency, skipping the unindexed tail of the WAL.
+        eventual_consistency: bool,
+    },
+    /// Return a previously fetched response, e.g. from an application cache.
+    Cached(Box<QueryResponse>),
+}
+
+#[derive(Debug, Clone)]
+pub struct LatencyBudget {
+    pub budget: Duration,
+    pub fallback: Fallback,
+}
+
+impl LatencyBudget {
+    /// Fall back to the same query with eventual consistency.
+    pub fn new(budget: Duration) -> Self {
+        Self {
+            budget,
+            fallback: Fallback::Cheaper { top_k: None, eventual_consistency: true },
+        }
+    }
+
+    pub fn fallback(mut self, fallback: Fallback) -> Self {
+        self.fallback = fallback;
+        self
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BudgetedResponse {
+    pub response: QueryResponse,
+    /// The budget ran out and `response` came from the fallback.
+    pub degraded: bool,
+}
+
+impl Namespace {
+    /// Run a query, switching to `budget.fallback` if it takes longer than
+    /// `budget.budget`.
+    ///
+    /// The fallback query itself is not time-limited, and its errors are
+    /// returned as-is.
+    pub async fn query_with_budget(&self, params: QueryParams, budget: &LatencyBudget) -> Result<BudgetedResponse> {
+        if let Ok(result) = rt::timeout(budget.budget, self.query(params.clone())).await {
+            return result.map(|response| BudgetedResponse { response, degraded: false });
+        }
+
+        let response = match &budget.fallback {
+            Fallback::Cheaper { top_k, eventual_consistency } => {
+                self.query(cheaper_query(&params, *top_k, *eventual_consistency)).await?
+            }
+            Fallback::Cached(cached) => (**cached).clone(),
+        };
+        Ok(BudgetedResponse { response, degraded: true })
+    }
+}
+
+/// Limits shared by all subqueries of [`Namespace::multi_query_with_budget`].
+#[derive(Debug, Clone, Default)]
+pub struct MultiQueryBudget {
+    /// Total rows across subqueries. Each gets an equal share, and shares
+    /// a subquery's own `top_k` leaves unused go to the others. Every
+    /// subquery gets at least one row, so the total can exceed a budget
+    /// smaller than the number of subqueries.
+    pub max_rows: Option<u64>,
+    /// Limit on the whole call. Subqueries run in one request, so they
+    /// share it rather than getting a slice each.
+    pub latency: Option<Duration>,
+}
+
+impl Namespace {
+    /// Run a multi-query within `budget`, lowering subqueries' `top_k` to
+    /// fit `max_rows` and failing with [`Error::DeadlineExceeded`] once
+    /// `latency` is up.
+    pub async fn multi_query_with_budget(
+        &self,
+        mut params: MultiQueryParams,
+        budget: &MultiQueryBudget,
+    ) -> Result<MultiQueryResponse> {
+        if let Some(max_rows) = budget.max_rows {
+            split_rows(&mut params.queries, max_rows);
+        }
+        match budget.latency {
+            Some(latency) => rt::timeout(latency, self.multi_query(params))
+                .await
+                .unwrap_or(Err(Error::DeadlineExceeded)),
+            None => self.multi_query(params).await,
+        }
+    }
+}
+
+/// Give each query a fair share of `max_rows`: smallest requests are
+/// satisfied first and what they leave is split among the rest. Queries
+/// without `top_k` take their full share.
+fn split_rows(queries: &mut [QueryParams], max_rows: u64) {
+    let mut order: Vec<usize> = (0..queries.len()).collect();
+    order.sort_by_key(|&i| queries[i].top_k.unwrap_or(u64::MAX));
+    let mut remaining = max_rows;
+    for (n, &i) in order.iter().enumerate() {
+        let share = remaining / (order.len() - n) as u64;
+        let top_k = queries[i].top_k.map_or(share, |k| k.min(share)).max(1);
+        queries[i].top_k = Some(top_k);
+        remaining = remaining.saturating_sub(top_k);
+    }
+}
+
+fn cheaper_query(params: &QueryParams, top_k: Option<u64>, eventual_consistency: bool) -> QueryParams {
+    let mut query = params.clone();
+    if let Some(top_k) = top_k {
+        query.top_k = Some(query.top_k.map_or(top_k, |k| k.min(top_k)));
+    }
+    if eventual_consistency {
+        query.consistency = Some(Consistency { level: ConsistencyLevel::Eventual });
+    }
+    query
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cheaper_query_lowers_top_k_and_consistency() {
+        let params = QueryParams { top_k: Some(100), ..Default::default() };
+        let query = serde_json::to_value(cheaper_query(&params, Some(10), true)).unwrap();
+        assert_eq!(query["top_k"], 10);
+        assert_eq!(query["consistency"]["level"], "eventual");
+
+        // Never raises top_k above the original.
+        let params = QueryParams { top_k: Some(5), ..Default::default() };
+        assert_eq!(cheaper_query(&params, Some(10), false).top_k, Some(5));
+    }
+
+    #[test]
+    fn test_split_rows_shares_unused_budget() {
+        let top_ks = |requested: &[Option<u64>], max_rows| {
+            let mut queries: Vec<QueryParams> =
+                requested.iter().map(|&top_k| QueryParams { top_k, ..Default::default() }).collect();
+            split_rows(&mut queries, max_rows);
+            queries.iter().map(|q| q.top_k.unwrap()).collect::<Vec<_>>()
+        };
+        assert_eq!(top_ks(&[Some(100), Some(5), None], 60), [27, 5, 28]);
+        assert_eq!(top_ks(&[Some(10), Some(20)], 100), [10, 20]);
+        assert_eq!(top_ks(&[None, None, None], 2), [1, 1, 1]);
+    }
+}
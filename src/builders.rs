@@ -0,0 +1,298 @@
+use std::collections::HashMap;
+use std::future::IntoFuture;
+
+use crate::rt::BoxFuture;
+use crate::{
+    AggregateBy, CallOptions, Consistency, ConsistencyLevel, DistanceMetric, Filter, IncludeAttributes, Namespace,
+    QueryParams, QueryResponse, RankBy, Result, Row, Schema, VectorEncoding, WriteParams, WriteResponse,
+};
+
+/// Builds a query step by step; `.await` it to send.
+///
+/// ```no_run
+/// # async fn run(ns: rs_puff::Namespace) -> rs_puff::Result<()> {
+/// use rs_puff::{Filter, RankBy};
+///
+/// let results = ns
+///     .query_builder()
+///     .rank_by(RankBy::vector("vector", vec![0.1, 0.2]))
+///     .filters(Filter::eq("lang", "en"))
+///     .top_k(10)
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+#[must_use = "builders do nothing unless awaited"]
+pub struct QueryBuilder<'n> {
+    ns: &'n Namespace,
+    params: QueryParams,
+    opts: CallOptions,
+}
+
+impl<'n> QueryBuilder<'n> {
+    pub(crate) fn new(ns: &'n Namespace) -> Self {
+        Self { ns, params: QueryParams::default(), opts: CallOptions::default() }
+    }
+
+    pub fn rank_by(mut self, rank_by: RankBy) -> Self {
+        self.params.rank_by = Some(rank_by);
+        self
+    }
+
+    pub fn top_k(mut self, top_k: u64) -> Self {
+        self.params.top_k = Some(top_k);
+        self
+    }
+
+    pub fn filters(mut self, filters: Filter) -> Self {
+        self.params.filters = Some(filters);
+        self
+    }
+
+    pub fn include_attributes<I, S>(mut self, attributes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.params.include_attributes = Some(IncludeAttributes::List(attributes.into_iter().map(Into::into).collect()));
+        self
+    }
+
+    pub fn include_all_attributes(mut self) -> Self {
+        self.params.include_attributes = Some(IncludeAttributes::All(true));
+        self
+    }
+
+    pub fn exclude_attributes<I, S>(mut self, attributes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.params.exclude_attributes = Some(attributes.into_iter().map(Into::into).collect());
+        self
+    }
+
+    pub fn vector_encoding(mut self, encoding: VectorEncoding) -> Self {
+        self.params.vector_encoding = Some(encoding);
+        self
+    }
+
+    pub fn distance_metric(mut self, metric: DistanceMetric) -> Self {
+        self.params.distance_metric = Some(metric);
+        self
+    }
+
+    pub fn consistency(mut self, level: ConsistencyLevel) -> Self {
+        self.params.consistency = Some(Consistency { level });
+        self
+    }
+
+    /// Add a named aggregation.
+    pub fn aggregate_by(mut self, name: impl Into<String>, aggregate: AggregateBy) -> Self {
+        self.params.aggregate_by.get_or_insert_with(HashMap::new).insert(name.into(), aggregate);
+        self
+    }
+
+    pub fn group_by<I, S>(mut self, attributes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.params.group_by = Some(attributes.into_iter().map(Into::into).collect());
+        self
+    }
+
+    pub fn options(mut self, opts: CallOptions) -> Self {
+        self.opts = opts;
+        self
+    }
+
+    /// The assembled parameters, without sending.
+    pub fn into_params(self) -> QueryParams {
+        self.params
+    }
+
+    pub async fn send(self) -> Result<QueryResponse> {
+        self.ns.query_with_options(self.params, &self.opts).await
+    }
+}
+
+impl<'n> IntoFuture for QueryBuilder<'n> {
+    type Output = Result<QueryResponse>;
+    type IntoFuture = BoxFuture<'n, Self::Output>;
+
+    fn into_future(self) -> Self::IntoFuture {
+        Box::pin(self.send())
+    }
+}
+
+/// Builds a write step by step; `.await` it to send.
+///
+/// ```no_run
+/// # async fn run(ns: rs_puff::Namespace, row: rs_puff::Row) -> rs_puff::Result<()> {
+/// use rs_puff::DistanceMetric;
+///
+/// ns.write_builder()
+///     .upsert(row)
+///     .delete(7)
+///     .distance_metric(DistanceMetric::CosineDistance)
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+#[must_use = "builders do nothing unless awaited"]
+pub struct WriteBuilder<'n> {
+    ns: &'n Namespace,
+    params: WriteParams,
+    opts: CallOptions,
+}
+
+impl<'n> WriteBuilder<'n> {
+    pub(crate) fn new(ns: &'n Namespace) -> Self {
+        Self { ns, params: WriteParams::default(), opts: CallOptions::default() }
+    }
+
+    /// Add one row to upsert.
+    pub fn upsert(mut self, row: Row) -> Self {
+        self.params.upsert_rows.get_or_insert_with(Vec::new).push(row);
+        self
+    }
+
+    pub fn upsert_rows(mut self, rows: impl IntoIterator<Item = Row>) -> Self {
+        self.params.upsert_rows.get_or_insert_with(Vec::new).extend(rows);
+        self
+    }
+
+    /// Add one row to patch.
+    pub fn patch(mut self, row: Row) -> Self {
+        self.params.patch_rows.get_or_insert_with(Vec::new).push(row);
+        self
+    }
+
+    pub fn patch_rows(mut self, rows: impl IntoIterator<Item = Row>) -> Self {
+        self.params.patch_rows.get_or_insert_with(Vec::new).extend(rows);
+        self
+    }
+
+    /// Add one id to delete.
+    pub fn delete(mut self, id: impl Into<serde_json::Value>) -> Self {
+        self.params.deletes.get_or_insert_with(Vec::new).push(id.into());
+        self
+    }
+
+    pub fn delete_by_filter(mut self, filter: Filter) -> Self {
+        self.params.delete_by_filter = Some(filter);
+        self
+    }
+
+    pub fn upsert_condition(mut self, condition: Filter) -> Self {
+        self.params.upsert_condition = Some(condition);
+        self
+    }
+
+    pub fn patch_condition(mut self, condition: Filter) -> Self {
+        self.params.patch_condition = Some(condition);
+        self
+    }
+
+    pub fn delete_condition(mut self, condition: Filter) -> Self {
+        self.params.delete_condition = Some(condition);
+        self
+    }
+
+    pub fn distance_metric(mut self, metric: DistanceMetric) -> Self {
+        self.params.distance_metric = Some(metric);
+        self
+    }
+
+    pub fn schema(mut self, schema: Schema) -> Self {
+        self.params.schema = Some(schema.into_map());
+        self
+    }
+
+    pub fn return_affected_ids(mut self) -> Self {
+        self.params.return_affected_ids = Some(true);
+        self
+    }
+
+    pub fn options(mut self, opts: CallOptions) -> Self {
+        self.opts = opts;
+        self
+    }
+
+    /// The assembled parameters, without sending.
+    pub fn into_params(self) -> WriteParams {
+        self.params
+    }
+
+    pub async fn send(self) -> Result<WriteResponse> {
+        self.ns.write_with_options(self.params, &self.opts).await
+    }
+}
+
+impl<'n> IntoFuture for WriteBuilder<'n> {
+    type Output = Result<WriteResponse>;
+    type IntoFuture = BoxFuture<'n, Self::Output>;
+
+    fn into_future(self) -> Self::IntoFuture {
+        Box::pin(self.send())
+    }
+}
+
+impl Namespace {
+    /// Start a query; see [`QueryBuilder`].
+    pub fn query_builder(&self) -> QueryBuilder<'_> {
+        QueryBuilder::new(self)
+    }
+
+    /// Start a write; see [`WriteBuilder`].
+    pub fn write_builder(&self) -> WriteBuilder<'_> {
+        WriteBuilder::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Client;
+
+    #[test]
+    fn test_query_builder_params() {
+        let client = Client::new("key");
+        let ns = client.namespace("docs");
+        let params = ns
+            .query_builder()
+            .rank_by(RankBy::asc("id"))
+            .top_k(5)
+            .include_attributes(["title"])
+            .consistency(ConsistencyLevel::Eventual)
+            .aggregate_by("n", AggregateBy::Count)
+            .into_params();
+        assert_eq!(
+            serde_json::to_value(params).unwrap(),
+            serde_json::json!({
+                "rank_by": ["id", "asc"],
+                "top_k": 5,
+                "include_attributes": ["title"],
+                "consistency": {"level": "eventual"},
+                "aggregate_by": {"n": ["Count"]},
+            })
+        );
+    }
+
+    #[test]
+    fn test_write_builder_accumulates_rows_and_deletes() {
+        let client = Client::new("key");
+        let ns = client.namespace("docs");
+        let row: Row = serde_json::from_value(serde_json::json!({"id": 1})).unwrap();
+        let params = ns
+            .write_builder()
+            .upsert(row.clone())
+            .upsert_rows(vec![row])
+            .delete(7)
+            .delete("doc-8")
+            .into_params();
+        assert_eq!(params.upsert_rows.unwrap().len(), 2);
+        assert_eq!(params.deletes.unwrap(), [serde_json::json!(7), serde_json::json!("doc-8")]);
+    }
+}
@@ -0,0 +1,474 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::get::GET_BATCH_SIZE;
+use crate::rt::Instant;
+use crate::testing::canonicalize;
+use crate::{
+    DistanceMetric, Error, Filter, IncludeAttributes, Namespace, QueryParams, RankBy, Result, Row, WriteParams,
+    WriteResponse, MAX_WRITE_REQUEST_BYTES, MAX_WRITE_REQUEST_ROWS,
+};
+
+const DEFAULT_BATCH_SIZE: usize = 1000;
+
+// Room left in each request for everything but the rows.
+const REQUEST_OVERHEAD_BYTES: usize = 1024;
+
+/// Totals for a bulk write, returned by [`BulkWriter`],
+/// [`IngestQueue`](crate::IngestQueue), [`SyncEngine`](crate::sync::SyncEngine)
+/// and [`DirectoryIngester`](crate::ingest::DirectoryIngester).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BulkWriteReport {
+    /// Batches written successfully.
+    pub batches: u64,
+    pub rows_upserted: u64,
+    pub rows_patched: u64,
+    pub rows_deleted: u64,
+    /// Rows not written because their content hash was already stored.
+    pub rows_skipped: u64,
+    pub billable_logical_bytes_written: u64,
+    /// Time spent in write requests, including retries.
+    pub elapsed: Duration,
+    pub failures: Vec<BatchFailure>,
+}
+
+/// A batch that failed to write.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchFailure {
+    /// Position of the batch among all batches attempted, from 0.
+    pub batch: u64,
+    /// Ids of the rows upserted, patched or deleted by the batch, e.g. to
+    /// re-send them from the source.
+    pub ids: Vec<serde_json::Value>,
+    pub error: String,
+}
+
+impl BulkWriteReport {
+    pub fn rows_written(&self) -> u64 {
+        self.rows_upserted + self.rows_patched + self.rows_deleted
+    }
+
+    fn add(&mut self, sent: &SentRows, resp: &WriteResponse) {
+        self.batches += 1;
+        self.rows_upserted += resp.rows_upserted.unwrap_or(sent.upserts);
+        self.rows_patched += resp.rows_patched.unwrap_or(sent.patches);
+        self.rows_deleted += resp.rows_deleted.unwrap_or(sent.deletes);
+        if let Some(billing) = &resp.billing {
+            self.billable_logical_bytes_written += billing.billable_logical_bytes_written;
+        }
+    }
+}
+
+/// What a batch asked to write, for when the response doesn't say.
+struct SentRows {
+    upserts: u64,
+    patches: u64,
+    deletes: u64,
+    ids: Vec<serde_json::Value>,
+}
+
+impl SentRows {
+    fn new(params: &WriteParams) -> Self {
+        let upserts = ids(&params.upsert_rows, &params.upsert_columns);
+        let patches = ids(&params.patch_rows, &params.patch_columns);
+        let deletes = params.deletes.clone().unwrap_or_default();
+        Self {
+            upserts: upserts.len() as u64,
+            patches: patches.len() as u64,
+            deletes: deletes.len() as u64,
+            ids: upserts.into_iter().chain(patches).chain(deletes).collect(),
+        }
+    }
+}
+
+fn ids(rows: &Option<Vec<Row>>, columns: &Option<HashMap<String, Vec<serde_json::Value>>>) -> Vec<serde_json::Value> {
+    let column_ids = columns.as_ref().and_then(|columns| columns.get("id")).into_iter().flatten();
+    rows.iter().flatten().filter_map(|row| row.get("id")).chain(column_ids).cloned().collect()
+}
+
+/// Write one batch of a bulk helper, adding the outcome to `report`.
+pub(crate) async fn write_reported(ns: &Namespace, params: WriteParams, report: &mut BulkWriteReport) -> Result<()> {
+    let sent = SentRows::new(&params);
+    let start = Instant::now();
+    let result = ns.write_batch(params).await;
+    report.elapsed += start.elapsed();
+    match result {
+        Ok(resp) => {
+            report.add(&sent, &resp);
+            Ok(())
+        }
+        Err(e) => {
+            report.failures.push(BatchFailure {
+                batch: report.batches + report.failures.len() as u64,
+                ids: sent.ids,
+                error: e.to_string(),
+            });
+            Err(e)
+        }
+    }
+}
+
+/// Buffers rows and upserts them in fixed-size batches.
+///
+/// A batch is also written early when the next row would take the request
+/// past [`MAX_WRITE_REQUEST_BYTES`] (or [`BulkWriter::max_batch_bytes`]).
+/// Call [`BulkWriter::finish`] to write the final partial batch; rows still
+/// buffered when the writer is dropped are discarded.
+///
+/// A failed batch is recorded in [`BulkWriteReport::failures`] and its error
+/// returned, unless [`continue_on_error`](Self::continue_on_error) is set.
+pub struct BulkWriter<'a> {
+    ns: &'a Namespace,
+    batch_size: usize,
+    max_batch_bytes: usize,
+    distance_metric: Option<DistanceMetric>,
+    schema: Option<HashMap<String, serde_json::Value>>,
+    hash_attribute: Option<String>,
+    continue_on_error: bool,
+    buffer: Vec<Row>,
+    buffered_bytes: usize,
+    report: BulkWriteReport,
+}
+
+impl<'a> BulkWriter<'a> {
+    pub fn new(ns: &'a Namespace) -> Self {
+        Self {
+            ns,
+            batch_size: DEFAULT_BATCH_SIZE,
+            max_batch_bytes: MAX_WRITE_REQUEST_BYTES,
+            distance_metric: None,
+            schema: None,
+            hash_attribute: None,
+            continue_on_error: false,
+            buffer: Vec::new(),
+            buffered_bytes: 0,
+            report: BulkWriteReport::default(),
+        }
+    }
+
+    /// Rows per request. Can't exceed [`MAX_WRITE_REQUEST_ROWS`].
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.clamp(1, MAX_WRITE_REQUEST_ROWS);
+        self
+    }
+
+    /// Cap on each request's size, e.g. to keep requests small on slow
+    /// links. Can't exceed [`MAX_WRITE_REQUEST_BYTES`].
+    pub fn max_batch_bytes(mut self, bytes: usize) -> Self {
+        self.max_batch_bytes = bytes.min(MAX_WRITE_REQUEST_BYTES);
+        self
+    }
+
+    pub fn distance_metric(mut self, metric: DistanceMetric) -> Self {
+        self.distance_metric = Some(metric);
+        self
+    }
+
+    /// Schema sent with every batch.
+    pub fn schema(mut self, schema: HashMap<String, serde_json::Value>) -> Self {
+        self.schema = Some(schema);
+        self
+    }
+
+    /// Store a hash of each row's content in `attribute` and skip rows whose
+    /// stored hash already matches, so re-running a pipeline only writes
+    /// changed rows.
+    ///
+    /// Costs one query per 1000 rows of each batch to fetch the stored
+    /// hashes. A batch whose lookup fails is recorded as a failed batch.
+    pub fn content_hash(mut self, attribute: impl Into<String>) -> Self {
+        self.hash_attribute = Some(attribute.into());
+        self
+    }
+
+    /// Record failed batches in the report and carry on with the next one
+    /// instead of returning the error, e.g. for best-effort backfills that
+    /// re-send failures afterwards.
+    pub fn continue_on_error(mut self, enabled: bool) -> Self {
+        self.continue_on_error = enabled;
+        self
+    }
+
+    /// Buffer a row, writing a batch when it is full.
+    ///
+    /// Fails with [`Error::InvalidParams`] if the row alone would exceed the
+    /// request size limit.
+    pub async fn push(&mut self, row: Row) -> Result<()> {
+        let size = self.row_bytes(&row);
+        let limit = self.rows_budget();
+        if size > limit {
+            let id = row.get("id").map(|id| format!(" {}", id)).unwrap_or_default();
+            return Err(Error::InvalidParams(format!(
+                "row{} is {} bytes, over the {}-byte write request limit",
+                id, size, limit
+            )));
+        }
+        if self.buffered_bytes + size > limit {
+            self.flush().await?;
+        }
+        self.buffered_bytes += size;
+        self.buffer.push(row);
+        if self.buffer.len() >= self.batch_size {
+            self.flush().await?;
+        }
+        Ok(())
+    }
+
+    pub async fn extend(&mut self, rows: impl IntoIterator<Item = Row>) -> Result<()> {
+        for row in rows {
+            self.push(row).await?;
+        }
+        Ok(())
+    }
+
+    /// Write any buffered rows now.
+    pub async fn flush(&mut self) -> Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let mut rows = std::mem::take(&mut self.buffer);
+        self.buffered_bytes = 0;
+        if let Some(attribute) = self.hash_attribute.clone() {
+            let before = rows.len();
+            for row in &mut rows {
+                let hash = content_hash(row, &attribute);
+                row.insert(attribute.clone(), serde_json::json!(hash));
+            }
+            let stored = match self.stored_hashes(&rows, &attribute).await {
+                Ok(stored) => stored,
+                Err(e) => return self.lookup_failed(&rows, e),
+            };
+            rows.retain(|row| {
+                let stored_hash = row.get("id").and_then(|id| stored.get(&id.to_string()));
+                stored_hash != row.get(&attribute)
+            });
+            self.report.rows_skipped += (before - rows.len()) as u64;
+            if rows.is_empty() {
+                return Ok(());
+            }
+        }
+        let params = WriteParams {
+            upsert_rows: Some(rows),
+            distance_metric: self.distance_metric,
+            schema: self.schema.clone(),
+            ..Default::default()
+        };
+        match write_reported(self.ns, params, &mut self.report).await {
+            Err(_) if self.continue_on_error => Ok(()),
+            result => result,
+        }
+    }
+
+    /// Flush remaining rows and return totals for the whole run.
+    pub async fn finish(mut self) -> Result<BulkWriteReport> {
+        self.flush().await?;
+        Ok(self.report)
+    }
+
+    // A batch whose hash lookup failed is recorded like a failed write, so
+    // its rows aren't dropped without a trace.
+    fn lookup_failed(&mut self, rows: &[Row], error: Error) -> Result<()> {
+        self.report.failures.push(BatchFailure {
+            batch: self.report.batches + self.report.failures.len() as u64,
+            ids: rows.iter().filter_map(|row| row.get("id").cloned()).collect(),
+            error: error.to_string(),
+        });
+        if self.continue_on_error { Ok(()) } else { Err(error) }
+    }
+
+    // Hash attribute of each row already in the namespace, keyed by id JSON.
+    async fn stored_hashes(&self, rows: &[Row], attribute: &str) -> Result<HashMap<String, serde_json::Value>> {
+        let ids: Vec<_> = rows.iter().filter_map(|row| row.get("id").cloned()).collect();
+        let mut stored = HashMap::with_capacity(ids.len());
+        for batch in ids.chunks(GET_BATCH_SIZE) {
+            let query = QueryParams {
+                rank_by: Some(RankBy::asc("id")),
+                top_k: Some(batch.len() as u64),
+                filters: Some(Filter::r#in("id", batch.to_vec())),
+                include_attributes: Some(IncludeAttributes::List(vec![attribute.to_string()])),
+                ..Default::default()
+            };
+            // A hook that masks the hash would make every row look changed.
+            let resp = match self.ns.query_unhooked(&query).await {
+                Ok(resp) => resp,
+                Err(Error::Api { status: 404, .. }) => return Ok(HashMap::new()),
+                Err(e) => return Err(e),
+            };
+            stored.extend(
+                resp.rows
+                    .into_iter()
+                    .filter_map(|mut row| Some((row.remove("id")?.to_string(), row.remove(attribute)?))),
+            );
+        }
+        Ok(stored)
+    }
+
+    // Serialized size of a row within a batch, including its content hash.
+    fn row_bytes(&self, row: &Row) -> usize {
+        let hash = self.hash_attribute.as_ref().map_or(0, |attr| attr.len() + 22);
+        serde_json::to_vec(row).expect("rows always serialize").len() + hash + 1
+    }
+
+    // Bytes per request available for rows.
+    fn rows_budget(&self) -> usize {
+        let schema = self.schema.as_ref().map_or(0, |schema| serde_json::to_vec(schema).expect("schemas always serialize").len());
+        self.max_batch_bytes.saturating_sub(schema + REQUEST_OVERHEAD_BYTES)
+    }
+
+    pub fn report(&self) -> &BulkWriteReport {
+        &self.report
+    }
+
+    pub fn buffered(&self) -> usize {
+        self.buffer.len()
+    }
+}
+
+/// FNV-1a over the row's canonical JSON, ignoring `id` and the hash attribute.
+fn content_hash(row: &Row, hash_attribute: &str) -> String {
+    let content: serde_json::Map<_, _> = row
+        .iter()
+        .filter(|(k, _)| *k != "id" && *k != hash_attribute)
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect();
+    let bytes = serde_json::to_vec(&canonicalize(serde_json::Value::Object(content))).expect("rows always serialize");
+    let hash = bytes
+        .iter()
+        .fold(0xcbf2_9ce4_8422_2325u64, |h, b| (h ^ *b as u64).wrapping_mul(0x0000_0100_0000_01b3));
+    format!("{:016x}", hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(value: serde_json::Value) -> Row {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_content_hash_ignores_id_hash_and_key_order() {
+        let a = row(serde_json::json!({"id": 1, "title": "a", "meta": {"x": 1, "y": 2}}));
+        let b = row(serde_json::json!({"id": 2, "meta": {"y": 2, "x": 1}, "title": "a", "_hash": "old"}));
+        assert_eq!(content_hash(&a, "_hash"), content_hash(&b, "_hash"));
+
+        let c = row(serde_json::json!({"id": 1, "title": "b", "meta": {"x": 1, "y": 2}}));
+        assert_ne!(content_hash(&a, "_hash"), content_hash(&c, "_hash"));
+    }
+
+    #[test]
+    fn test_row_bytes_counts_hash_attribute() {
+        let client = crate::Client::new("key");
+        let ns = client.namespace("ns");
+        let r = row(serde_json::json!({"id": 1, "title": "abc"}));
+        let plain = BulkWriter::new(&ns);
+        assert_eq!(plain.row_bytes(&r), serde_json::to_vec(&r).unwrap().len() + 1);
+        let hashed = BulkWriter::new(&ns).content_hash("_hash");
+        // ,"_hash":"0123456789abcdef"
+        assert_eq!(hashed.row_bytes(&r), plain.row_bytes(&r) + 27);
+    }
+
+    #[test]
+    fn test_report_counts_fall_back_to_sent_rows() {
+        let params = WriteParams {
+            upsert_rows: Some(vec![row(serde_json::json!({"id": 1})), row(serde_json::json!({"id": 2}))]),
+            deletes: Some(vec![serde_json::json!(3)]),
+            ..Default::default()
+        };
+        let sent = SentRows::new(&params);
+        assert_eq!(sent.ids, [serde_json::json!(1), serde_json::json!(2), serde_json::json!(3)]);
+
+        let resp: WriteResponse = serde_json::from_value(serde_json::json!({
+            "rows_affected": 3,
+            "rows_deleted": 0,
+            "billing": {"billable_logical_bytes_written": 512}
+        }))
+        .unwrap();
+        let mut report = BulkWriteReport::default();
+        report.add(&sent, &resp);
+        report.add(&sent, &resp);
+        assert_eq!((report.batches, report.rows_upserted, report.rows_deleted), (2, 4, 0));
+        assert_eq!(report.rows_written(), 4);
+        assert_eq!(report.billable_logical_bytes_written, 1024);
+    }
+
+    #[tokio::test]
+    async fn test_push_rejects_oversized_row() {
+        let client = crate::Client::new("key");
+        let ns = client.namespace("ns");
+        let mut writer = BulkWriter::new(&ns).max_batch_bytes(2048);
+        let big = row(serde_json::json!({"id": 7, "text": "x".repeat(2048)}));
+        let size = serde_json::to_vec(&big).unwrap().len() + 1;
+        let err = writer.push(big).await.unwrap_err();
+        assert_eq!(err.to_string(), format!("invalid params: row 7 is {} bytes, over the 1024-byte write request limit", size));
+        assert_eq!(writer.buffered(), 0);
+    }
+
+    /// Answers hash lookups with no rows, or a 503 if `fail_lookups`, and
+    /// accepts writes, recording each lookup's `top_k`.
+    #[derive(Default)]
+    struct Lookups {
+        fail_lookups: bool,
+        top_ks: std::sync::Mutex<Vec<u64>>,
+    }
+
+    impl crate::HttpTransport for Lookups {
+        fn send(&self, req: reqwest::Request) -> crate::rt::BoxFuture<'_, Result<reqwest::Response>> {
+            let resp = if req.url().path().ends_with("/query") {
+                let body = req.body().and_then(|body| body.as_bytes()).unwrap();
+                let query: serde_json::Value = serde_json::from_slice(body).unwrap();
+                self.top_ks.lock().unwrap().push(query["top_k"].as_u64().unwrap());
+                let status = if self.fail_lookups { 503 } else { 200 };
+                http::Response::builder().status(status).body(r#"{"rows": []}"#).unwrap()
+            } else {
+                http::Response::new(r#"{"rows_affected": 0}"#)
+            };
+            Box::pin(async move { Ok(reqwest::Response::from(resp)) })
+        }
+    }
+
+    fn rows(n: usize) -> Vec<Row> {
+        (0..n).map(|i| row(serde_json::json!({"id": i, "title": "a"}))).collect()
+    }
+
+    #[tokio::test]
+    async fn test_hash_lookups_are_chunked() {
+        let transport = std::sync::Arc::new(Lookups::default());
+        let client = crate::Client::new("key").with_transport(transport.clone());
+        let ns = client.namespace("ns");
+        let mut writer = BulkWriter::new(&ns).batch_size(2500).content_hash("_hash");
+        writer.extend(rows(2500)).await.unwrap();
+        let report = writer.finish().await.unwrap();
+        assert_eq!(*transport.top_ks.lock().unwrap(), [1000, 1000, 500]);
+        assert_eq!((report.batches, report.rows_upserted), (1, 2500));
+    }
+
+    #[tokio::test]
+    async fn test_failed_hash_lookup_is_reported() {
+        let transport = std::sync::Arc::new(Lookups { fail_lookups: true, ..Default::default() });
+        let client = crate::Client::new("key")
+            .with_retry_policy(crate::RetryPolicy::none())
+            .with_transport(transport);
+        let ns = client.namespace("ns");
+
+        let mut writer = BulkWriter::new(&ns).batch_size(2).content_hash("_hash");
+        assert!(writer.extend(rows(2)).await.is_err());
+        let failures = &writer.report().failures;
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].ids, [serde_json::json!(0), serde_json::json!(1)]);
+
+        let mut writer = BulkWriter::new(&ns).batch_size(2).content_hash("_hash").continue_on_error(true);
+        writer.extend(rows(4)).await.unwrap();
+        let report = writer.finish().await.unwrap();
+        assert_eq!(report.failures.iter().map(|f| f.batch).collect::<Vec<_>>(), [0, 1]);
+        assert_eq!(report.batches, 0);
+    }
+
+    #[test]
+    fn test_batch_size_is_capped_at_request_row_limit() {
+        let client = crate::Client::new("key");
+        let ns = client.namespace("ns");
+        assert_eq!(BulkWriter::new(&ns).batch_size(usize::MAX).batch_size, MAX_WRITE_REQUEST_ROWS);
+        assert_eq!(BulkWriter::new(&ns).batch_size(0).batch_size, 1);
+    }
+}
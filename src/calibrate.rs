@@ -0,0 +1,111 @@
+//! Turning `$dist` values into similarity scores that compare the same way
+//! whatever produced them, so thresholds don't depend on the metric.
+
+use crate::{DistanceMetric, Row};
+
+/// Attribute [`annotate_scores`] writes each row's score to.
+pub const SCORE_ATTRIBUTE: &str = "$score";
+
+/// What a result's `$dist` measures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScoreSource {
+    /// Distance of a vector query under the namespace's metric.
+    Vector(DistanceMetric),
+    /// BM25 relevance, already higher-is-better.
+    Bm25,
+}
+
+/// Similarity for a vector distance, higher is more similar: cosine
+/// similarity (`1 - distance`, in `[-1, 1]`) for cosine distance and
+/// `1 / (1 + distance)` (in `(0, 1]`) for squared Euclidean distance.
+pub fn similarity(dist: f64, metric: DistanceMetric) -> f64 {
+    match metric {
+        DistanceMetric::CosineDistance => 1.0 - dist,
+        DistanceMetric::EuclideanSquared => 1.0 / (1.0 + dist.max(0.0)),
+    }
+}
+
+/// Higher-is-better score for each row, `None` for rows without `$dist`.
+///
+/// With `min_max`, scores are rescaled over the result set so the best is
+/// `1.0` and the worst `0.0` (all `1.0` if they are equal). That makes lists
+/// comparable but says nothing about absolute relevance.
+pub fn calibrated_scores(rows: &[Row], source: ScoreSource, min_max: bool) -> Vec<Option<f64>> {
+    let mut scores: Vec<Option<f64>> = rows
+        .iter()
+        .map(|row| {
+            let dist = row.get("$dist")?.as_f64()?;
+            Some(match source {
+                ScoreSource::Vector(metric) => similarity(dist, metric),
+                ScoreSource::Bm25 => dist,
+            })
+        })
+        .collect();
+    if min_max {
+        let known = scores.iter().flatten();
+        let min = known.clone().copied().fold(f64::INFINITY, f64::min);
+        let max = known.copied().fold(f64::NEG_INFINITY, f64::max);
+        for score in scores.iter_mut().flatten() {
+            *score = if max > min { (*score - min) / (max - min) } else { 1.0 };
+        }
+    }
+    scores
+}
+
+/// Write [`calibrated_scores`] into each row as [`SCORE_ATTRIBUTE`].
+pub fn annotate_scores(rows: &mut [Row], source: ScoreSource, min_max: bool) {
+    let scores = calibrated_scores(rows, source, min_max);
+    for (row, score) in rows.iter_mut().zip(scores) {
+        if let Some(score) = score {
+            row.insert(SCORE_ATTRIBUTE.to_string(), score.into());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows(dists: &[Option<f64>]) -> Vec<Row> {
+        dists
+            .iter()
+            .enumerate()
+            .map(|(id, dist)| {
+                let mut row: Row = serde_json::from_value(serde_json::json!({"id": id})).unwrap();
+                if let Some(dist) = dist {
+                    row.insert("$dist".to_string(), (*dist).into());
+                }
+                row
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_similarity_per_metric() {
+        assert_eq!(similarity(0.0, DistanceMetric::CosineDistance), 1.0);
+        assert_eq!(similarity(2.0, DistanceMetric::CosineDistance), -1.0);
+        assert_eq!(similarity(0.0, DistanceMetric::EuclideanSquared), 1.0);
+        assert_eq!(similarity(3.0, DistanceMetric::EuclideanSquared), 0.25);
+    }
+
+    #[test]
+    fn test_calibrated_scores() {
+        let rows = rows(&[Some(0.1), Some(0.5), None, Some(0.3)]);
+        let cosine = ScoreSource::Vector(DistanceMetric::CosineDistance);
+        let rounded = |scores: Vec<Option<f64>>| -> Vec<Option<f64>> {
+            scores.into_iter().map(|s| s.map(|s| (s * 1e6).round() / 1e6)).collect()
+        };
+        assert_eq!(rounded(calibrated_scores(&rows, cosine, false)), [Some(0.9), Some(0.5), None, Some(0.7)]);
+        assert_eq!(rounded(calibrated_scores(&rows, cosine, true)), [Some(1.0), Some(0.0), None, Some(0.5)]);
+        assert_eq!(rounded(calibrated_scores(&rows, ScoreSource::Bm25, true)), [Some(0.0), Some(1.0), None, Some(0.5)]);
+        assert_eq!(calibrated_scores(&rows[..1], ScoreSource::Bm25, true), [Some(1.0)]);
+    }
+
+    #[test]
+    fn test_annotate_scores() {
+        let mut rows = rows(&[Some(1.0), None]);
+        annotate_scores(&mut rows, ScoreSource::Vector(DistanceMetric::EuclideanSquared), false);
+        assert_eq!(rows[0][SCORE_ATTRIBUTE], 0.5);
+        assert!(!rows[1].contains_key(SCORE_ATTRIBUTE));
+    }
+}
@@ -0,0 +1,101 @@
+/// Limits for [`chunk_text`], measured in characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkParams {
+    pub max_chars: usize,
+    /// Characters repeated from the end of one chunk at the start of the next.
+    pub overlap: usize,
+}
+
+impl Default for ChunkParams {
+    fn default() -> Self {
+        Self { max_chars: 1000, overlap: 100 }
+    }
+}
+
+/// Split text into chunks of at most `max_chars` characters.
+///
+/// Chunks break at the last whitespace before the limit when there is one,
+/// so words are only split when a single word exceeds `max_chars`.
+pub fn chunk_text(text: &str, params: &ChunkParams) -> Vec<String> {
+    let max_chars = params.max_chars.max(1);
+    let overlap = params.overlap.min(max_chars - 1);
+    let chars: Vec<char> = text.chars().collect();
+    let mut chunks = Vec::new();
+    let mut start = 0;
+
+    while start < chars.len() {
+        let mut end = (start + max_chars).min(chars.len());
+        if end < chars.len()
+            && let Some(space) = chars[start..end].iter().rposition(|c| c.is_whitespace())
+            && space > 0
+        {
+            end = start + space;
+        }
+
+        let chunk: String = chars[start..end].iter().collect();
+        let trimmed = chunk.trim();
+        if !trimmed.is_empty() {
+            chunks.push(trimmed.to_string());
+        }
+        if end == chars.len() {
+            break;
+        }
+
+        let next = end.saturating_sub(overlap).max(start + 1);
+        // Don't resume mid-word when overlapping.
+        start = match chars[next..end].iter().position(|c| c.is_whitespace()) {
+            Some(offset) if next > start + 1 && overlap > 0 => next + offset,
+            _ => next,
+        };
+        while start < chars.len() && chars[start].is_whitespace() {
+            start += 1;
+        }
+    }
+
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_short_text_is_one_chunk() {
+        let chunks = chunk_text("hello world", &ChunkParams::default());
+        assert_eq!(chunks, vec!["hello world"]);
+    }
+
+    #[test]
+    fn test_breaks_on_whitespace() {
+        let params = ChunkParams { max_chars: 12, overlap: 0 };
+        let chunks = chunk_text("the quick brown fox jumps over", &params);
+        assert_eq!(chunks, vec!["the quick", "brown fox", "jumps over"]);
+        assert!(chunks.iter().all(|c| c.chars().count() <= 12));
+    }
+
+    #[test]
+    fn test_overlap_repeats_trailing_words() {
+        let params = ChunkParams { max_chars: 12, overlap: 6 };
+        let chunks = chunk_text("the quick brown fox jumps over", &params);
+        assert_eq!(chunks[0], "the quick");
+        assert!(chunks[1].starts_with("quick"), "{chunks:?}");
+        assert_eq!(chunks.last().unwrap(), "jumps over");
+    }
+
+    #[test]
+    fn test_long_word_is_split() {
+        let params = ChunkParams { max_chars: 4, overlap: 0 };
+        assert_eq!(chunk_text("abcdefghij", &params), vec!["abcd", "efgh", "ij"]);
+    }
+
+    #[test]
+    fn test_multibyte_characters() {
+        let params = ChunkParams { max_chars: 3, overlap: 0 };
+        assert_eq!(chunk_text("héllo", &params), vec!["hél", "lo"]);
+    }
+
+    #[test]
+    fn test_empty_text() {
+        assert!(chunk_text("   ", &ChunkParams::default()).is_empty());
+    }
+}
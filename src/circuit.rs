@@ -0,0 +1,405 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use crate::metrics::path_template;
+use crate::rt::Instant;
+use crate::{Error, Result};
+
+/// Fails requests fast while turbopuffer keeps failing them, instead of
+/// piling more load onto a struggling namespace; register with
+/// [`Client::with_circuit_breaker`](crate::Client::with_circuit_breaker).
+///
+/// Each namespace has its own circuit; requests that aren't for a namespace,
+/// such as listing namespaces, share one per endpoint. A circuit opens once
+/// at least [`min_requests`](Self::min_requests) attempts were made within
+/// [`window`](Self::window) and at least a [`failure_rate`](Self::failure_rate)
+/// fraction of them failed with a 5xx, a timeout or a connection error. Rate
+/// limits (429) and other 4xx responses don't count as failures.
+///
+/// While open, requests fail immediately with [`Error::CircuitOpen`]. After
+/// [`cooldown`](Self::cooldown) the circuit is half-open: up to
+/// [`half_open_probes`](Self::half_open_probes) requests go through as
+/// probes, and the circuit closes once that many succeed, or opens again on
+/// the first failure.
+///
+/// Clones share their circuits, so a clone kept after registering can report
+/// [`state`](Self::state). Closed circuits without attempts in the window are
+/// dropped, so a client used across many namespaces doesn't keep one for each.
+#[derive(Debug, Clone)]
+pub struct CircuitBreaker {
+    failure_rate: f64,
+    min_requests: usize,
+    window: Duration,
+    cooldown: Duration,
+    half_open_probes: u32,
+    circuits: Arc<Mutex<Circuits>>,
+}
+
+#[derive(Debug, Default)]
+struct Circuits {
+    by_scope: HashMap<String, Circuit>,
+    /// When idle circuits were last dropped.
+    swept_at: Option<Instant>,
+}
+
+/// The state of one circuit; see [`CircuitBreaker::state`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Requests are sent normally.
+    Closed,
+    /// Requests fail fast until the cooldown ends, in `retry_after`.
+    Open { retry_after: Duration },
+    /// Probe requests are sent to test whether the failures are over.
+    HalfOpen,
+}
+
+#[derive(Debug, Default)]
+struct Circuit {
+    state: State,
+    /// Attempt outcomes while closed, oldest first: `true` for a failure.
+    outcomes: VecDeque<(Instant, bool)>,
+    /// Bumped on every state change, so late outcomes don't count towards
+    /// the wrong state.
+    generation: u64,
+}
+
+#[derive(Debug, Default)]
+enum State {
+    #[default]
+    Closed,
+    Open { until: Instant },
+    HalfOpen { probes: u32, successes: u32 },
+}
+
+/// Leave to send one attempt; report its outcome with [`record`](Self::record).
+pub(crate) struct Permit {
+    breaker: CircuitBreaker,
+    scope: String,
+    generation: u64,
+    probe: bool,
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CircuitBreaker {
+    /// A breaker that opens when half of at least 20 attempts within 30
+    /// seconds fail, and probes with one request after a 30 second cooldown.
+    pub fn new() -> Self {
+        Self {
+            failure_rate: 0.5,
+            min_requests: 20,
+            window: Duration::from_secs(30),
+            cooldown: Duration::from_secs(30),
+            half_open_probes: 1,
+            circuits: Arc::default(),
+        }
+    }
+
+    /// Fraction of attempts in the window that must fail to open the circuit.
+    pub fn failure_rate(mut self, rate: f64) -> Self {
+        self.failure_rate = rate;
+        self
+    }
+
+    /// Attempts the window must hold before the failure rate is considered,
+    /// so a few failures after a quiet period don't open the circuit.
+    pub fn min_requests(mut self, min: usize) -> Self {
+        self.min_requests = min.max(1);
+        self
+    }
+
+    /// How far back attempts count towards the failure rate.
+    pub fn window(mut self, window: Duration) -> Self {
+        self.window = window;
+        self
+    }
+
+    /// How long an open circuit fails requests before probing.
+    pub fn cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldown = cooldown;
+        self
+    }
+
+    /// Probes let through at once while half-open, and the successes needed
+    /// to close the circuit again.
+    pub fn half_open_probes(mut self, probes: u32) -> Self {
+        self.half_open_probes = probes.max(1);
+        self
+    }
+
+    /// The state of the circuit for `namespace`, or for an endpoint path
+    /// such as `/v1/namespaces`.
+    pub fn state(&self, namespace: &str) -> CircuitState {
+        let now = Instant::now();
+        match self.lock().by_scope.get(namespace).map(|circuit| &circuit.state) {
+            Some(State::Open { until }) if *until > now => CircuitState::Open { retry_after: *until - now },
+            Some(State::Open { .. } | State::HalfOpen { .. }) => CircuitState::HalfOpen,
+            Some(State::Closed) | None => CircuitState::Closed,
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Circuits> {
+        self.circuits.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Ask to send an attempt for `path`, failing with [`Error::CircuitOpen`]
+    /// if its circuit is open or already has every probe in flight.
+    pub(crate) fn acquire(&self, path: &str) -> Result<Permit> {
+        self.acquire_at(scope(path), Instant::now())
+    }
+
+    fn acquire_at(&self, scope: String, now: Instant) -> Result<Permit> {
+        let mut circuits = self.lock();
+        let circuit = circuits.by_scope.entry(scope.clone()).or_default();
+        if let State::Open { until } = circuit.state {
+            if until > now {
+                return Err(Error::CircuitOpen { scope, retry_after: until - now });
+            }
+            circuit.set(State::HalfOpen { probes: 0, successes: 0 });
+        }
+        let probe = match &mut circuit.state {
+            State::HalfOpen { probes, .. } if *probes >= self.half_open_probes => {
+                return Err(Error::CircuitOpen { scope, retry_after: Duration::ZERO });
+            }
+            State::HalfOpen { probes, .. } => {
+                *probes += 1;
+                true
+            }
+            _ => false,
+        };
+        let generation = circuit.generation;
+        drop(circuits);
+        Ok(Permit { breaker: self.clone(), scope, generation, probe })
+    }
+
+    fn record_at(&self, permit: &Permit, failed: bool, now: Instant) {
+        let mut circuits = self.lock();
+        let Some(circuit) = circuits.by_scope.get_mut(&permit.scope) else { return };
+        if circuit.generation != permit.generation {
+            // Sent before the circuit last changed state.
+            return;
+        }
+        match &mut circuit.state {
+            State::HalfOpen { probes, successes } => {
+                *probes -= 1;
+                if failed {
+                    circuit.set(State::Open { until: now + self.cooldown });
+                } else {
+                    *successes += 1;
+                    if *successes >= self.half_open_probes {
+                        circuit.set(State::Closed);
+                    }
+                }
+            }
+            State::Closed => {
+                circuit.outcomes.push_back((now, failed));
+                while circuit.outcomes.front().is_some_and(|(at, _)| now.duration_since(*at) > self.window) {
+                    circuit.outcomes.pop_front();
+                }
+                let failures = circuit.outcomes.iter().filter(|(_, failed)| *failed).count();
+                if circuit.outcomes.len() >= self.min_requests
+                    && failures as f64 >= self.failure_rate * circuit.outcomes.len() as f64
+                {
+                    circuit.set(State::Open { until: now + self.cooldown });
+                }
+            }
+            State::Open { .. } => {}
+        }
+        self.sweep(&mut circuits, now);
+    }
+
+    /// Drop closed circuits with no attempts in the window, at most once per
+    /// window; a new attempt starts them afresh in the same state.
+    fn sweep(&self, circuits: &mut Circuits, now: Instant) {
+        if circuits.swept_at.is_some_and(|at| now.duration_since(at) < self.window) {
+            return;
+        }
+        circuits.swept_at = Some(now);
+        circuits.by_scope.retain(|_, circuit| {
+            !matches!(circuit.state, State::Closed)
+                || circuit.outcomes.back().is_some_and(|(at, _)| now.duration_since(*at) <= self.window)
+        });
+    }
+
+    /// Free a probe that was abandoned before its outcome was known.
+    fn release(&self, permit: &Permit) {
+        if let Some(circuit) = self.lock().by_scope.get_mut(&permit.scope)
+            && circuit.generation == permit.generation
+            && let State::HalfOpen { probes, .. } = &mut circuit.state
+        {
+            *probes -= 1;
+        }
+    }
+}
+
+impl Circuit {
+    fn set(&mut self, state: State) {
+        self.state = state;
+        self.outcomes.clear();
+        self.generation += 1;
+    }
+}
+
+impl Permit {
+    /// Count the attempt's outcome towards its circuit.
+    pub(crate) fn record<T>(self, result: &Result<T>) {
+        let failed = result.as_ref().is_err_and(is_failure);
+        self.finish(failed, Instant::now());
+    }
+
+    fn finish(mut self, failed: bool, now: Instant) {
+        self.breaker.record_at(&self, failed, now);
+        self.probe = false;
+    }
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        if self.probe {
+            self.breaker.release(self);
+        }
+    }
+}
+
+/// The namespace a request is for, or its endpoint if it isn't for one.
+fn scope(path: &str) -> String {
+    let (template, namespace) = path_template(path);
+    namespace.unwrap_or(template)
+}
+
+/// Server errors and transport failures; rate limits mean the server is
+/// healthy enough to push back.
+fn is_failure(e: &Error) -> bool {
+    e.is_retryable() && !matches!(e, Error::RateLimited(_))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Client;
+
+    const FAILURE: bool = true;
+    const SUCCESS: bool = false;
+
+    fn breaker() -> CircuitBreaker {
+        CircuitBreaker::new().failure_rate(0.5).min_requests(4).cooldown(Duration::from_secs(10))
+    }
+
+    fn attempt(breaker: &CircuitBreaker, failed: bool, now: Instant) -> Result<()> {
+        breaker.acquire_at("docs".to_string(), now)?.finish(failed, now);
+        Ok(())
+    }
+
+    #[test]
+    fn test_opens_at_failure_rate_and_recovers_through_probes() {
+        let breaker = breaker();
+        let now = Instant::now();
+        for failed in [FAILURE, SUCCESS, FAILURE] {
+            attempt(&breaker, failed, now).unwrap();
+        }
+        assert_eq!(breaker.state("docs"), CircuitState::Closed);
+        attempt(&breaker, SUCCESS, now).unwrap();
+        assert!(matches!(breaker.state("docs"), CircuitState::Open { .. }));
+
+        let err = attempt(&breaker, SUCCESS, now + Duration::from_secs(4)).unwrap_err();
+        let Error::CircuitOpen { scope, retry_after } = err else { panic!("expected an open circuit, got {:?}", err) };
+        assert_eq!((scope.as_str(), retry_after), ("docs", Duration::from_secs(6)));
+        assert_eq!(breaker.state("other"), CircuitState::Closed);
+
+        // A failed probe reopens the circuit for another cooldown.
+        let later = now + Duration::from_secs(10);
+        attempt(&breaker, FAILURE, later).unwrap();
+        assert!(attempt(&breaker, SUCCESS, later + Duration::from_secs(9)).is_err());
+        attempt(&breaker, SUCCESS, later + Duration::from_secs(10)).unwrap();
+        assert_eq!(breaker.state("docs"), CircuitState::Closed);
+    }
+
+    #[test]
+    fn test_idle_closed_circuits_are_dropped() {
+        let breaker = breaker().window(Duration::from_secs(60));
+        let now = Instant::now();
+        for scope in ["a", "b"] {
+            breaker.acquire_at(scope.to_string(), now).unwrap().finish(SUCCESS, now);
+        }
+        for _ in 0..4 {
+            attempt(&breaker, FAILURE, now).unwrap();
+        }
+        assert_eq!(breaker.lock().by_scope.len(), 3);
+
+        let later = now + Duration::from_secs(61);
+        breaker.acquire_at("b".to_string(), later).unwrap().finish(SUCCESS, later);
+        let mut scopes: Vec<_> = breaker.lock().by_scope.keys().cloned().collect();
+        scopes.sort();
+        assert_eq!(scopes, ["b", "docs"]);
+        assert!(matches!(breaker.state("docs"), CircuitState::Open { .. } | CircuitState::HalfOpen));
+    }
+
+    #[test]
+    fn test_old_failures_leave_the_window() {
+        let breaker = breaker().window(Duration::from_secs(60));
+        let now = Instant::now();
+        for _ in 0..3 {
+            attempt(&breaker, FAILURE, now).unwrap();
+        }
+        attempt(&breaker, FAILURE, now + Duration::from_secs(61)).unwrap();
+        assert_eq!(breaker.state("docs"), CircuitState::Closed);
+    }
+
+    #[test]
+    fn test_half_open_limits_probes() {
+        let breaker = breaker().min_requests(1);
+        let now = Instant::now();
+        attempt(&breaker, FAILURE, now).unwrap();
+
+        let later = now + Duration::from_secs(10);
+        let probe = breaker.acquire_at("docs".to_string(), later).unwrap();
+        assert!(breaker.acquire_at("docs".to_string(), later).is_err());
+        drop(probe);
+        assert!(breaker.acquire_at("docs".to_string(), later).is_ok());
+    }
+
+    #[test]
+    fn test_scopes_and_failures() {
+        assert_eq!(scope("/v2/namespaces/docs/query"), "docs");
+        assert_eq!(scope("/v1/namespaces?prefix=a"), "/v1/namespaces");
+        assert!(is_failure(&Error::Api { status: 503, message: String::new() }));
+        assert!(!is_failure(&Error::Api { status: 404, message: String::new() }));
+        assert!(!is_failure(&Error::RateLimited(crate::RateLimit { retry_after: None, message: String::new() })));
+    }
+
+    #[derive(Default)]
+    struct Unavailable(std::sync::atomic::AtomicUsize);
+
+    impl crate::HttpTransport for Unavailable {
+        fn send(&self, _req: reqwest::Request) -> crate::rt::BoxFuture<'_, Result<reqwest::Response>> {
+            self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            let resp = http::Response::builder().status(503).body("unavailable").unwrap();
+            Box::pin(async move { Ok(reqwest::Response::from(resp)) })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_client_fails_fast_while_open() {
+        let transport = Arc::new(Unavailable::default());
+        let breaker = CircuitBreaker::new().min_requests(3);
+        let client = Client::new("key")
+            .with_retry_policy(crate::RetryPolicy::new(0))
+            .with_circuit_breaker(breaker.clone())
+            .with_transport(transport.clone());
+        let ns = client.namespace("docs");
+        for _ in 0..5 {
+            let _ = ns.metadata().await;
+        }
+        assert_eq!(transport.0.load(std::sync::atomic::Ordering::SeqCst), 3);
+        assert!(matches!(ns.metadata().await, Err(Error::CircuitOpen { .. })));
+        assert!(matches!(breaker.state("docs"), CircuitState::Open { .. }));
+
+        let other = client.namespace("other").metadata().await.unwrap_err();
+        assert!(matches!(other, Error::Api { status: 503, .. }));
+    }
+}
@@ -1,6 +1,24 @@
-use crate::{Error, Namespace, NamespacesResponse, Result};
+use std::sync::Arc;
+use std::time::Duration;
+
+use bytes::Bytes;
+use reqwest::StatusCode;
+use reqwest::header::{CONTENT_TYPE, HeaderMap, HeaderName, HeaderValue, RETRY_AFTER};
+use tokio::sync::Semaphore;
+
+use crate::auth::{ApiKeyProvider, StaticKey};
+use crate::circuit::CircuitBreaker;
+use crate::error::decode;
+use crate::metrics::{ExhaustiveSearchAlert, ExhaustiveSearchEvent, MetricsObserver, RequestMetrics};
+use crate::middleware::{Middleware, RequestParts, ResponseParts};
+use crate::transport::{self, HttpConfig, HttpTransport, IpPreference};
+use crate::usage::UsageMeter;
+use crate::retry;
+use crate::rt::{self, Instant, SystemTime};
+use crate::{CallOptions, Error, Namespace, NamespacesResponse, RateLimit, Result, RetryPolicy};
 
 const DEFAULT_BASE_URL: &str = "https://api.turbopuffer.com";
+const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
 
 #[derive(Debug, Clone, Default, serde::Serialize)]
 pub struct NamespacesParams {
@@ -14,36 +32,116 @@ pub struct NamespacesParams {
     pub page_size: Option<u32>,
 }
 
+/// A turbopuffer client.
+///
+/// Cloning is cheap: clones share the configuration, connection pool and
+/// [`with_max_in_flight_requests`](Self::with_max_in_flight_requests) limit,
+/// so pass clones around, e.g. in an axum state struct. The `with_*`
+/// methods change only the handle they are called on.
+#[derive(Clone)]
 pub struct Client {
-    pub(crate) api_key: String,
+    pub(crate) inner: Arc<ClientInner>,
+}
+
+#[derive(Clone)]
+pub(crate) struct ClientInner {
+    api_key: Arc<dyn ApiKeyProvider>,
     pub(crate) base_url: String,
     pub(crate) http: reqwest::Client,
+    http_config: HttpConfig,
+    /// Replaces `http` for sending; `http` still builds the requests.
+    transport: Option<Arc<dyn HttpTransport>>,
+    /// Sent with every request, before middleware runs.
+    headers: HeaderMap,
+    pub(crate) timeout: Option<Duration>,
+    max_response_bytes: Option<u64>,
+    /// Shared by clones, so it bounds every handle made from this client.
+    in_flight: Option<Arc<Semaphore>>,
+    circuit_breaker: Option<CircuitBreaker>,
+    pub(crate) retry_policy: RetryPolicy,
+    pub(crate) metrics: Option<Arc<dyn MetricsObserver>>,
+    pub(crate) middleware: Vec<Arc<dyn Middleware>>,
+    pub(crate) exhaustive_search_alert: Option<Arc<ExhaustiveSearchAlert>>,
+    pub(crate) usage_meter: Option<UsageMeter>,
+    #[cfg(feature = "fault-injection")]
+    pub(crate) faults: Option<Arc<crate::faults::FaultInjector>>,
+}
+
+/// A request body, built once so retries resend the same content.
+#[derive(Clone)]
+pub(crate) enum Payload {
+    Json(Bytes),
+    /// Makes a fresh streaming body for each attempt.
+    #[cfg(feature = "stream-writes")]
+    Stream(Arc<dyn Fn() -> reqwest::Body + Send + Sync>),
+}
+
+impl Payload {
+    pub(crate) fn json<T: serde::Serialize + ?Sized>(body: &T) -> Result<Self> {
+        Ok(Payload::Json(Bytes::from(serde_json::to_vec(body)?)))
+    }
+
+    /// The body, if it is held in memory.
+    fn bytes(&self) -> Option<Bytes> {
+        match self {
+            Payload::Json(bytes) => Some(bytes.clone()),
+            #[cfg(feature = "stream-writes")]
+            Payload::Stream(_) => None,
+        }
+    }
+}
+
+/// What happened while sending one logical request, across retries.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct RequestStats {
+    pub retries: u32,
+    /// Responses with status 429.
+    pub throttled: u32,
+    pub request_bytes: u64,
 }
 
 impl Client {
     pub fn new(api_key: impl Into<String>) -> Self {
-        Self {
-            api_key: api_key.into(),
-            base_url: DEFAULT_BASE_URL.to_string(),
-            http: reqwest::Client::new(),
-        }
+        Self::with_base_url(api_key, DEFAULT_BASE_URL)
     }
 
     pub fn with_region(api_key: impl Into<String>, region: &str) -> Self {
-        let base_url = format!("https://{}.turbopuffer.com", region);
-        Self {
-            api_key: api_key.into(),
-            base_url,
-            http: reqwest::Client::new(),
-        }
+        Self::with_base_url(api_key, format!("https://{}.turbopuffer.com", region))
     }
 
     pub fn with_base_url(api_key: impl Into<String>, base_url: impl Into<String>) -> Self {
-        Self {
-            api_key: api_key.into(),
+        let http_config = HttpConfig { connect_timeout: Some(DEFAULT_CONNECT_TIMEOUT), ..Default::default() };
+        let inner = ClientInner {
+            api_key: Arc::new(StaticKey(api_key.into())),
             base_url: base_url.into(),
-            http: reqwest::Client::new(),
-        }
+            http: transport::http_client(&http_config),
+            http_config,
+            transport: None,
+            headers: HeaderMap::new(),
+            timeout: None,
+            max_response_bytes: None,
+            in_flight: None,
+            circuit_breaker: None,
+            retry_policy: RetryPolicy::default(),
+            metrics: None,
+            middleware: Vec::new(),
+            exhaustive_search_alert: None,
+            usage_meter: None,
+            #[cfg(feature = "fault-injection")]
+            faults: None,
+        };
+        Self { inner: Arc::new(inner) }
+    }
+
+    // Copies the configuration first if other clones share it.
+    fn inner_mut(&mut self) -> &mut ClientInner {
+        Arc::make_mut(&mut self.inner)
+    }
+
+    /// Configure a client with several settings at once, e.g. a region,
+    /// timeouts and a proxy.
+    pub fn builder() -> ClientBuilder {
+        ClientBuilder::default()
     }
 
     pub fn from_env() -> Result<Self> {
@@ -57,15 +155,166 @@ impl Client {
             .map(|r| format!("https://{}.turbopuffer.com", r))
             .unwrap_or_else(|_| DEFAULT_BASE_URL.to_string());
 
-        Ok(Self {
-            api_key,
-            base_url,
-            http: reqwest::Client::new(),
-        })
+        Ok(Self::with_base_url(api_key, base_url))
+    }
+
+    /// Ask `provider` for the API key before every request attempt instead
+    /// of using the fixed one, so rotated keys are picked up without
+    /// rebuilding the client; see [`ApiKeyProvider`].
+    pub fn with_api_key_provider(mut self, provider: impl ApiKeyProvider + 'static) -> Self {
+        self.inner_mut().api_key = Arc::new(provider);
+        self
+    }
+
+    /// Retry policy used by every call that doesn't override it with [`CallOptions`].
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.inner_mut().retry_policy = policy;
+        self
+    }
+
+    /// Which IP versions to connect over. Use [`IpPreference::Ipv4Only`] or
+    /// [`IpPreference::PreferIpv4`] where IPv6 is broken, e.g. dual-stack
+    /// networks where IPv6 connects hang for seconds before falling back.
+    /// Ignored on wasm32, where the runtime's `fetch` connects.
+    pub fn with_ip_preference(mut self, preference: IpPreference) -> Self {
+        let inner = self.inner_mut();
+        inner.http_config.ip_preference = preference;
+        inner.http = transport::http_client(&inner.http_config);
+        self
     }
 
-    pub fn namespace(&self, name: impl Into<String>) -> Namespace<'_> {
-        Namespace::new(self, name.into())
+    /// Limit on establishing each connection; `None` waits as long as the OS
+    /// does. Defaults to 10 seconds. Ignored on wasm32.
+    pub fn with_connect_timeout(mut self, timeout: Option<Duration>) -> Self {
+        let inner = self.inner_mut();
+        inner.http_config.connect_timeout = timeout;
+        inner.http = transport::http_client(&inner.http_config);
+        self
+    }
+
+    /// Whether to ask for gzip-compressed responses, which are decompressed
+    /// transparently. On by default; large responses such as full-attribute
+    /// scans with vectors shrink considerably. On wasm32 the runtime's
+    /// `fetch` negotiates compression instead.
+    #[cfg(feature = "gzip")]
+    pub fn with_gzip(mut self, enabled: bool) -> Self {
+        let inner = self.inner_mut();
+        inner.http_config.gzip = enabled;
+        inner.http = transport::http_client(&inner.http_config);
+        self
+    }
+
+    /// Send requests with `http`, e.g. one shared with the rest of an
+    /// application or configured with an organization's proxy and TLS
+    /// settings. Connection settings such as
+    /// [`with_connect_timeout`](Self::with_connect_timeout) and
+    /// [`with_ip_preference`](Self::with_ip_preference) don't apply to it.
+    pub fn with_http_client(self, http: reqwest::Client) -> Self {
+        self.with_transport(http)
+    }
+
+    /// Send requests through `transport` instead of the built-in HTTP
+    /// client, e.g. to use another HTTP stack. As with
+    /// [`with_http_client`](Self::with_http_client), connection settings
+    /// don't apply to it.
+    pub fn with_transport(mut self, transport: impl HttpTransport + 'static) -> Self {
+        self.inner_mut().transport = Some(Arc::new(transport));
+        self
+    }
+
+    /// Limit on each request attempt, from connecting until the response body
+    /// is read. Unlimited by default; override per call with
+    /// [`CallOptions::timeout`]. A timed-out attempt is retried like any other
+    /// transient failure.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.inner_mut().timeout = Some(timeout);
+        self
+    }
+
+    /// Abandon responses whose body is larger than `bytes`, failing the call
+    /// with [`Error::ResponseTooLarge`] instead of buffering it, e.g. to
+    /// protect a service from an accidental `include_attributes: All` over
+    /// large vectors. Unlimited by default; override per call with
+    /// [`CallOptions::max_response_bytes`].
+    pub fn with_max_response_bytes(mut self, bytes: u64) -> Self {
+        self.inner_mut().max_response_bytes = Some(bytes);
+        self
+    }
+
+    /// Send at most `limit` requests at once, across this client and its
+    /// clones; further calls wait for a slot. Keeps bursts, e.g. hundreds of
+    /// writes awaited with `join_all`, from opening a connection each and
+    /// tripping rate limits. A call holds its slot until its response is
+    /// read, retries included. Unlimited by default.
+    pub fn with_max_in_flight_requests(mut self, limit: usize) -> Self {
+        self.inner_mut().in_flight = Some(Arc::new(Semaphore::new(limit.max(1))));
+        self
+    }
+
+    /// Fail requests fast with [`Error::CircuitOpen`] while a namespace
+    /// keeps failing, instead of sending more; see [`CircuitBreaker`]. Off by
+    /// default. Each attempt, retries included, counts towards the circuit.
+    pub fn with_circuit_breaker(mut self, breaker: CircuitBreaker) -> Self {
+        self.inner_mut().circuit_breaker = Some(breaker);
+        self
+    }
+
+    /// Send `value` as header `name` with every request, e.g. an org id or a
+    /// routing header for an internal proxy. Middleware sees it and may
+    /// change it; override per call with [`CallOptions::header`].
+    pub fn with_header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.inner_mut().headers.append(name, value);
+        self
+    }
+
+    /// Report metrics such as per-request latency and per-batch write stats
+    /// to `observer`.
+    pub fn with_metrics_observer(mut self, observer: impl MetricsObserver + 'static) -> Self {
+        self.inner_mut().metrics = Some(Arc::new(observer));
+        self
+    }
+
+    /// Call `callback` whenever a query reports an `exhaustive_search_count`
+    /// above `threshold`, i.e. it silently fell back to scanning instead of
+    /// using an index. Runs inline on the request path.
+    pub fn with_exhaustive_search_alert(
+        mut self,
+        threshold: u64,
+        callback: impl Fn(&ExhaustiveSearchEvent<'_>) + Send + Sync + 'static,
+    ) -> Self {
+        self.inner_mut().exhaustive_search_alert = Some(Arc::new(ExhaustiveSearchAlert { threshold, callback: Box::new(callback) }));
+        self
+    }
+
+    /// Add the billing of every query to `meter`, e.g. for a
+    /// [`UsageRecorder`](crate::usage::UsageRecorder).
+    pub fn with_usage_meter(mut self, meter: UsageMeter) -> Self {
+        self.inner_mut().usage_meter = Some(meter);
+        self
+    }
+
+    /// Inject faults into every request attempt; see [`crate::faults`].
+    #[cfg(feature = "fault-injection")]
+    pub fn with_fault_injection(mut self, injector: crate::faults::FaultInjector) -> Self {
+        self.inner_mut().faults = Some(Arc::new(injector));
+        self
+    }
+
+    /// Run `middleware` on every outgoing request and its response, after
+    /// any added earlier.
+    pub fn with_middleware(mut self, middleware: impl Middleware + 'static) -> Self {
+        self.inner_mut().middleware.push(Arc::new(middleware));
+        self
+    }
+
+    /// A handle to namespace `name`, holding a clone of this client.
+    pub fn namespace(&self, name: impl Into<String>) -> Namespace {
+        Namespace::new(self.clone(), name.into())
+    }
+
+    #[deprecated(note = "namespaces own their client; use `Client::namespace`")]
+    pub fn namespace_owned(&self, name: impl Into<String>) -> Namespace {
+        self.namespace(name)
     }
 
     pub async fn namespaces(&self, params: NamespacesParams) -> Result<NamespacesResponse> {
@@ -89,25 +338,292 @@ impl Client {
         self.request_no_body(reqwest::Method::GET, &path).await
     }
 
+    /// Check that the configured region is reachable and accepts the API
+    /// key, returning the round-trip time, e.g. for readiness probes and
+    /// startup checks. Lists at most one namespace and touches none.
+    ///
+    /// Makes a single attempt so an outage is reported promptly; use
+    /// [`ping_with_options`](Self::ping_with_options) to retry or bound it.
+    pub async fn ping(&self) -> Result<Duration> {
+        self.ping_with_options(&CallOptions::no_retry()).await
+    }
+
+    pub async fn ping_with_options(&self, opts: &CallOptions) -> Result<Duration> {
+        let start = Instant::now();
+        self.request_with::<(), serde::de::IgnoredAny>(
+            reqwest::Method::GET,
+            "/v1/namespaces?page_size=1",
+            None,
+            opts,
+            true,
+        )
+        .await?;
+        Ok(start.elapsed())
+    }
+
+    /// Call an arbitrary endpoint, e.g. one this crate does not model yet.
+    ///
+    /// `path` is relative to the base URL (`/v2/namespaces/foo/query`). An empty
+    /// response body is returned as `Value::Null`.
+    pub async fn raw_request(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+        body: Option<&serde_json::Value>,
+    ) -> Result<serde_json::Value> {
+        let bytes = self.request_bytes(method, path, body).await?;
+        if bytes.is_empty() {
+            return Ok(serde_json::Value::Null);
+        }
+        decode(&bytes)
+    }
+
     pub(crate) async fn request<T, R>(&self, method: reqwest::Method, path: &str, body: Option<&T>) -> Result<R>
     where
         T: serde::Serialize + ?Sized,
         R: serde::de::DeserializeOwned,
     {
-        let url = format!("{}{}", self.base_url, path);
+        self.request_with(method, path, body, &CallOptions::default(), true).await
+    }
+
+    /// `idempotent: false` stops retries once the request may have reached the
+    /// server, so it is never applied twice.
+    pub(crate) async fn request_with<T, R>(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+        body: Option<&T>,
+        opts: &CallOptions,
+        idempotent: bool,
+    ) -> Result<R>
+    where
+        T: serde::Serialize + ?Sized,
+        R: serde::de::DeserializeOwned,
+    {
+        self.request_with_stats(method, path, body, opts, idempotent, &mut RequestStats::default())
+            .await
+    }
+
+    pub(crate) async fn request_with_stats<T, R>(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+        body: Option<&T>,
+        opts: &CallOptions,
+        idempotent: bool,
+        stats: &mut RequestStats,
+    ) -> Result<R>
+    where
+        T: serde::Serialize + ?Sized,
+        R: serde::de::DeserializeOwned,
+    {
+        let payload = body.map(Payload::json).transpose()?;
+        self.request_payload(method, path, payload, opts, idempotent, stats).await
+    }
 
-        let mut req = self.http
-            .request(method, &url)
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .header("Content-Type", "application/json");
+    pub(crate) async fn request_payload<R>(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+        payload: Option<Payload>,
+        opts: &CallOptions,
+        idempotent: bool,
+        stats: &mut RequestStats,
+    ) -> Result<R>
+    where
+        R: serde::de::DeserializeOwned,
+    {
+        decode(&self.send_bytes(method, path, payload, opts, idempotent, stats).await?)
+    }
 
-        if let Some(body) = body {
-            req = req.json(body);
+    pub(crate) async fn request_bytes<T>(&self, method: reqwest::Method, path: &str, body: Option<&T>) -> Result<Bytes>
+    where
+        T: serde::Serialize + ?Sized,
+    {
+        let payload = body.map(Payload::json).transpose()?;
+        self.send_bytes(method, path, payload, &CallOptions::default(), true, &mut RequestStats::default())
+            .await
+    }
+
+    pub(crate) async fn request_no_body<R>(&self, method: reqwest::Method, path: &str) -> Result<R>
+    where
+        R: serde::de::DeserializeOwned,
+    {
+        self.request::<(), R>(method, path, None).await
+    }
+
+    // Sends the request and reads the response body, reporting the call to
+    // the metrics observer.
+    async fn send_bytes(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+        body: Option<Payload>,
+        opts: &CallOptions,
+        idempotent: bool,
+        stats: &mut RequestStats,
+    ) -> Result<Bytes> {
+        let deadline = opts.deadline.map(|d| Instant::now() + d);
+        let acquire = async {
+            match &self.inner.in_flight {
+                Some(in_flight) => Some(in_flight.acquire().await.expect("the semaphore is never closed")),
+                None => None,
+            }
+        };
+        let _slot = match deadline {
+            Some(deadline) => rt::timeout_at(deadline, acquire).await.map_err(|_| Error::DeadlineExceeded)?,
+            None => acquire.await,
+        };
+        let start = Instant::now();
+        let limit = opts.max_response_bytes.or(self.inner.max_response_bytes);
+        let result = async {
+            let resp = self.send(method.clone(), path, body, opts, deadline, idempotent, stats).await?;
+            let status = resp.status();
+            Ok((status, read_body(resp, limit).await?))
+        };
+        let result: Result<(StatusCode, Bytes)> = match deadline {
+            Some(deadline) => rt::timeout_at(deadline, result).await.unwrap_or(Err(Error::DeadlineExceeded)),
+            None => result.await,
+        };
+        if let Some(observer) = &self.inner.metrics {
+            observer.on_request(&RequestMetrics::new(
+                method,
+                path,
+                start.elapsed(),
+                stats.retries,
+                stats.request_bytes,
+                &result,
+            ));
+        }
+        result.map(|(_, body)| body)
+    }
+
+    // Sends the request, retrying per the effective retry policy, and maps
+    // non-2xx responses to `Error::Api`. Retries that can't start before
+    // `deadline` aren't attempted.
+    #[allow(clippy::too_many_arguments)]
+    async fn send(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+        body: Option<Payload>,
+        opts: &CallOptions,
+        deadline: Option<Instant>,
+        idempotent: bool,
+        stats: &mut RequestStats,
+    ) -> Result<reqwest::Response> {
+        let retry = opts.retry.as_ref().unwrap_or(&self.inner.retry_policy);
+        let timeout = opts.timeout.or(self.inner.timeout);
+        stats.request_bytes = body.as_ref().and_then(Payload::bytes).map_or(0, |b| b.len() as u64);
+
+        loop {
+            let permit = match &self.inner.circuit_breaker {
+                Some(breaker) => Some(breaker.acquire(path)?),
+                None => None,
+            };
+            let start = Instant::now();
+            let result = self.send_once(method.clone(), path, body.clone(), &opts.headers, timeout).await;
+            if let Some(permit) = permit {
+                permit.record(&result);
+            }
+            if let Err(Error::RateLimited(_)) = &result {
+                stats.throttled += 1;
+            }
+            match result {
+                Err(e) if stats.retries < retry.max_retries
+                    && e.is_retryable()
+                    && (idempotent || !e.may_have_been_applied()) =>
+                {
+                    let Some(delay) = retry.delay(stats.retries, &e) else {
+                        return Err(e);
+                    };
+                    if deadline.is_some_and(|deadline| Instant::now() + delay >= deadline) {
+                        return Err(e);
+                    }
+                    rt::sleep(delay).await;
+                    stats.retries += 1;
+                }
+                Err(Error::Http(e)) if e.is_request() || transport::is_connect(&e) || e.is_timeout() => {
+                    let diagnosed = transport::diagnose(e, &self.inner.base_url, start.elapsed()).await;
+                    return Err(Error::Transport(Box::new(diagnosed)));
+                }
+                result => return result,
+            }
         }
+    }
+
+    async fn send_once(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+        body: Option<Payload>,
+        call_headers: &HeaderMap,
+        timeout: Option<Duration>,
+    ) -> Result<reqwest::Response> {
+        let mut headers = self.inner.headers.clone();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        // Replaces every value of a header the client also sets.
+        headers.extend(call_headers.clone());
+        let mut parts = RequestParts { method, path: path.to_string(), headers, body: body.as_ref().and_then(Payload::bytes) };
+        for middleware in &self.inner.middleware {
+            middleware.on_request(&mut parts)?;
+        }
+
+        let mut req = self.inner.http
+            .request(parts.method.clone(), format!("{}{}", self.inner.base_url, parts.path))
+            .headers(parts.headers.clone())
+            .header("Authorization", format!("Bearer {}", self.inner.api_key.api_key().await?));
 
-        let resp = req.send().await?;
+        match (parts.body.clone(), body) {
+            (Some(bytes), _) => req = req.body(bytes),
+            #[cfg(feature = "stream-writes")]
+            (None, Some(Payload::Stream(stream))) => req = req.body(stream()),
+            _ => {}
+        }
+        if let Some(timeout) = timeout {
+            req = req.timeout(timeout);
+        }
+
+        #[cfg(feature = "fault-injection")]
+        let fault = self.inner.faults.as_ref().map(|faults| faults.draw());
+        let start = Instant::now();
+        let sent: Result<reqwest::Response> = async {
+            #[cfg(feature = "fault-injection")]
+            if let Some(fault) = &fault {
+                fault.before_send().await?;
+            }
+            match &self.inner.transport {
+                Some(transport) => transport.send(req.build()?).await,
+                None => Ok(req.send().await?),
+            }
+        }
+        .await;
+        let resp = match sent {
+            Ok(resp) => resp,
+            Err(e) => {
+                for middleware in &self.inner.middleware {
+                    middleware.on_error(&parts, &e);
+                }
+                return Err(e);
+            }
+        };
         let status = resp.status();
+        if !self.inner.middleware.is_empty() {
+            let response = ResponseParts { status, headers: resp.headers().clone(), elapsed: start.elapsed() };
+            for middleware in &self.inner.middleware {
+                middleware.on_response(&parts, &response);
+            }
+        }
 
+        if status == StatusCode::TOO_MANY_REQUESTS {
+            let retry_after = resp
+                .headers()
+                .get(RETRY_AFTER)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| retry::parse_retry_after(v, SystemTime::now()));
+            let message = resp.text().await.unwrap_or_default();
+            return Err(Error::RateLimited(RateLimit { retry_after, message }));
+        }
         if !status.is_success() {
             let message = resp.text().await.unwrap_or_default();
             return Err(Error::Api {
@@ -116,14 +632,881 @@ impl Client {
             });
         }
 
-        let result = resp.json().await?;
-        Ok(result)
+        #[cfg(feature = "fault-injection")]
+        if let Some(fault) = &fault {
+            return fault.after_response(resp).await;
+        }
+        Ok(resp)
     }
+}
 
-    pub(crate) async fn request_no_body<R>(&self, method: reqwest::Method, path: &str) -> Result<R>
-    where
-        R: serde::de::DeserializeOwned,
+/// Read a response body, failing as soon as it grows past `limit`.
+#[cfg(not(target_arch = "wasm32"))]
+async fn read_body(mut resp: reqwest::Response, limit: Option<u64>) -> Result<Bytes> {
+    let Some(limit) = limit else {
+        return Ok(resp.bytes().await?);
+    };
+    if resp.content_length().is_some_and(|len| len > limit) {
+        return Err(Error::ResponseTooLarge { limit });
+    }
+    let mut body = Vec::new();
+    while let Some(chunk) = resp.chunk().await? {
+        if (body.len() + chunk.len()) as u64 > limit {
+            return Err(Error::ResponseTooLarge { limit });
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(Bytes::from(body))
+}
+
+// `fetch` bodies are read whole on wasm32, so only a declared length can
+// stop the read early.
+#[cfg(target_arch = "wasm32")]
+async fn read_body(resp: reqwest::Response, limit: Option<u64>) -> Result<Bytes> {
+    if let Some(limit) = limit
+        && resp.content_length().is_some_and(|len| len > limit)
     {
-        self.request::<(), R>(method, path, None).await
+        return Err(Error::ResponseTooLarge { limit });
+    }
+    let body = resp.bytes().await?;
+    match limit {
+        Some(limit) if body.len() as u64 > limit => Err(Error::ResponseTooLarge { limit }),
+        _ => Ok(body),
+    }
+}
+
+/// Builds a [`Client`] from any combination of settings; see [`Client::builder`].
+///
+/// ```no_run
+/// # fn example() -> rs_puff::Result<()> {
+/// use std::time::Duration;
+/// use rs_puff::{Client, RetryPolicy};
+///
+/// let client = Client::builder()
+///     .api_key("your-api-key")
+///     .region("gcp-us-east1")
+///     .timeout(Duration::from_secs(30))
+///     .proxy("http://proxy.internal:3128")
+///     .retry_policy(RetryPolicy::new(5))
+///     .build()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+#[must_use = "call .build() to create the client"]
+pub struct ClientBuilder {
+    api_key: Option<String>,
+    api_key_provider: Option<Arc<dyn ApiKeyProvider>>,
+    region: Option<String>,
+    base_url: Option<String>,
+    connect_timeout: Option<Option<Duration>>,
+    timeout: Option<Duration>,
+    max_response_bytes: Option<u64>,
+    max_in_flight_requests: Option<usize>,
+    circuit_breaker: Option<CircuitBreaker>,
+    proxy: Option<String>,
+    proxy_auth: Option<(String, String)>,
+    no_proxy: Option<String>,
+    system_proxy: Option<bool>,
+    #[cfg(any(feature = "native-tls", feature = "rustls"))]
+    root_certificates: Vec<Vec<u8>>,
+    headers: Vec<(String, String)>,
+    ip_preference: IpPreference,
+    #[cfg(feature = "gzip")]
+    gzip: Option<bool>,
+    retry_policy: Option<RetryPolicy>,
+    http_client: Option<reqwest::Client>,
+}
+
+impl ClientBuilder {
+    /// Required, unless [`api_key_provider`](Self::api_key_provider) is set.
+    pub fn api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    /// See [`Client::with_api_key_provider`]. Conflicts with [`api_key`](Self::api_key).
+    pub fn api_key_provider(mut self, provider: impl ApiKeyProvider + 'static) -> Self {
+        self.api_key_provider = Some(Arc::new(provider));
+        self
+    }
+
+    /// Connect to `https://{region}.turbopuffer.com`. Conflicts with [`base_url`](Self::base_url).
+    pub fn region(mut self, region: impl Into<String>) -> Self {
+        self.region = Some(region.into());
+        self
+    }
+
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = Some(base_url.into());
+        self
+    }
+
+    /// See [`Client::with_connect_timeout`].
+    pub fn connect_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// See [`Client::with_timeout`].
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// See [`Client::with_max_response_bytes`].
+    pub fn max_response_bytes(mut self, bytes: u64) -> Self {
+        self.max_response_bytes = Some(bytes);
+        self
+    }
+
+    /// See [`Client::with_max_in_flight_requests`].
+    pub fn max_in_flight_requests(mut self, limit: usize) -> Self {
+        self.max_in_flight_requests = Some(limit);
+        self
+    }
+
+    /// See [`Client::with_circuit_breaker`].
+    pub fn circuit_breaker(mut self, breaker: CircuitBreaker) -> Self {
+        self.circuit_breaker = Some(breaker);
+        self
+    }
+
+    /// Send every request through an HTTP(S) proxy, e.g. `http://proxy:3128`,
+    /// or a SOCKS proxy (e.g. `socks5h://proxy:1080`, with the `socks`
+    /// feature). Overrides the environment; see [`system_proxy`](Self::system_proxy).
+    /// Not supported on wasm32, where the runtime's `fetch` picks the route.
+    pub fn proxy(mut self, url: impl Into<String>) -> Self {
+        self.proxy = Some(url.into());
+        self
+    }
+
+    /// Basic credentials for [`proxy`](Self::proxy), an alternative to
+    /// putting them in the URL that needs no percent-encoding.
+    pub fn proxy_auth(mut self, username: impl Into<String>, password: impl Into<String>) -> Self {
+        self.proxy_auth = Some((username.into(), password.into()));
+        self
+    }
+
+    /// Hosts that bypass [`proxy`](Self::proxy), in `NO_PROXY` syntax, e.g.
+    /// `"localhost, .internal, 10.0.0.0/8"`.
+    pub fn no_proxy(mut self, hosts: impl Into<String>) -> Self {
+        self.no_proxy = Some(hosts.into());
+        self
+    }
+
+    /// Without [`proxy`](Self::proxy), requests follow `HTTPS_PROXY`,
+    /// `HTTP_PROXY`, `ALL_PROXY` and `NO_PROXY` (or their lowercase forms)
+    /// as set when the client is built. Defaults to `true`; `false`
+    /// connects directly whatever the environment says.
+    pub fn system_proxy(mut self, enabled: bool) -> Self {
+        self.system_proxy = Some(enabled);
+        self
+    }
+
+    /// Trust the PEM certificates in `pem` in addition to the TLS backend's
+    /// built-in roots, e.g. the CA of a gateway that terminates and
+    /// re-encrypts TLS. Can be called more than once. Not supported on wasm32,
+    /// where the browser or runtime decides which roots to trust.
+    #[cfg(any(feature = "native-tls", feature = "rustls"))]
+    pub fn root_certificate(mut self, pem: impl Into<Vec<u8>>) -> Self {
+        self.root_certificates.push(pem.into());
+        self
+    }
+
+    /// Add a header to every request. Middleware sees it and may change it.
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// See [`Client::with_ip_preference`].
+    pub fn ip_preference(mut self, preference: IpPreference) -> Self {
+        self.ip_preference = preference;
+        self
+    }
+
+    /// See [`Client::with_gzip`].
+    #[cfg(feature = "gzip")]
+    pub fn gzip(mut self, enabled: bool) -> Self {
+        self.gzip = Some(enabled);
+        self
+    }
+
+    /// See [`Client::with_retry_policy`].
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = Some(policy);
+        self
+    }
+
+    /// See [`Client::with_http_client`]. Conflicts with the connection
+    /// settings: [`proxy`](Self::proxy), [`system_proxy`](Self::system_proxy),
+    /// `root_certificate`, [`connect_timeout`](Self::connect_timeout) and
+    /// [`ip_preference`](Self::ip_preference).
+    pub fn http_client(mut self, http: reqwest::Client) -> Self {
+        self.http_client = Some(http);
+        self
+    }
+
+    /// Fails with [`Error::InvalidParams`] if the API key is missing or set
+    /// both directly and as a provider, both a region and a base URL are set, connection settings are combined with
+    /// an HTTP client, proxy options are set without a proxy, or the proxy, a
+    /// root certificate or a header is malformed.
+    pub fn build(self) -> Result<Client> {
+        #[cfg(any(feature = "native-tls", feature = "rustls"))]
+        let root_certificates = !self.root_certificates.is_empty();
+        #[cfg(not(any(feature = "native-tls", feature = "rustls")))]
+        let root_certificates = false;
+        if self.http_client.is_some()
+            && (self.proxy.is_some()
+                || self.system_proxy.is_some()
+                || root_certificates
+                || self.connect_timeout.is_some()
+                || self.ip_preference != IpPreference::Any)
+        {
+            return Err(Error::InvalidParams(
+                "proxy, system_proxy, root_certificate, connect_timeout and ip_preference can't be combined with http_client"
+                    .to_string(),
+            ));
+        }
+        if self.proxy.is_none() && (self.proxy_auth.is_some() || self.no_proxy.is_some()) {
+            return Err(Error::InvalidParams("proxy_auth and no_proxy need a proxy".to_string()));
+        }
+        let api_key: Arc<dyn ApiKeyProvider> = match (self.api_key, self.api_key_provider) {
+            (Some(_), Some(_)) => {
+                return Err(Error::InvalidParams("set either api_key or api_key_provider, not both".to_string()));
+            }
+            (Some(api_key), None) => Arc::new(StaticKey(api_key)),
+            (None, Some(provider)) => provider,
+            (None, None) => return Err(Error::InvalidParams("api_key is required".to_string())),
+        };
+        let base_url = match (self.region, self.base_url) {
+            (Some(_), Some(_)) => {
+                return Err(Error::InvalidParams("set either region or base_url, not both".to_string()));
+            }
+            (Some(region), None) => format!("https://{}.turbopuffer.com", region),
+            (None, Some(base_url)) => base_url,
+            (None, None) => DEFAULT_BASE_URL.to_string(),
+        };
+        let mut client = Client::with_base_url(String::new(), base_url);
+        let inner = client.inner_mut();
+        inner.api_key = api_key;
+
+        #[cfg(target_arch = "wasm32")]
+        if self.proxy.is_some() {
+            return Err(Error::InvalidParams("proxy isn't supported on wasm32".to_string()));
+        }
+        #[cfg(target_arch = "wasm32")]
+        if root_certificates {
+            return Err(Error::InvalidParams("root_certificate isn't supported on wasm32".to_string()));
+        }
+        #[cfg(all(any(feature = "native-tls", feature = "rustls"), not(target_arch = "wasm32")))]
+        for pem in &self.root_certificates {
+            inner.http_config.root_certificates.extend(root_certificates_from_pem(pem)?);
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(url) = self.proxy {
+            let mut proxy = proxy(&url)?;
+            if let Some((username, password)) = &self.proxy_auth {
+                proxy = proxy.basic_auth(username, password);
+            }
+            if let Some(hosts) = &self.no_proxy {
+                proxy = proxy.no_proxy(reqwest::NoProxy::from_string(hosts));
+            }
+            inner.http_config.proxy = Some(proxy);
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(enabled) = self.system_proxy {
+            inner.http_config.system_proxy = enabled;
+        }
+        inner.http_config.ip_preference = self.ip_preference;
+        #[cfg(feature = "gzip")]
+        if let Some(gzip) = self.gzip {
+            inner.http_config.gzip = gzip;
+        }
+        if let Some(timeout) = self.connect_timeout {
+            inner.http_config.connect_timeout = timeout;
+        }
+        inner.http = transport::http_client(&inner.http_config);
+
+        for (name, value) in self.headers {
+            let header = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|_| Error::InvalidParams(format!("invalid header name {:?}", name)))?;
+            let value = HeaderValue::from_str(&value)
+                .map_err(|_| Error::InvalidParams(format!("invalid value for header {:?}", name)))?;
+            inner.headers.append(header, value);
+        }
+        inner.timeout = self.timeout;
+        inner.max_response_bytes = self.max_response_bytes;
+        if let Some(limit) = self.max_in_flight_requests {
+            inner.in_flight = Some(Arc::new(Semaphore::new(limit.max(1))));
+        }
+        inner.circuit_breaker = self.circuit_breaker;
+        if let Some(policy) = self.retry_policy {
+            inner.retry_policy = policy;
+        }
+        if let Some(http) = self.http_client {
+            client = client.with_http_client(http);
+        }
+        Ok(client)
+    }
+}
+
+#[cfg(all(any(feature = "native-tls", feature = "rustls"), not(target_arch = "wasm32")))]
+fn root_certificates_from_pem(pem: &[u8]) -> Result<Vec<reqwest::Certificate>> {
+    let invalid = |reason: &str| Error::InvalidParams(format!("invalid root certificate: {}", reason));
+    let certificates = reqwest::Certificate::from_pem_bundle(pem).map_err(|e| invalid(&e.to_string()))?;
+    if certificates.is_empty() {
+        return Err(invalid("no PEM certificates found"));
+    }
+    Ok(certificates)
+}
+
+/// Parse a proxy URL up front; reqwest would only reject an unusable scheme
+/// on the first request.
+#[cfg(not(target_arch = "wasm32"))]
+fn proxy(url: &str) -> Result<reqwest::Proxy> {
+    let invalid = |reason: String| Error::InvalidParams(format!("invalid proxy URL {:?}: {}", url, reason));
+    let parsed = reqwest::Url::parse(url).map_err(|e| invalid(e.to_string()))?;
+    match parsed.scheme() {
+        "http" | "https" => {}
+        "socks4" | "socks4a" | "socks5" | "socks5h" if cfg!(feature = "socks") => {}
+        "socks4" | "socks4a" | "socks5" | "socks5h" => return Err(invalid("SOCKS proxies need the `socks` feature".to_string())),
+        scheme => return Err(invalid(format!("unsupported scheme {:?}", scheme))),
+    }
+    reqwest::Proxy::all(parsed).map_err(|e| invalid(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builder_composes_settings() {
+        let client = Client::builder()
+            .api_key("key")
+            .region("gcp-us-east1")
+            .timeout(Duration::from_secs(5))
+            .connect_timeout(None)
+            .header("x-team", "search")
+            .retry_policy(RetryPolicy::none())
+            .build()
+            .unwrap();
+        assert_eq!(client.inner.base_url, "https://gcp-us-east1.turbopuffer.com");
+        assert_eq!(client.inner.timeout, Some(Duration::from_secs(5)));
+        assert_eq!(client.inner.http_config.connect_timeout, None);
+        assert_eq!(client.inner.headers["x-team"], "search");
+        assert_eq!(client.inner.retry_policy, RetryPolicy::none());
+
+        let defaults = Client::builder().api_key("key").build().unwrap();
+        assert_eq!(defaults.inner.base_url, DEFAULT_BASE_URL);
+        assert_eq!(defaults.inner.http_config.connect_timeout, Some(DEFAULT_CONNECT_TIMEOUT));
+    }
+
+    /// Serve `responses` in order, one per connection. Joining the handle
+    /// yields each request's head, lowercased.
+    fn serve<R: AsRef<[u8]> + Send + 'static>(responses: Vec<R>) -> (String, std::thread::JoinHandle<Vec<String>>) {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            use std::io::{Read, Write};
+            let mut requests = Vec::new();
+            for response in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut request = [0; 4096];
+                let n = stream.read(&mut request).unwrap_or(0);
+                requests.push(String::from_utf8_lossy(&request[..n]).to_lowercase());
+                stream.write_all(response.as_ref()).unwrap();
+            }
+            requests
+        });
+        (format!("http://{}", addr), server)
+    }
+
+    const RATE_LIMITED: &str = "HTTP/1.1 429 Too Many Requests\r\nretry-after: 0\r\nconnection: close\r\ncontent-length: 4\r\n\r\nslow";
+    const OK: &str = "HTTP/1.1 200 OK\r\nconnection: close\r\ncontent-length: 2\r\n\r\n{}";
+
+    #[tokio::test]
+    async fn test_rate_limited_requests_wait_and_retry() {
+        let client = Client::with_base_url("key", serve(vec![RATE_LIMITED, OK]).0);
+        let body = client.raw_request(reqwest::Method::GET, "/v1/namespaces", None).await.unwrap();
+        assert_eq!(body, serde_json::json!({}));
+
+        let long_wait = "HTTP/1.1 429 Too Many Requests\r\nretry-after: 3600\r\nconnection: close\r\ncontent-length: 4\r\n\r\nslow";
+        let client = Client::with_base_url("key", serve(vec![long_wait]).0);
+        let err = client.raw_request(reqwest::Method::GET, "/v1/namespaces", None).await.unwrap_err();
+        let Error::RateLimited(limit) = err else { panic!("expected a rate limit error, got {:?}", err) };
+        assert_eq!(limit.retry_after, Some(Duration::from_secs(3600)));
+        assert_eq!(limit.message, "slow");
+    }
+
+    #[tokio::test]
+    async fn test_oversized_responses_are_abandoned() {
+        let unsized_body = "HTTP/1.1 200 OK\r\nconnection: close\r\n\r\n{\"rows\": []}";
+        let client = Client::with_base_url("key", serve(vec![OK, OK, unsized_body]).0).with_max_response_bytes(2);
+        client.raw_request(reqwest::Method::GET, "/v1/namespaces", None).await.unwrap();
+
+        let opts = CallOptions::default().max_response_bytes(1);
+        let err = client
+            .request_with::<(), serde_json::Value>(reqwest::Method::GET, "/v1/namespaces", None, &opts, true)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::ResponseTooLarge { limit: 1 }));
+
+        // Without a content-length the body is measured as it arrives.
+        let err = client.raw_request(reqwest::Method::GET, "/v1/namespaces", None).await.unwrap_err();
+        assert!(matches!(err, Error::ResponseTooLarge { limit: 2 }));
+    }
+
+    struct Recorder(Arc<std::sync::Mutex<Vec<(String, u16)>>>);
+
+    impl Middleware for Recorder {
+        fn on_request(&self, request: &mut RequestParts) -> Result<()> {
+            request.headers.insert("x-proxy-auth", HeaderValue::from_static("token"));
+            Ok(())
+        }
+
+        fn on_response(&self, request: &RequestParts, response: &ResponseParts) {
+            assert_eq!(request.headers["x-proxy-auth"], "token");
+            self.0.lock().unwrap().push((request.path.clone(), response.status.as_u16()));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_middleware_sees_every_response() {
+        let seen = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let client = Client::with_base_url("key", serve(vec![RATE_LIMITED, OK]).0)
+            .with_middleware(|request: &mut RequestParts| {
+                request.path.push_str("?audited=1");
+                Ok(())
+            })
+            .with_middleware(Recorder(seen.clone()));
+        client.raw_request(reqwest::Method::GET, "/v1/namespaces", None).await.unwrap();
+        let path = "/v1/namespaces?audited=1".to_string();
+        assert_eq!(*seen.lock().unwrap(), [(path.clone(), 429), (path, 200)]);
+    }
+
+    /// `data` as gzip with a single uncompressed deflate block.
+    #[cfg(feature = "gzip")]
+    fn gzip_stored(data: &[u8]) -> Vec<u8> {
+        let crc = !data.iter().fold(!0u32, |crc, &b| {
+            (0..8).fold(crc ^ b as u32, |c, _| if c & 1 == 1 { (c >> 1) ^ 0xedb8_8320 } else { c >> 1 })
+        });
+        let len = data.len() as u16;
+        let mut out = vec![0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 0xff, 1];
+        out.extend(len.to_le_bytes());
+        out.extend((!len).to_le_bytes());
+        out.extend(data);
+        out.extend(crc.to_le_bytes());
+        out.extend((data.len() as u32).to_le_bytes());
+        out
+    }
+
+    #[cfg(feature = "gzip")]
+    #[tokio::test]
+    async fn test_gzip_responses_are_decompressed() {
+        let body = gzip_stored(br#"{"rows": []}"#);
+        let mut response = format!(
+            "HTTP/1.1 200 OK\r\ncontent-encoding: gzip\r\nconnection: close\r\ncontent-length: {}\r\n\r\n",
+            body.len()
+        )
+        .into_bytes();
+        response.extend(body);
+        let (url, server) = serve(vec![response]);
+
+        let client = Client::with_base_url("key", url);
+        let body = client.raw_request(reqwest::Method::GET, "/v1/namespaces", None).await.unwrap();
+        assert_eq!(body, serde_json::json!({"rows": []}));
+        assert!(server.join().unwrap()[0].contains("accept-encoding: gzip"));
+    }
+
+    #[test]
+    fn test_builder_rejects_invalid_settings() {
+        let invalid = |builder: ClientBuilder| matches!(builder.build(), Err(Error::InvalidParams(_)));
+        assert!(invalid(Client::builder()));
+        assert!(invalid(Client::builder().api_key("key").region("r").base_url("http://localhost")));
+        assert!(invalid(Client::builder().api_key("key").api_key_provider(|| "rotated".to_string())));
+        assert!(invalid(Client::builder().api_key("key").header("bad header", "v")));
+        assert!(invalid(Client::builder().api_key("key").proxy("not a url")));
+        assert!(invalid(Client::builder().api_key("key").proxy("ftp://proxy.internal")));
+        #[cfg(not(feature = "socks"))]
+        assert!(invalid(Client::builder().api_key("key").proxy("socks5h://proxy.internal:1080")));
+        assert!(invalid(Client::builder().api_key("key").proxy_auth("user", "pass")));
+        assert!(invalid(Client::builder().api_key("key").http_client(reqwest::Client::new()).system_proxy(false)));
+        assert!(invalid(Client::builder().api_key("key").http_client(reqwest::Client::new()).connect_timeout(None)));
+    }
+
+    #[tokio::test]
+    async fn test_custom_http_client_sends_requests() {
+        use reqwest::header::USER_AGENT;
+
+        let (url, server) = serve(vec![OK]);
+        let mut headers = HeaderMap::new();
+        headers.insert(USER_AGENT, HeaderValue::from_static("org-proxy-client"));
+        let http = reqwest::Client::builder().default_headers(headers).build().unwrap();
+        let client = Client::builder()
+            .api_key("key")
+            .base_url(url)
+            .http_client(http)
+            .build()
+            .unwrap();
+        client.raw_request(reqwest::Method::GET, "/v1/namespaces", None).await.unwrap();
+        assert!(server.join().unwrap()[0].contains("user-agent: org-proxy-client"));
+    }
+
+    // Self-signed test CA; the key was thrown away.
+    #[cfg(any(feature = "native-tls", feature = "rustls"))]
+    const TEST_CA: &str = "\
+-----BEGIN CERTIFICATE-----\n\
+MIIBjDCCATGgAwIBAgIUdwOYTdvhmUpskFGCVpfNu+ZP46gwCgYIKoZIzj0EAwIw\n\
+GjEYMBYGA1UEAwwPcnMtcHVmZiB0ZXN0IENBMCAXDTI2MTAxNjE4MTE0MloYDzIx\n\
+MjYwOTIyMTgxMTQyWjAaMRgwFgYDVQQDDA9ycy1wdWZmIHRlc3QgQ0EwWTATBgcq\n\
+hkjOPQIBBggqhkjOPQMBBwNCAARphpZdB09Hoq1Rb/0KSjgKlq05wgpE8yIJEa8F\n\
+/OrIiHrwAPWlJnkCOCdX1GSLHJYUwtodhIXnSl6YEnvKhQa2o1MwUTAdBgNVHQ4E\n\
+FgQUPVAui01ePthALS113inBIISj4W8wHwYDVR0jBBgwFoAUPVAui01ePthALS11\n\
+3inBIISj4W8wDwYDVR0TAQH/BAUwAwEB/zAKBggqhkjOPQQDAgNJADBGAiEA6EkU\n\
++SpOgmT4OxdflF43BCz2D3zyycg8qaCktgFpwAcCIQDbqst8T3SvJrPUZEQ0Uat3\n\
+HsvAq3x04tScVxc4zEdcFQ==\n\
+-----END CERTIFICATE-----\n\
+";
+
+    #[cfg(any(feature = "native-tls", feature = "rustls"))]
+    #[test]
+    fn test_root_certificates_are_parsed() {
+        let bundle = format!("{}{}", TEST_CA, TEST_CA);
+        let client = Client::builder().api_key("key").root_certificate(TEST_CA).root_certificate(bundle).build().unwrap();
+        assert_eq!(client.inner.http_config.root_certificates.len(), 3);
+
+        let invalid = |builder: ClientBuilder| matches!(builder.build(), Err(Error::InvalidParams(_)));
+        assert!(invalid(Client::builder().api_key("key").root_certificate("not a certificate")));
+        assert!(invalid(Client::builder().api_key("key").root_certificate(TEST_CA).http_client(reqwest::Client::new())));
+    }
+
+    #[tokio::test]
+    async fn test_requests_go_through_proxy() {
+        let (proxy, server) = serve(vec![OK]);
+        let client = Client::builder()
+            .api_key("key")
+            .base_url("http://turbopuffer.invalid")
+            .proxy(proxy)
+            .proxy_auth("user", "pass")
+            .build()
+            .unwrap();
+        client.raw_request(reqwest::Method::GET, "/v1/namespaces", None).await.unwrap();
+        let request = &server.join().unwrap()[0];
+        assert!(request.starts_with("get http://turbopuffer.invalid/v1/namespaces"), "{}", request);
+        assert!(request.contains("proxy-authorization: basic dxnlcjpwyxnz"), "{}", request);
+    }
+
+    /// A request as [`Stub`] saw it.
+    struct Sent {
+        method: reqwest::Method,
+        path: String,
+        query: Option<String>,
+        headers: HeaderMap,
+    }
+
+    /// Answers every request with `body`, recording it. The first `failures`
+    /// requests get a 503 and the rest `status`, each after `delay`.
+    struct Stub {
+        body: &'static str,
+        status: u16,
+        failures: usize,
+        delay: Duration,
+        sent: std::sync::Mutex<Vec<Sent>>,
+        in_flight: std::sync::atomic::AtomicUsize,
+        peak: std::sync::atomic::AtomicUsize,
+    }
+
+    impl Stub {
+        fn new(body: &'static str) -> Self {
+            Stub {
+                body,
+                status: 200,
+                failures: 0,
+                delay: Duration::ZERO,
+                sent: Default::default(),
+                in_flight: Default::default(),
+                peak: Default::default(),
+            }
+        }
+
+        fn status(self, status: u16) -> Self {
+            Stub { status, ..self }
+        }
+
+        fn failures(self, failures: usize) -> Self {
+            Stub { failures, ..self }
+        }
+
+        fn delay(self, delay: Duration) -> Self {
+            Stub { delay, ..self }
+        }
+
+        fn sent(&self) -> std::sync::MutexGuard<'_, Vec<Sent>> {
+            self.sent.lock().unwrap()
+        }
+
+        fn header(&self, name: &str) -> Vec<String> {
+            self.sent().iter().map(|sent| sent.headers[name].to_str().unwrap().to_string()).collect()
+        }
+    }
+
+    impl HttpTransport for Stub {
+        fn send(&self, request: reqwest::Request) -> crate::rt::BoxFuture<'_, Result<reqwest::Response>> {
+            use std::sync::atomic::Ordering;
+
+            let mut sent = self.sent();
+            sent.push(Sent {
+                method: request.method().clone(),
+                path: request.url().path().to_string(),
+                query: request.url().query().map(str::to_string),
+                headers: request.headers().clone(),
+            });
+            let status = if sent.len() <= self.failures { 503 } else { self.status };
+            drop(sent);
+            Box::pin(async move {
+                let now = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                self.peak.fetch_max(now, Ordering::SeqCst);
+                if !self.delay.is_zero() {
+                    tokio::time::sleep(self.delay).await;
+                }
+                self.in_flight.fetch_sub(1, Ordering::SeqCst);
+                Ok(reqwest::Response::from(http::Response::builder().status(status).body(self.body).unwrap()))
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_client_and_call_headers_are_sent() {
+        let transport = Arc::new(Stub::new(r#"{"rows": []}"#));
+        let client = Client::new("key")
+            .with_header(HeaderName::from_static("x-org-id"), HeaderValue::from_static("org-1"))
+            .with_header(HeaderName::from_static("x-route"), HeaderValue::from_static("default"))
+            .with_transport(transport.clone());
+        let ns = client.namespace("docs");
+        ns.query(crate::QueryParams::default()).await.unwrap();
+        let opts = CallOptions::default()
+            .header("x-route", HeaderValue::from_static("canary"))
+            .header("x-request-id", HeaderValue::from_static("r-42"));
+        ns.query_with_options(crate::QueryParams::default(), &opts).await.unwrap();
+
+        let sent: Vec<_> = transport.sent().iter().map(|sent| sent.headers.clone()).collect();
+        assert_eq!(sent[0]["x-org-id"], "org-1");
+        assert_eq!(sent[0]["x-route"], "default");
+        assert!(!sent[0].contains_key("x-request-id"));
+        assert_eq!(sent[1]["x-org-id"], "org-1");
+        assert_eq!(sent[1].get_all("x-route").iter().collect::<Vec<_>>(), ["canary"]);
+        assert_eq!(sent[1]["x-request-id"], "r-42");
+        assert_eq!(sent[1]["authorization"], "Bearer key");
+    }
+
+    #[tokio::test]
+    async fn test_namespaces_are_owned_handles() {
+        fn assert_owned<T: Clone + Send + Sync + 'static>(_: &T) {}
+
+        let transport = Arc::new(Stub::new(r#"{"rows": []}"#));
+        let client = Client::new("key").with_transport(transport.clone());
+        let ns = client.namespace("docs");
+        assert_owned(&ns);
+        let task = tokio::spawn({
+            let ns = ns.clone();
+            async move { ns.query(crate::QueryParams::default()).await }
+        });
+        task.await.unwrap().unwrap();
+
+        // Reconfiguring a clone leaves the original and its namespaces alone.
+        let tagged = client.clone().with_header(HeaderName::from_static("x-org-id"), HeaderValue::from_static("org-1"));
+        tagged.namespace("docs").query(crate::QueryParams::default()).await.unwrap();
+        drop(client);
+        ns.query(crate::QueryParams::default()).await.unwrap();
+
+        let sent = transport.sent();
+        assert_eq!(sent.len(), 3);
+        assert!(!sent[0].headers.contains_key("x-org-id"));
+        assert_eq!(sent[1].headers["x-org-id"], "org-1");
+        assert!(!sent[2].headers.contains_key("x-org-id"));
+    }
+
+    const ACCEPTED: &str = r#"{"rows_affected": 1}"#;
+
+    #[tokio::test]
+    async fn test_write_retries_reuse_idempotency_key() {
+        use crate::{Filter, Row, WriteParams};
+
+        let transport = Arc::new(Stub::new(ACCEPTED).failures(1));
+        let client = Client::new("key")
+            .with_retry_policy(RetryPolicy::new(2).initial_backoff(Duration::ZERO).jitter(false))
+            .with_transport(transport.clone());
+        let ns = client.namespace("docs");
+        let upsert = || WriteParams {
+            upsert_rows: Some(vec![Row::from_iter([("id".to_string(), 1.into())])]),
+            ..Default::default()
+        };
+        ns.write(upsert()).await.unwrap();
+        ns.write(upsert()).await.unwrap();
+        ns.write_with_options(upsert(), &CallOptions::default().idempotency_key("batch-7")).await.unwrap();
+        let keys = transport.header(crate::IDEMPOTENCY_KEY_HEADER);
+        assert_eq!(keys.len(), 4);
+        assert_eq!(keys[0], keys[1]);
+        assert_ne!(keys[1], keys[2]);
+        assert_eq!(keys[3], "batch-7");
+
+        // Conditional writes aren't retried, but report the key to resend with.
+        let transport = Arc::new(Stub::new(ACCEPTED).failures(1));
+        let ns = client.clone().with_transport(transport.clone()).namespace("docs");
+        let conditional = WriteParams { upsert_condition: Some(Filter::eq("v", 1)), ..upsert() };
+        let Err(Error::AmbiguousWrite { idempotency_key, .. }) = ns.write(conditional).await else {
+            panic!("expected an ambiguous write");
+        };
+        assert_eq!(transport.header(crate::IDEMPOTENCY_KEY_HEADER), [idempotency_key]);
+
+        // Verification can't confirm a delete, so the write stays ambiguous
+        // without a read-back.
+        let transport = Arc::new(Stub::new(ACCEPTED).failures(1));
+        let ns = client.with_transport(transport.clone()).namespace("docs");
+        let mixed = WriteParams { delete_by_filter: Some(Filter::eq("v", 0)), ..upsert() };
+        let verify = CallOptions::default().verify_ambiguous_writes();
+        let result = ns.write_with_options(mixed, &verify).await;
+        assert!(matches!(result, Err(Error::AmbiguousWrite { .. })), "{:?}", result);
+        assert_eq!(transport.sent().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_api_key_provider_consulted_per_request() {
+        use std::sync::atomic::{AtomicU32, Ordering};
+
+        let version = Arc::new(AtomicU32::new(1));
+        let current = version.clone();
+        let transport = Arc::new(Stub::new("{}"));
+        let client = Client::builder()
+            .api_key_provider(move || format!("key-{}", current.load(Ordering::SeqCst)))
+            .build()
+            .unwrap()
+            .with_transport(transport.clone());
+        client.raw_request(reqwest::Method::GET, "/v1/namespaces", None).await.unwrap();
+        version.store(2, Ordering::SeqCst);
+        client.raw_request(reqwest::Method::GET, "/v1/namespaces", None).await.unwrap();
+        assert_eq!(transport.header("authorization"), ["Bearer key-1", "Bearer key-2"]);
+    }
+
+    #[tokio::test]
+    async fn test_in_flight_requests_are_limited() {
+        let transport = Arc::new(Stub::new("{}").delay(Duration::from_millis(20)));
+        let client = Client::builder()
+            .api_key("key")
+            .max_in_flight_requests(3)
+            .build()
+            .unwrap()
+            .with_transport(transport.clone());
+        let calls = (0..12).map(|_| {
+            let client = client.clone();
+            async move { client.raw_request(reqwest::Method::GET, "/v1/namespaces", None).await }
+        });
+        for result in futures_util::future::join_all(calls).await {
+            result.unwrap();
+        }
+        assert_eq!(transport.peak.load(std::sync::atomic::Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_call_deadline_covers_the_whole_call() {
+        use crate::{Filter, QueryParams, WriteParams};
+
+        let client = Client::new("key").with_transport(Arc::new(Stub::new("{}").delay(Duration::from_millis(20))));
+        let ns = client.namespace("docs");
+        let tight = CallOptions::default().deadline(Duration::from_millis(5));
+        let err = ns.query_with_options(QueryParams::default(), &tight).await.unwrap_err();
+        assert!(matches!(err, Error::DeadlineExceeded), "{:?}", err);
+
+        // The write may have reached the server before the deadline.
+        let conditional = WriteParams {
+            upsert_rows: Some(vec![serde_json::from_value(serde_json::json!({"id": 1})).unwrap()]),
+            upsert_condition: Some(Filter::eq("id", 1)),
+            ..Default::default()
+        };
+        let err = ns.write_with_options(conditional, &tight).await.unwrap_err();
+        assert!(matches!(&err, Error::AmbiguousWrite { source, .. } if matches!(**source, Error::DeadlineExceeded)));
+
+        // A retry that couldn't start before the deadline isn't waited for.
+        let transport = Arc::new(Stub::new(ACCEPTED).failures(3));
+        let client = Client::new("key")
+            .with_retry_policy(RetryPolicy::new(3).initial_backoff(Duration::from_secs(1)).jitter(false))
+            .with_transport(transport.clone());
+        let start = Instant::now();
+        let err = client
+            .namespace("docs")
+            .write_with_options(WriteParams { deletes: Some(vec![1.into()]), ..Default::default() }, &tight)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::Api { status: 503, .. }), "{:?}", err);
+        assert!(start.elapsed() < Duration::from_millis(500));
+        assert_eq!(transport.sent().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_ping_checks_credentials_without_retrying() {
+        const NAMESPACES: &str = r#"{"namespaces": []}"#;
+
+        let ok = Arc::new(Stub::new(NAMESPACES));
+        Client::new("key").with_transport(ok.clone()).ping().await.unwrap();
+        assert_eq!(ok.sent().len(), 1);
+        assert_eq!(ok.sent()[0].query.as_deref(), Some("page_size=1"));
+
+        let denied = Arc::new(Stub::new(NAMESPACES).status(401));
+        let err = Client::new("bad").with_transport(denied.clone()).ping().await.unwrap_err();
+        assert!(matches!(err, Error::Api { status: 401, .. }), "{:?}", err);
+
+        let unavailable = Arc::new(Stub::new(NAMESPACES).status(503));
+        let client = Client::new("key")
+            .with_retry_policy(RetryPolicy::new(3).initial_backoff(Duration::from_millis(1)))
+            .with_transport(unavailable.clone());
+        assert!(client.ping().await.is_err());
+        assert_eq!(unavailable.sent().len(), 1);
+        let retried = CallOptions::default().retry(RetryPolicy::new(2).initial_backoff(Duration::from_millis(1)));
+        assert!(client.ping_with_options(&retried).await.is_err());
+        assert_eq!(unavailable.sent().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_every_query_path_is_metered() {
+        use crate::{QueryParams, UsageMeter};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let body = r#"{"rows": [], "billing": {"billable_logical_bytes_queried": 10, "billable_logical_bytes_returned": 1},
+            "performance": {"exhaustive_search_count": 5}}"#;
+        let meter = UsageMeter::new();
+        let alerts = Arc::new(AtomicUsize::new(0));
+        let client = Client::new("key")
+            .with_usage_meter(meter.clone())
+            .with_exhaustive_search_alert(1, {
+                let alerts = alerts.clone();
+                move |_: &ExhaustiveSearchEvent<'_>| {
+                    alerts.fetch_add(1, Ordering::SeqCst);
+                }
+            })
+            .with_transport(Arc::new(Stub::new(body)));
+        let plain = client.namespace("docs");
+        let hooked = client.namespace("docs").with_read_hook(|_: &mut crate::Row| {});
+        for ns in [plain, hooked] {
+            ns.query(QueryParams::default()).await.unwrap();
+            ns.query_rows::<crate::Row>(QueryParams::default()).await.unwrap();
+            ns.query_raw_value(QueryParams::default()).await.unwrap();
+            ns.query_raw_bytes(QueryParams::default()).await.unwrap();
+        }
+        let usage = meter.query_usage("docs");
+        assert_eq!((usage.queries, usage.billable_logical_bytes_queried), (8, 80));
+        assert_eq!(alerts.load(Ordering::SeqCst), 8);
+    }
+
+    #[tokio::test]
+    async fn test_transport_replaces_http_stack() {
+        let transport = Arc::new(Stub::new(r#"{"namespaces": []}"#));
+        let client = Client::new("key").with_transport(transport.clone());
+        let body = client.raw_request(reqwest::Method::GET, "/v1/namespaces", None).await.unwrap();
+        assert_eq!(body, serde_json::json!({"namespaces": []}));
+        let sent = transport.sent();
+        assert_eq!((&sent[0].method, sent[0].path.as_str()), (&reqwest::Method::GET, "/v1/namespaces"));
+        assert_eq!(sent.len(), 1);
     }
 }
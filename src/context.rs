@@ -0,0 +1,137 @@
+//! Assembling retrieved rows into an LLM prompt context under a token budget.
+
+use crate::{Id, Order, Row};
+
+/// Options for [`assemble_context`].
+#[derive(Debug, Clone)]
+pub struct ContextParams {
+    /// Token budget for the assembled text, separators included.
+    pub max_tokens: usize,
+    /// Attribute holding each row's text; rows without it are skipped.
+    pub text_attribute: String,
+    /// Placed between rows' texts.
+    pub separator: String,
+    /// How `$dist` ranks rows: [`Order::Asc`] for vector distances,
+    /// [`Order::Desc`] for BM25 scores. Rows without `$dist` come last.
+    pub order: Order,
+}
+
+impl ContextParams {
+    pub fn new(max_tokens: usize) -> Self {
+        Self {
+            max_tokens,
+            text_attribute: "text".to_string(),
+            separator: "\n\n".to_string(),
+            order: Order::Asc,
+        }
+    }
+}
+
+/// Result of [`assemble_context`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AssembledContext {
+    /// Included texts, best first, joined by the separator.
+    pub text: String,
+    /// Ids of the included rows, in the same order as their texts.
+    pub ids: Vec<Id>,
+    /// Tokens used, as counted by the token counter.
+    pub tokens: usize,
+    /// Rows with text that didn't fit in the budget.
+    pub skipped: usize,
+}
+
+/// Rough token count for English text, about four characters per token.
+/// Pass a real tokenizer to [`assemble_context_with`] when the budget is tight.
+pub fn approx_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+/// [`assemble_context_with`] using [`approx_tokens`].
+pub fn assemble_context(rows: &[Row], params: &ContextParams) -> AssembledContext {
+    assemble_context_with(rows, params, approx_tokens)
+}
+
+/// Greedily take rows, best score first, whose texts fit in
+/// `params.max_tokens` when joined.
+///
+/// A row too long for the remaining budget is skipped and shorter,
+/// lower-scored rows are still considered.
+pub fn assemble_context_with(
+    rows: &[Row],
+    params: &ContextParams,
+    count_tokens: impl Fn(&str) -> usize,
+) -> AssembledContext {
+    let mut ranked: Vec<&Row> = rows.iter().collect();
+    let dist = |row: &Row| row.get("$dist").and_then(|d| d.as_f64());
+    // Stable, so ties keep their result order.
+    ranked.sort_by(|a, b| match (dist(a), dist(b)) {
+        (Some(a), Some(b)) => match params.order {
+            Order::Asc => a.total_cmp(&b),
+            Order::Desc => b.total_cmp(&a),
+        },
+        (a, b) => b.is_some().cmp(&a.is_some()),
+    });
+
+    let separator_tokens = count_tokens(&params.separator);
+    let mut context = AssembledContext::default();
+    for row in ranked {
+        let Some(text) = row.get(&params.text_attribute).and_then(|t| t.as_str()) else {
+            continue;
+        };
+        let cost = count_tokens(text) + if context.ids.is_empty() { 0 } else { separator_tokens };
+        let id = row.get("id").and_then(|id| serde_json::from_value::<Id>(id.clone()).ok());
+        let Some(id) = id.filter(|_| context.tokens + cost <= params.max_tokens) else {
+            context.skipped += 1;
+            continue;
+        };
+        if !context.ids.is_empty() {
+            context.text.push_str(&params.separator);
+        }
+        context.text.push_str(text);
+        context.tokens += cost;
+        context.ids.push(id);
+    }
+    context
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(id: u64, dist: f64, text: &str) -> Row {
+        serde_json::from_value(serde_json::json!({"id": id, "$dist": dist, "text": text})).unwrap()
+    }
+
+    fn words(text: &str) -> usize {
+        text.split_whitespace().count()
+    }
+
+    #[test]
+    fn test_takes_best_rows_that_fit() {
+        let rows = vec![
+            row(1, 0.3, "third best here"),
+            row(2, 0.1, "best"),
+            row(3, 0.2, "second best row is far too long"),
+            serde_json::from_value(serde_json::json!({"id": 4, "$dist": 0.0})).unwrap(),
+        ];
+        let mut params = ContextParams::new(5);
+        params.separator = " | ".to_string();
+        let context = assemble_context_with(&rows, &params, words);
+        assert_eq!(context.text, "best | third best here");
+        assert_eq!(context.ids, [Id::from(2u64), Id::from(1u64)]);
+        assert_eq!(context.tokens, 5);
+        assert_eq!(context.skipped, 1);
+
+        params.order = Order::Desc;
+        let context = assemble_context_with(&rows, &params, words);
+        assert_eq!(context.ids, [Id::from(1u64), Id::from(2u64)]);
+    }
+
+    #[test]
+    fn test_approx_tokens() {
+        assert_eq!(approx_tokens(""), 0);
+        assert_eq!(approx_tokens("abcde"), 2);
+        let rows = vec![row(1, 0.1, "abcdefgh"), row(2, 0.2, "abcd")];
+        assert_eq!(assemble_context(&rows, &ContextParams::new(2)).ids, [Id::from(1u64)]);
+    }
+}
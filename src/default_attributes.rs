@@ -0,0 +1,197 @@
+use crate::{IncludeAttributes, QueryParams};
+
+/// How a namespace's [`DefaultAttributes`] combine with a query's own
+/// `include_attributes` and `exclude_attributes`.
+///
+/// A side that leaves a field unset never affects it: a query that sets
+/// neither field gets the defaults whatever the merge.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AttributeMerge {
+    /// A query that sets either field keeps its own projection; the defaults
+    /// only fill in queries that set neither.
+    #[default]
+    PerCall,
+    /// Return attributes either side asks for: include lists are combined
+    /// (`true` on either side includes everything) and an attribute stays
+    /// excluded only if both sides exclude it.
+    Union,
+    /// Return only attributes both sides allow: an attribute stays included
+    /// only if both include lists name it (`false` on either side includes
+    /// none) and exclusions from both sides apply.
+    Intersection,
+}
+
+/// Projection applied to every query through a namespace handle; see
+/// [`Namespace::with_default_attributes`](crate::Namespace::with_default_attributes).
+///
+/// ```
+/// use rs_puff::{AttributeMerge, DefaultAttributes};
+///
+/// // Never return vectors, even to queries that list their own attributes.
+/// let defaults = DefaultAttributes::exclude(["vector"]).merge(AttributeMerge::Intersection);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct DefaultAttributes {
+    pub include_attributes: Option<IncludeAttributes>,
+    pub exclude_attributes: Option<Vec<String>>,
+    /// Used unless the call sets
+    /// [`CallOptions::attribute_merge`](crate::CallOptions::attribute_merge).
+    pub merge: AttributeMerge,
+}
+
+impl DefaultAttributes {
+    pub fn include<I, S>(attributes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            include_attributes: Some(IncludeAttributes::List(attributes.into_iter().map(Into::into).collect())),
+            ..Default::default()
+        }
+    }
+
+    pub fn exclude<I, S>(attributes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self { exclude_attributes: Some(attributes.into_iter().map(Into::into).collect()), ..Default::default() }
+    }
+
+    pub fn merge(mut self, merge: AttributeMerge) -> Self {
+        self.merge = merge;
+        self
+    }
+
+    /// Merge into `params` with `merge`, or [`Self::merge`] when `None`.
+    pub(crate) fn apply(&self, params: &mut QueryParams, merge: Option<AttributeMerge>) {
+        let include = params.include_attributes.take();
+        let exclude = params.exclude_attributes.take();
+        let default_include = self.include_attributes.clone();
+        let default_exclude = self.exclude_attributes.clone();
+        (params.include_attributes, params.exclude_attributes) = match merge.unwrap_or(self.merge) {
+            AttributeMerge::PerCall if include.is_some() || exclude.is_some() => (include, exclude),
+            AttributeMerge::PerCall => (default_include, default_exclude),
+            AttributeMerge::Union => {
+                (union_include(default_include, include), either(default_exclude, exclude, intersection))
+            }
+            AttributeMerge::Intersection => {
+                (intersect_include(default_include, include), either(default_exclude, exclude, union))
+            }
+        };
+    }
+}
+
+fn union_include(a: Option<IncludeAttributes>, b: Option<IncludeAttributes>) -> Option<IncludeAttributes> {
+    use IncludeAttributes::{All, List};
+    match (a, b) {
+        (None, other) | (other, None) => other,
+        (Some(All(true)), _) | (_, Some(All(true))) => Some(All(true)),
+        (Some(All(false)), other) | (other, Some(All(false))) => other,
+        (Some(List(a)), Some(List(b))) => Some(List(union(a, b))),
+    }
+}
+
+fn intersect_include(a: Option<IncludeAttributes>, b: Option<IncludeAttributes>) -> Option<IncludeAttributes> {
+    use IncludeAttributes::{All, List};
+    match (a, b) {
+        (None, other) | (other, None) => other,
+        (Some(All(false)), _) | (_, Some(All(false))) => Some(All(false)),
+        (Some(All(true)), other) | (other, Some(All(true))) => other,
+        (Some(List(a)), Some(List(b))) => Some(List(intersection(a, b))),
+    }
+}
+
+/// Combine two optional lists with `f`, keeping whichever one is set.
+fn either(
+    a: Option<Vec<String>>,
+    b: Option<Vec<String>>,
+    f: fn(Vec<String>, Vec<String>) -> Vec<String>,
+) -> Option<Vec<String>> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(f(a, b)),
+        (a, b) => a.or(b),
+    }
+}
+
+/// `a`'s attributes, then `b`'s not already in `a`.
+fn union(mut a: Vec<String>, b: Vec<String>) -> Vec<String> {
+    for attr in b {
+        if !a.contains(&attr) {
+            a.push(attr);
+        }
+    }
+    a
+}
+
+/// `b`'s attributes that are also in `a`, in `b`'s order.
+fn intersection(a: Vec<String>, b: Vec<String>) -> Vec<String> {
+    b.into_iter().filter(|attr| a.contains(attr)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn merged(defaults: &DefaultAttributes, merge: AttributeMerge, params: QueryParams) -> serde_json::Value {
+        let mut params = params;
+        defaults.apply(&mut params, Some(merge));
+        serde_json::json!([params.include_attributes, params.exclude_attributes])
+    }
+
+    fn list(attrs: &[&str]) -> Option<IncludeAttributes> {
+        Some(IncludeAttributes::List(attrs.iter().map(|a| a.to_string()).collect()))
+    }
+
+    #[test]
+    fn test_per_call_projection_replaces_defaults() {
+        let defaults = DefaultAttributes {
+            exclude_attributes: Some(vec!["vector".into()]),
+            ..DefaultAttributes::include(["title", "url"])
+        };
+        let own = QueryParams { include_attributes: list(&["body"]), ..Default::default() };
+        assert_eq!(merged(&defaults, AttributeMerge::PerCall, own), serde_json::json!([["body"], null]));
+        assert_eq!(
+            merged(&defaults, AttributeMerge::PerCall, QueryParams::default()),
+            serde_json::json!([["title", "url"], ["vector"]])
+        );
+    }
+
+    #[test]
+    fn test_union_returns_attributes_either_side_asks_for() {
+        let defaults = DefaultAttributes {
+            exclude_attributes: Some(vec!["vector".into(), "raw".into()]),
+            ..DefaultAttributes::include(["title", "url"])
+        };
+        let own = QueryParams {
+            include_attributes: list(&["body", "title"]),
+            exclude_attributes: Some(vec!["raw".into()]),
+            ..Default::default()
+        };
+        let expected = serde_json::json!([["title", "url", "body"], ["raw"]]);
+        assert_eq!(merged(&defaults, AttributeMerge::Union, own), expected);
+
+        let everything = QueryParams { include_attributes: Some(IncludeAttributes::All(true)), ..Default::default() };
+        assert_eq!(merged(&defaults, AttributeMerge::Union, everything), serde_json::json!([true, ["vector", "raw"]]));
+    }
+
+    #[test]
+    fn test_intersection_returns_attributes_both_sides_allow() {
+        let defaults = DefaultAttributes::exclude(["vector"]).merge(AttributeMerge::Intersection);
+        let own = QueryParams {
+            include_attributes: list(&["title", "vector"]),
+            exclude_attributes: Some(vec!["title".into()]),
+            ..Default::default()
+        };
+        // The default merge applies when the call doesn't choose one.
+        let mut params = own.clone();
+        defaults.apply(&mut params, None);
+        assert_eq!(serde_json::to_value(&params.exclude_attributes).unwrap(), serde_json::json!(["vector", "title"]));
+
+        let defaults = DefaultAttributes::include(["title", "url"]);
+        assert_eq!(merged(&defaults, AttributeMerge::Intersection, own), serde_json::json!([["title"], ["title"]]));
+        let nothing = QueryParams { include_attributes: Some(IncludeAttributes::All(false)), ..Default::default() };
+        assert_eq!(merged(&defaults, AttributeMerge::Intersection, nothing), serde_json::json!([false, null]));
+    }
+}
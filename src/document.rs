@@ -0,0 +1,144 @@
+use std::marker::PhantomData;
+
+use serde::de::DeserializeOwned;
+
+#[cfg(feature = "client")]
+use crate::{Namespace, QueryParams};
+use crate::{Error, IncludeAttributes, Result};
+
+/// A type that query rows deserialize into, with the attributes it reads.
+///
+/// With the `derive` feature, `#[derive(Document)]` implements this from the
+/// struct's fields, following serde renames:
+///
+/// ```ignore
+/// #[derive(serde::Deserialize, rs_puff::Document)]
+/// struct Article {
+///     id: u64,
+///     title: String,
+///     #[serde(rename = "url")]
+///     link: String,
+/// }
+///
+/// assert_eq!(Article::ATTRIBUTES, ["id", "title", "url"]);
+/// ```
+pub trait Document: DeserializeOwned {
+    /// Attribute names the type deserializes from.
+    const ATTRIBUTES: &'static [&'static str];
+
+    /// [`Self::ATTRIBUTES`] as an `include_attributes` value.
+    fn include_attributes() -> IncludeAttributes {
+        IncludeAttributes::List(Self::ATTRIBUTES.iter().map(|a| a.to_string()).collect())
+    }
+
+    /// Fetch only some of the type's attributes; see [`Selection`].
+    ///
+    /// Fails with [`Error::InvalidParams`] if an attribute isn't one of
+    /// [`Self::ATTRIBUTES`].
+    fn select<I, S>(attributes: I) -> Result<Selection<Self>>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let attributes = attributes
+            .into_iter()
+            .map(|attr| {
+                let attr = attr.as_ref();
+                Self::ATTRIBUTES
+                    .iter()
+                    .find(|known| **known == attr)
+                    .copied()
+                    .ok_or_else(|| Error::InvalidParams(format!("{} is not an attribute of {}", attr, std::any::type_name::<Self>())))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Selection { attributes, _marker: PhantomData })
+    }
+}
+
+/// A subset of a [`Document`]'s attributes, from [`Document::select`].
+///
+/// Rows are fetched with only these attributes (plus `id`) and deserialized
+/// into the full type, so fields left out of the selection must be
+/// `Option`s or have `#[serde(default)]`.
+#[derive(Debug)]
+pub struct Selection<T> {
+    attributes: Vec<&'static str>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Clone for Selection<T> {
+    fn clone(&self) -> Self {
+        Self { attributes: self.attributes.clone(), _marker: PhantomData }
+    }
+}
+
+impl<T: Document> Selection<T> {
+    pub fn attributes(&self) -> &[&'static str] {
+        &self.attributes
+    }
+
+    pub fn include_attributes(&self) -> IncludeAttributes {
+        IncludeAttributes::List(self.attributes.iter().map(|a| a.to_string()).collect())
+    }
+}
+
+#[cfg(feature = "client")]
+impl Namespace {
+    /// Like [`Namespace::query_as`], but fetches only the attributes `T`
+    /// reads unless `params.include_attributes` is already set.
+    pub async fn query_documents<T: Document>(&self, mut params: QueryParams) -> Result<Vec<T>> {
+        params.include_attributes.get_or_insert_with(T::include_attributes);
+        self.query_as(params).await
+    }
+
+    /// Query with `selection`'s attributes, replacing
+    /// `params.include_attributes`, and deserialize rows into `T`.
+    pub async fn query_selected<T: Document>(&self, selection: &Selection<T>, mut params: QueryParams) -> Result<Vec<T>> {
+        params.include_attributes = Some(selection.include_attributes());
+        self.query_as(params).await
+    }
+}
+
+#[cfg(all(test, feature = "derive"))]
+mod tests {
+    use super::*;
+
+    #[allow(dead_code)]
+    #[derive(serde::Deserialize, rs_puff::Document)]
+    #[serde(rename_all = "camelCase")]
+    struct Article {
+        id: u64,
+        page_title: String,
+        #[serde(rename = "url")]
+        link: String,
+        #[serde(skip)]
+        cached: bool,
+    }
+
+    #[test]
+    fn test_derive_lists_renamed_attributes() {
+        assert_eq!(Article::ATTRIBUTES, ["id", "pageTitle", "url"]);
+    }
+
+    #[derive(Debug, PartialEq, serde::Deserialize, rs_puff::Document)]
+    struct Page {
+        id: u64,
+        title: Option<String>,
+        #[serde(default)]
+        url: String,
+        body: Option<String>,
+    }
+
+    #[test]
+    fn test_select_builds_projection_and_partial_rows_deserialize() {
+        let selection = Page::select(["title", "url"]).unwrap();
+        assert_eq!(selection.attributes(), ["title", "url"]);
+        assert_eq!(serde_json::to_value(selection.include_attributes()).unwrap(), serde_json::json!(["title", "url"]));
+        assert!(Page::select(["title", "secret"]).is_err());
+
+        let resp: crate::QueryResponse =
+            serde_json::from_value(serde_json::json!({"rows": [{"id": 1, "title": "Hello"}]})).unwrap();
+        let pages: Vec<Page> = resp.rows_as().unwrap();
+        assert_eq!(pages, [Page { id: 1, title: Some("Hello".to_string()), url: String::new(), body: None }]);
+    }
+}
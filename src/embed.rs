@@ -0,0 +1,90 @@
+use std::future::Future;
+
+use crate::Result;
+
+/// Turns text into vectors, e.g. by calling an embedding API.
+pub trait Embedder {
+    /// Embed each text; the output must have one vector per input, in order.
+    fn embed(&self, texts: &[String]) -> impl Future<Output = Result<Vec<Vec<f32>>>> + Send;
+}
+
+/// Deterministic embedder for tests: hashes each word into a fixed-size,
+/// unit-length vector without any network calls.
+///
+/// Texts sharing words get similar vectors, so rankings are meaningful as well
+/// as reproducible across runs and platforms. Texts without words map to the
+/// first basis vector.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HashEmbedder {
+    dimensions: usize,
+}
+
+impl HashEmbedder {
+    pub fn new(dimensions: usize) -> Self {
+        Self { dimensions: dimensions.max(1) }
+    }
+
+    pub fn dimensions(&self) -> usize {
+        self.dimensions
+    }
+
+    pub fn embed_one(&self, text: &str) -> Vec<f32> {
+        let mut vector = vec![0f32; self.dimensions];
+        for word in text.split(|c: char| !c.is_alphanumeric()).filter(|w| !w.is_empty()) {
+            let hash = fnv1a(word.to_lowercase().as_bytes());
+            let sign = if hash >> 63 == 0 { 1.0 } else { -1.0 };
+            vector[(hash % self.dimensions as u64) as usize] += sign;
+        }
+        let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+        if norm == 0.0 {
+            vector[0] = 1.0;
+        } else {
+            vector.iter_mut().for_each(|x| *x /= norm);
+        }
+        vector
+    }
+}
+
+impl Embedder for HashEmbedder {
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        Ok(texts.iter().map(|text| self.embed_one(text)).collect())
+    }
+}
+
+/// 64-bit FNV-1a; unlike `std`'s hashers it is stable across releases.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &b| (hash ^ b as u64).wrapping_mul(0x0100_0000_01b3))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dot(a: &[f32], b: &[f32]) -> f32 {
+        a.iter().zip(b).map(|(x, y)| x * y).sum()
+    }
+
+    #[test]
+    fn test_hash_embedder_is_stable_and_normalized() {
+        let embedder = HashEmbedder::new(64);
+        let v = embedder.embed_one("The quick brown fox");
+        assert_eq!(v.len(), 64);
+        assert!((dot(&v, &v) - 1.0).abs() < 1e-6);
+        assert_eq!(v, embedder.embed_one("the QUICK brown fox!"));
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+
+        let empty = embedder.embed_one("  ...  ");
+        assert_eq!(empty[0], 1.0);
+        assert_eq!(dot(&empty, &empty), 1.0);
+    }
+
+    #[tokio::test]
+    async fn test_hash_embedder_ranks_by_shared_words() {
+        let embedder = HashEmbedder::new(256);
+        let texts = ["rust vector database client", "rust client", "banana bread recipe"].map(String::from);
+        let vectors = embedder.embed(&texts).await.unwrap();
+        let query = embedder.embed_one("rust vector client");
+        let scores: Vec<f32> = vectors.iter().map(|v| dot(&query, v)).collect();
+        assert!(scores[0] > scores[1] && scores[1] > scores[2], "{scores:?}");
+    }
+}
@@ -0,0 +1,734 @@
+//! The emulator's in-memory backend: namespaces of rows, and the write and
+//! query semantics applied to them. Requests and responses are handled as
+//! JSON, as they travel over the wire.
+
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+use serde_json::{Map, Value, json};
+
+use crate::{AttributeType, Row};
+use crate::paginate::compare_values;
+
+const DEFAULT_TOP_K: usize = 10;
+const DEFAULT_PAGE_SIZE: usize = 1000;
+
+const WRITE_KEYS: &[&str] = &[
+    "upsert_rows",
+    "upsert_columns",
+    "patch_rows",
+    "patch_columns",
+    "deletes",
+    "delete_by_filter",
+    "patch_by_filter",
+    "upsert_condition",
+    "patch_condition",
+    "delete_condition",
+    "distance_metric",
+    "schema",
+    "delete_by_filter_allow_partial",
+    "patch_by_filter_allow_partial",
+    "disable_backpressure",
+    "return_affected_ids",
+    "copy_from_namespace",
+    "encryption",
+];
+
+const QUERY_KEYS: &[&str] = &[
+    "rank_by",
+    "top_k",
+    "filters",
+    "include_attributes",
+    "exclude_attributes",
+    "vector_encoding",
+    "distance_metric",
+    "consistency",
+    "aggregate_by",
+    "group_by",
+];
+
+const MULTI_QUERY_KEYS: &[&str] = &["queries", "vector_encoding", "consistency"];
+
+/// A request the backend refused, and the status to answer it with.
+#[derive(Debug)]
+pub(crate) struct Rejection {
+    pub status: u16,
+    pub message: String,
+}
+
+type Outcome<T = Value> = std::result::Result<T, Rejection>;
+
+fn bad_request(message: impl Into<String>) -> Rejection {
+    Rejection { status: 400, message: message.into() }
+}
+
+fn not_found(namespace: &str) -> Rejection {
+    Rejection { status: 404, message: format!("namespace '{}' was not found", namespace) }
+}
+
+fn check_keys(body: &Map<String, Value>, allowed: &[&str], what: &str) -> Outcome<()> {
+    match body.keys().find(|key| !allowed.contains(&key.as_str())) {
+        Some(key) => Err(bad_request(format!("unknown {} parameter {:?}", what, key))),
+        None => Ok(()),
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Store {
+    namespaces: BTreeMap<String, Namespace>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Namespace {
+    /// Keyed by the id's JSON, so `1` and `"1"` are different rows.
+    rows: BTreeMap<String, Row>,
+    distance_metric: Option<String>,
+    /// Attributes configured explicitly; the rest are inferred from rows.
+    schema: Map<String, Value>,
+}
+
+#[derive(Default)]
+struct Affected {
+    upserted: Vec<Value>,
+    patched: Vec<Value>,
+    deleted: Vec<Value>,
+}
+
+impl Store {
+    pub fn write(&mut self, name: &str, body: Value) -> Outcome {
+        let body = object(body, "write body")?;
+        check_keys(&body, WRITE_KEYS, "write")?;
+        // Applied to a copy, so a rejected write changes nothing.
+        let mut ns = match body.get("copy_from_namespace") {
+            Some(source) => {
+                let source = source.as_str().ok_or_else(|| bad_request("copy_from_namespace must be a string"))?;
+                self.namespaces.get(source).cloned().ok_or_else(|| not_found(source))?
+            }
+            None => self.namespaces.get(name).cloned().unwrap_or_default(),
+        };
+        if let Some(metric) = body.get("distance_metric") {
+            let metric = metric.as_str().ok_or_else(|| bad_request("distance_metric must be a string"))?;
+            ns.distance_metric = Some(metric.to_string());
+        }
+        if let Some(schema) = body.get("schema") {
+            ns.schema.extend(object(schema.clone(), "schema")?);
+        }
+        let affected = ns.apply(&body)?;
+        self.namespaces.insert(name.to_string(), ns);
+
+        let mut resp = json!({
+            "rows_affected": affected.upserted.len() + affected.patched.len() + affected.deleted.len(),
+            "rows_upserted": affected.upserted.len(),
+            "rows_patched": affected.patched.len(),
+            "rows_deleted": affected.deleted.len(),
+        });
+        if body.get("return_affected_ids") == Some(&Value::Bool(true)) {
+            resp["upserted_ids"] = Value::Array(affected.upserted);
+            resp["patched_ids"] = Value::Array(affected.patched);
+            resp["deleted_ids"] = Value::Array(affected.deleted);
+        }
+        Ok(resp)
+    }
+
+    pub fn query(&self, name: &str, body: Value) -> Outcome {
+        let ns = self.namespaces.get(name).ok_or_else(|| not_found(name))?;
+        let body = object(body, "query body")?;
+        let Some(queries) = body.get("queries") else {
+            check_keys(&body, QUERY_KEYS, "query")?;
+            return ns.query(&body);
+        };
+        check_keys(&body, MULTI_QUERY_KEYS, "multi-query")?;
+        let queries = queries.as_array().ok_or_else(|| bad_request("queries must be an array"))?;
+        let results = queries
+            .iter()
+            .map(|query| {
+                let query = object(query.clone(), "query")?;
+                check_keys(&query, QUERY_KEYS, "query")?;
+                ns.query(&query)
+            })
+            .collect::<Outcome<Vec<_>>>()?;
+        Ok(json!({ "results": results }))
+    }
+
+    pub fn delete_all(&mut self, name: &str) -> Outcome {
+        self.namespaces.remove(name).ok_or_else(|| not_found(name))?;
+        Ok(json!({ "status": "ok" }))
+    }
+
+    pub fn metadata(&self, name: &str) -> Outcome {
+        let ns = self.namespaces.get(name).ok_or_else(|| not_found(name))?;
+        let bytes: usize = ns.rows.values().map(|row| json!(row).to_string().len()).sum();
+        Ok(json!({
+            "approx_row_count": ns.rows.len(),
+            "approx_logical_bytes": bytes,
+            "schema": ns.schema(),
+        }))
+    }
+
+    pub fn schema(&self, name: &str) -> Outcome {
+        let ns = self.namespaces.get(name).ok_or_else(|| not_found(name))?;
+        Ok(Value::Object(ns.schema()))
+    }
+
+    pub fn update_schema(&mut self, name: &str, body: Value) -> Outcome {
+        let ns = self.namespaces.get_mut(name).ok_or_else(|| not_found(name))?;
+        ns.schema.extend(object(body, "schema")?);
+        Ok(Value::Object(ns.schema()))
+    }
+
+    pub fn hint_cache_warm(&self, name: &str) -> Outcome {
+        self.namespaces.get(name).ok_or_else(|| not_found(name))?;
+        Ok(json!({ "status": "OK" }))
+    }
+
+    pub fn list(&self, prefix: Option<&str>, cursor: Option<&str>, page_size: Option<usize>) -> Outcome {
+        let page_size = page_size.unwrap_or(DEFAULT_PAGE_SIZE).max(1);
+        let mut names = self
+            .namespaces
+            .keys()
+            .filter(|name| prefix.is_none_or(|prefix| name.starts_with(prefix)))
+            .filter(|name| cursor.is_none_or(|cursor| name.as_str() > cursor));
+        let page: Vec<&String> = names.by_ref().take(page_size).collect();
+        let next_cursor = match names.next() {
+            Some(_) => page.last().map(|name| json!(name)),
+            None => None,
+        };
+        let namespaces: Vec<Value> = page.into_iter().map(|name| json!({ "id": name })).collect();
+        Ok(json!({ "namespaces": namespaces, "next_cursor": next_cursor }))
+    }
+}
+
+impl Namespace {
+    /// Apply a write's operations in the API's order: delete by filter,
+    /// patch by filter, upserts, patches, deletes.
+    fn apply(&mut self, body: &Map<String, Value>) -> Outcome<Affected> {
+        let mut affected = Affected::default();
+        if let Some(filter) = body.get("delete_by_filter") {
+            for key in self.matching(filter)? {
+                if let Some(row) = self.rows.remove(&key) {
+                    affected.deleted.push(row["id"].clone());
+                }
+            }
+        }
+        if let Some(patch_by_filter) = body.get("patch_by_filter") {
+            let filter = patch_by_filter.get("filters").ok_or_else(|| bad_request("patch_by_filter needs filters"))?;
+            let patch = object(patch_by_filter.get("patch").cloned().unwrap_or_default(), "patch")?;
+            for key in self.matching(filter)? {
+                let row = self.rows.get_mut(&key).expect("matching keys exist");
+                row.extend(patch.clone());
+                affected.patched.push(row["id"].clone());
+            }
+        }
+
+        for row in rows(body, "upsert_rows", "upsert_columns")? {
+            let key = row_key(&row)?;
+            if let Some(condition) = body.get("upsert_condition")
+                && let Some(existing) = self.rows.get(&key)
+                && !matches(&bind_refs(condition, &row), existing)?
+            {
+                continue;
+            }
+            affected.upserted.push(row["id"].clone());
+            self.rows.insert(key, row);
+        }
+        for patch in rows(body, "patch_rows", "patch_columns")? {
+            let key = row_key(&patch)?;
+            let Some(existing) = self.rows.get_mut(&key) else {
+                continue;
+            };
+            if let Some(condition) = body.get("patch_condition")
+                && !matches(&bind_refs(condition, &patch), existing)?
+            {
+                continue;
+            }
+            affected.patched.push(patch["id"].clone());
+            existing.extend(patch);
+        }
+        if let Some(deletes) = body.get("deletes") {
+            let deletes = deletes.as_array().ok_or_else(|| bad_request("deletes must be an array"))?;
+            for id in deletes {
+                let key = id_key(id)?;
+                if let Some(condition) = body.get("delete_condition")
+                    && let Some(existing) = self.rows.get(&key)
+                    && !matches(condition, existing)?
+                {
+                    continue;
+                }
+                if self.rows.remove(&key).is_some() {
+                    affected.deleted.push(id.clone());
+                }
+            }
+        }
+        Ok(affected)
+    }
+
+    /// Keys of the rows matching `filter`.
+    fn matching(&self, filter: &Value) -> Outcome<Vec<String>> {
+        let mut keys = Vec::new();
+        for (key, row) in &self.rows {
+            if matches(filter, row)? {
+                keys.push(key.clone());
+            }
+        }
+        Ok(keys)
+    }
+
+    fn query(&self, query: &Map<String, Value>) -> Outcome {
+        let mut rows = Vec::new();
+        for row in self.rows.values() {
+            if query.get("filters").is_none_or(|filter| matches(filter, row).unwrap_or(false)) {
+                rows.push(row);
+            }
+        }
+        // Surface malformed filters even when no row reached them.
+        if let (Some(filter), Some(row)) = (query.get("filters"), self.rows.values().next()) {
+            matches(filter, row)?;
+        }
+        let top_k = match query.get("top_k") {
+            Some(top_k) => top_k.as_u64().ok_or_else(|| bad_request("top_k must be a positive integer"))? as usize,
+            None => DEFAULT_TOP_K,
+        };
+        if let Some(aggregate_by) = query.get("aggregate_by") {
+            return aggregate(&rows, aggregate_by, query.get("group_by"), top_k);
+        }
+
+        let mut ranked = self.rank(rows, query.get("rank_by"))?;
+        ranked.truncate(top_k);
+        let rows = ranked
+            .into_iter()
+            .map(|(row, rank)| {
+                let mut out = project(row, query)?;
+                match rank {
+                    Rank::Distance(dist) => out.insert("$dist".to_string(), json!(dist)),
+                    Rank::Score(score) => out.insert("$score".to_string(), json!(score)),
+                    Rank::Order => None,
+                };
+                Ok(Value::Object(out))
+            })
+            .collect::<Outcome<Vec<_>>>()?;
+        Ok(json!({ "rows": rows }))
+    }
+
+    fn rank<'a>(&self, rows: Vec<&'a Row>, rank_by: Option<&Value>) -> Outcome<Vec<(&'a Row, Rank)>> {
+        let by_id = |a: &&Row, b: &&Row| compare_values(a.get("id"), b.get("id"));
+        let Some(rank_by) = rank_by else {
+            let mut rows = rows;
+            rows.sort_by(by_id);
+            return Ok(rows.into_iter().map(|row| (row, Rank::Order)).collect());
+        };
+        let parts = rank_by.as_array().ok_or_else(|| bad_request("rank_by must be an array"))?;
+        match parts.as_slice() {
+            [Value::String(attr), Value::String(op), query] if op == "ANN" || op == "kNN" => {
+                let query = vector(query).ok_or_else(|| bad_request("rank_by vector must be an array of numbers"))?;
+                let metric = self.distance_metric.as_deref().unwrap_or("cosine_distance");
+                let mut ranked: Vec<(&Row, f64)> = Vec::new();
+                for row in rows {
+                    if let Some(v) = row.get(attr).and_then(vector) {
+                        if v.len() != query.len() {
+                            return Err(bad_request(format!(
+                                "query vector has {} dimensions, {} has {}",
+                                query.len(),
+                                attr,
+                                v.len()
+                            )));
+                        }
+                        ranked.push((row, distance(metric, &v, &query)?));
+                    }
+                }
+                ranked.sort_by(|a, b| a.1.total_cmp(&b.1).then_with(|| by_id(&a.0, &b.0)));
+                Ok(ranked.into_iter().map(|(row, dist)| (row, Rank::Distance(dist))).collect())
+            }
+            [Value::String(attr), Value::String(order)] if order == "asc" || order == "desc" => {
+                let mut rows = rows;
+                rows.sort_by(|a, b| {
+                    let ord = compare_values(a.get(attr), b.get(attr));
+                    let ord = if order == "desc" { ord.reverse() } else { ord };
+                    ord.then_with(|| by_id(a, b))
+                });
+                Ok(rows.into_iter().map(|row| (row, Rank::Order)).collect())
+            }
+            _ => {
+                let mut ranked: Vec<(&Row, f64)> = Vec::new();
+                for row in rows {
+                    let score = text_score(rank_by, row)?;
+                    if score > 0.0 {
+                        ranked.push((row, score));
+                    }
+                }
+                ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| by_id(&a.0, &b.0)));
+                Ok(ranked.into_iter().map(|(row, score)| (row, Rank::Score(score))).collect())
+            }
+        }
+    }
+
+    /// Explicitly configured attributes, plus types inferred from the rows.
+    fn schema(&self) -> Map<String, Value> {
+        let mut schema = Map::new();
+        for row in self.rows.values() {
+            for (attr, value) in row {
+                if schema.contains_key(attr) || value.is_null() {
+                    continue;
+                }
+                if let Some(inferred) = self.infer_type(attr, value) {
+                    schema.insert(attr.clone(), inferred);
+                }
+            }
+        }
+        schema.extend(self.schema.clone());
+        schema
+    }
+
+    fn infer_type(&self, attr: &str, value: &Value) -> Option<Value> {
+        let scalar = |value: &Value| match value {
+            Value::String(_) => Some("string"),
+            Value::Bool(_) => Some("bool"),
+            Value::Number(n) if n.is_u64() => Some("uint"),
+            Value::Number(n) if n.is_i64() => Some("int"),
+            Value::Number(_) => Some("float"),
+            _ => None,
+        };
+        match value {
+            Value::Array(items) if attr == "vector" => Some(json!({
+                "type": AttributeType::vector_f32(items.len()),
+                "ann": { "distance_metric": self.distance_metric.as_deref().unwrap_or("cosine_distance") },
+            })),
+            Value::Array(items) => Some(json!({ "type": format!("[]{}", items.first().and_then(scalar)?) })),
+            _ if attr == "id" && value.is_string() => Some(json!({ "type": "string" })),
+            _ => Some(json!({ "type": scalar(value)? })),
+        }
+    }
+}
+
+enum Rank {
+    Distance(f64),
+    Score(f64),
+    Order,
+}
+
+fn object(value: Value, what: &str) -> Outcome<Map<String, Value>> {
+    match value {
+        Value::Object(map) => Ok(map),
+        _ => Err(bad_request(format!("{} must be an object", what))),
+    }
+}
+
+fn id_key(id: &Value) -> Outcome<String> {
+    match id {
+        Value::Number(n) if n.is_u64() => Ok(id.to_string()),
+        Value::String(_) => Ok(id.to_string()),
+        _ => Err(bad_request(format!("invalid id {}: must be an unsigned integer or a string", id))),
+    }
+}
+
+fn row_key(row: &Row) -> Outcome<String> {
+    id_key(row.get("id").ok_or_else(|| bad_request("every row needs an id"))?)
+}
+
+/// The rows of a write, given either row-wise or column-wise.
+fn rows(body: &Map<String, Value>, rows_key: &str, columns_key: &str) -> Outcome<Vec<Row>> {
+    let mut out = Vec::new();
+    if let Some(rows) = body.get(rows_key) {
+        let rows = rows.as_array().ok_or_else(|| bad_request(format!("{} must be an array", rows_key)))?;
+        for row in rows {
+            out.push(object(row.clone(), "row")?.into_iter().collect());
+        }
+    }
+    if let Some(columns) = body.get(columns_key) {
+        let columns = object(columns.clone(), columns_key)?;
+        let ids = columns
+            .get("id")
+            .and_then(Value::as_array)
+            .ok_or_else(|| bad_request(format!("{} needs an id column", columns_key)))?;
+        for (attr, values) in &columns {
+            if values.as_array().is_none_or(|values| values.len() != ids.len()) {
+                return Err(bad_request(format!("column {} must have one value per id", attr)));
+            }
+        }
+        for i in 0..ids.len() {
+            out.push(columns.iter().map(|(attr, values)| (attr.clone(), values[i].clone())).collect());
+        }
+    }
+    Ok(out)
+}
+
+/// Replace `{"$ref_new": attr}` operands in a condition with the new row's values.
+fn bind_refs(condition: &Value, new: &Row) -> Value {
+    match condition {
+        Value::Object(map) if map.len() == 1 && map.contains_key("$ref_new") => map["$ref_new"]
+            .as_str()
+            .and_then(|attr| new.get(attr))
+            .cloned()
+            .unwrap_or(Value::Null),
+        Value::Array(items) => Value::Array(items.iter().map(|item| bind_refs(item, new)).collect()),
+        other => other.clone(),
+    }
+}
+
+fn matches(filter: &Value, row: &Row) -> Outcome<bool> {
+    let malformed = || bad_request(format!("malformed filter {}", filter));
+    let parts = filter.as_array().ok_or_else(malformed)?;
+    match parts.as_slice() {
+        [Value::String(op), operand] => match op.as_str() {
+            "And" | "Or" => {
+                let children = operand.as_array().ok_or_else(malformed)?;
+                let mut results = children.iter().map(|child| matches(child, row));
+                if op == "And" {
+                    results.try_fold(true, |all, m| Ok(all && m?))
+                } else {
+                    results.try_fold(false, |any, m| Ok(any || m?))
+                }
+            }
+            "Not" => Ok(!matches(operand, row)?),
+            _ => Err(malformed()),
+        },
+        [Value::String(attr), Value::String(op), operand, ..] => {
+            let value = row.get(attr).unwrap_or(&Value::Null);
+            let items = || value.as_array().map(Vec::as_slice).unwrap_or_default();
+            let operands = || operand.as_array().ok_or_else(malformed);
+            let pattern = || operand.as_str().ok_or_else(malformed);
+            Ok(match op.as_str() {
+                "Eq" => same(value, operand),
+                "NotEq" => !same(value, operand),
+                "Lt" => order(value, operand) == Some(Ordering::Less),
+                "Lte" => matches!(order(value, operand), Some(Ordering::Less | Ordering::Equal)),
+                "Gt" => order(value, operand) == Some(Ordering::Greater),
+                "Gte" => matches!(order(value, operand), Some(Ordering::Greater | Ordering::Equal)),
+                "AnyLt" => items().iter().any(|item| order(item, operand) == Some(Ordering::Less)),
+                "AnyLte" => items().iter().any(|item| matches!(order(item, operand), Some(Ordering::Less | Ordering::Equal))),
+                "AnyGt" => items().iter().any(|item| order(item, operand) == Some(Ordering::Greater)),
+                "AnyGte" => items().iter().any(|item| matches!(order(item, operand), Some(Ordering::Greater | Ordering::Equal))),
+                "In" => operands()?.iter().any(|candidate| same(value, candidate)),
+                "NotIn" => !operands()?.iter().any(|candidate| same(value, candidate)),
+                "Contains" => items().iter().any(|item| same(item, operand)),
+                "NotContains" => !items().iter().any(|item| same(item, operand)),
+                "ContainsAny" => operands()?.iter().any(|candidate| items().iter().any(|item| same(item, candidate))),
+                "NotContainsAny" => !operands()?.iter().any(|candidate| items().iter().any(|item| same(item, candidate))),
+                "Glob" => text_glob(value, pattern()?, false),
+                "NotGlob" => !text_glob(value, pattern()?, false),
+                "IGlob" => text_glob(value, pattern()?, true),
+                "NotIGlob" => !text_glob(value, pattern()?, true),
+                "ContainsAllTokens" => {
+                    let text = tokens(value.as_str().unwrap_or_default());
+                    tokens(pattern()?).iter().all(|token| text.contains(token))
+                }
+                "ContainsTokenSequence" => {
+                    let text = tokens(value.as_str().unwrap_or_default());
+                    let sequence = tokens(pattern()?);
+                    sequence.is_empty() || text.windows(sequence.len()).any(|window| window == sequence)
+                }
+                other => return Err(bad_request(format!("filter operator {} isn't supported by the emulator", other))),
+            })
+        }
+        _ => Err(malformed()),
+    }
+}
+
+/// Equality with numbers compared by value, so `1` equals `1.0`.
+fn same(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => a.as_f64() == b.as_f64(),
+        _ => a == b,
+    }
+}
+
+/// Order between values of the same kind; `None` for anything else.
+fn order(a: &Value, b: &Value) -> Option<Ordering> {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => a.as_f64()?.partial_cmp(&b.as_f64()?),
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        (Value::Bool(a), Value::Bool(b)) => Some(a.cmp(b)),
+        _ => None,
+    }
+}
+
+/// `*` matches any run of characters, `?` any single one.
+fn glob(pattern: &[u8], s: &[u8]) -> bool {
+    match (pattern.first(), s.first()) {
+        (None, _) => s.is_empty(),
+        (Some(b'*'), _) => glob(&pattern[1..], s) || (!s.is_empty() && glob(pattern, &s[1..])),
+        (Some(b'?'), Some(_)) => glob(&pattern[1..], &s[1..]),
+        (Some(p), Some(c)) => p == c && glob(&pattern[1..], &s[1..]),
+        (Some(_), None) => false,
+    }
+}
+
+fn text_glob(value: &Value, pattern: &str, case_insensitive: bool) -> bool {
+    match value.as_str() {
+        Some(s) if case_insensitive => glob(pattern.to_lowercase().as_bytes(), s.to_lowercase().as_bytes()),
+        Some(s) => glob(pattern.as_bytes(), s.as_bytes()),
+        None => false,
+    }
+}
+
+fn tokens(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|token| !token.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+fn vector(value: &Value) -> Option<Vec<f64>> {
+    value.as_array()?.iter().map(Value::as_f64).collect()
+}
+
+fn distance(metric: &str, a: &[f64], b: &[f64]) -> Outcome<f64> {
+    match metric {
+        "euclidean_squared" => Ok(a.iter().zip(b).map(|(x, y)| (x - y) * (x - y)).sum()),
+        "cosine_distance" => {
+            let dot: f64 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+            let norm = |v: &[f64]| v.iter().map(|x| x * x).sum::<f64>().sqrt();
+            let norms = norm(a) * norm(b);
+            Ok(if norms == 0.0 { 1.0 } else { 1.0 - dot / norms })
+        }
+        other => Err(bad_request(format!("unknown distance metric {}", other))),
+    }
+}
+
+/// A term-frequency score standing in for BM25, with its combinators.
+fn text_score(rank_by: &Value, row: &Row) -> Outcome<f64> {
+    let unsupported = || bad_request(format!("rank_by {} isn't supported by the emulator", rank_by));
+    let parts = rank_by.as_array().ok_or_else(unsupported)?;
+    match parts.as_slice() {
+        [Value::String(op), Value::Array(subqueries)] if op == "Sum" || op == "Max" => {
+            let scores = subqueries.iter().map(|sub| text_score(sub, row)).collect::<Outcome<Vec<_>>>()?;
+            Ok(if op == "Sum" { scores.iter().sum() } else { scores.iter().copied().fold(0.0, f64::max) })
+        }
+        [Value::String(op), weight, subquery] if op == "Product" => {
+            Ok(weight.as_f64().ok_or_else(unsupported)? * text_score(subquery, row)?)
+        }
+        [Value::String(attr), Value::String(op), Value::String(query), ..] if op == "BM25" => {
+            let text = tokens(row.get(attr).and_then(Value::as_str).unwrap_or_default());
+            Ok(tokens(query)
+                .iter()
+                .map(|token| text.iter().filter(|t| *t == token).count() as f64)
+                .sum())
+        }
+        _ => Err(unsupported()),
+    }
+}
+
+/// The attributes of `row` a query asked for; `id` is always included.
+fn project(row: &Row, query: &Map<String, Value>) -> Outcome<Map<String, Value>> {
+    let mut out = Map::new();
+    out.insert("id".to_string(), row["id"].clone());
+    let exclude: Vec<&str> = match query.get("exclude_attributes") {
+        Some(Value::Array(attrs)) => attrs.iter().filter_map(Value::as_str).collect(),
+        Some(_) => return Err(bad_request("exclude_attributes must be an array")),
+        None => Vec::new(),
+    };
+    match query.get("include_attributes") {
+        Some(Value::Bool(true)) => out.extend(row.iter().map(|(k, v)| (k.clone(), v.clone()))),
+        Some(Value::Array(attrs)) => {
+            for attr in attrs.iter().filter_map(Value::as_str) {
+                if let Some(value) = row.get(attr) {
+                    out.insert(attr.to_string(), value.clone());
+                }
+            }
+        }
+        Some(Value::Bool(false)) | None if !exclude.is_empty() => {
+            out.extend(row.iter().map(|(k, v)| (k.clone(), v.clone())))
+        }
+        Some(Value::Bool(false)) | None => {}
+        Some(_) => return Err(bad_request("include_attributes must be a boolean or an array")),
+    }
+    out.retain(|attr, _| attr == "id" || !exclude.contains(&attr.as_str()));
+    Ok(out)
+}
+
+fn aggregate(rows: &[&Row], aggregate_by: &Value, group_by: Option<&Value>, top_k: usize) -> Outcome {
+    let aggregate_by = object(aggregate_by.clone(), "aggregate_by")?;
+    let compute = |rows: &[&Row]| -> Outcome<Map<String, Value>> {
+        let mut out = Map::new();
+        for (label, aggregation) in &aggregate_by {
+            let value = match aggregation.as_array().map(Vec::as_slice) {
+                Some([Value::String(op)]) if op == "Count" => json!(rows.len()),
+                Some([Value::String(op), Value::String(attr)]) if op == "Sum" => {
+                    json!(rows.iter().filter_map(|row| row.get(attr).and_then(Value::as_f64)).sum::<f64>())
+                }
+                _ => return Err(bad_request(format!("aggregation {} isn't supported by the emulator", aggregation))),
+            };
+            out.insert(label.clone(), value);
+        }
+        Ok(out)
+    };
+    let Some(group_by) = group_by else {
+        return Ok(json!({ "aggregations": compute(rows)? }));
+    };
+    let attrs: Vec<&str> = group_by
+        .as_array()
+        .ok_or_else(|| bad_request("group_by must be an array"))?
+        .iter()
+        .filter_map(Value::as_str)
+        .collect();
+    // Keyed by the group's JSON, which also orders the groups.
+    let mut groups: BTreeMap<String, (Map<String, Value>, Vec<&Row>)> = BTreeMap::new();
+    for row in rows {
+        let key: Map<String, Value> =
+            attrs.iter().map(|attr| (attr.to_string(), row.get(*attr).cloned().unwrap_or(Value::Null))).collect();
+        groups.entry(Value::Object(key.clone()).to_string()).or_insert_with(|| (key, Vec::new())).1.push(row);
+    }
+    let groups = groups
+        .into_values()
+        .take(top_k)
+        .map(|(mut key, rows)| {
+            key.extend(compute(&rows)?);
+            Ok(Value::Object(key))
+        })
+        .collect::<Outcome<Vec<_>>>()?;
+    Ok(json!({ "aggregation_groups": groups }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(value: Value) -> Row {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_filters_match_wire_format() {
+        let r = row(json!({"id": 1, "lang": "en", "tags": ["a", "b"], "price": 5, "title": "Fast vector search"}));
+        let filter = |f: crate::Filter| matches(&serde_json::to_value(f).unwrap(), &r).unwrap();
+        use crate::Filter;
+        assert!(filter(Filter::and(vec![Filter::eq("lang", "en"), Filter::gte("price", 5.0)])));
+        assert!(filter(Filter::or(vec![Filter::eq("lang", "de"), Filter::contains("tags", "b")])));
+        assert!(filter(Filter::not(Filter::r#in("id", vec![json!(2), json!(3)]))));
+        assert!(filter(Filter::eq("missing", Value::Null)));
+        assert!(filter(Filter::glob("title", "Fast*search")));
+        assert!(filter(Filter::iglob("title", "fast ?ector*")));
+        assert!(filter(Filter::contains_all_tokens("title", "search VECTOR")));
+        assert!(!filter(Filter::lt("lang", 3)));
+        assert!(matches(&json!(["title", "Regex", "x"]), &r).is_err());
+    }
+
+    #[test]
+    fn test_writes_apply_in_order_and_reject_unknown_params() {
+        let mut store = Store::default();
+        store
+            .write("ns", json!({"upsert_columns": {"id": [1, 2, 3], "n": [1, 2, 3]}, "distance_metric": "cosine_distance"}))
+            .unwrap();
+        let resp = store
+            .write(
+                "ns",
+                json!({
+                    "delete_by_filter": ["n", "Gte", 3],
+                    "patch_rows": [{"id": 1, "n": 10}, {"id": 9, "n": 9}],
+                    "upsert_rows": [{"id": 2, "n": 20}],
+                    "upsert_condition": ["n", "Lt", {"$ref_new": "n"}],
+                    "return_affected_ids": true,
+                }),
+            )
+            .unwrap();
+        assert_eq!(resp["rows_affected"], 3);
+        assert_eq!(resp["deleted_ids"], json!([3]));
+        assert_eq!(resp["patched_ids"], json!([1]));
+
+        let rows = store.query("ns", json!({"rank_by": ["n", "desc"], "include_attributes": ["n"]})).unwrap();
+        assert_eq!(rows["rows"], json!([{"id": 2, "n": 20}, {"id": 1, "n": 10}]));
+
+        let err = store.write("ns", json!({"upsert_row": []})).unwrap_err();
+        assert_eq!(err.status, 400);
+        assert_eq!(store.query("missing", json!({})).unwrap_err().status, 404);
+    }
+}
@@ -0,0 +1,305 @@
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use ring::aead::{AES_256_GCM, Aad, LessSafeKey, NONCE_LEN, Nonce, UnboundKey};
+use ring::rand::{SecureRandom, SystemRandom};
+
+use crate::base64;
+use crate::{Error, QueryResponse, Result, Row, WriteParams};
+
+/// Marks a stored value as ciphertext produced by [`AttributeEncryptor`].
+const PREFIX: &str = "enc:v1:";
+
+// Values sealed under one data key before a fresh one is requested, well
+// below the birthday bound for random 96-bit nonces.
+const KEY_USAGE_LIMIT: u64 = 1 << 30;
+
+/// A data key from [`KeyProvider::generate_data_key`].
+pub struct DataKey {
+    /// 32-byte AES-256-GCM key. Only ever held in memory.
+    pub plaintext: Vec<u8>,
+    /// The key encrypted under the provider's master key; stored alongside
+    /// every value sealed with it. At most 65535 bytes.
+    pub wrapped: Vec<u8>,
+}
+
+/// Issues and unwraps data keys, typically by calling a KMS.
+pub trait KeyProvider: Send + Sync {
+    fn generate_data_key(&self) -> impl Future<Output = Result<DataKey>> + Send;
+
+    /// Recover the plaintext of a key returned by `generate_data_key`.
+    fn unwrap_data_key(&self, wrapped: &[u8]) -> impl Future<Output = Result<Vec<u8>>> + Send;
+}
+
+struct CurrentKey {
+    wrapped: Vec<u8>,
+    key: Arc<LessSafeKey>,
+    uses: u64,
+}
+
+/// Envelope-encrypts selected attributes before they are written and
+/// decrypts them in query results.
+///
+/// Each value is serialized to JSON, sealed with AES-256-GCM under a data key
+/// from the [`KeyProvider`], and stored as a string holding the wrapped key,
+/// nonce and ciphertext. The attribute name is bound as associated data, so a
+/// ciphertext copied to another attribute fails to decrypt. Nulls are left
+/// as-is, as are plaintext values on read, so existing rows keep working
+/// while they are rewritten.
+///
+/// Encrypted attributes can't be filtered, sorted or full-text searched on;
+/// declare them as plain `string` attributes.
+pub struct AttributeEncryptor<K> {
+    provider: K,
+    attributes: HashSet<String>,
+    rng: SystemRandom,
+    current: Mutex<Option<CurrentKey>>,
+    // Unwrapped keys by wrapped key, so reads call the provider once per key.
+    keys: Mutex<HashMap<Vec<u8>, Arc<LessSafeKey>>>,
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+impl<K: KeyProvider> AttributeEncryptor<K> {
+    pub fn new<I, S>(provider: K, attributes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            provider,
+            attributes: attributes.into_iter().map(Into::into).collect(),
+            rng: SystemRandom::new(),
+            current: Mutex::new(None),
+            keys: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Encrypt the configured attributes in every upserted or patched row and
+    /// column, and in the values set by a patch by filter.
+    pub async fn encrypt_write(&self, params: &mut WriteParams) -> Result<()> {
+        for rows in [&mut params.upsert_rows, &mut params.patch_rows].into_iter().flatten() {
+            for row in rows {
+                self.encrypt_row(row).await?;
+            }
+        }
+        if let Some(patch_by_filter) = &mut params.patch_by_filter {
+            self.encrypt_row(&mut patch_by_filter.patch).await?;
+        }
+        for columns in [&mut params.upsert_columns, &mut params.patch_columns].into_iter().flatten() {
+            for (attribute, values) in columns.iter_mut() {
+                if self.attributes.contains(attribute) {
+                    for value in values {
+                        if !value.is_null() {
+                            *value = self.encrypt_value(attribute, value).await?;
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn encrypt_row(&self, row: &mut Row) -> Result<()> {
+        for attribute in &self.attributes {
+            if let Some(value) = row.get_mut(attribute)
+                && !value.is_null()
+            {
+                *value = self.encrypt_value(attribute, value).await?;
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn decrypt_response(&self, response: &mut QueryResponse) -> Result<()> {
+        for row in &mut response.rows {
+            self.decrypt_row(row).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn decrypt_row(&self, row: &mut Row) -> Result<()> {
+        for attribute in &self.attributes {
+            if let Some(serde_json::Value::String(stored)) = row.get(attribute)
+                && let Some(encoded) = stored.strip_prefix(PREFIX)
+            {
+                let value = self.decrypt_value(attribute, encoded).await?;
+                row.insert(attribute.clone(), value);
+            }
+        }
+        Ok(())
+    }
+
+    async fn encrypt_value(&self, attribute: &str, value: &serde_json::Value) -> Result<serde_json::Value> {
+        let (wrapped, key) = self.current_key().await?;
+        let mut nonce = [0u8; NONCE_LEN];
+        self.rng
+            .fill(&mut nonce)
+            .map_err(|_| Error::External("failed to generate a nonce".into()))?;
+        let mut sealed = serde_json::to_vec(value)?;
+        key.seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::from(attribute.as_bytes()), &mut sealed)
+            .map_err(|_| Error::External(format!("failed to encrypt attribute {:?}", attribute).into()))?;
+
+        let mut envelope = Vec::with_capacity(2 + wrapped.len() + NONCE_LEN + sealed.len());
+        envelope.extend_from_slice(&(wrapped.len() as u16).to_be_bytes());
+        envelope.extend_from_slice(&wrapped);
+        envelope.extend_from_slice(&nonce);
+        envelope.extend_from_slice(&sealed);
+        Ok(serde_json::Value::String(format!("{}{}", PREFIX, base64::encode(&envelope))))
+    }
+
+    async fn decrypt_value(&self, attribute: &str, encoded: &str) -> Result<serde_json::Value> {
+        let malformed = || Error::External(format!("malformed ciphertext in attribute {:?}", attribute).into());
+        let envelope = base64::decode(encoded).ok_or_else(malformed)?;
+        let (len, rest) = envelope.split_first_chunk::<2>().ok_or_else(malformed)?;
+        let wrapped_len = u16::from_be_bytes(*len) as usize;
+        if rest.len() < wrapped_len + NONCE_LEN {
+            return Err(malformed());
+        }
+        let (wrapped, rest) = rest.split_at(wrapped_len);
+        let (nonce, sealed) = rest.split_at(NONCE_LEN);
+
+        let key = self.key_for(wrapped).await?;
+        let nonce = Nonce::try_assume_unique_for_key(nonce).map_err(|_| malformed())?;
+        let mut sealed = sealed.to_vec();
+        let plaintext = key
+            .open_in_place(nonce, Aad::from(attribute.as_bytes()), &mut sealed)
+            .map_err(|_| Error::External(format!("failed to decrypt attribute {:?}", attribute).into()))?;
+        Ok(serde_json::from_slice(plaintext)?)
+    }
+
+    async fn current_key(&self) -> Result<(Vec<u8>, Arc<LessSafeKey>)> {
+        {
+            let mut current = lock(&self.current);
+            if let Some(current) = current.as_mut()
+                && current.uses < KEY_USAGE_LIMIT
+            {
+                current.uses += 1;
+                return Ok((current.wrapped.clone(), current.key.clone()));
+            }
+        }
+
+        let data_key = self.provider.generate_data_key().await?;
+        if data_key.wrapped.len() > u16::MAX as usize {
+            return Err(Error::InvalidParams("wrapped data keys must be at most 65535 bytes".into()));
+        }
+        let key = Arc::new(aes_key(&data_key.plaintext)?);
+        lock(&self.keys).insert(data_key.wrapped.clone(), key.clone());
+        *lock(&self.current) = Some(CurrentKey { wrapped: data_key.wrapped.clone(), key: key.clone(), uses: 1 });
+        Ok((data_key.wrapped, key))
+    }
+
+    async fn key_for(&self, wrapped: &[u8]) -> Result<Arc<LessSafeKey>> {
+        if let Some(key) = lock(&self.keys).get(wrapped) {
+            return Ok(key.clone());
+        }
+        let key = Arc::new(aes_key(&self.provider.unwrap_data_key(wrapped).await?)?);
+        lock(&self.keys).insert(wrapped.to_vec(), key.clone());
+        Ok(key)
+    }
+}
+
+fn aes_key(plaintext: &[u8]) -> Result<LessSafeKey> {
+    let key = UnboundKey::new(&AES_256_GCM, plaintext)
+        .map_err(|_| Error::InvalidParams("data keys must be 32 bytes".into()))?;
+    Ok(LessSafeKey::new(key))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    /// Wraps keys by XOR with a fixed master key; good enough to test the envelope.
+    #[derive(Default)]
+    struct XorProvider {
+        generated: AtomicUsize,
+        unwrapped: AtomicUsize,
+    }
+
+    const MASTER: u8 = 0x5a;
+
+    impl KeyProvider for XorProvider {
+        async fn generate_data_key(&self) -> Result<DataKey> {
+            let n = self.generated.fetch_add(1, Ordering::SeqCst) as u8;
+            let plaintext = vec![n; 32];
+            let wrapped = plaintext.iter().map(|b| b ^ MASTER).collect();
+            Ok(DataKey { plaintext, wrapped })
+        }
+
+        async fn unwrap_data_key(&self, wrapped: &[u8]) -> Result<Vec<u8>> {
+            self.unwrapped.fetch_add(1, Ordering::SeqCst);
+            Ok(wrapped.iter().map(|b| b ^ MASTER).collect())
+        }
+    }
+
+    fn row(value: serde_json::Value) -> Row {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_encrypts_configured_attributes_and_round_trips() {
+        let encryptor = AttributeEncryptor::new(XorProvider::default(), ["email", "ssn"]);
+        let original = row(serde_json::json!({"id": 1, "email": "a@example.com", "ssn": null, "title": "hi"}));
+        let mut written = original.clone();
+        encryptor.encrypt_row(&mut written).await.unwrap();
+
+        let stored = written["email"].as_str().unwrap();
+        assert!(stored.starts_with(PREFIX));
+        assert!(!stored.contains("example"));
+        assert_eq!(written["ssn"], serde_json::Value::Null);
+        assert_eq!(written["title"], "hi");
+
+        // A fresh encryptor has to unwrap the data key through the provider.
+        let reader = AttributeEncryptor::new(XorProvider::default(), ["email", "ssn"]);
+        let mut response: QueryResponse = serde_json::from_value(serde_json::json!({"rows": [written]})).unwrap();
+        reader.decrypt_response(&mut response).await.unwrap();
+        assert_eq!(response.rows[0], original);
+        assert_eq!(reader.provider.unwrapped.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_reuses_data_key_and_binds_attribute_name() {
+        let encryptor = AttributeEncryptor::new(XorProvider::default(), ["email", "phone"]);
+        let mut params = WriteParams {
+            upsert_rows: Some(vec![row(serde_json::json!({"id": 1, "email": "a"}))]),
+            patch_columns: Some(HashMap::from([("phone".to_string(), vec![serde_json::json!(5551234)])])),
+            ..Default::default()
+        };
+        encryptor.encrypt_write(&mut params).await.unwrap();
+        assert_eq!(encryptor.provider.generated.load(Ordering::SeqCst), 1);
+
+        // Ciphertext moved to another encrypted attribute is rejected.
+        let phone = params.patch_columns.unwrap()["phone"][0].clone();
+        let mut swapped = row(serde_json::json!({"id": 1, "email": phone}));
+        assert!(encryptor.decrypt_row(&mut swapped).await.is_err());
+
+        // Plaintext left over from before encryption was enabled passes through.
+        let mut legacy = row(serde_json::json!({"id": 2, "email": "b@example.com"}));
+        encryptor.decrypt_row(&mut legacy).await.unwrap();
+        assert_eq!(legacy["email"], "b@example.com");
+    }
+
+    #[tokio::test]
+    async fn test_encrypts_patch_by_filter_values() {
+        let encryptor = AttributeEncryptor::new(XorProvider::default(), ["email"]);
+        let mut params = WriteParams {
+            patch_by_filter: Some(crate::PatchByFilter {
+                filters: crate::Filter::eq("team", "ops"),
+                patch: row(serde_json::json!({"email": "ops@example.com", "team": "sre"})),
+            }),
+            ..Default::default()
+        };
+        encryptor.encrypt_write(&mut params).await.unwrap();
+
+        let mut patch = params.patch_by_filter.unwrap().patch;
+        assert!(patch["email"].as_str().unwrap().starts_with(PREFIX));
+        assert_eq!(patch["team"], "sre");
+        encryptor.decrypt_row(&mut patch).await.unwrap();
+        assert_eq!(patch["email"], "ops@example.com");
+    }
+}
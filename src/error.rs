@@ -1,15 +1,220 @@
+use std::time::Duration;
+
 use thiserror::Error;
 
+use crate::Id;
+#[cfg(feature = "client")]
+use crate::TransportError;
+
 #[derive(Error, Debug)]
 pub enum Error {
+    #[cfg(feature = "client")]
     #[error("HTTP error: {0}")]
     Http(#[from] reqwest::Error),
 
+    /// Sending a request failed before a response arrived: DNS, connect,
+    /// TLS, timeout or a dropped connection.
+    #[cfg(feature = "client")]
+    #[error("{0}")]
+    Transport(Box<TransportError>),
+
     #[error("API error ({status}): {message}")]
     Api { status: u16, message: String },
 
+    /// The server rejected the request with 429 Too Many Requests.
+    #[error("{0}")]
+    RateLimited(RateLimit),
+
+    /// Parameters the client rejected before sending a request.
+    #[error("invalid params: {0}")]
+    InvalidParams(String),
+
+    /// A response body didn't match the type it was decoded into.
+    #[error("failed to decode response as {target}: {source}; body: {body}")]
+    Decode {
+        /// Rust type the body was decoded into.
+        target: &'static str,
+        /// Start of the response body, truncated to a few KB.
+        body: String,
+        source: serde_json::Error,
+    },
+
     #[error("JSON error: {0}")]
     Json(#[from] serde_json::Error),
+
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// Error raised by user-supplied code such as a sync source adapter.
+    #[error("{0}")]
+    External(Box<dyn std::error::Error + Send + Sync>),
+
+    #[cfg(feature = "object_store")]
+    #[error("object store error: {0}")]
+    ObjectStore(#[from] object_store::Error),
+
+    #[error("failed to convert row{}: {source}", row_context(.id, .field))]
+    RowConversion {
+        id: Option<Id>,
+        field: Option<String>,
+        source: serde_json::Error,
+    },
+
+    /// The call did not finish before its
+    /// [`CallOptions::deadline`](crate::CallOptions::deadline) or a fan-out
+    /// deadline, or an [`AsyncOperation`](crate::AsyncOperation) ran out of
+    /// polls.
+    #[error("deadline exceeded")]
+    DeadlineExceeded,
+
+    /// A non-idempotent write failed after it may have reached the server, so
+    /// it may or may not have been applied. Resending it with
+    /// [`CallOptions::idempotency_key`](crate::CallOptions::idempotency_key)
+    /// set to `idempotency_key` lets a deduplicating server tell the two apart.
+    #[error("write may or may not have been applied: {source}")]
+    AmbiguousWrite { source: Box<Error>, idempotency_key: String },
+
+    /// A versioned write found the row at a different version than expected,
+    /// or the row doesn't exist; see [`Namespace::patch_versioned`](crate::Namespace::patch_versioned).
+    #[error("version conflict on row{}: expected version {expected_version}", id_context(.id))]
+    Conflict { id: Id, expected_version: u64 },
+
+    /// A check after a multi-step operation failed; the steps already taken
+    /// are not rolled back. See [`Client::rename_namespace`](crate::Client::rename_namespace).
+    /// Also returned by [`verify_fixture`](crate::verify_fixture) for cases
+    /// that don't round-trip, and by
+    /// [`Namespace::patch_by_filters`](crate::Namespace::patch_by_filters)
+    /// for a partial rule that never finishes.
+    #[error("verification failed: {0}")]
+    Verification(String),
+
+    /// A read-modify-write kept losing to concurrent writers; see
+    /// [`Namespace::patch_merge`](crate::Namespace::patch_merge).
+    #[error("row{} changed during every merge attempt", id_context(.id))]
+    ConcurrentUpdate { id: Id },
+
+    /// A response body was larger than the limit set with
+    /// [`Client::with_max_response_bytes`](crate::Client::with_max_response_bytes)
+    /// and was abandoned.
+    #[error("response body exceeded the {limit}-byte limit")]
+    ResponseTooLarge { limit: u64 },
+
+    /// The request wasn't sent because the circuit for its namespace is
+    /// open; see [`CircuitBreaker`](crate::CircuitBreaker). `scope` is the
+    /// namespace, or the endpoint path for requests outside a namespace.
+    #[error("circuit open for {scope} (retry after {retry_after:?})")]
+    CircuitOpen { scope: String, retry_after: Duration },
+}
+
+/// Details of a 429 response, for callers applying their own backpressure.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RateLimit {
+    /// How long the server asked clients to wait, from `Retry-After`.
+    pub retry_after: Option<Duration>,
+    pub message: String,
+}
+
+impl std::fmt::Display for RateLimit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "rate limited")?;
+        if let Some(retry_after) = self.retry_after {
+            write!(f, " (retry after {:?})", retry_after)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+fn row_context(id: &Option<Id>, field: &Option<String>) -> String {
+    let mut out = id.as_ref().map(id_context).unwrap_or_default();
+    if let Some(field) = field {
+        out.push_str(&format!(" at `{}`", field));
+    }
+    out
+}
+
+fn id_context(id: &Id) -> String {
+    match id {
+        Id::Uint(id) => format!(" with id {}", id),
+        Id::String(id) => format!(" with id {:?}", id),
+    }
+}
+
+impl Error {
+    /// Whether the failure is transient and the request can be retried:
+    /// server errors, rate limiting (429), timeouts and dropped or refused
+    /// connections.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Error::Api { status, .. } => *status >= 500,
+            Error::RateLimited(_) => true,
+            #[cfg(feature = "client")]
+            Error::Http(e) => crate::transport::is_connect(e) || e.is_timeout() || e.is_request(),
+            #[cfg(feature = "client")]
+            Error::Transport(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Whether the failed request may still have been applied by the server.
+    pub fn may_have_been_applied(&self) -> bool {
+        match self {
+            Error::Api { status, .. } => *status >= 500,
+            #[cfg(feature = "client")]
+            Error::Http(e) => !crate::transport::is_connect(e) && !e.is_builder(),
+            #[cfg(feature = "client")]
+            Error::Transport(e) => !e.before_request_sent(),
+            Error::AmbiguousWrite { .. } => true,
+            // The request may have been sent before the deadline cut it off.
+            Error::DeadlineExceeded => true,
+            _ => false,
+        }
+    }
+}
+
+#[cfg(feature = "client")]
+const MAX_DECODE_BODY: usize = 2048;
+
+/// Decode a response body, keeping the start of the body on failure.
+#[cfg(feature = "client")]
+pub(crate) fn decode<T: serde::de::DeserializeOwned>(bytes: &[u8]) -> Result<T> {
+    serde_json::from_slice(bytes).map_err(|source| {
+        let mut body = String::from_utf8_lossy(&bytes[..bytes.len().min(MAX_DECODE_BODY)]).into_owned();
+        if bytes.len() > MAX_DECODE_BODY {
+            body.push_str("...");
+        }
+        Error::Decode { target: std::any::type_name::<T>(), body, source }
+    })
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "client")]
+    #[test]
+    fn test_decode_error_includes_type_and_truncated_body() {
+        let body = format!(r#"{{"rows_affected": "many", "pad": "{}"}}"#, "x".repeat(3000));
+        let err = decode::<crate::WriteResponse>(body.as_bytes()).unwrap_err();
+        let Error::Decode { target, body, .. } = &err else {
+            panic!("expected a decode error, got {:?}", err);
+        };
+        assert_eq!(*target, "rs_puff::responses::WriteResponse");
+        assert!(body.starts_with(r#"{"rows_affected": "many""#));
+        assert_eq!(body.len(), MAX_DECODE_BODY + 3);
+    }
+
+    #[test]
+    fn test_rate_limits_and_server_errors_are_retryable() {
+        let limited = Error::RateLimited(RateLimit { retry_after: Some(Duration::from_secs(2)), message: "slow down".into() });
+        assert!(limited.is_retryable());
+        assert!(!limited.may_have_been_applied());
+        assert_eq!(limited.to_string(), "rate limited (retry after 2s): slow down");
+
+        let api = |status| Error::Api { status, message: String::new() };
+        assert!(api(503).is_retryable());
+        assert!(!api(400).is_retryable());
+        assert!(!api(404).is_retryable());
+    }
+}
@@ -0,0 +1,146 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{Error, Filter, IncludeAttributes, Namespace, QueryParams, RankBy, Result, Row};
+
+const DEFAULT_PAGE_SIZE: u64 = 1000;
+
+#[derive(Debug, Clone)]
+pub struct ExportParams {
+    /// Rows fetched per query; must be at least 1.
+    pub page_size: u64,
+    /// Attributes to export; all by default. `id` is always included.
+    pub include_attributes: IncludeAttributes,
+    /// Attributes to leave out, e.g. `vector` for metadata-only backups.
+    pub exclude_attributes: Option<Vec<String>>,
+}
+
+impl Default for ExportParams {
+    fn default() -> Self {
+        Self {
+            page_size: DEFAULT_PAGE_SIZE,
+            include_attributes: IncludeAttributes::All(true),
+            exclude_attributes: None,
+        }
+    }
+}
+
+/// Progress of a checkpointed export, saved after each completed part so an
+/// interrupted export can resume instead of starting over.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ExportCheckpoint {
+    /// Id of the last row in a completed part.
+    pub last_id: Option<serde_json::Value>,
+    pub rows: u64,
+    pub bytes: u64,
+    /// Number of parts written so far.
+    pub parts: u64,
+}
+
+/// Pages through every row of a namespace in ascending `id` order.
+///
+/// Each page is a query filtered to ids greater than the last one seen, so
+/// rows written during the export may or may not be included.
+pub struct Exporter<'a> {
+    ns: &'a Namespace,
+    params: ExportParams,
+    last_id: Option<serde_json::Value>,
+    done: bool,
+}
+
+impl<'a> Exporter<'a> {
+    pub fn new(ns: &'a Namespace, params: ExportParams) -> Self {
+        Self { ns, params, last_id: None, done: false }
+    }
+
+    /// Continue after `last_id` instead of from the first row.
+    pub fn resume_after(mut self, last_id: Option<serde_json::Value>) -> Self {
+        self.last_id = last_id;
+        self
+    }
+
+    /// Id of the last exported row, if any.
+    pub fn last_id(&self) -> Option<&serde_json::Value> {
+        self.last_id.as_ref()
+    }
+
+    /// Fetch the next page, or `None` once the namespace is exhausted.
+    pub async fn next_page(&mut self) -> Result<Option<Vec<Row>>> {
+        if self.done {
+            return Ok(None);
+        }
+        if self.params.page_size == 0 {
+            return Err(Error::InvalidParams("page_size must be at least 1".to_string()));
+        }
+
+        // Exports are restored as written, so they hold the stored rows
+        // rather than what the handle's read hooks would return.
+        let resp = self.ns.query_unhooked(&self.page_query()).await?;
+        if (resp.rows.len() as u64) < self.params.page_size {
+            self.done = true;
+        }
+        if resp.rows.is_empty() {
+            return Ok(None);
+        }
+        self.last_id = resp.rows.last().and_then(|row| row.get("id").cloned());
+        Ok(Some(resp.rows))
+    }
+
+    fn page_query(&self) -> QueryParams {
+        QueryParams {
+            rank_by: Some(RankBy::asc("id")),
+            top_k: Some(self.params.page_size),
+            filters: self.last_id.clone().map(|id| Filter::gt("id", id)),
+            include_attributes: Some(self.params.include_attributes.clone()),
+            exclude_attributes: self.params.exclude_attributes.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+impl Namespace {
+    pub fn exporter(&self, params: ExportParams) -> Exporter<'_> {
+        Exporter::new(self, params)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Client;
+
+    #[test]
+    fn test_page_query_resumes_after_last_id() {
+        let client = Client::new("test");
+        let ns = client.namespace("ns");
+        let mut exporter = ns.exporter(ExportParams { page_size: 2, ..Default::default() });
+
+        let first = serde_json::to_value(exporter.page_query()).unwrap();
+        assert_eq!(first["rank_by"], serde_json::json!(["id", "asc"]));
+        assert_eq!(first["top_k"], 2);
+        assert!(first.get("filters").is_none());
+
+        exporter.last_id = Some(serde_json::json!(41));
+        let next = serde_json::to_value(exporter.page_query()).unwrap();
+        assert_eq!(next["filters"], serde_json::json!(["id", "Gt", 41]));
+
+        let projected = ns.exporter(ExportParams {
+            exclude_attributes: Some(vec!["vector".to_string()]),
+            ..Default::default()
+        });
+        let query = serde_json::to_value(projected.page_query()).unwrap();
+        assert_eq!(query["include_attributes"], true);
+        assert_eq!(query["exclude_attributes"], serde_json::json!(["vector"]));
+
+        let resumed = ns.exporter(ExportParams { page_size: 2, ..Default::default() }).resume_after(Some(serde_json::json!(7)));
+        let query = serde_json::to_value(resumed.page_query()).unwrap();
+        assert_eq!(query["filters"], serde_json::json!(["id", "Gt", 7]));
+    }
+
+    #[tokio::test]
+    async fn test_empty_pages_are_rejected() {
+        let client = Client::new("test");
+        let ns = client.namespace("ns");
+        let mut exporter = ns.exporter(ExportParams { page_size: 0, ..Default::default() });
+        assert!(matches!(exporter.next_page().await, Err(Error::InvalidParams(_))));
+    }
+}
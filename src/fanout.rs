@@ -0,0 +1,207 @@
+use std::future::Future;
+use std::time::Duration;
+
+use futures_util::StreamExt;
+
+use crate::rt::{self, Instant};
+use crate::{
+    Client, Error, HintCacheWarmResponse, Namespace, NamespacesParams, QueryParams, QueryResponse, Result,
+};
+
+const DEFAULT_CONCURRENCY: usize = 8;
+
+#[derive(Debug, Clone)]
+pub struct FanoutParams {
+    /// Maximum number of requests in flight at once.
+    pub concurrency: usize,
+    /// Time budget for the whole fan-out; targets still pending when it
+    /// elapses fail with [`Error::DeadlineExceeded`].
+    pub deadline: Option<Duration>,
+}
+
+impl Default for FanoutParams {
+    fn default() -> Self {
+        Self { concurrency: DEFAULT_CONCURRENCY, deadline: None }
+    }
+}
+
+/// Per-target outcomes of a fan-out, in the order the targets were given.
+#[derive(Debug)]
+pub struct FanoutResult<T> {
+    pub results: Vec<Result<T>>,
+}
+
+impl<T> FanoutResult<T> {
+    pub fn successes(&self) -> impl Iterator<Item = (usize, &T)> {
+        self.results.iter().enumerate().filter_map(|(i, r)| r.as_ref().ok().map(|v| (i, v)))
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = (usize, &Error)> {
+        self.results.iter().enumerate().filter_map(|(i, r)| r.as_ref().err().map(|e| (i, e)))
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.results.iter().all(|r| r.is_ok())
+    }
+
+    /// All values, or the first error if any target failed.
+    pub fn into_result(self) -> Result<Vec<T>> {
+        self.results.into_iter().collect()
+    }
+}
+
+pub(crate) async fn fanout<F, T>(tasks: Vec<F>, params: &FanoutParams) -> FanoutResult<T>
+where
+    F: Future<Output = Result<T>>,
+{
+    let deadline = params.deadline.map(|d| Instant::now() + d);
+    let results = futures_util::stream::iter(tasks)
+        .map(|task| async move {
+            match deadline {
+                Some(deadline) => rt::timeout_at(deadline, task)
+                    .await
+                    .unwrap_or(Err(Error::DeadlineExceeded)),
+                None => task.await,
+            }
+        })
+        .buffered(params.concurrency.max(1))
+        .collect()
+        .await;
+    FanoutResult { results }
+}
+
+/// Outcome of [`Client::warm_namespaces`].
+#[derive(Debug)]
+pub struct WarmReport {
+    /// Matching namespaces, in the order of `results`.
+    pub namespaces: Vec<String>,
+    pub results: FanoutResult<HintCacheWarmResponse>,
+}
+
+impl WarmReport {
+    pub fn already_warm(&self) -> impl Iterator<Item = &str> {
+        self.results
+            .successes()
+            .filter(|(_, resp)| resp.is_already_warm())
+            .map(|(i, _)| self.namespaces[i].as_str())
+    }
+
+    /// Namespaces whose cache started warming because of this call.
+    pub fn warming(&self) -> impl Iterator<Item = &str> {
+        self.results
+            .successes()
+            .filter(|(_, resp)| !resp.is_already_warm())
+            .map(|(i, _)| self.namespaces[i].as_str())
+    }
+
+    pub fn failed(&self) -> impl Iterator<Item = (&str, &Error)> {
+        self.results.errors().map(|(i, e)| (self.namespaces[i].as_str(), e))
+    }
+}
+
+impl Client {
+    /// Hint a cache warm for every namespace whose name starts with `prefix`,
+    /// e.g. ahead of an expected traffic spike.
+    ///
+    /// Listing the namespaces must succeed; warm failures are reported per
+    /// namespace.
+    pub async fn warm_namespaces(&self, prefix: &str, params: &FanoutParams) -> Result<WarmReport> {
+        let namespaces = self.namespace_ids(prefix).await?;
+        let tasks = namespaces
+            .iter()
+            .map(|name| {
+                let ns = self.namespace(name);
+                async move { ns.hint_cache_warm().await }
+            })
+            .collect();
+        let results = fanout(tasks, params).await;
+        Ok(WarmReport { namespaces, results })
+    }
+
+    /// Names of every namespace starting with `prefix`, across all pages.
+    pub(crate) async fn namespace_ids(&self, prefix: &str) -> Result<Vec<String>> {
+        let mut namespaces = Vec::new();
+        let mut cursor = None;
+        loop {
+            let page = self
+                .namespaces(NamespacesParams {
+                    prefix: Some(prefix.to_string()),
+                    cursor,
+                    page_size: None,
+                })
+                .await?;
+            namespaces.extend(page.namespaces.into_iter().map(|ns| ns.id));
+            cursor = page.next_cursor;
+            if cursor.is_none() {
+                return Ok(namespaces);
+            }
+        }
+    }
+
+    /// Run the same query against several namespaces concurrently.
+    pub async fn query_namespaces<S: AsRef<str>>(
+        &self,
+        names: &[S],
+        query: &QueryParams,
+        params: &FanoutParams,
+    ) -> FanoutResult<QueryResponse> {
+        let tasks = names
+            .iter()
+            .map(|name| {
+                let ns = self.namespace(name.as_ref());
+                let query = query.clone();
+                async move { ns.query(query).await }
+            })
+            .collect();
+        fanout(tasks, params).await
+    }
+}
+
+impl Namespace {
+    /// Run independent queries concurrently as separate requests.
+    ///
+    /// Unlike [`Namespace::multi_query`], one slow or failing query doesn't
+    /// fail the rest.
+    pub async fn query_many(&self, queries: Vec<QueryParams>, params: &FanoutParams) -> FanoutResult<QueryResponse> {
+        let tasks = queries.into_iter().map(|query| self.query(query)).collect();
+        fanout(tasks, params).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_fanout_keeps_order_and_times_out_stragglers() {
+        let tasks = [0u64, 50, 5]
+            .into_iter()
+            .map(|ms| async move {
+                tokio::time::sleep(Duration::from_millis(ms)).await;
+                Ok(ms)
+            })
+            .collect();
+        let params = FanoutParams { concurrency: 3, deadline: Some(Duration::from_millis(25)) };
+        let result = fanout(tasks, &params).await;
+
+        assert_eq!(result.successes().collect::<Vec<_>>(), vec![(0, &0), (2, &5)]);
+        let errors: Vec<_> = result.errors().collect();
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(errors[0], (1, Error::DeadlineExceeded)));
+        assert!(!result.is_complete());
+    }
+
+    #[test]
+    fn test_warm_report_partitions_namespaces() {
+        let warm = |status: &str| HintCacheWarmResponse { status: status.to_string(), message: None, extra: Default::default() };
+        let report = WarmReport {
+            namespaces: vec!["a".into(), "b".into(), "c".into()],
+            results: FanoutResult {
+                results: vec![Ok(warm("OK")), Ok(warm("ACCEPTED")), Err(Error::DeadlineExceeded)],
+            },
+        };
+        assert_eq!(report.already_warm().collect::<Vec<_>>(), ["a"]);
+        assert_eq!(report.warming().collect::<Vec<_>>(), ["b"]);
+        assert_eq!(report.failed().map(|(ns, _)| ns).collect::<Vec<_>>(), ["c"]);
+    }
+}
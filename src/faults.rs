@@ -0,0 +1,244 @@
+//! Fault injection for testing retry, backoff and degradation logic against
+//! realistic failures without a misbehaving server.
+//!
+//! ```no_run
+//! # fn example() -> rs_puff::Result<()> {
+//! use std::time::Duration;
+//! use rs_puff::Client;
+//! use rs_puff::faults::FaultInjector;
+//!
+//! let client = Client::from_env()?.with_fault_injection(
+//!     FaultInjector::new()
+//!         .latency(0.2, Duration::from_millis(300))
+//!         .rate_limits(0.05)
+//!         .server_errors(0.05)
+//!         .seed(42),
+//! );
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use reqwest::header::CONTENT_LENGTH;
+
+use crate::rt;
+use crate::{Error, RateLimit, Result};
+
+/// Message of the error returned for an injected status.
+pub const INJECTED_FAULT_MESSAGE: &str = "injected fault";
+
+/// Injects faults into requests sent by a client; register with
+/// [`Client::with_fault_injection`](crate::Client::with_fault_injection).
+///
+/// Each attempt, retries included, independently draws whether to be
+/// delayed, and then at most one of: a 429, a 503, or a response body cut in
+/// half. Injected statuses are returned without sending the request.
+#[derive(Debug)]
+pub struct FaultInjector {
+    latency: Option<(f64, Duration)>,
+    rate_limits: f64,
+    server_errors: f64,
+    truncated_bodies: f64,
+    state: Mutex<State>,
+}
+
+#[derive(Debug)]
+struct State {
+    rng: u64,
+    counts: FaultCounts,
+}
+
+/// Faults injected so far by a [`FaultInjector`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FaultCounts {
+    pub delays: u64,
+    pub rate_limits: u64,
+    pub server_errors: u64,
+    pub truncated_bodies: u64,
+}
+
+/// What to do to one attempt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Fault {
+    pub delay: Option<Duration>,
+    pub status: Option<u16>,
+    pub truncate: bool,
+}
+
+impl Default for FaultInjector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FaultInjector {
+    /// An injector with every fault rate at zero.
+    pub fn new() -> Self {
+        let seed = RandomState::new().build_hasher().finish();
+        Self {
+            latency: None,
+            rate_limits: 0.0,
+            server_errors: 0.0,
+            truncated_bodies: 0.0,
+            state: Mutex::new(State { rng: seed, counts: FaultCounts::default() }),
+        }
+    }
+
+    /// Delay a `rate` fraction of attempts by `delay` before sending.
+    pub fn latency(mut self, rate: f64, delay: Duration) -> Self {
+        self.latency = Some((rate, delay));
+        self
+    }
+
+    /// Fail a `rate` fraction of attempts with 429 Too Many Requests.
+    pub fn rate_limits(mut self, rate: f64) -> Self {
+        self.rate_limits = rate;
+        self
+    }
+
+    /// Fail a `rate` fraction of attempts with 503 Service Unavailable.
+    pub fn server_errors(mut self, rate: f64) -> Self {
+        self.server_errors = rate;
+        self
+    }
+
+    /// Cut the body of a `rate` fraction of successful responses in half.
+    pub fn truncated_bodies(mut self, rate: f64) -> Self {
+        self.truncated_bodies = rate;
+        self
+    }
+
+    /// Make the sequence of injected faults reproducible.
+    pub fn seed(self, seed: u64) -> Self {
+        self.lock().rng = seed;
+        self
+    }
+
+    pub fn counts(&self) -> FaultCounts {
+        self.lock().counts
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Decide the faults for one attempt.
+    pub(crate) fn draw(&self) -> Fault {
+        let mut state = self.lock();
+        let mut fault = Fault { delay: None, status: None, truncate: false };
+        if let Some((rate, delay)) = self.latency
+            && state.chance(rate)
+        {
+            fault.delay = Some(delay);
+            state.counts.delays += 1;
+        }
+        // One roll picks at most one outcome fault, so rates add up.
+        let roll = state.next_fraction();
+        if roll < self.rate_limits {
+            fault.status = Some(429);
+            state.counts.rate_limits += 1;
+        } else if roll < self.rate_limits + self.server_errors {
+            fault.status = Some(503);
+            state.counts.server_errors += 1;
+        } else if roll < self.rate_limits + self.server_errors + self.truncated_bodies {
+            fault.truncate = true;
+            state.counts.truncated_bodies += 1;
+        }
+        fault
+    }
+}
+
+impl Fault {
+    /// Apply the delay and any injected status before the request is sent.
+    pub(crate) async fn before_send(&self) -> Result<()> {
+        if let Some(delay) = self.delay {
+            rt::sleep(delay).await;
+        }
+        let message = INJECTED_FAULT_MESSAGE.to_string();
+        match self.status {
+            Some(429) => Err(Error::RateLimited(RateLimit { retry_after: None, message })),
+            Some(status) => Err(Error::Api { status, message }),
+            None => Ok(()),
+        }
+    }
+
+    pub(crate) async fn after_response(&self, resp: reqwest::Response) -> Result<reqwest::Response> {
+        if !self.truncate {
+            return Ok(resp);
+        }
+        let mut builder = http::Response::builder().status(resp.status());
+        if let Some(headers) = builder.headers_mut() {
+            *headers = resp.headers().clone();
+            headers.remove(CONTENT_LENGTH);
+        }
+        let body = resp.bytes().await?;
+        let truncated = builder
+            .body(body.slice(..body.len() / 2))
+            .map_err(|e| Error::External(Box::new(e)))?;
+        Ok(reqwest::Response::from(truncated))
+    }
+}
+
+impl State {
+    fn chance(&mut self, rate: f64) -> bool {
+        self.next_fraction() < rate
+    }
+
+    /// SplitMix64, uniform in `[0, 1)`.
+    fn next_fraction(&mut self) -> f64 {
+        self.rng = self.rng.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.rng;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+        (z >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn draws(injector: &FaultInjector, n: usize) -> Vec<Fault> {
+        (0..n).map(|_| injector.draw()).collect()
+    }
+
+    #[test]
+    fn test_seeded_injectors_agree() {
+        let injector = || FaultInjector::new().rate_limits(0.3).server_errors(0.3).truncated_bodies(0.2).seed(7);
+        let (a, b) = (injector(), injector());
+        assert_eq!(draws(&a, 100), draws(&b, 100));
+
+        let counts = a.counts();
+        assert_eq!(counts.delays, 0);
+        assert!(counts.rate_limits > 10 && counts.server_errors > 10 && counts.truncated_bodies > 5);
+        assert!(counts.rate_limits + counts.server_errors + counts.truncated_bodies <= 100);
+    }
+
+    #[test]
+    fn test_rates_of_zero_and_one() {
+        assert!(draws(&FaultInjector::new(), 50).iter().all(|f| *f == Fault { delay: None, status: None, truncate: false }));
+
+        let injector = FaultInjector::new().latency(1.0, Duration::from_millis(5)).rate_limits(1.0);
+        let fault = injector.draw();
+        assert_eq!(fault.delay, Some(Duration::from_millis(5)));
+        assert_eq!(fault.status, Some(429));
+    }
+
+    #[tokio::test]
+    async fn test_faults_applied_to_requests() {
+        let fault = Fault { delay: None, status: Some(503), truncate: false };
+        let err = fault.before_send().await.unwrap_err();
+        assert!(err.is_retryable());
+        assert!(err.to_string().contains(INJECTED_FAULT_MESSAGE));
+
+        let resp = reqwest::Response::from(http::Response::new(r#"{"rows": []}"#.to_string()));
+        let fault = Fault { delay: None, status: None, truncate: true };
+        let body = fault.after_response(resp).await.unwrap().bytes().await.unwrap();
+        assert_eq!(&body[..], br#"{"rows"#);
+    }
+}
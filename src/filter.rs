@@ -1,5 +1,9 @@
+use serde::de::{self, Deserializer};
 use serde::ser::{SerializeSeq, Serializer};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::wire::from_value;
 
 #[derive(Debug, Clone, PartialEq, Deserialize)]
 pub struct ContainsAllTokensParams {
@@ -109,6 +113,8 @@ impl Filter {
         Filter::Or(filters)
     }
 
+    // A constructor like `and` and `or`, not `std::ops::Not`.
+    #[allow(clippy::should_implement_trait)]
     pub fn not(filter: Filter) -> Self {
         Filter::Not(Box::new(filter))
     }
@@ -334,6 +340,72 @@ impl Serialize for Filter {
     }
 }
 
+// Logical ops are `[op, operands]`, everything else `[attr, op, value]`
+// (plus params for `ContainsAllTokens`), so an attribute named "And" is
+// still unambiguous.
+impl<'de> Deserialize<'de> for Filter {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let parts = Vec::<Value>::deserialize(deserializer)?;
+        let mut parts = parts.into_iter();
+        match (parts.next(), parts.next(), parts.next(), parts.next(), parts.next()) {
+            (Some(Value::String(op)), Some(operands), None, None, None) => logical(&op, operands),
+            (Some(Value::String(attr)), Some(Value::String(op)), Some(value), params, None) => {
+                comparison(attr, &op, value, params)
+            }
+            _ => Err("expected [attr, op, value] or [op, operands]".to_string()),
+        }
+        .map_err(de::Error::custom)
+    }
+}
+
+fn logical(op: &str, operands: Value) -> Result<Filter, String> {
+    match op {
+        "And" => Ok(Filter::And(from_value(operands)?)),
+        "Or" => Ok(Filter::Or(from_value(operands)?)),
+        "Not" => Ok(Filter::Not(Box::new(from_value(operands)?))),
+        op => Err(format!("unknown logical operator {:?}", op)),
+    }
+}
+
+fn comparison(attr: String, op: &str, value: Value, params: Option<Value>) -> Result<Filter, String> {
+    if params.is_some() && op != "ContainsAllTokens" {
+        return Err(format!("{} takes no params", op));
+    }
+    Ok(match op {
+        "Eq" => Filter::Eq { attr, value },
+        "NotEq" => Filter::NotEq { attr, value },
+        "Lt" => Filter::Lt { attr, value },
+        "Lte" => Filter::Lte { attr, value },
+        "Gt" => Filter::Gt { attr, value },
+        "Gte" => Filter::Gte { attr, value },
+        "AnyLt" => Filter::AnyLt { attr, value },
+        "AnyLte" => Filter::AnyLte { attr, value },
+        "AnyGt" => Filter::AnyGt { attr, value },
+        "AnyGte" => Filter::AnyGte { attr, value },
+        "In" => Filter::In { attr, values: from_value(value)? },
+        "NotIn" => Filter::NotIn { attr, values: from_value(value)? },
+        "Contains" => Filter::Contains { attr, value },
+        "NotContains" => Filter::NotContains { attr, value },
+        "ContainsAny" => Filter::ContainsAny { attr, values: from_value(value)? },
+        "NotContainsAny" => Filter::NotContainsAny { attr, values: from_value(value)? },
+        "Glob" => Filter::Glob { attr, pattern: from_value(value)? },
+        "NotGlob" => Filter::NotGlob { attr, pattern: from_value(value)? },
+        "IGlob" => Filter::IGlob { attr, pattern: from_value(value)? },
+        "NotIGlob" => Filter::NotIGlob { attr, pattern: from_value(value)? },
+        "Regex" => Filter::Regex { attr, pattern: from_value(value)? },
+        "ContainsAllTokens" => Filter::ContainsAllTokens {
+            attr,
+            value: from_value(value)?,
+            params: params.map(from_value).transpose()?,
+        },
+        "ContainsTokenSequence" => Filter::ContainsTokenSequence { attr, value: from_value(value)? },
+        op => return Err(format!("unknown filter operator {:?}", op)),
+    })
+}
+
 impl Serialize for ContainsAllTokensParams {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -489,6 +561,36 @@ mod tests {
         assert_eq!(json, r#"["price","Eq",19.99]"#);
     }
 
+    #[test]
+    fn test_deserialize_roundtrips() {
+        let filters = [
+            Filter::and(vec![
+                Filter::or(vec![Filter::eq("a", 1), Filter::r#in("b", vec!["x".into(), "y".into()])]),
+                Filter::not(Filter::iglob("title", "*rust*")),
+                Filter::AnyGte { attr: "scores".into(), value: 0.5.into() },
+            ]),
+            Filter::contains_all_tokens_with_params("body", "quick fo", ContainsAllTokensParams { last_as_prefix: Some(true) }),
+            Filter::eq("And", serde_json::json!([1, 2])),
+        ];
+        for filter in filters {
+            let json = serde_json::to_value(&filter).unwrap();
+            assert_eq!(serde_json::from_value::<Filter>(json).unwrap(), filter);
+        }
+    }
+
+    #[test]
+    fn test_deserialize_rejects_malformed_filters() {
+        for json in [
+            r#"["a","Between",1]"#,
+            r#"["Xor",[]]"#,
+            r#"["a","Glob",1]"#,
+            r#"["a","Eq",1,{}]"#,
+            r#"["a"]"#,
+        ] {
+            assert!(serde_json::from_str::<Filter>(json).is_err(), "{}", json);
+        }
+    }
+
     #[test]
     fn test_null_value() {
         let f = Filter::Eq { attr: "field".into(), value: serde_json::Value::Null };
@@ -0,0 +1,86 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{Filter, Id, IncludeAttributes, Namespace, QueryParams, RankBy, Result, Row};
+
+// Ids per query; keeps filters and top_k within API limits.
+pub(crate) const GET_BATCH_SIZE: usize = 1000;
+
+/// Outcome of [`Namespace::get_many`], both lists in request order.
+#[derive(Debug, Clone, Default)]
+pub struct GetManyResult {
+    pub found: Vec<Row>,
+    pub missing: Vec<Id>,
+}
+
+impl Namespace {
+    /// Fetch rows by id with all attributes, reporting ids that don't exist.
+    ///
+    /// Duplicate ids are looked up and reported once.
+    pub async fn get_many<I>(&self, ids: impl IntoIterator<Item = I>) -> Result<GetManyResult>
+    where
+        I: Into<Id>,
+    {
+        let mut seen = HashSet::new();
+        let ids: Vec<Id> = ids.into_iter().map(Into::into).filter(|id| seen.insert(id.clone())).collect();
+
+        let mut rows = HashMap::with_capacity(ids.len());
+        for batch in ids.chunks(GET_BATCH_SIZE) {
+            let resp = self.query(get_query(batch)?).await?;
+            for row in resp.rows {
+                if let Some(id) = row.get("id").and_then(|id| serde_json::from_value::<Id>(id.clone()).ok()) {
+                    rows.insert(id, row);
+                }
+            }
+        }
+        Ok(partition(ids, rows))
+    }
+
+    /// Fetch one row by id with all attributes.
+    pub async fn get(&self, id: impl Into<Id>) -> Result<Option<Row>> {
+        Ok(self.get_many([id.into()]).await?.found.pop())
+    }
+}
+
+fn get_query(ids: &[Id]) -> Result<QueryParams> {
+    let ids = ids.iter().map(serde_json::to_value).collect::<serde_json::Result<Vec<_>>>()?;
+    Ok(QueryParams {
+        rank_by: Some(RankBy::asc("id")),
+        top_k: Some(ids.len() as u64),
+        filters: Some(Filter::r#in("id", ids)),
+        include_attributes: Some(IncludeAttributes::All(true)),
+        ..Default::default()
+    })
+}
+
+fn partition(ids: Vec<Id>, mut rows: HashMap<Id, Row>) -> GetManyResult {
+    let mut result = GetManyResult::default();
+    for id in ids {
+        match rows.remove(&id) {
+            Some(row) => result.found.push(row),
+            None => result.missing.push(id),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_query() {
+        let query = serde_json::to_value(get_query(&[Id::from(2u64), Id::from("a")]).unwrap()).unwrap();
+        assert_eq!(query["filters"], serde_json::json!(["id", "In", [2, "a"]]));
+        assert_eq!(query["top_k"], 2);
+        assert_eq!(query["include_attributes"], true);
+    }
+
+    #[test]
+    fn test_partition_keeps_request_order() {
+        let row = |id: u64| -> Row { serde_json::from_value(serde_json::json!({"id": id})).unwrap() };
+        let rows = HashMap::from([(Id::from(3u64), row(3)), (Id::from(1u64), row(1))]);
+        let result = partition(vec![Id::from(1u64), Id::from(2u64), Id::from(3u64), Id::from("x")], rows);
+        assert_eq!(result.found, [row(1), row(3)]);
+        assert_eq!(result.missing, [Id::from(2u64), Id::from("x")]);
+    }
+}
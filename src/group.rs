@@ -0,0 +1,88 @@
+use std::collections::HashMap;
+
+use crate::{IncludeAttributes, Namespace, QueryParams, Result, Row};
+
+// Candidates fetched per requested row when `top_k` isn't set.
+const CANDIDATES_PER_ROW: u64 = 10;
+
+/// Rows sharing one value of the grouping attribute; see
+/// [`Namespace::top_k_per_group`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct RowGroup {
+    /// The attribute's value, `Null` for rows without it.
+    pub key: serde_json::Value,
+    /// At most `k` rows, in rank order.
+    pub rows: Vec<Row>,
+}
+
+impl Namespace {
+    /// The best `k` rows for each value of `attr`, e.g. the best chunks per
+    /// document. Groups are ordered by their best row.
+    ///
+    /// Grouping happens client-side over the query's results, so
+    /// `params.top_k` is the number of candidates considered; it defaults to
+    /// `10 * k`. A group can have fewer than `k` rows, or be missing, if its
+    /// rows rank below the candidates. `attr` is added to the returned
+    /// attributes if needed.
+    pub async fn top_k_per_group(&self, attr: &str, k: usize, mut params: QueryParams) -> Result<Vec<RowGroup>> {
+        params.top_k.get_or_insert(k as u64 * CANDIDATES_PER_ROW);
+        match &mut params.include_attributes {
+            Some(IncludeAttributes::List(attrs)) if !attrs.iter().any(|a| a == attr) => attrs.push(attr.to_string()),
+            Some(_) => {}
+            // With only exclusions set, everything else is already returned.
+            None if params.exclude_attributes.is_some() => {}
+            None => params.include_attributes = Some(IncludeAttributes::List(vec![attr.to_string()])),
+        }
+        if let Some(excluded) = &mut params.exclude_attributes {
+            excluded.retain(|a| a != attr);
+        }
+        let resp = self.query(params).await?;
+        Ok(group_rows(resp.rows, attr, k))
+    }
+}
+
+fn group_rows(rows: Vec<Row>, attr: &str, k: usize) -> Vec<RowGroup> {
+    let mut groups: Vec<RowGroup> = Vec::new();
+    // Keyed by the value's JSON text, which keeps 1 and "1" apart.
+    let mut index: HashMap<String, usize> = HashMap::new();
+    if k == 0 {
+        return groups;
+    }
+    for row in rows {
+        let key = row.get(attr).cloned().unwrap_or_default();
+        match index.get(&key.to_string()) {
+            Some(&i) if groups[i].rows.len() < k => groups[i].rows.push(row),
+            Some(_) => {}
+            None => {
+                index.insert(key.to_string(), groups.len());
+                groups.push(RowGroup { key, rows: vec![row] });
+            }
+        }
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(id: u64, doc: Option<&str>) -> Row {
+        let mut row: Row = serde_json::from_value(serde_json::json!({"id": id})).unwrap();
+        if let Some(doc) = doc {
+            row.insert("doc".to_string(), doc.into());
+        }
+        row
+    }
+
+    #[test]
+    fn test_group_rows_keeps_best_k_per_value() {
+        let rows = vec![row(1, Some("a")), row(2, Some("b")), row(3, Some("a")), row(4, Some("a")), row(5, None)];
+        let groups = group_rows(rows, "doc", 2);
+        let ids: Vec<(serde_json::Value, Vec<u64>)> = groups
+            .iter()
+            .map(|g| (g.key.clone(), g.rows.iter().map(|r| r["id"].as_u64().unwrap()).collect()))
+            .collect();
+        assert_eq!(ids, [("a".into(), vec![1, 3]), ("b".into(), vec![2]), (serde_json::Value::Null, vec![5])]);
+        assert!(group_rows(vec![row(1, Some("a"))], "doc", 0).is_empty());
+    }
+}
@@ -0,0 +1,142 @@
+use std::sync::Arc;
+
+use crate::Row;
+
+/// Post-processes every row a [`Namespace`](crate::Namespace) returns from a
+/// query, e.g. to strip or mask attributes a caller may not see.
+///
+/// Register hooks with [`Namespace::with_read_hook`](crate::Namespace::with_read_hook);
+/// they run in order.
+pub trait ReadHook: Send + Sync {
+    fn apply(&self, row: &mut Row);
+}
+
+impl<F: Fn(&mut Row) + Send + Sync> ReadHook for F {
+    fn apply(&self, row: &mut Row) {
+        self(row)
+    }
+}
+
+/// Removes the listed attributes from every row.
+#[derive(Debug, Clone)]
+pub struct Redact {
+    attributes: Vec<String>,
+}
+
+impl Redact {
+    pub fn new<I, S>(attributes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self { attributes: attributes.into_iter().map(Into::into).collect() }
+    }
+}
+
+impl ReadHook for Redact {
+    fn apply(&self, row: &mut Row) {
+        for attribute in &self.attributes {
+            row.remove(attribute);
+        }
+    }
+}
+
+/// Replaces non-null values of the listed attributes with a fixed value, so
+/// callers can tell the attribute is set without seeing it.
+#[derive(Debug, Clone)]
+pub struct Mask {
+    attributes: Vec<String>,
+    replacement: serde_json::Value,
+}
+
+impl Mask {
+    pub fn new<I, S>(attributes: I, replacement: impl Into<serde_json::Value>) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            attributes: attributes.into_iter().map(Into::into).collect(),
+            replacement: replacement.into(),
+        }
+    }
+}
+
+impl ReadHook for Mask {
+    fn apply(&self, row: &mut Row) {
+        for attribute in &self.attributes {
+            if let Some(value) = row.get_mut(attribute)
+                && !value.is_null()
+            {
+                *value = self.replacement.clone();
+            }
+        }
+    }
+}
+
+pub(crate) fn apply(hooks: &[Arc<dyn ReadHook>], rows: &mut [Row]) {
+    for row in rows {
+        for hook in hooks {
+            hook.apply(row);
+        }
+    }
+}
+
+/// Apply hooks to the `rows` of an untyped query (or multi-query result) body.
+pub(crate) fn apply_to_value(hooks: &[Arc<dyn ReadHook>], body: &mut serde_json::Value) {
+    if hooks.is_empty() {
+        return;
+    }
+    let responses = match body.get_mut("results").and_then(|r| r.as_array_mut()) {
+        Some(results) => results.iter_mut().collect(),
+        None => vec![body],
+    };
+    for response in responses {
+        let Some(rows) = response.get_mut("rows").and_then(|r| r.as_array_mut()) else {
+            continue;
+        };
+        for value in rows {
+            if let serde_json::Value::Object(map) = value {
+                let mut row: Row = std::mem::take(map).into_iter().collect();
+                for hook in hooks {
+                    hook.apply(&mut row);
+                }
+                *map = row.into_iter().collect();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_and_mask() {
+        let hooks: Vec<Arc<dyn ReadHook>> = vec![
+            Arc::new(Redact::new(["ssn"])),
+            Arc::new(Mask::new(["email", "phone"], "***")),
+        ];
+        let mut rows: Vec<Row> =
+            serde_json::from_value(serde_json::json!([{"id": 1, "ssn": "123", "email": "a@b.c", "phone": null}])).unwrap();
+        apply(&hooks, &mut rows);
+        assert_eq!(
+            serde_json::to_value(&rows).unwrap(),
+            serde_json::json!([{"id": 1, "email": "***", "phone": null}])
+        );
+    }
+
+    #[test]
+    fn test_apply_to_raw_query_and_multi_query_bodies() {
+        let hooks: Vec<Arc<dyn ReadHook>> = vec![Arc::new(|row: &mut Row| {
+            row.remove("secret");
+        })];
+        let mut body = serde_json::json!({"rows": [{"id": 1, "secret": "x"}], "billing": {}});
+        apply_to_value(&hooks, &mut body);
+        assert_eq!(body["rows"], serde_json::json!([{"id": 1}]));
+
+        let mut body = serde_json::json!({"results": [{"rows": [{"id": 1, "secret": "x"}]}, {"rows": []}]});
+        apply_to_value(&hooks, &mut body);
+        assert_eq!(body["results"][0]["rows"], serde_json::json!([{"id": 1}]));
+    }
+}
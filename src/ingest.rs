@@ -0,0 +1,340 @@
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::chunk::{ChunkParams, chunk_text};
+use crate::embed::Embedder;
+use crate::middleware::sha256_hex;
+use crate::bulk::{self, BulkWriteReport};
+use crate::rt;
+use crate::{DistanceMetric, Error, Namespace, Result, Row, WriteParams};
+
+const DEFAULT_EXTENSIONS: &[&str] = &["txt", "md", "markdown", "json"];
+
+// turbopuffer rejects longer string ids.
+const MAX_ID_BYTES: usize = 64;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IngestReport {
+    pub files: u64,
+    pub chunks: u64,
+    pub writes: BulkWriteReport,
+}
+
+/// Chunks and upserts every text, markdown and JSON file under a directory.
+///
+/// Each chunk becomes a row with id `"<relative path>#<chunk index>"` and the
+/// attributes `path`, `mtime` (seconds since the epoch), `chunk_index` and the
+/// chunk text. Ids that would exceed 64 bytes use `"sha256:<hash of the
+/// path>#<chunk index>"` instead. JSON objects with a string field named like the text attribute
+/// are chunked on that field and keep their other scalar fields as attributes;
+/// any other JSON file is chunked as plain text.
+///
+/// Chunks left over from a previous, longer version of a file are not removed.
+pub struct DirectoryIngester<'a> {
+    ns: &'a Namespace,
+    root: PathBuf,
+    extensions: Vec<String>,
+    chunk_params: ChunkParams,
+    text_attribute: String,
+    vector_attribute: String,
+    distance_metric: DistanceMetric,
+    batch_size: usize,
+}
+
+impl<'a> DirectoryIngester<'a> {
+    pub fn new(ns: &'a Namespace, root: impl Into<PathBuf>) -> Self {
+        Self {
+            ns,
+            root: root.into(),
+            extensions: DEFAULT_EXTENSIONS.iter().map(|e| e.to_string()).collect(),
+            chunk_params: ChunkParams::default(),
+            text_attribute: "text".to_string(),
+            vector_attribute: "vector".to_string(),
+            distance_metric: DistanceMetric::CosineDistance,
+            batch_size: 500,
+        }
+    }
+
+    pub fn extensions(mut self, extensions: &[&str]) -> Self {
+        self.extensions = extensions.iter().map(|e| e.to_string()).collect();
+        self
+    }
+
+    pub fn chunk_params(mut self, params: ChunkParams) -> Self {
+        self.chunk_params = params;
+        self
+    }
+
+    pub fn text_attribute(mut self, attr: impl Into<String>) -> Self {
+        self.text_attribute = attr.into();
+        self
+    }
+
+    pub fn vector_attribute(mut self, attr: impl Into<String>) -> Self {
+        self.vector_attribute = attr.into();
+        self
+    }
+
+    pub fn distance_metric(mut self, metric: DistanceMetric) -> Self {
+        self.distance_metric = metric;
+        self
+    }
+
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Ingest without vectors, e.g. for BM25-only namespaces.
+    pub async fn ingest(&self) -> Result<IngestReport> {
+        self.run(None::<&NoEmbedder>).await
+    }
+
+    /// Ingest, embedding each chunk into the vector attribute.
+    pub async fn ingest_with_embedder<E: Embedder + Sync>(&self, embedder: &E) -> Result<IngestReport> {
+        self.run(Some(embedder)).await
+    }
+
+    async fn run<E: Embedder + Sync>(&self, embedder: Option<&E>) -> Result<IngestReport> {
+        let mut report = IngestReport::default();
+        let mut batch = Vec::new();
+
+        for path in self.collect_files().await? {
+            let rows = self.file_rows(&path).await?;
+            report.files += 1;
+            report.chunks += rows.len() as u64;
+            batch.extend(rows);
+            while batch.len() >= self.batch_size {
+                let rest = batch.split_off(self.batch_size);
+                self.flush(std::mem::replace(&mut batch, rest), embedder, &mut report.writes).await?;
+            }
+        }
+        if !batch.is_empty() {
+            self.flush(batch, embedder, &mut report.writes).await?;
+        }
+
+        Ok(report)
+    }
+
+    async fn flush<E: Embedder + Sync>(
+        &self,
+        mut rows: Vec<Row>,
+        embedder: Option<&E>,
+        report: &mut BulkWriteReport,
+    ) -> Result<()> {
+        let mut distance_metric = None;
+        if let Some(embedder) = embedder {
+            let texts: Vec<String> = rows
+                .iter()
+                .map(|row| row[&self.text_attribute].as_str().unwrap_or_default().to_string())
+                .collect();
+            let vectors = embedder.embed(&texts).await?;
+            if vectors.len() != rows.len() {
+                return Err(Error::External(
+                    format!("embedder returned {} vectors for {} texts", vectors.len(), rows.len()).into(),
+                ));
+            }
+            for (row, vector) in rows.iter_mut().zip(vectors) {
+                row.insert(self.vector_attribute.clone(), serde_json::json!(vector));
+            }
+            distance_metric = Some(self.distance_metric);
+        }
+
+        let params = WriteParams {
+            upsert_rows: Some(rows),
+            distance_metric,
+            ..Default::default()
+        };
+        bulk::write_reported(self.ns, params, report).await
+    }
+
+    async fn collect_files(&self) -> Result<Vec<PathBuf>> {
+        let root = self.root.clone();
+        let extensions = self.extensions.clone();
+        rt::blocking(move || walk(&root, &extensions)).await?
+    }
+
+    async fn file_rows(&self, path: &Path) -> Result<Vec<Row>> {
+        let file = path.to_path_buf();
+        let (content, mtime) = rt::blocking(move || read_file(&file)).await??;
+        let rel_path = path
+            .strip_prefix(&self.root)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .replace('\\', "/");
+
+        let mut base = Row::new();
+        let mut text = content.clone();
+        let is_json = path.extension().is_some_and(|e| e.eq_ignore_ascii_case("json"));
+        if is_json
+            && let Ok(serde_json::Value::Object(obj)) = serde_json::from_str::<serde_json::Value>(&content)
+            && let Some(body) = obj.get(&self.text_attribute).and_then(|v| v.as_str())
+        {
+            text = body.to_string();
+            for (k, v) in &obj {
+                if k != &self.text_attribute && k != "id" && !v.is_array() && !v.is_object() {
+                    base.insert(k.clone(), v.clone());
+                }
+            }
+        }
+        base.insert("path".to_string(), serde_json::json!(rel_path));
+        base.insert("mtime".to_string(), serde_json::json!(mtime));
+
+        Ok(chunk_text(&text, &self.chunk_params)
+            .into_iter()
+            .enumerate()
+            .map(|(i, chunk)| {
+                let mut row = base.clone();
+                row.insert("id".to_string(), serde_json::json!(chunk_id(&rel_path, i)));
+                row.insert("chunk_index".to_string(), serde_json::json!(i));
+                row.insert(self.text_attribute.clone(), serde_json::json!(chunk));
+                row
+            })
+            .collect())
+    }
+}
+
+fn chunk_id(rel_path: &str, index: usize) -> String {
+    let id = format!("{}#{}", rel_path, index);
+    if id.len() <= MAX_ID_BYTES {
+        return id;
+    }
+    format!("sha256:{}#{}", &sha256_hex(rel_path.as_bytes())[..32], index)
+}
+
+/// Files under `root` with one of `extensions`, sorted. Symlinked
+/// directories aren't followed, so a link back up the tree can't loop.
+fn walk(root: &Path, extensions: &[String]) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut dirs = vec![root.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in std::fs::read_dir(&dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            if entry.file_type()?.is_dir() {
+                dirs.push(path);
+            } else if matches_extension(&path, extensions) && path.is_file() {
+                files.push(path);
+            }
+        }
+    }
+    // Deterministic order keeps batches stable across runs.
+    files.sort();
+    Ok(files)
+}
+
+fn matches_extension(path: &Path, extensions: &[String]) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| extensions.iter().any(|x| x.eq_ignore_ascii_case(e)))
+}
+
+/// A file's content and modification time in seconds since the epoch.
+fn read_file(path: &Path) -> Result<(String, Option<u64>)> {
+    let content = std::fs::read_to_string(path)?;
+    let mtime = std::fs::metadata(path)?
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs());
+    Ok((content, mtime))
+}
+
+struct NoEmbedder;
+
+impl Embedder for NoEmbedder {
+    async fn embed(&self, _texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        Ok(Vec::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Client;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("rs_puff_ingest_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("nested")).unwrap();
+        dir
+    }
+
+    #[tokio::test]
+    async fn test_collects_matching_files_recursively() {
+        let dir = temp_dir("collect");
+        std::fs::write(dir.join("a.md"), "# Title").unwrap();
+        std::fs::write(dir.join("nested/b.TXT"), "body").unwrap();
+        std::fs::write(dir.join("image.png"), [0u8]).unwrap();
+        // A link back to the root isn't walked again.
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(&dir, dir.join("nested/loop")).unwrap();
+
+        let client = Client::new("test");
+        let ns = client.namespace("docs");
+        let ingester = DirectoryIngester::new(&ns, &dir);
+        let files = ingester.collect_files().await.unwrap();
+        assert_eq!(files, vec![dir.join("a.md"), dir.join("nested/b.TXT")]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_file_rows_chunk_text_with_metadata() {
+        let dir = temp_dir("rows");
+        std::fs::write(dir.join("nested/notes.md"), "alpha beta gamma delta").unwrap();
+
+        let client = Client::new("test");
+        let ns = client.namespace("docs");
+        let ingester = DirectoryIngester::new(&ns, &dir).chunk_params(ChunkParams { max_chars: 11, overlap: 0 });
+        let rows = ingester.file_rows(&dir.join("nested/notes.md")).await.unwrap();
+
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0]["id"], "nested/notes.md#0");
+        assert_eq!(rows[0]["text"], "alpha beta");
+        assert_eq!(rows[1]["text"], "gamma delta");
+        assert_eq!(rows[1]["chunk_index"], 1);
+        assert_eq!(rows[1]["path"], "nested/notes.md");
+        assert!(rows[0]["mtime"].is_u64());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_json_text_field_and_attributes() {
+        let dir = temp_dir("json");
+        let doc = serde_json::json!({"title": "Walrus", "text": "tusks", "tags": ["a"], "id": 9});
+        std::fs::write(dir.join("doc.json"), doc.to_string()).unwrap();
+
+        let client = Client::new("test");
+        let ns = client.namespace("docs");
+        let rows = DirectoryIngester::new(&ns, &dir).file_rows(&dir.join("doc.json")).await.unwrap();
+
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0]["text"], "tusks");
+        assert_eq!(rows[0]["title"], "Walrus");
+        assert_eq!(rows[0]["id"], "doc.json#0");
+        assert!(!rows[0].contains_key("tags"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_long_paths_get_hashed_ids() {
+        let dir = temp_dir("long");
+        let nested = dir.join("nested").join("a".repeat(40)).join("b".repeat(40));
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::write(nested.join("notes.md"), "text").unwrap();
+
+        let client = Client::new("test");
+        let ns = client.namespace("docs");
+        let rows = DirectoryIngester::new(&ns, &dir).file_rows(&nested.join("notes.md")).await.unwrap();
+
+        let id = rows[0]["id"].as_str().unwrap();
+        assert!(id.starts_with("sha256:") && id.ends_with("#0") && id.len() <= MAX_ID_BYTES, "{}", id);
+        assert_eq!(rows[0]["path"], format!("nested/{}/{}/notes.md", "a".repeat(40), "b".repeat(40)));
+        assert_eq!(chunk_id("a.md", 3), "a.md#3");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
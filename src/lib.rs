@@ -1,17 +1,229 @@
+// Lets `#[derive(Document)]` output, which names `::rs_puff`, compile inside this crate.
+extern crate self as rs_puff;
+
+#[cfg(all(
+    target_arch = "wasm32",
+    any(
+        feature = "blocking",
+        feature = "emulator",
+        feature = "fault-injection",
+        feature = "object_store",
+        feature = "postgres",
+        feature = "socks",
+        feature = "stream-writes",
+    )
+))]
+compile_error!("the blocking, emulator, fault-injection, object_store, postgres, socks and stream-writes features aren't supported on wasm32");
+
+#[cfg(feature = "client")]
+mod auth;
+#[cfg(any(feature = "client", feature = "encryption"))]
+mod base64;
+#[cfg(feature = "blocking")]
+pub mod blocking;
+#[cfg(feature = "client")]
+mod blue_green;
+#[cfg(feature = "client")]
+pub mod budget;
+#[cfg(feature = "client")]
+pub mod builders;
+#[cfg(feature = "client")]
+pub mod bulk;
+pub mod calibrate;
+pub mod chunk;
+#[cfg(feature = "client")]
+mod circuit;
+#[cfg(feature = "client")]
 mod client;
+pub mod context;
+#[cfg(feature = "client")]
+mod default_attributes;
+mod document;
+#[cfg(feature = "client")]
+pub mod embed;
+#[cfg(feature = "emulator")]
+pub mod emulator;
+#[cfg(feature = "encryption")]
+pub mod encryption;
 mod error;
+#[cfg(feature = "client")]
+pub mod export;
+#[cfg(feature = "client")]
+pub mod fanout;
+#[cfg(feature = "fault-injection")]
+pub mod faults;
 mod filter;
+#[cfg(feature = "client")]
+mod get;
+#[cfg(feature = "client")]
+mod group;
+#[cfg(feature = "client")]
+pub mod hooks;
+#[cfg(feature = "client")]
+pub mod ingest;
+#[cfg(feature = "client")]
+pub mod merge;
+#[cfg(feature = "client")]
+pub mod metrics;
+#[cfg(feature = "client")]
+pub mod middleware;
+#[cfg(feature = "client")]
 mod namespace;
+#[cfg(feature = "object_store")]
+pub mod object_storage;
+#[cfg(feature = "client")]
+mod operation;
+#[cfg(feature = "client")]
+mod options;
+#[cfg(feature = "client")]
+pub mod paginate;
 pub mod params;
+#[cfg(feature = "client")]
+mod patch_merge;
+#[cfg(feature = "client")]
+mod patch_rules;
+#[cfg(feature = "postgres")]
+pub mod postgres;
+mod presets;
+#[cfg(feature = "client")]
+pub mod profile;
+pub mod quantize;
+#[cfg(feature = "client")]
+mod query_string;
+#[cfg(all(feature = "client", not(target_arch = "wasm32")))]
+pub mod queue;
 mod rank_by;
+#[cfg(feature = "client")]
+pub mod recall;
+#[cfg(feature = "client")]
+mod rename;
+#[cfg(feature = "client")]
+mod repository;
 pub mod responses;
+#[cfg(feature = "client")]
+mod retry;
+mod rt;
+mod schema;
+#[cfg(feature = "client")]
+mod schema_export;
+#[cfg(feature = "client")]
+mod self_test;
+#[cfg(feature = "client")]
+mod shape;
+#[cfg(feature = "client")]
+mod spec;
+#[cfg(feature = "stream-writes")]
+mod stream_write;
+#[cfg(feature = "client")]
+pub mod sync;
+#[cfg(feature = "client")]
+pub mod testing;
+#[cfg(feature = "client")]
+mod total;
+#[cfg(feature = "client")]
+mod transport;
+mod ttl;
 pub mod types;
+#[cfg(feature = "client")]
+pub mod usage;
+#[cfg(feature = "client")]
+mod verify;
+#[cfg(feature = "client")]
+mod versioned;
+#[cfg(feature = "client")]
+pub mod watch;
+mod wire;
 
-pub use client::{Client, NamespacesParams};
-pub use error::{Error, Result};
+#[cfg(feature = "client")]
+pub use auth::ApiKeyProvider;
+#[cfg(feature = "client")]
+pub use blue_green::{BlueGreenIndex, StandbyChecks, StandbyReport};
+#[cfg(feature = "client")]
+pub use budget::{BudgetedResponse, Fallback, LatencyBudget, MultiQueryBudget};
+#[cfg(feature = "client")]
+pub use builders::{QueryBuilder, WriteBuilder};
+#[cfg(feature = "client")]
+pub use bulk::{BatchFailure, BulkWriteReport, BulkWriter};
+#[cfg(feature = "client")]
+pub use bytes::Bytes;
+pub use calibrate::{ScoreSource, calibrated_scores};
+#[cfg(feature = "client")]
+pub use circuit::{CircuitBreaker, CircuitState};
+#[cfg(feature = "client")]
+pub use client::{Client, ClientBuilder, NamespacesParams};
+pub use context::{AssembledContext, ContextParams, assemble_context};
+#[cfg(feature = "client")]
+pub use default_attributes::{AttributeMerge, DefaultAttributes};
+pub use document::{Document, Selection};
+#[cfg(feature = "derive")]
+pub use rs_puff_derive::Document;
+#[cfg(feature = "client")]
+pub use embed::{Embedder, HashEmbedder};
+pub use error::{Error, RateLimit, Result};
+#[cfg(feature = "client")]
+pub use export::{ExportCheckpoint, ExportParams, Exporter};
+#[cfg(feature = "client")]
+pub use fanout::{FanoutParams, FanoutResult, WarmReport};
 pub use filter::{ContainsAllTokensParams, Filter};
+#[cfg(feature = "client")]
+pub use get::GetManyResult;
+#[cfg(feature = "client")]
+pub use group::RowGroup;
+#[cfg(feature = "client")]
+pub use hooks::{Mask, ReadHook, Redact};
+#[cfg(feature = "client")]
+pub use merge::{MergeStrategy, merge_results};
+#[cfg(feature = "client")]
+pub use metrics::{ExhaustiveSearchEvent, MetricsObserver, RequestMetrics, WriteBatchMetrics};
+#[cfg(feature = "client")]
+pub use middleware::{Middleware, RequestParts, ResponseParts};
+#[cfg(feature = "client")]
 pub use namespace::Namespace;
+#[cfg(feature = "client")]
+pub use operation::{AsyncOperation, PollStatus};
+#[cfg(feature = "client")]
+pub use options::{CallOptions, IDEMPOTENCY_KEY_HEADER};
+#[cfg(feature = "client")]
+pub use paginate::{OrderBy, Page, PageToken};
 pub use params::*;
+#[cfg(feature = "client")]
+pub use patch_rules::{PatchRuleReport, PatchRulesParams};
+pub use presets::RAG_VECTOR_ATTRIBUTE;
+#[cfg(feature = "client")]
+pub use profile::{ProfileReport, Profiler};
+#[cfg(feature = "client")]
+pub use query_string::QueryStringParams;
+#[cfg(all(feature = "client", not(target_arch = "wasm32")))]
+pub use queue::{IngestQueue, IngestQueueParams};
 pub use rank_by::{Bm25Params, Order, RankBy};
+#[cfg(feature = "client")]
+pub use recall::AnnComparison;
+#[cfg(feature = "client")]
+pub use rename::{RenameParams, RenameReport};
+#[cfg(feature = "client")]
+pub use reqwest::Method;
 pub use responses::*;
+#[cfg(feature = "client")]
+pub use repository::{NamespaceRepository, VectorRepository};
+#[cfg(feature = "client")]
+pub use retry::RetryPolicy;
+pub use schema::{
+    AttributeSchema, AttributeSchemaConfig, AttributeType, FullTextSearch, FullTextSearchConfig, Schema, VectorDtype,
+};
+#[cfg(feature = "client")]
+pub use schema_export::SchemaDocument;
+#[cfg(feature = "client")]
+pub use self_test::{SelfTestReport, SelfTestStep};
+#[cfg(feature = "client")]
+pub use spec::{EnsureReport, NamespaceSpec};
+#[cfg(feature = "client")]
+pub use transport::{HttpTransport, IpPreference, TransportError, TransportStage};
+pub use ttl::{EXPIRES_AT_ATTRIBUTE, expires_in};
 pub use types::*;
+#[cfg(feature = "client")]
+pub use usage::{UsageMeter, UsageRecorder, UsageSink, UsageSnapshot};
+#[cfg(feature = "client")]
+pub use versioned::VERSION_ATTRIBUTE;
+#[cfg(feature = "client")]
+pub use watch::MetadataDiff;
+pub use wire::{WireFormat, verify_fixture};
@@ -0,0 +1,161 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::fanout::FanoutResult;
+use crate::{MultiQueryResponse, Order, QueryResponse, Row};
+
+/// `k` from the original reciprocal rank fusion paper.
+pub const DEFAULT_RRF_K: f64 = 60.0;
+
+/// How [`merge_results`] combines several result lists.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MergeStrategy {
+    /// Sort all rows by `$dist`: [`Order::Asc`] for vector distances,
+    /// [`Order::Desc`] for BM25 scores. Rows without `$dist` go last.
+    ScoreSort(Order),
+    /// Reciprocal rank fusion: each row scores `1 / (k + rank)` per list it
+    /// appears in, so lists on different scales (e.g. vector and BM25) can
+    /// be combined.
+    Rrf { k: f64 },
+    /// Take the first row of each list, then the second of each, and so on.
+    Interleave,
+}
+
+impl MergeStrategy {
+    /// [`MergeStrategy::Rrf`] with [`DEFAULT_RRF_K`].
+    pub fn rrf() -> Self {
+        MergeStrategy::Rrf { k: DEFAULT_RRF_K }
+    }
+}
+
+/// Merge the rows of several responses into one list, best first.
+///
+/// A row whose `id` was already merged is dropped, so each id appears once,
+/// at its best position. Rows without an `id` are kept as-is.
+pub fn merge_results(responses: Vec<QueryResponse>, strategy: MergeStrategy) -> Vec<Row> {
+    let lists: Vec<Vec<Row>> = responses.into_iter().map(|resp| resp.rows).collect();
+    let merged = match strategy {
+        MergeStrategy::ScoreSort(order) => score_sort(lists, order),
+        MergeStrategy::Rrf { k } => rrf(lists, k),
+        MergeStrategy::Interleave => interleave(lists),
+    };
+    dedup_by_id(merged)
+}
+
+fn score_sort(lists: Vec<Vec<Row>>, order: Order) -> Vec<Row> {
+    let mut rows: Vec<Row> = lists.into_iter().flatten().collect();
+    let dist = |row: &Row| row.get("$dist").and_then(|d| d.as_f64());
+    // Stable, so ties keep their original list order.
+    rows.sort_by(|a, b| match (dist(a), dist(b)) {
+        (Some(a), Some(b)) => match order {
+            Order::Asc => a.total_cmp(&b),
+            Order::Desc => b.total_cmp(&a),
+        },
+        (a, b) => b.is_some().cmp(&a.is_some()),
+    });
+    rows
+}
+
+fn rrf(lists: Vec<Vec<Row>>, k: f64) -> Vec<Row> {
+    let mut scores: HashMap<String, f64> = HashMap::new();
+    for list in &lists {
+        for (rank, row) in list.iter().enumerate() {
+            if let Some(key) = id_key(row) {
+                *scores.entry(key).or_default() += 1.0 / (k + rank as f64 + 1.0);
+            }
+        }
+    }
+    let mut rows: Vec<(f64, Row)> = lists
+        .into_iter()
+        .flat_map(|list| list.into_iter().enumerate())
+        .map(|(rank, row)| {
+            let score = id_key(&row).map_or(1.0 / (k + rank as f64 + 1.0), |key| scores[&key]);
+            (score, row)
+        })
+        .collect();
+    rows.sort_by(|a, b| b.0.total_cmp(&a.0));
+    rows.into_iter().map(|(_, row)| row).collect()
+}
+
+fn interleave(lists: Vec<Vec<Row>>) -> Vec<Row> {
+    let mut iters: Vec<_> = lists.into_iter().map(|list| list.into_iter()).collect();
+    let mut rows = Vec::new();
+    loop {
+        let before = rows.len();
+        rows.extend(iters.iter_mut().filter_map(|it| it.next()));
+        if rows.len() == before {
+            return rows;
+        }
+    }
+}
+
+// The JSON form keeps numeric and string ids (1 vs "1") distinct.
+fn id_key(row: &Row) -> Option<String> {
+    row.get("id").map(|id| id.to_string())
+}
+
+fn dedup_by_id(rows: Vec<Row>) -> Vec<Row> {
+    let mut seen = HashSet::new();
+    rows.into_iter()
+        .filter(|row| id_key(row).is_none_or(|key| seen.insert(key)))
+        .collect()
+}
+
+impl FanoutResult<QueryResponse> {
+    /// Merge the successful responses; failed targets are skipped.
+    pub fn merge(self, strategy: MergeStrategy) -> Vec<Row> {
+        merge_results(self.results.into_iter().filter_map(|r| r.ok()).collect(), strategy)
+    }
+}
+
+impl MultiQueryResponse {
+    /// Merge the sub-query results, e.g. the vector and BM25 halves of a hybrid query.
+    pub fn merge(self, strategy: MergeStrategy) -> Vec<Row> {
+        merge_results(self.results, strategy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(rows: serde_json::Value) -> QueryResponse {
+        serde_json::from_value(serde_json::json!({ "rows": rows })).unwrap()
+    }
+
+    fn ids(rows: &[Row]) -> Vec<serde_json::Value> {
+        rows.iter().map(|row| row["id"].clone()).collect()
+    }
+
+    fn responses() -> Vec<QueryResponse> {
+        vec![
+            response(serde_json::json!([{"id": 1, "$dist": 0.1}, {"id": 2, "$dist": 0.4}, {"id": 3, "$dist": 0.5}])),
+            response(serde_json::json!([{"id": 3, "$dist": 0.2}, {"id": "1", "$dist": 0.3}, {"id": 1, "$dist": 0.9}])),
+        ]
+    }
+
+    #[test]
+    fn test_score_sort_dedups_keeping_best() {
+        let rows = merge_results(responses(), MergeStrategy::ScoreSort(Order::Asc));
+        assert_eq!(ids(&rows), serde_json::json!([1, 3, "1", 2]).as_array().unwrap().clone());
+        assert_eq!(rows[1]["$dist"], 0.2);
+
+        let rows = merge_results(responses(), MergeStrategy::ScoreSort(Order::Desc));
+        assert_eq!(ids(&rows), serde_json::json!([1, 3, 2, "1"]).as_array().unwrap().clone());
+    }
+
+    #[test]
+    fn test_rrf_rewards_rows_in_several_lists() {
+        let rows = merge_results(responses(), MergeStrategy::rrf());
+        // 1: ranks 1 and 3; 3: ranks 3 and 1; tie broken by list order.
+        assert_eq!(ids(&rows), serde_json::json!([1, 3, 2, "1"]).as_array().unwrap().clone());
+    }
+
+    #[test]
+    fn test_interleave_round_robins() {
+        let rows = merge_results(responses(), MergeStrategy::Interleave);
+        assert_eq!(ids(&rows), serde_json::json!([1, 3, 2, "1"]).as_array().unwrap().clone());
+
+        let uneven = vec![response(serde_json::json!([{"id": 1}, {"id": 2}, {"id": 4}])), response(serde_json::json!([{"id": 3}]))];
+        assert_eq!(ids(&merge_results(uneven, MergeStrategy::Interleave)), [1, 3, 2, 4]);
+    }
+}
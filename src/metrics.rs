@@ -0,0 +1,243 @@
+use std::time::Duration;
+
+use reqwest::{Method, StatusCode};
+use serde::Deserialize;
+
+use crate::{Error, QueryParams, QueryPerformance, Result, WriteParams};
+
+/// Receives client metrics, e.g. to forward them to a metrics backend.
+///
+/// Every method has a no-op default, so implementors only override what they
+/// record. Callbacks run inline on the request path and should not block.
+pub trait MetricsObserver: Send + Sync {
+    /// Called after each batch written by a write pipeline such as
+    /// [`BulkWriter`](crate::BulkWriter) or [`IngestQueue`](crate::IngestQueue),
+    /// whether or not it succeeded.
+    fn on_write_batch(&self, _metrics: &WriteBatchMetrics) {}
+
+    /// Called after every API call made by the client, successful or not,
+    /// once its response body has been read.
+    fn on_request(&self, _metrics: &RequestMetrics) {}
+}
+
+/// One API call, across retries; see [`MetricsObserver::on_request`].
+#[derive(Debug, Clone)]
+pub struct RequestMetrics {
+    pub method: Method,
+    /// The path with the namespace replaced by `{namespace}` and without the
+    /// query string, e.g. `/v2/namespaces/{namespace}/query`, so it can be
+    /// used as a low-cardinality label.
+    pub path_template: String,
+    pub namespace: Option<String>,
+    /// Status of the final response; `None` if none arrived.
+    pub status: Option<u16>,
+    /// Time from the first attempt until the response body was read,
+    /// including retries.
+    pub latency: Duration,
+    pub retries: u32,
+    /// Size of the serialized request body.
+    pub request_bytes: u64,
+    /// Size of the response body; 0 for failed calls.
+    pub response_bytes: u64,
+    /// Billing reported in the response body, for queries and writes.
+    pub billable_logical_bytes_queried: Option<u64>,
+    pub billable_logical_bytes_returned: Option<u64>,
+    pub billable_logical_bytes_written: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct BillingBody {
+    billing: Option<Billing>,
+}
+
+#[derive(Deserialize)]
+struct Billing {
+    billable_logical_bytes_queried: Option<u64>,
+    billable_logical_bytes_returned: Option<u64>,
+    billable_logical_bytes_written: Option<u64>,
+}
+
+impl RequestMetrics {
+    pub(crate) fn new(
+        method: Method,
+        path: &str,
+        latency: Duration,
+        retries: u32,
+        request_bytes: u64,
+        result: &Result<(StatusCode, bytes::Bytes)>,
+    ) -> Self {
+        let (path_template, namespace) = path_template(path);
+        let (status, body) = match result {
+            Ok((status, body)) => (Some(status.as_u16()), Some(body)),
+            Err(Error::Api { status, .. }) => (Some(*status), None),
+            Err(Error::RateLimited(_)) => (Some(429), None),
+            Err(_) => (None, None),
+        };
+        // Only queries and writes report billing; other bodies, which can be
+        // large listings or exports, aren't parsed a second time for it.
+        let billing = body
+            .filter(|_| reports_billing(&method, &path_template))
+            .and_then(|body| serde_json::from_slice::<BillingBody>(body).ok())
+            .and_then(|body| body.billing);
+        Self {
+            method,
+            path_template,
+            namespace,
+            status,
+            latency,
+            retries,
+            request_bytes,
+            response_bytes: body.map_or(0, |body| body.len() as u64),
+            billable_logical_bytes_queried: billing.as_ref().and_then(|b| b.billable_logical_bytes_queried),
+            billable_logical_bytes_returned: billing.as_ref().and_then(|b| b.billable_logical_bytes_returned),
+            billable_logical_bytes_written: billing.as_ref().and_then(|b| b.billable_logical_bytes_written),
+        }
+    }
+}
+
+fn reports_billing(method: &Method, path_template: &str) -> bool {
+    *method == Method::POST
+        && matches!(path_template, "/v2/namespaces/{namespace}" | "/v2/namespaces/{namespace}/query")
+}
+
+/// `path` without its query string and with the segment after `namespaces`
+/// replaced by `{namespace}`, and that namespace.
+pub(crate) fn path_template(path: &str) -> (String, Option<String>) {
+    let path = path.split('?').next().unwrap_or_default();
+    let mut namespace = None;
+    let mut previous = "";
+    let segments: Vec<&str> = path
+        .split('/')
+        .map(|segment| {
+            let templated = if previous == "namespaces" && !segment.is_empty() {
+                namespace = Some(segment.to_string());
+                "{namespace}"
+            } else {
+                segment
+            };
+            previous = segment;
+            templated
+        })
+        .collect();
+    (segments.join("/"), namespace)
+}
+
+#[derive(Debug, Clone)]
+pub struct WriteBatchMetrics {
+    pub namespace: String,
+    /// Rows upserted, patched or deleted by id in the batch.
+    pub rows: u64,
+    /// Size of the serialized request body.
+    pub bytes: u64,
+    /// Time from the first attempt until the final response, including retries.
+    pub latency: Duration,
+    pub retries: u32,
+    /// Attempts rejected with 429 because the namespace was applying backpressure.
+    pub backpressure_events: u32,
+    pub success: bool,
+}
+
+/// Passed to the callback registered with
+/// [`Client::with_exhaustive_search_alert`](crate::Client::with_exhaustive_search_alert).
+#[derive(Debug)]
+pub struct ExhaustiveSearchEvent<'a> {
+    pub namespace: &'a str,
+    pub query: &'a QueryParams,
+    pub performance: &'a QueryPerformance,
+    /// The `exhaustive_search_count` reported for the query.
+    pub exhaustive_search_count: u64,
+}
+
+type ExhaustiveSearchCallback = dyn Fn(&ExhaustiveSearchEvent<'_>) + Send + Sync;
+
+pub(crate) struct ExhaustiveSearchAlert {
+    pub threshold: u64,
+    pub callback: Box<ExhaustiveSearchCallback>,
+}
+
+impl ExhaustiveSearchAlert {
+    pub(crate) fn check(&self, namespace: &str, query: &QueryParams, performance: Option<&QueryPerformance>) {
+        if let Some(performance) = performance
+            && let Some(count) = performance.exhaustive_search_count
+            && count > self.threshold
+        {
+            (self.callback)(&ExhaustiveSearchEvent { namespace, query, performance, exhaustive_search_count: count });
+        }
+    }
+}
+
+pub(crate) fn batch_rows(params: &WriteParams) -> u64 {
+    let rows = |rows: &Option<Vec<_>>| rows.as_ref().map_or(0, |r| r.len());
+    let columns = |columns: &Option<std::collections::HashMap<String, Vec<serde_json::Value>>>| {
+        columns.as_ref().and_then(|c| c.get("id")).map_or(0, |ids| ids.len())
+    };
+    (rows(&params.upsert_rows)
+        + rows(&params.patch_rows)
+        + params.deletes.as_ref().map_or(0, |d| d.len())
+        + columns(&params.upsert_columns)
+        + columns(&params.patch_columns)) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batch_rows_counts_rows_columns_and_deletes() {
+        let params = WriteParams {
+            upsert_rows: Some(vec![Default::default(), Default::default()]),
+            upsert_columns: Some([("id".to_string(), vec![serde_json::json!(3)])].into()),
+            deletes: Some(vec![serde_json::json!(4)]),
+            ..Default::default()
+        };
+        assert_eq!(batch_rows(&params), 4);
+    }
+
+    #[test]
+    fn test_request_metrics_template_path_and_read_billing() {
+        assert_eq!(path_template("/v1/namespaces?prefix=a"), ("/v1/namespaces".to_string(), None));
+        assert_eq!(
+            path_template("/v2/namespaces/docs/query"),
+            ("/v2/namespaces/{namespace}/query".to_string(), Some("docs".to_string()))
+        );
+
+        let body = bytes::Bytes::from(r#"{"rows": [], "billing": {"billable_logical_bytes_queried": 10, "billable_logical_bytes_returned": 2}}"#);
+        let metrics =
+            RequestMetrics::new(Method::POST, "/v2/namespaces/docs/query", Duration::ZERO, 1, 5, &Ok((StatusCode::OK, body)));
+        assert_eq!(metrics.status, Some(200));
+        assert_eq!(metrics.namespace.as_deref(), Some("docs"));
+        assert_eq!(metrics.billable_logical_bytes_queried, Some(10));
+        assert_eq!(metrics.billable_logical_bytes_written, None);
+
+        let body = bytes::Bytes::from(r#"{"billing": {"billable_logical_bytes_queried": 10}}"#);
+        let metrics =
+            RequestMetrics::new(Method::GET, "/v1/namespaces/docs/metadata", Duration::ZERO, 0, 0, &Ok((StatusCode::OK, body)));
+        assert_eq!(metrics.billable_logical_bytes_queried, None);
+
+        let err = Err(Error::Api { status: 503, message: String::new() });
+        let metrics = RequestMetrics::new(Method::GET, "/v1/namespaces", Duration::ZERO, 3, 0, &err);
+        assert_eq!((metrics.status, metrics.response_bytes), (Some(503), 0));
+    }
+
+    #[test]
+    fn test_exhaustive_search_alert_fires_above_threshold() {
+        let fired = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let alert = ExhaustiveSearchAlert {
+            threshold: 2,
+            callback: Box::new({
+                let fired = fired.clone();
+                move |event: &ExhaustiveSearchEvent<'_>| {
+                    fired.lock().unwrap().push((event.namespace.to_string(), event.exhaustive_search_count))
+                }
+            }),
+        };
+        let perf = |count: u64| -> QueryPerformance {
+            serde_json::from_value(serde_json::json!({"exhaustive_search_count": count})).unwrap()
+        };
+        let query = QueryParams::default();
+        alert.check("docs", &query, Some(&perf(2)));
+        alert.check("docs", &query, Some(&perf(3)));
+        alert.check("docs", &query, None);
+        assert_eq!(*fired.lock().unwrap(), [("docs".to_string(), 3)]);
+    }
+}
@@ -0,0 +1,157 @@
+use std::time::Duration;
+
+use bytes::Bytes;
+use reqwest::header::HeaderMap;
+use reqwest::{Method, StatusCode};
+use sha2::{Digest, Sha256};
+
+use crate::{Error, Result};
+use crate::testing::canonicalize;
+
+/// An outgoing request as seen by [`Middleware::on_request`].
+///
+/// The `Authorization` header is added after middleware runs and is not
+/// visible here.
+#[derive(Debug, Clone)]
+pub struct RequestParts {
+    pub method: Method,
+    /// Path and query string, e.g. `/v2/namespaces/docs/query`.
+    pub path: String,
+    pub headers: HeaderMap,
+    /// Serialized JSON body.
+    pub body: Option<Bytes>,
+}
+
+impl RequestParts {
+    /// The body as JSON with object keys sorted and no whitespace, so equal
+    /// requests render identically; `None` without a JSON body.
+    pub fn canonical_body(&self) -> Option<String> {
+        let value: serde_json::Value = serde_json::from_slice(self.body.as_ref()?).ok()?;
+        Some(canonicalize(value).to_string())
+    }
+
+    /// Hex-encoded SHA-256 of [`canonical_body`](Self::canonical_body).
+    pub fn body_sha256(&self) -> Option<String> {
+        self.canonical_body().map(|body| sha256_hex(body.as_bytes()))
+    }
+}
+
+/// A response as seen by [`Middleware::on_response`], before its body is
+/// read.
+#[derive(Debug, Clone)]
+pub struct ResponseParts {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    /// Time from sending the request until the response headers arrived.
+    pub elapsed: Duration,
+}
+
+pub(crate) fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Observes or rewrites requests before they are sent, and observes their
+/// responses; register with
+/// [`Client::with_middleware`](crate::Client::with_middleware).
+///
+/// Closures taking `&mut RequestParts` are middleware that only see
+/// requests.
+pub trait Middleware: Send + Sync {
+    /// Called before every attempt, including retries. An error fails the
+    /// call without sending the request.
+    fn on_request(&self, request: &mut RequestParts) -> Result<()>;
+
+    /// Called for every response, including error statuses such as 429 and
+    /// 5xx, with the request as this middleware's `on_request` left it.
+    fn on_response(&self, _request: &RequestParts, _response: &ResponseParts) {}
+
+    /// Called when an attempt fails before a response arrives, e.g. a
+    /// connection error or timeout.
+    fn on_error(&self, _request: &RequestParts, _error: &Error) {}
+}
+
+impl<F> Middleware for F
+where
+    F: Fn(&mut RequestParts) -> Result<()> + Send + Sync,
+{
+    fn on_request(&self, request: &mut RequestParts) -> Result<()> {
+        self(request)
+    }
+}
+
+/// One entry produced by [`AuditLog`].
+#[derive(Debug, Clone)]
+pub struct AuditRecord {
+    pub method: Method,
+    pub path: String,
+    /// See [`RequestParts::canonical_body`].
+    pub canonical_body: Option<String>,
+    /// See [`RequestParts::body_sha256`].
+    pub body_sha256: Option<String>,
+}
+
+/// Middleware that hands an [`AuditRecord`] of every attempt to a sink,
+/// e.g. a compliance log.
+pub struct AuditLog<F> {
+    sink: F,
+}
+
+impl<F: Fn(&AuditRecord) + Send + Sync> AuditLog<F> {
+    pub fn new(sink: F) -> Self {
+        Self { sink }
+    }
+}
+
+impl<F: Fn(&AuditRecord) + Send + Sync> Middleware for AuditLog<F> {
+    fn on_request(&self, request: &mut RequestParts) -> Result<()> {
+        let canonical_body = request.canonical_body();
+        (self.sink)(&AuditRecord {
+            method: request.method.clone(),
+            path: request.path.clone(),
+            body_sha256: canonical_body.as_deref().map(|body| sha256_hex(body.as_bytes())),
+            canonical_body,
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    fn parts(body: &str) -> RequestParts {
+        RequestParts {
+            method: Method::POST,
+            path: "/v2/namespaces/docs".to_string(),
+            headers: HeaderMap::new(),
+            body: Some(Bytes::from(body.to_string())),
+        }
+    }
+
+    #[test]
+    fn test_canonical_body_ignores_key_order_and_whitespace() {
+        let a = parts(r#"{"b": 1, "a": {"y": [1, 2], "x": null}}"#);
+        let b = parts(r#"{"a":{"x":null,"y":[1,2]},"b":1}"#);
+        assert_eq!(a.canonical_body().unwrap(), r#"{"a":{"x":null,"y":[1,2]},"b":1}"#);
+        assert_eq!(a.body_sha256(), b.body_sha256());
+        assert_ne!(a.body_sha256(), parts(r#"{"b": 2}"#).body_sha256());
+        assert_eq!(sha256_hex(b"abc"), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+    }
+
+    #[test]
+    fn test_audit_log_records_every_request() {
+        let records = Mutex::new(Vec::new());
+        let audit = AuditLog::new(|record: &AuditRecord| records.lock().unwrap().push(record.clone()));
+        audit.on_request(&mut parts(r#"{"top_k": 1}"#)).unwrap();
+        let mut no_body = parts("");
+        no_body.body = None;
+        audit.on_request(&mut no_body).unwrap();
+
+        let records = records.into_inner().unwrap();
+        assert_eq!(records[0].canonical_body.as_deref(), Some(r#"{"top_k":1}"#));
+        assert_eq!(records[0].body_sha256.as_ref().unwrap().len(), 64);
+        assert!(records[1].body_sha256.is_none());
+    }
+}
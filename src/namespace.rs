@@ -1,22 +1,74 @@
+use std::borrow::Cow;
+use std::sync::Arc;
+
+use bytes::Bytes;
 use reqwest::Method;
+use reqwest::header::HeaderValue;
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
 
+use crate::client::{Payload, RequestStats};
+use crate::options::{IDEMPOTENCY_KEY_HEADER, new_idempotency_key};
+use crate::hooks::{self, ReadHook};
+use crate::metrics::{self, WriteBatchMetrics};
+use crate::rt::Instant;
+use crate::verify;
 use crate::{
-    Client, Error, Result,
-    params::{MultiQueryParams, QueryParams, WriteParams},
+    AttributeMerge, CallOptions, Client, DefaultAttributes, Error, Result, Row, Schema,
+    params::{MultiQueryParams, QueryParams, WriteParams, WriteParamsRef},
     responses::{
-        DeleteAllResponse, HintCacheWarmResponse, MultiQueryResponse, NamespaceMetadata,
-        QueryResponse, SchemaResponse, WriteResponse,
+        DeleteAllResponse, DeleteAllStatus, HintCacheWarmResponse, MultiQueryResponse, NamespaceMetadata,
+        QueryBilling, QueryPerformance, QueryResponse, SchemaResponse, WriteResponse,
     },
 };
 
-pub struct Namespace<'a> {
-    client: &'a Client,
+/// A handle to one namespace.
+///
+/// Handles own a clone of their [`Client`], so they are cheap to clone and
+/// can be stored in structs or moved into tasks.
+#[derive(Clone)]
+pub struct Namespace {
+    pub(crate) client: Client,
     name: String,
+    read_hooks: Vec<Arc<dyn ReadHook>>,
+    default_attributes: Option<DefaultAttributes>,
 }
 
-impl<'a> Namespace<'a> {
-    pub(crate) fn new(client: &'a Client, name: String) -> Self {
-        Self { client, name }
+impl Namespace {
+    pub(crate) fn new(client: Client, name: String) -> Self {
+        Self { client, name, read_hooks: Vec::new(), default_attributes: None }
+    }
+
+    #[deprecated(note = "namespaces own their client; this returns `self`")]
+    pub fn into_owned(self) -> Namespace {
+        self
+    }
+
+    /// Run `hook` on every row returned by queries through this handle,
+    /// including raw and multi-query responses. Reads the crate makes for its
+    /// own use, e.g. by [`Exporter`](crate::Exporter) or
+    /// [`BulkWriter::content_hash`](crate::BulkWriter::content_hash), see the
+    /// stored rows.
+    pub fn with_read_hook(mut self, hook: impl ReadHook + 'static) -> Self {
+        self.read_hooks.push(Arc::new(hook));
+        self
+    }
+
+    /// Merge `defaults` into the projection of every query through this
+    /// handle, including raw and multi-queries; see [`AttributeMerge`].
+    /// Like read hooks, defaults don't apply to the crate's own reads, so
+    /// exports keep every attribute.
+    pub fn with_default_attributes(mut self, defaults: DefaultAttributes) -> Self {
+        self.default_attributes = Some(defaults);
+        self
+    }
+
+    /// `params` with the default attributes merged in.
+    fn with_defaults(&self, mut params: QueryParams, merge: Option<AttributeMerge>) -> QueryParams {
+        if let Some(defaults) = &self.default_attributes {
+            defaults.apply(&mut params, merge);
+        }
+        params
     }
 
     pub fn name(&self) -> &str {
@@ -32,21 +84,237 @@ impl<'a> Namespace<'a> {
     }
 
     pub async fn write(&self, params: WriteParams) -> Result<WriteResponse> {
-        self.client
-            .request(Method::POST, &self.v2_path(""), Some(&params))
-            .await
+        self.write_with_options(params, &CallOptions::default()).await
+    }
+
+    /// Write with per-call options.
+    ///
+    /// Every attempt carries the same [`CallOptions::idempotency_key`], one
+    /// generated per call unless set.
+    ///
+    /// Writes that are not [idempotent](WriteParams::is_idempotent) are only
+    /// retried when they cannot have reached the server; any later failure is
+    /// returned as [`Error::AmbiguousWrite`], unless
+    /// [`CallOptions::verify_ambiguous_writes`] confirms the upserted rows of
+    /// a write that only upserts.
+    pub async fn write_with_options(&self, params: WriteParams, opts: &CallOptions) -> Result<WriteResponse> {
+        self.write_with_stats(params, opts, &mut RequestStats::default()).await
+    }
+
+    /// Like [`write`](Self::write), with the rows borrowed; see
+    /// [`WriteParamsRef`].
+    pub async fn write_ref(&self, params: WriteParamsRef<'_>) -> Result<WriteResponse> {
+        self.write_ref_with_options(params, &CallOptions::default()).await
+    }
+
+    pub async fn write_ref_with_options(&self, params: WriteParamsRef<'_>, opts: &CallOptions) -> Result<WriteResponse> {
+        params.validate()?;
+        let idempotent = params.params.is_idempotent();
+        let payload = Payload::json(&params)?;
+        let verifiable = params.patch_rows.is_none() && verify::upserts_only(&params.params);
+        let upsert_rows = params.upsert_rows.filter(|_| verifiable);
+        self.send_write(payload, upsert_rows, idempotent, opts, &mut RequestStats::default()).await
+    }
+
+    /// Write one batch of a write pipeline, reporting it to the client's
+    /// metrics observer.
+    pub(crate) async fn write_batch(&self, params: WriteParams) -> Result<WriteResponse> {
+        let Some(observer) = &self.client.inner.metrics else {
+            return self.write(params).await;
+        };
+        let rows = metrics::batch_rows(&params);
+        let mut stats = RequestStats::default();
+        let start = Instant::now();
+        let result = self.write_with_stats(params, &CallOptions::default(), &mut stats).await;
+        observer.on_write_batch(&WriteBatchMetrics {
+            namespace: self.name.clone(),
+            rows,
+            bytes: stats.request_bytes,
+            latency: start.elapsed(),
+            retries: stats.retries,
+            backpressure_events: stats.throttled,
+            success: result.is_ok(),
+        });
+        result
+    }
+
+    async fn write_with_stats(
+        &self,
+        params: WriteParams,
+        opts: &CallOptions,
+        stats: &mut RequestStats,
+    ) -> Result<WriteResponse> {
+        params.validate()?;
+        let idempotent = params.is_idempotent();
+        let upsert_rows = params.upsert_rows.as_deref().filter(|_| verify::upserts_only(&params));
+        self.send_write(Payload::json(&params)?, upsert_rows, idempotent, opts, stats).await
+    }
+
+    /// `upsert_rows` are read back to confirm an ambiguous write; pass them
+    /// only when the write does nothing else, see [`verify::upserts_only`].
+    pub(crate) async fn send_write(
+        &self,
+        payload: Payload,
+        upsert_rows: Option<&[Row]>,
+        idempotent: bool,
+        opts: &CallOptions,
+        stats: &mut RequestStats,
+    ) -> Result<WriteResponse> {
+        let idempotency_key = opts.idempotency_key.clone().unwrap_or_else(new_idempotency_key);
+        let mut opts = Cow::Borrowed(opts);
+        let value = HeaderValue::from_str(&idempotency_key)
+            .map_err(|_| Error::InvalidParams(format!("invalid idempotency key {:?}", idempotency_key)))?;
+        opts.to_mut().headers.insert(IDEMPOTENCY_KEY_HEADER, value);
+        let result = self
+            .client
+            .request_payload(Method::POST, &self.v2_path(""), Some(payload), &opts, idempotent, stats)
+            .await;
+
+        match result {
+            Err(e) if !idempotent && e.may_have_been_applied() => {
+                if opts.verify_ambiguous_writes
+                    && let Some(rows) = upsert_rows
+                    && self.upserts_applied(rows).await?
+                {
+                    return Ok(verify::applied_response(rows));
+                }
+                Err(Error::AmbiguousWrite { source: Box::new(e), idempotency_key })
+            }
+            other => other,
+        }
+    }
+
+    async fn upserts_applied(&self, rows: &[Row]) -> Result<bool> {
+        let Some(params) = verify::read_back_query(rows) else {
+            return Ok(false);
+        };
+        // Read hooks may hide attributes, so compare against the stored rows.
+        let resp = self.query_unhooked(&params).await?;
+        Ok(verify::rows_match(rows, &resp.rows))
+    }
+
+    /// Query the stored rows, without read hooks or default attributes.
+    pub(crate) async fn query_unhooked(&self, params: &QueryParams) -> Result<QueryResponse> {
+        let resp: QueryResponse = self.client.request(Method::POST, &self.v2_path("/query"), Some(params)).await?;
+        self.check_exhaustive_search(params, resp.performance.as_ref());
+        self.record_usage(resp.billing.as_ref());
+        Ok(resp)
     }
 
     pub async fn query(&self, params: QueryParams) -> Result<QueryResponse> {
-        self.client
-            .request(Method::POST, &self.v2_path("/query"), Some(&params))
-            .await
+        self.query_with_options(params, &CallOptions::default()).await
     }
 
-    pub async fn multi_query(&self, params: MultiQueryParams) -> Result<MultiQueryResponse> {
-        self.client
+    pub async fn query_with_options(&self, params: QueryParams, opts: &CallOptions) -> Result<QueryResponse> {
+        let params = self.with_defaults(params, opts.attribute_merge);
+        let mut resp: QueryResponse = self
+            .client
+            .request_with(Method::POST, &self.v2_path("/query"), Some(&params), opts, true)
+            .await?;
+        self.check_exhaustive_search(&params, resp.performance.as_ref());
+        self.record_usage(resp.billing.as_ref());
+        hooks::apply(&self.read_hooks, &mut resp.rows);
+        Ok(resp)
+    }
+
+    fn check_exhaustive_search(&self, query: &QueryParams, performance: Option<&QueryPerformance>) {
+        if let Some(alert) = &self.client.inner.exhaustive_search_alert {
+            alert.check(&self.name, query, performance);
+        }
+    }
+
+    fn record_usage(&self, billing: Option<&QueryBilling>) {
+        if let Some(meter) = &self.client.inner.usage_meter {
+            meter.record(&self.name, billing);
+        }
+    }
+
+    /// Check and record a query response that isn't parsed into a [`QueryResponse`].
+    fn observe_raw<'de>(&self, query: &QueryParams, body: impl serde::Deserializer<'de>) {
+        let inner = &self.client.inner;
+        if inner.usage_meter.is_none() && inner.exhaustive_search_alert.is_none() {
+            return;
+        }
+        if let Ok(accounting) = QueryAccounting::deserialize(body) {
+            self.check_exhaustive_search(query, accounting.performance.as_ref());
+            self.record_usage(accounting.billing.as_ref());
+        }
+    }
+
+    /// Run a query and deserialize each row into `T`.
+    ///
+    /// See [`Namespace::query_documents`] to fetch only the attributes `T` uses.
+    pub async fn query_as<T: DeserializeOwned>(&self, params: QueryParams) -> Result<Vec<T>> {
+        self.query(params).await?.rows_as()
+    }
+
+    /// Run a query, deserializing rows straight into `R` in one pass.
+    ///
+    /// `R` can be a map over a custom attribute value type, e.g.
+    /// `HashMap<String, MyValue>`, or a struct. With read hooks registered
+    /// rows pass through [`Row`] first so the hooks can run.
+    pub async fn query_rows<R: DeserializeOwned>(&self, params: QueryParams) -> Result<QueryResponse<R>> {
+        let params = self.with_defaults(params, None);
+        let resp: QueryResponse<R> = if self.read_hooks.is_empty() {
+            self.client
+                .request(Method::POST, &self.v2_path("/query"), Some(&params))
+                .await?
+        } else {
+            serde_json::from_value(self.hooked_query_value(&params).await?)?
+        };
+        self.check_exhaustive_search(&params, resp.performance.as_ref());
+        self.record_usage(resp.billing.as_ref());
+        Ok(resp)
+    }
+
+    /// Run a query and return the response body as untyped JSON.
+    pub async fn query_raw_value(&self, params: QueryParams) -> Result<serde_json::Value> {
+        let params = self.with_defaults(params, None);
+        let body = self.hooked_query_value(&params).await?;
+        self.observe_raw(&params, &body);
+        Ok(body)
+    }
+
+    async fn hooked_query_value(&self, params: &QueryParams) -> Result<serde_json::Value> {
+        let mut body = self
+            .client
+            .request(Method::POST, &self.v2_path("/query"), Some(params))
+            .await?;
+        hooks::apply_to_value(&self.read_hooks, &mut body);
+        Ok(body)
+    }
+
+    /// Run a query and return the response body untouched, e.g. to forward it to a browser.
+    ///
+    /// With read hooks registered the body is parsed and re-serialized so the
+    /// hooks can run.
+    pub async fn query_raw_bytes(&self, params: QueryParams) -> Result<Bytes> {
+        let params = self.with_defaults(params, None);
+        if !self.read_hooks.is_empty() {
+            let body = self.hooked_query_value(&params).await?;
+            self.observe_raw(&params, &body);
+            return Ok(Bytes::from(serde_json::to_vec(&body)?));
+        }
+        let body = self
+            .client
+            .request_bytes(Method::POST, &self.v2_path("/query"), Some(&params))
+            .await?;
+        self.observe_raw(&params, &mut serde_json::Deserializer::from_slice(&body));
+        Ok(body)
+    }
+
+    pub async fn multi_query(&self, mut params: MultiQueryParams) -> Result<MultiQueryResponse> {
+        params.queries = params.queries.into_iter().map(|query| self.with_defaults(query, None)).collect();
+        let mut resp: MultiQueryResponse = self
+            .client
             .request(Method::POST, &self.v2_path("/query"), Some(&params))
-            .await
+            .await?;
+        for (query, result) in params.queries.iter().zip(&mut resp.results) {
+            self.check_exhaustive_search(query, result.performance.as_ref());
+            self.record_usage(result.billing.as_ref());
+            hooks::apply(&self.read_hooks, &mut result.rows);
+        }
+        Ok(resp)
     }
 
     pub async fn delete_all(&self) -> Result<DeleteAllResponse> {
@@ -55,6 +323,16 @@ impl<'a> Namespace<'a> {
             .await
     }
 
+    /// Delete the namespace, treating one that doesn't exist as already
+    /// deleted rather than an error. Useful in cleanup paths.
+    pub async fn delete_all_if_exists(&self) -> Result<DeleteAllStatus> {
+        match self.delete_all().await {
+            Ok(resp) => Ok(DeleteAllStatus::Deleted(resp)),
+            Err(Error::Api { status: 404, .. }) => Ok(DeleteAllStatus::AlreadyAbsent),
+            Err(e) => Err(e),
+        }
+    }
+
     pub async fn metadata(&self) -> Result<NamespaceMetadata> {
         self.client
             .request_no_body(Method::GET, &self.v1_path("/metadata"))
@@ -67,6 +345,14 @@ impl<'a> Namespace<'a> {
             .await
     }
 
+    /// Add attributes or change options; attributes not in `schema` are left
+    /// as they are. Returns the updated schema.
+    pub async fn update_schema(&self, schema: Schema) -> Result<SchemaResponse> {
+        self.client
+            .request(Method::POST, &self.v1_path("/schema"), Some(&schema))
+            .await
+    }
+
     pub async fn hint_cache_warm(&self) -> Result<HintCacheWarmResponse> {
         self.client
             .request_no_body(Method::GET, &self.v1_path("/hint_cache_warm"))
@@ -85,3 +371,12 @@ impl<'a> Namespace<'a> {
         }
     }
 }
+
+/// The parts of a query response the client meters.
+#[derive(Deserialize)]
+struct QueryAccounting {
+    #[serde(default)]
+    billing: Option<QueryBilling>,
+    #[serde(default)]
+    performance: Option<QueryPerformance>,
+}
@@ -0,0 +1,324 @@
+use std::time::{Duration, Instant};
+
+use futures_util::{StreamExt, TryStreamExt};
+use object_store::{ObjectStore, WriteMultipart, path::Path};
+
+use crate::sync::CheckpointStore;
+use crate::{DistanceMetric, Error, ExportCheckpoint, ExportParams, Exporter, Namespace, Result, Row, WriteParams};
+
+// Upload parts buffered in memory before the export waits on the store.
+const MAX_CONCURRENT_PARTS: usize = 4;
+
+#[derive(Debug, Clone)]
+pub struct ImportParams {
+    /// Rows upserted per write request; must be at least 1.
+    pub batch_size: usize,
+
+    /// Required when importing into a namespace that does not exist yet.
+    pub distance_metric: Option<DistanceMetric>,
+}
+
+impl Default for ImportParams {
+    fn default() -> Self {
+        Self { batch_size: 1000, distance_metric: None }
+    }
+}
+
+impl ImportParams {
+    fn validate(&self) -> Result<()> {
+        if self.batch_size == 0 {
+            return Err(Error::InvalidParams("batch_size must be at least 1".to_string()));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TransferSummary {
+    pub rows: u64,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct PartedExportParams {
+    pub export: ExportParams,
+    /// Rows per part object; the checkpoint is saved after each part.
+    pub rows_per_part: u64,
+    /// Stop after this long, closing the current part early and saving the
+    /// checkpoint, e.g. to fit a job's time limit.
+    pub deadline: Option<Duration>,
+}
+
+impl Default for PartedExportParams {
+    fn default() -> Self {
+        Self { export: ExportParams::default(), rows_per_part: 100_000, deadline: None }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PartedExportSummary {
+    /// Totals across all runs, including those before a resume.
+    pub checkpoint: ExportCheckpoint,
+    /// Whether every row has been exported; `false` when the deadline was hit.
+    pub complete: bool,
+}
+
+/// Object name of part `index` under `prefix`; zero-padded so parts list in order.
+fn part_path(prefix: &Path, index: u64) -> Path {
+    prefix.child(format!("part-{:06}.ndjson", index))
+}
+
+/// Index of the part at `location`, if it was written directly under
+/// `prefix` by [`part_path`].
+fn part_index(prefix: &Path, location: &Path) -> Option<u64> {
+    let mut rest = location.prefix_match(prefix)?;
+    let (Some(name), None) = (rest.next(), rest.next()) else {
+        return None;
+    };
+    let index = name.as_ref().strip_prefix("part-")?.strip_suffix(".ndjson")?;
+    if index.len() < 6 || !index.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    index.parse().ok()
+}
+
+impl Namespace {
+    /// Export every row as newline-delimited JSON to `path`.
+    ///
+    /// Pages are streamed into a multipart upload, so nothing is staged on
+    /// local disk. The upload is aborted if the export fails part-way.
+    pub async fn export_to_object_store(
+        &self,
+        store: &dyn ObjectStore,
+        path: &Path,
+        params: ExportParams,
+    ) -> Result<TransferSummary> {
+        let mut writer = WriteMultipart::new(store.put_multipart(path).await?);
+        let mut exporter = self.exporter(params);
+        let mut summary = TransferSummary::default();
+
+        loop {
+            let page = match exporter.next_page().await {
+                Ok(Some(page)) => page,
+                Ok(None) => break,
+                Err(e) => {
+                    let _ = writer.abort().await;
+                    return Err(e);
+                }
+            };
+            let buf = ndjson(&page)?;
+            writer.wait_for_capacity(MAX_CONCURRENT_PARTS).await?;
+            writer.write(&buf);
+            summary.rows += page.len() as u64;
+            summary.bytes += buf.len() as u64;
+        }
+
+        writer.finish().await?;
+        Ok(summary)
+    }
+
+    /// Export every row as newline-delimited JSON part objects under `prefix`,
+    /// resuming from the checkpoint in `checkpoints` if there is one.
+    ///
+    /// A part interrupted before its checkpoint was saved is rewritten from
+    /// its first row on resume, so parts never contain duplicates.
+    pub async fn export_parts_to_object_store<K: CheckpointStore<ExportCheckpoint>>(
+        &self,
+        store: &dyn ObjectStore,
+        prefix: &Path,
+        params: PartedExportParams,
+        checkpoints: &mut K,
+    ) -> Result<PartedExportSummary> {
+        let deadline = params.deadline.map(|d| Instant::now() + d);
+        let mut checkpoint = checkpoints.load().await?.unwrap_or_default();
+        let mut exporter = Exporter::new(self, params.export).resume_after(checkpoint.last_id.clone());
+
+        loop {
+            let mut writer: Option<WriteMultipart> = None;
+            let mut part = TransferSummary::default();
+            let mut exhausted = false;
+
+            while part.rows < params.rows_per_part.max(1) {
+                let page = match exporter.next_page().await {
+                    Ok(Some(page)) => page,
+                    Ok(None) => {
+                        exhausted = true;
+                        break;
+                    }
+                    Err(e) => {
+                        if let Some(writer) = writer {
+                            let _ = writer.abort().await;
+                        }
+                        return Err(e);
+                    }
+                };
+                let writer = match &mut writer {
+                    Some(writer) => writer,
+                    None => writer.insert(WriteMultipart::new(
+                        store.put_multipart(&part_path(prefix, checkpoint.parts)).await?,
+                    )),
+                };
+                let buf = ndjson(&page)?;
+                writer.wait_for_capacity(MAX_CONCURRENT_PARTS).await?;
+                writer.write(&buf);
+                part.rows += page.len() as u64;
+                part.bytes += buf.len() as u64;
+
+                if deadline.is_some_and(|d| Instant::now() >= d) {
+                    break;
+                }
+            }
+
+            if let Some(writer) = writer {
+                writer.finish().await?;
+                checkpoint.last_id = exporter.last_id().cloned();
+                checkpoint.rows += part.rows;
+                checkpoint.bytes += part.bytes;
+                checkpoint.parts += 1;
+                checkpoints.save(&checkpoint).await?;
+            }
+            if exhausted {
+                return Ok(PartedExportSummary { checkpoint, complete: true });
+            }
+            if deadline.is_some_and(|d| Instant::now() >= d) {
+                return Ok(PartedExportSummary { checkpoint, complete: false });
+            }
+        }
+    }
+
+    /// Upsert every part written by [`Namespace::export_parts_to_object_store`]
+    /// under `prefix`, in order. Other objects under `prefix`, such as a
+    /// checkpoint, are ignored.
+    pub async fn import_parts_from_object_store(
+        &self,
+        store: &dyn ObjectStore,
+        prefix: &Path,
+        params: ImportParams,
+    ) -> Result<TransferSummary> {
+        params.validate()?;
+        let mut parts: Vec<(u64, Path)> = store
+            .list(Some(prefix))
+            .try_filter_map(|meta| async move { Ok(part_index(prefix, &meta.location).map(|i| (i, meta.location))) })
+            .try_collect()
+            .await?;
+        parts.sort();
+
+        let mut summary = TransferSummary::default();
+        for (_, part) in parts {
+            let imported = self.import_from_object_store(store, &part, params.clone()).await?;
+            summary.rows += imported.rows;
+            summary.bytes += imported.bytes;
+        }
+        Ok(summary)
+    }
+
+    /// Upsert rows from a newline-delimited JSON object written by
+    /// [`Namespace::export_to_object_store`].
+    pub async fn import_from_object_store(
+        &self,
+        store: &dyn ObjectStore,
+        path: &Path,
+        params: ImportParams,
+    ) -> Result<TransferSummary> {
+        params.validate()?;
+        let mut stream = store.get(path).await?.into_stream();
+        let mut summary = TransferSummary::default();
+        let mut pending = Vec::new();
+        let mut batch: Vec<Row> = Vec::new();
+
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk?;
+            summary.bytes += chunk.len() as u64;
+            pending.extend_from_slice(&chunk);
+
+            let mut start = 0;
+            while let Some(offset) = pending[start..].iter().position(|b| *b == b'\n') {
+                parse_line(&pending[start..start + offset], &mut batch)?;
+                start += offset + 1;
+            }
+            pending.drain(..start);
+
+            while batch.len() >= params.batch_size {
+                let rest = batch.split_off(params.batch_size);
+                self.upsert_batch(std::mem::replace(&mut batch, rest), &params, &mut summary).await?;
+            }
+        }
+        parse_line(&pending, &mut batch)?;
+        if !batch.is_empty() {
+            self.upsert_batch(batch, &params, &mut summary).await?;
+        }
+
+        Ok(summary)
+    }
+
+    async fn upsert_batch(&self, rows: Vec<Row>, params: &ImportParams, summary: &mut TransferSummary) -> Result<()> {
+        let count = rows.len() as u64;
+        self.write(WriteParams {
+            upsert_rows: Some(rows),
+            distance_metric: params.distance_metric,
+            ..Default::default()
+        })
+        .await?;
+        summary.rows += count;
+        Ok(())
+    }
+}
+
+fn ndjson(rows: &[Row]) -> Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    for row in rows {
+        serde_json::to_writer(&mut buf, row)?;
+        buf.push(b'\n');
+    }
+    Ok(buf)
+}
+
+fn parse_line(line: &[u8], batch: &mut Vec<Row>) -> Result<()> {
+    if line.iter().all(u8::is_ascii_whitespace) {
+        return Ok(());
+    }
+    batch.push(serde_json::from_slice(line)?);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_line_skips_blank_lines() {
+        let mut batch = Vec::new();
+        parse_line(b"", &mut batch).unwrap();
+        parse_line(b"  \r", &mut batch).unwrap();
+        parse_line(br#"{"id": 1, "name": "a"}"#, &mut batch).unwrap();
+        assert_eq!(batch.len(), 1);
+        assert_eq!(batch[0]["name"], "a");
+    }
+
+    #[test]
+    fn test_part_paths_sort_in_order() {
+        let prefix = Path::from("backups/ns");
+        assert_eq!(part_path(&prefix, 7).as_ref(), "backups/ns/part-000007.ndjson");
+        assert!(part_path(&prefix, 9) < part_path(&prefix, 10));
+    }
+
+    #[test]
+    fn test_only_parts_are_imported() {
+        let prefix = Path::from("backups/ns");
+        assert_eq!(part_index(&prefix, &part_path(&prefix, 7)), Some(7));
+        assert_eq!(part_index(&prefix, &part_path(&prefix, 1_234_567)), Some(1_234_567));
+        for other in ["backups/ns/checkpoint.json", "backups/ns/part-7.ndjson", "backups/ns/old/part-000001.ndjson"] {
+            assert_eq!(part_index(&prefix, &Path::from(other)), None, "{}", other);
+        }
+        assert_eq!(part_index(&prefix, &Path::from("backups/nsx/part-000001.ndjson")), None);
+    }
+
+    #[tokio::test]
+    async fn test_import_rejects_empty_batches() {
+        let client = crate::Client::new("test");
+        let store = object_store::memory::InMemory::new();
+        let params = ImportParams { batch_size: 0, ..Default::default() };
+        let err = client.namespace("ns").import_from_object_store(&store, &Path::from("a"), params).await.unwrap_err();
+        assert!(matches!(err, Error::InvalidParams(_)), "{:?}", err);
+    }
+}
@@ -0,0 +1,126 @@
+use std::future::Future;
+use std::time::Duration;
+
+use crate::rt;
+use crate::{Error, HintCacheWarmResponse, Namespace, Result, RetryPolicy};
+
+/// Result of polling an [`AsyncOperation`] once.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PollStatus<T> {
+    /// The server accepted the operation but hasn't finished it.
+    Pending,
+    Done(T),
+}
+
+/// An operation the server finishes in the background after answering
+/// `ACCEPTED`, such as a cache warm, polled until it is done.
+///
+/// The delay between polls follows a [`RetryPolicy`]'s backoff, and
+/// `max_retries` caps the number of polls after the first; when they run
+/// out, [`wait`](Self::wait) fails with [`Error::DeadlineExceeded`].
+///
+/// ```no_run
+/// # async fn run(ns: rs_puff::Namespace) -> rs_puff::Result<()> {
+/// use rs_puff::{AsyncOperation, PollStatus};
+///
+/// AsyncOperation::new(|| async {
+///     let resp = ns.hint_cache_warm().await?;
+///     Ok(if resp.is_already_warm() { PollStatus::Done(resp) } else { PollStatus::Pending })
+/// })
+/// .wait()
+/// .await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct AsyncOperation<F> {
+    poll: F,
+    policy: RetryPolicy,
+}
+
+impl<F, Fut, T> AsyncOperation<F>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<PollStatus<T>>>,
+{
+    /// Poll every second at first, backing off to every 30 seconds, and give
+    /// up after 20 polls past the first.
+    pub fn new(poll: F) -> Self {
+        let policy = RetryPolicy::new(20)
+            .initial_backoff(Duration::from_secs(1))
+            .max_backoff(Duration::from_secs(30));
+        Self { poll, policy }
+    }
+
+    pub fn policy(mut self, policy: RetryPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Poll until the operation is done. Poll errors are returned as-is.
+    pub async fn wait(mut self) -> Result<T> {
+        let mut polls = 0;
+        loop {
+            if let PollStatus::Done(value) = (self.poll)().await? {
+                return Ok(value);
+            }
+            if polls >= self.policy.max_retries {
+                return Err(Error::DeadlineExceeded);
+            }
+            rt::sleep(self.policy.backoff(polls)).await;
+            polls += 1;
+        }
+    }
+}
+
+impl Namespace {
+    /// Hint a cache warm and wait until the cache reports warm, polling per
+    /// `policy`; see [`AsyncOperation`].
+    pub async fn warm_cache(&self, policy: RetryPolicy) -> Result<HintCacheWarmResponse> {
+        AsyncOperation::new(|| async {
+            let resp = self.hint_cache_warm().await?;
+            Ok(if resp.is_already_warm() { PollStatus::Done(resp) } else { PollStatus::Pending })
+        })
+        .policy(policy)
+        .wait()
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    fn fast(polls: u32) -> RetryPolicy {
+        RetryPolicy::new(polls).initial_backoff(Duration::from_millis(1))
+    }
+
+    #[tokio::test]
+    async fn test_wait_polls_until_done() {
+        let calls = AtomicU32::new(0);
+        let result = AsyncOperation::new(|| async {
+            let n = calls.fetch_add(1, Ordering::SeqCst) + 1;
+            Ok(if n == 3 { PollStatus::Done(n) } else { PollStatus::Pending })
+        })
+        .policy(fast(5))
+        .wait()
+        .await
+        .unwrap();
+        assert_eq!(result, 3);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_wait_gives_up_after_max_polls() {
+        let calls = AtomicU32::new(0);
+        let result = AsyncOperation::new(|| async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Ok(PollStatus::<()>::Pending)
+        })
+        .policy(fast(2))
+        .wait()
+        .await;
+        assert!(matches!(result, Err(Error::DeadlineExceeded)));
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+}
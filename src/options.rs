@@ -0,0 +1,111 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use reqwest::header::{HeaderMap, HeaderValue, IntoHeaderName};
+
+use crate::{AttributeMerge, RetryPolicy};
+
+/// Header carrying a write's idempotency key; see
+/// [`CallOptions::idempotency_key`].
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+/// Per-call overrides of client settings.
+#[derive(Debug, Clone, Default)]
+pub struct CallOptions {
+    /// Replaces the client's retry policy for this call.
+    pub retry: Option<RetryPolicy>,
+    /// Replaces the client's per-attempt timeout for this call; see
+    /// [`Client::with_timeout`](crate::Client::with_timeout).
+    pub timeout: Option<Duration>,
+    /// Budget for the whole call, including retries, backoff and reading the
+    /// response. A call still running when it elapses fails with
+    /// [`Error::DeadlineExceeded`], or with the last error once no retry could
+    /// start in time. A write cut off this way may still have been applied,
+    /// so non-idempotent ones fail with [`Error::AmbiguousWrite`].
+    ///
+    /// [`Error::DeadlineExceeded`]: crate::Error::DeadlineExceeded
+    /// [`Error::AmbiguousWrite`]: crate::Error::AmbiguousWrite
+    pub deadline: Option<Duration>,
+    /// Replaces the client's response size limit for this call; see
+    /// [`Client::with_max_response_bytes`](crate::Client::with_max_response_bytes).
+    pub max_response_bytes: Option<u64>,
+    /// After an ambiguous non-idempotent write, read the upserted rows back
+    /// with strong consistency instead of returning [`Error::AmbiguousWrite`]
+    /// when they already hold the written values. Only writes that do nothing
+    /// but upsert rows, e.g. with an `upsert_condition`, are verified.
+    ///
+    /// [`Error::AmbiguousWrite`]: crate::Error::AmbiguousWrite
+    pub verify_ambiguous_writes: bool,
+    /// Sent with this call's requests, replacing any client header of the
+    /// same name; see [`Client::with_header`](crate::Client::with_header).
+    pub headers: HeaderMap,
+    /// Sent as [`IDEMPOTENCY_KEY_HEADER`] with every attempt of a write, so
+    /// a server or gateway that deduplicates on it applies the write once.
+    /// Each write call without one gets a fresh random key; set it to resend
+    /// an earlier write under the same key, e.g. the one in
+    /// [`Error::AmbiguousWrite`](crate::Error::AmbiguousWrite).
+    pub idempotency_key: Option<String>,
+    /// Replaces the namespace's [`DefaultAttributes::merge`](crate::DefaultAttributes::merge)
+    /// for this query.
+    pub attribute_merge: Option<AttributeMerge>,
+}
+
+impl CallOptions {
+    /// Never retry this call, e.g. for conditional writes that must not be re-applied.
+    pub fn no_retry() -> Self {
+        Self::default().retry(RetryPolicy::none())
+    }
+
+    pub fn retry(mut self, policy: RetryPolicy) -> Self {
+        self.retry = Some(policy);
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    pub fn max_response_bytes(mut self, bytes: u64) -> Self {
+        self.max_response_bytes = Some(bytes);
+        self
+    }
+
+    pub fn verify_ambiguous_writes(mut self) -> Self {
+        self.verify_ambiguous_writes = true;
+        self
+    }
+
+    /// Add a header to this call, e.g. `.header("x-request-id", HeaderValue::from_str(&id)?)`.
+    pub fn header(mut self, name: impl IntoHeaderName, value: HeaderValue) -> Self {
+        self.headers.append(name, value);
+        self
+    }
+
+    pub fn idempotency_key(mut self, key: impl Into<String>) -> Self {
+        self.idempotency_key = Some(key.into());
+        self
+    }
+
+    pub fn attribute_merge(mut self, merge: AttributeMerge) -> Self {
+        self.attribute_merge = Some(merge);
+        self
+    }
+}
+
+/// A key that is unique within this process and unlikely to collide with
+/// other processes' keys. It is not random: the first half comes from
+/// `RandomState`, which is seeded once per thread, and the second is a
+/// process-wide counter.
+pub(crate) fn new_idempotency_key() -> String {
+    static NEXT: AtomicU64 = AtomicU64::new(0);
+    let seed = RandomState::new().build_hasher().finish();
+    format!("{seed:016x}{:016x}", NEXT.fetch_add(1, Ordering::Relaxed))
+}
@@ -0,0 +1,423 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::middleware::sha256_hex;
+use crate::testing::canonicalize;
+use crate::{Error, Filter, IncludeAttributes, Namespace, Order, QueryParams, RankBy, Result, Row, base64};
+
+const DEFAULT_PAGE_SIZE: u64 = 100;
+
+/// Opaque cursor for [`Namespace::query_page`].
+///
+/// Serializes to a URL-safe string, so it can be handed to API clients and
+/// parsed back with [`str::parse`]. A token only continues the query it came
+/// from: using it with a different ordering or different filters fails with
+/// [`Error::InvalidParams`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub struct PageToken(TokenState);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct TokenState {
+    #[serde(rename = "a")]
+    attr: String,
+    #[serde(rename = "o")]
+    order: Order,
+    /// Order attribute value of the last row returned.
+    #[serde(rename = "v")]
+    last: serde_json::Value,
+    /// Ids already returned whose order value equals `last`, so ties are
+    /// neither skipped nor repeated.
+    #[serde(rename = "i", default, skip_serializing_if = "Vec::is_empty")]
+    seen: Vec<serde_json::Value>,
+    /// Hash of the query's filters.
+    #[serde(rename = "f")]
+    filters: String,
+}
+
+impl fmt::Display for PageToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let json = serde_json::to_vec(&self.0).expect("page tokens always serialize");
+        f.write_str(&base64::encode_url(&json))
+    }
+}
+
+impl FromStr for PageToken {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || Error::InvalidParams("malformed page token".to_string());
+        let json = base64::decode(s).ok_or_else(invalid)?;
+        serde_json::from_slice(&json).map(PageToken).map_err(|_| invalid())
+    }
+}
+
+impl From<PageToken> for String {
+    fn from(token: PageToken) -> Self {
+        token.to_string()
+    }
+}
+
+impl TryFrom<String> for PageToken {
+    type Error = Error;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Page {
+    pub rows: Vec<Row>,
+    /// Cursor for the next page; `None` on the last page.
+    pub next: Option<PageToken>,
+}
+
+/// A compound ordering for [`Namespace::query_page_ordered`], e.g.
+/// `category` ascending, then `created_at` descending.
+///
+/// The API orders by a single attribute, so only the first key is sent to
+/// the server. The rest are applied client-side with a stable sort, after
+/// fetching every row that shares the page's last value of the first key,
+/// so pages stay consistent with the full ordering.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderBy(pub Vec<(String, Order)>);
+
+impl OrderBy {
+    pub fn new<I, S>(keys: I) -> Self
+    where
+        I: IntoIterator<Item = (S, Order)>,
+        S: Into<String>,
+    {
+        OrderBy(keys.into_iter().map(|(attr, order)| (attr.into(), order)).collect())
+    }
+}
+
+// Most rows sharing one value of the first key that an ordered page can sort.
+const MAX_TIE_GROUP: u64 = 1000;
+
+impl Namespace {
+    /// Fetch one page of a query ordered by an attribute, for paginated UIs
+    /// and APIs.
+    ///
+    /// `params.rank_by` must be an attribute ordering (default: `id`
+    /// ascending) and `params.top_k` is the page size (default 100). Pass the
+    /// previous page's [`Page::next`] to continue. Pages are computed from the
+    /// last row seen rather than an offset, so rows written between calls
+    /// don't shift later pages. Rows without the order attribute are only
+    /// returned if the ordering is by `id`.
+    pub async fn query_page(&self, params: QueryParams, token: Option<&PageToken>) -> Result<Page> {
+        self.fetch_page(params, &[], token).await
+    }
+
+    /// Like [`query_page`](Self::query_page), ordered by several attributes;
+    /// see [`OrderBy`]. `params.rank_by` is replaced.
+    ///
+    /// Fails with [`Error::InvalidParams`] if more than 1000 rows share the
+    /// first key's value at a page boundary.
+    pub async fn query_page_ordered(
+        &self,
+        mut params: QueryParams,
+        order_by: &OrderBy,
+        token: Option<&PageToken>,
+    ) -> Result<Page> {
+        let Some(((attr, order), then_by)) = order_by.0.split_first() else {
+            return Err(Error::InvalidParams("order_by needs at least one attribute".to_string()));
+        };
+        params.rank_by = Some(RankBy::attribute(attr.clone(), *order));
+        self.fetch_page(params, then_by, token).await
+    }
+
+    async fn fetch_page(&self, params: QueryParams, then_by: &[(String, Order)], token: Option<&PageToken>) -> Result<Page> {
+        let plan = page_query(params, then_by, token)?;
+        let mut rows = self.query(plan.query.clone()).await?.rows;
+        let mut more = rows.len() as u64 >= plan.page_size;
+
+        if !then_by.is_empty() {
+            // Rows tied with the last one may continue past this page; fetch
+            // them all so the secondary keys pick the right ones.
+            if more && let Some(last) = rows.last().and_then(|row| row.get(&plan.attr)).cloned() {
+                let ties = self.query(tie_query(&plan, &last, token)).await?.rows;
+                if ties.len() as u64 >= MAX_TIE_GROUP {
+                    return Err(Error::InvalidParams(format!(
+                        "more than {} rows share {} = {}; add a more selective first order key",
+                        MAX_TIE_GROUP, plan.attr, last
+                    )));
+                }
+                rows.retain(|row| row.get(&plan.attr) != Some(&last));
+                rows.extend(ties);
+            }
+            sort_rows(&mut rows, &plan.attr, plan.order, then_by);
+            more |= rows.len() as u64 > plan.page_size;
+            rows.truncate(plan.page_size as usize);
+        }
+
+        let next = more
+            .then(|| next_token(&rows, plan.attr, plan.order, plan.hash, token))
+            .flatten();
+        Ok(Page { rows, next })
+    }
+}
+
+struct PagePlan {
+    query: QueryParams,
+    /// The caller's filters, without the keyset condition.
+    filters: Option<Filter>,
+    attr: String,
+    order: Order,
+    hash: String,
+    page_size: u64,
+}
+
+fn page_query(mut params: QueryParams, then_by: &[(String, Order)], token: Option<&PageToken>) -> Result<PagePlan> {
+    let (attr, order) = match params.rank_by.take() {
+        None => ("id".to_string(), Order::Asc),
+        Some(RankBy::Attribute { attr, order }) => (attr, order),
+        Some(_) => return Err(Error::InvalidParams("query_page requires an attribute ordering".to_string())),
+    };
+    let hash = query_hash(params.filters.as_ref(), then_by);
+    let filters = params.filters.clone();
+
+    if let Some(PageToken(state)) = token {
+        if state.attr != attr || state.order != order || state.filters != hash {
+            return Err(Error::InvalidParams("page token belongs to a different query".to_string()));
+        }
+        params.filters = Some(and(params.filters.take(), keyset_filter(state)));
+    }
+
+    // The next token and the client-side sort need the order values.
+    for key in std::iter::once(&attr).chain(then_by.iter().map(|(attr, _)| attr)) {
+        match &mut params.include_attributes {
+            None => params.include_attributes = Some(IncludeAttributes::List(vec![key.clone()])),
+            Some(IncludeAttributes::List(list)) if !list.contains(key) => list.push(key.clone()),
+            Some(_) => {}
+        }
+    }
+    let page_size = params.top_k.unwrap_or(DEFAULT_PAGE_SIZE);
+    params.top_k = Some(page_size);
+    params.rank_by = Some(RankBy::attribute(attr.clone(), order));
+    Ok(PagePlan { query: params, filters, attr, order, hash, page_size })
+}
+
+/// Every not-yet-returned row whose first order key equals `last`.
+fn tie_query(plan: &PagePlan, last: &serde_json::Value, token: Option<&PageToken>) -> QueryParams {
+    let mut tie = Filter::eq(plan.attr.clone(), last.clone());
+    if let Some(PageToken(state)) = token
+        && &state.last == last
+        && !state.seen.is_empty()
+    {
+        tie = Filter::and(vec![tie, Filter::not_in("id", state.seen.clone())]);
+    }
+    QueryParams {
+        filters: Some(and(plan.filters.clone(), tie)),
+        top_k: Some(MAX_TIE_GROUP),
+        ..plan.query.clone()
+    }
+}
+
+fn and(filters: Option<Filter>, extra: Filter) -> Filter {
+    match filters {
+        Some(filters) => Filter::and(vec![filters, extra]),
+        None => extra,
+    }
+}
+
+/// Stable sort by every key, then by id so equal rows have a fixed order.
+fn sort_rows(rows: &mut [Row], attr: &str, order: Order, then_by: &[(String, Order)]) {
+    let keys: Vec<(&str, Order)> = std::iter::once((attr, order))
+        .chain(then_by.iter().map(|(attr, order)| (attr.as_str(), *order)))
+        .chain(std::iter::once(("id", Order::Asc)))
+        .collect();
+    rows.sort_by(|a, b| {
+        keys.iter()
+            .map(|(key, order)| {
+                let ord = compare_values(a.get(*key), b.get(*key));
+                if *order == Order::Desc { ord.reverse() } else { ord }
+            })
+            .find(|ord| ord.is_ne())
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+}
+
+// Numbers compare numerically, strings lexically; nulls and missing values
+// sort before everything else, like the API's ascending order.
+pub(crate) fn compare_values(a: Option<&serde_json::Value>, b: Option<&serde_json::Value>) -> std::cmp::Ordering {
+    use serde_json::Value;
+
+    fn rank(v: Option<&Value>) -> u8 {
+        match v {
+            None | Some(Value::Null) => 0,
+            Some(Value::Bool(_)) => 1,
+            Some(Value::Number(_)) => 2,
+            Some(Value::String(_)) => 3,
+            Some(_) => 4,
+        }
+    }
+    match (a, b) {
+        (Some(Value::Bool(a)), Some(Value::Bool(b))) => a.cmp(b),
+        (Some(Value::Number(a)), Some(Value::Number(b))) => {
+            a.as_f64().unwrap_or_default().total_cmp(&b.as_f64().unwrap_or_default())
+        }
+        (Some(Value::String(a)), Some(Value::String(b))) => a.cmp(b),
+        _ => rank(a).cmp(&rank(b)),
+    }
+}
+
+/// Rows strictly after the token's position.
+fn keyset_filter(state: &TokenState) -> Filter {
+    let past = match state.order {
+        Order::Asc => Filter::gt(state.attr.clone(), state.last.clone()),
+        Order::Desc => Filter::lt(state.attr.clone(), state.last.clone()),
+    };
+    if state.attr == "id" || state.seen.is_empty() {
+        return past;
+    }
+    Filter::or(vec![
+        past,
+        Filter::and(vec![
+            Filter::eq(state.attr.clone(), state.last.clone()),
+            Filter::not_in("id", state.seen.clone()),
+        ]),
+    ])
+}
+
+fn next_token(rows: &[Row], attr: String, order: Order, filters: String, prev: Option<&PageToken>) -> Option<PageToken> {
+    let last = rows.last()?.get(&attr)?.clone();
+    let mut seen: Vec<_> = if attr == "id" {
+        Vec::new()
+    } else {
+        rows.iter()
+            .filter(|row| row.get(&attr) == Some(&last))
+            .filter_map(|row| row.get("id").cloned())
+            .collect()
+    };
+    // A page made up entirely of ties extends the previous page's ties.
+    if let Some(PageToken(prev)) = prev
+        && prev.last == last
+    {
+        seen.extend(prev.seen.iter().cloned());
+    }
+    Some(PageToken(TokenState { attr, order, last, seen, filters }))
+}
+
+/// Identifies the filters and secondary order keys a token belongs to.
+fn query_hash(filters: Option<&Filter>, then_by: &[(String, Order)]) -> String {
+    let value = serde_json::to_value((filters, then_by)).expect("filters always serialize");
+    let mut hash = sha256_hex(canonicalize(value).to_string().as_bytes());
+    hash.truncate(16);
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows(json: serde_json::Value) -> Vec<Row> {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn test_token_roundtrips_as_string() {
+        let token = next_token(
+            &rows(serde_json::json!([{"id": 1, "score": 5}, {"id": 2, "score": 5}])),
+            "score".to_string(),
+            Order::Desc,
+            query_hash(None, &[]),
+            None,
+        )
+        .unwrap();
+        let s = token.to_string();
+        assert!(s.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_'));
+        assert_eq!(s.parse::<PageToken>().unwrap(), token);
+        assert_eq!(serde_json::to_value(&token).unwrap(), serde_json::Value::String(s));
+        assert!("not a token".parse::<PageToken>().is_err());
+    }
+
+    #[test]
+    fn test_next_page_skips_seen_ties() {
+        let params = QueryParams {
+            rank_by: Some(RankBy::desc("score")),
+            top_k: Some(2),
+            filters: Some(Filter::eq("lang", "en")),
+            ..Default::default()
+        };
+        let page = rows(serde_json::json!([{"id": 1, "score": 9}, {"id": 2, "score": 5}]));
+        let token = next_token(&page, "score".to_string(), Order::Desc, query_hash(params.filters.as_ref(), &[]), None);
+
+        let query = serde_json::to_value(page_query(params.clone(), &[], token.as_ref()).unwrap().query).unwrap();
+        assert_eq!(
+            query["filters"],
+            serde_json::json!(["And", [
+                ["lang", "Eq", "en"],
+                ["Or", [["score", "Lt", 5], ["And", [["score", "Eq", 5], ["id", "NotIn", [2]]]]]],
+            ]])
+        );
+        assert_eq!(query["include_attributes"], serde_json::json!(["score"]));
+        assert_eq!(query["rank_by"], serde_json::json!(["score", "desc"]));
+
+        let other = QueryParams { filters: Some(Filter::eq("lang", "de")), ..params };
+        assert!(page_query(other, &[], token.as_ref()).is_err());
+    }
+
+    #[test]
+    fn test_all_tie_page_accumulates_seen_ids() {
+        let first = next_token(
+            &rows(serde_json::json!([{"id": 1, "score": 5}])),
+            "score".to_string(),
+            Order::Asc,
+            String::new(),
+            None,
+        );
+        let second = next_token(
+            &rows(serde_json::json!([{"id": 2, "score": 5}])),
+            "score".to_string(),
+            Order::Asc,
+            String::new(),
+            first.as_ref(),
+        )
+        .unwrap();
+        assert_eq!(second.0.seen, [serde_json::json!(2), serde_json::json!(1)]);
+    }
+
+    #[test]
+    fn test_sort_rows_by_compound_order() {
+        let mut page = rows(serde_json::json!([
+            {"id": 1, "category": "b", "created_at": 1},
+            {"id": 2, "category": "a", "created_at": 1},
+            {"id": 3, "category": "a", "created_at": 3},
+            {"id": 4, "category": null, "created_at": 2},
+            {"id": 5, "category": "a", "created_at": 3},
+        ]));
+        sort_rows(&mut page, "category", Order::Asc, &[("created_at".to_string(), Order::Desc)]);
+        let ids: Vec<_> = page.iter().map(|r| r["id"].as_u64().unwrap()).collect();
+        assert_eq!(ids, [4, 3, 5, 2, 1]);
+    }
+
+    #[test]
+    fn test_tie_query_excludes_seen_rows() {
+        let params = QueryParams { filters: Some(Filter::eq("lang", "en")), top_k: Some(2), ..Default::default() };
+        let then_by = [("created_at".to_string(), Order::Desc)];
+        let token = PageToken(TokenState {
+            attr: "category".to_string(),
+            order: Order::Asc,
+            last: serde_json::json!("a"),
+            seen: vec![serde_json::json!(3)],
+            filters: query_hash(params.filters.as_ref(), &then_by),
+        });
+        let params = QueryParams { rank_by: Some(RankBy::asc("category")), ..params };
+        let plan = page_query(params, &then_by, Some(&token)).unwrap();
+        assert_eq!(
+            serde_json::to_value(&plan.query.include_attributes).unwrap(),
+            serde_json::json!(["category", "created_at"])
+        );
+
+        let tie = serde_json::to_value(tie_query(&plan, &serde_json::json!("a"), Some(&token))).unwrap();
+        assert_eq!(
+            tie["filters"],
+            serde_json::json!(["And", [["lang", "Eq", "en"], ["And", [["category", "Eq", "a"], ["id", "NotIn", [3]]]]]])
+        );
+        assert_eq!(tie["top_k"], MAX_TIE_GROUP);
+    }
+}
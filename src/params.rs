@@ -1,18 +1,25 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
-use crate::{DistanceMetric, Filter, RankBy, VectorEncoding};
+use crate::{DistanceMetric, Error, Filter, RankBy, Row, VectorEncoding};
 
-#[derive(Debug, Clone, Default, Serialize)]
+/// Largest write request body the API accepts, in bytes.
+pub const MAX_WRITE_REQUEST_BYTES: usize = 256 * 1024 * 1024;
+
+/// Most rows one write request may upsert, patch or delete.
+pub const MAX_WRITE_REQUEST_ROWS: usize = 100_000;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct WriteParams {
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub upsert_rows: Option<Vec<HashMap<String, serde_json::Value>>>,
+    pub upsert_rows: Option<Vec<Row>>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub upsert_columns: Option<HashMap<String, Vec<serde_json::Value>>>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub patch_rows: Option<Vec<HashMap<String, serde_json::Value>>>,
+    pub patch_rows: Option<Vec<Row>>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub patch_columns: Option<HashMap<String, Vec<serde_json::Value>>>,
@@ -55,15 +62,163 @@ pub struct WriteParams {
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub copy_from_namespace: Option<String>,
+
+    /// Server-side encryption; only takes effect on the write that creates
+    /// the namespace.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encryption: Option<Encryption>,
+}
+
+impl WriteParams {
+    /// Whether applying this write twice has the same effect as applying it once.
+    ///
+    /// Conditional writes, filter-based patches and deletes, and namespace
+    /// copies are not: a retry can see state changed by the first attempt.
+    pub fn is_idempotent(&self) -> bool {
+        self.upsert_condition.is_none()
+            && self.patch_condition.is_none()
+            && self.delete_condition.is_none()
+            && self.delete_by_filter.is_none()
+            && self.patch_by_filter.is_none()
+            && self.copy_from_namespace.is_none()
+    }
+
+    /// Reject writes the API would refuse or that do nothing, before sending.
+    ///
+    /// Called by [`Namespace::write`](crate::Namespace::write). A write must
+    /// contain at least one operation, `copy_from_namespace` can't be combined
+    /// with other operations, conditions and `*_allow_partial` flags need the
+    /// operation they apply to, and every column in a columnar write must have
+    /// the same length.
+    pub fn validate(&self) -> crate::Result<()> {
+        self.validate_with(false, false)
+    }
+
+    /// [`validate`](Self::validate), counting rows held elsewhere, e.g. by a
+    /// [`WriteParamsRef`].
+    fn validate_with(&self, more_upserts: bool, more_patches: bool) -> crate::Result<()> {
+        let invalid = |msg: &str| Err(Error::InvalidParams(msg.to_string()));
+        let has_rows = |rows: &Option<Vec<Row>>| rows.as_ref().is_some_and(|rows| !rows.is_empty());
+        let has_columns = |columns: &Option<HashMap<String, Vec<serde_json::Value>>>| {
+            columns.as_ref().is_some_and(|columns| columns.values().any(|col| !col.is_empty()))
+        };
+
+        let upserts = more_upserts || has_rows(&self.upsert_rows) || has_columns(&self.upsert_columns);
+        let patches = more_patches || has_rows(&self.patch_rows) || has_columns(&self.patch_columns);
+        let deletes = self.deletes.as_ref().is_some_and(|ids| !ids.is_empty());
+        let operations = upserts
+            || patches
+            || deletes
+            || self.delete_by_filter.is_some()
+            || self.patch_by_filter.is_some();
+
+        if self.copy_from_namespace.is_some() {
+            if operations {
+                return invalid("copy_from_namespace can't be combined with other write operations");
+            }
+        } else if !operations && self.schema.is_none() {
+            return invalid("write contains no operations");
+        }
+        if self.upsert_condition.is_some() && !upserts {
+            return invalid("upsert_condition requires upsert_rows or upsert_columns");
+        }
+        if self.patch_condition.is_some() && !patches {
+            return invalid("patch_condition requires patch_rows or patch_columns");
+        }
+        if self.delete_condition.is_some() && !deletes {
+            return invalid("delete_condition requires deletes");
+        }
+        if self.delete_by_filter_allow_partial.is_some() && self.delete_by_filter.is_none() {
+            return invalid("delete_by_filter_allow_partial requires delete_by_filter");
+        }
+        if self.patch_by_filter_allow_partial.is_some() && self.patch_by_filter.is_none() {
+            return invalid("patch_by_filter_allow_partial requires patch_by_filter");
+        }
+        for (name, columns) in [("upsert_columns", &self.upsert_columns), ("patch_columns", &self.patch_columns)] {
+            let mut lengths = columns.iter().flatten().map(|(_, col)| col.len());
+            if let Some(first) = lengths.next()
+                && lengths.any(|len| len != first)
+            {
+                return Err(Error::InvalidParams(format!("all {} must have the same length", name)));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A write whose rows are borrowed rather than owned, so a large batch can be
+/// sent without cloning it into [`WriteParams`]; see
+/// [`Namespace::write_ref`](crate::Namespace::write_ref).
+///
+/// ```no_run
+/// # #[cfg(feature = "client")]
+/// # async fn example(ns: rs_puff::Namespace, rows: Vec<rs_puff::Row>) -> rs_puff::Result<()> {
+/// use rs_puff::{DistanceMetric, WriteParams, WriteParamsRef};
+///
+/// ns.write_ref(WriteParamsRef {
+///     upsert_rows: Some(&rows),
+///     params: WriteParams { distance_metric: Some(DistanceMetric::CosineDistance), ..Default::default() },
+///     ..Default::default()
+/// })
+/// .await?;
+/// // `rows` is still ours.
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct WriteParamsRef<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub upsert_rows: Option<&'a [Row]>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub patch_rows: Option<&'a [Row]>,
+
+    /// Every other part of the write. Its own `upsert_rows` and `patch_rows`
+    /// must be unset.
+    #[serde(flatten)]
+    pub params: WriteParams,
+}
+
+impl WriteParamsRef<'_> {
+    /// See [`WriteParams::validate`].
+    pub fn validate(&self) -> crate::Result<()> {
+        if self.params.upsert_rows.is_some() || self.params.patch_rows.is_some() {
+            return Err(Error::InvalidParams(
+                "set upsert_rows and patch_rows on WriteParamsRef, not its params".to_string(),
+            ));
+        }
+        let has_rows = |rows: Option<&[Row]>| rows.is_some_and(|rows| !rows.is_empty());
+        self.params.validate_with(has_rows(self.upsert_rows), has_rows(self.patch_rows))
+    }
+}
+
+/// Encrypt a namespace with a customer-managed key (CMEK).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Encryption {
+    pub cmek: Cmek,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cmek {
+    /// Full resource name of the key in the cloud provider's KMS.
+    pub key_name: String,
+}
+
+impl Encryption {
+    pub fn cmek(key_name: impl Into<String>) -> Self {
+        Encryption { cmek: Cmek { key_name: key_name.into() } }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct PatchByFilter {
     pub filters: Filter,
     pub patch: HashMap<String, serde_json::Value>,
 }
 
-#[derive(Debug, Clone, Default, Serialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct QueryParams {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub rank_by: Option<RankBy>,
@@ -96,19 +251,19 @@ pub struct QueryParams {
     pub group_by: Option<Vec<String>>,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum IncludeAttributes {
     All(bool),
     List(Vec<String>),
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Consistency {
     pub level: ConsistencyLevel,
 }
 
-#[derive(Debug, Clone, Copy, Serialize)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum ConsistencyLevel {
     Strong,
@@ -143,7 +298,21 @@ impl serde::Serialize for AggregateBy {
     }
 }
 
-#[derive(Debug, Clone, Default, Serialize)]
+impl<'de> Deserialize<'de> for AggregateBy {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        match <Vec<String>>::deserialize(deserializer)?.as_slice() {
+            [op] if op == "Count" => Ok(AggregateBy::Count),
+            [op, attr] if op == "Sum" => Ok(AggregateBy::Sum(attr.clone())),
+            parts => Err(serde::de::Error::custom(format!("unknown aggregation {:?}", parts))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct MultiQueryParams {
     pub queries: Vec<QueryParams>,
 
@@ -153,3 +322,96 @@ pub struct MultiQueryParams {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub consistency: Option<Consistency>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows(n: u64) -> Option<Vec<Row>> {
+        Some((0..n).map(|id| Row::from_iter([("id".to_string(), id.into())])).collect())
+    }
+
+    #[test]
+    fn test_write_params_ref_matches_owned_write() {
+        let upserts = rows(2).unwrap();
+        let borrowed = WriteParamsRef {
+            upsert_rows: Some(&upserts),
+            params: WriteParams { upsert_condition: Some(Filter::eq("v", 1)), ..Default::default() },
+            ..Default::default()
+        };
+        assert!(borrowed.validate().is_ok());
+        let owned = WriteParams { upsert_rows: Some(upserts.clone()), ..borrowed.params.clone() };
+        assert_eq!(serde_json::to_value(&borrowed).unwrap(), serde_json::to_value(&owned).unwrap());
+
+        assert!(WriteParamsRef { upsert_rows: Some(&[]), ..Default::default() }.validate().is_err());
+        let doubled = WriteParamsRef { upsert_rows: Some(&upserts), params: owned, ..Default::default() };
+        assert!(doubled.validate().is_err());
+    }
+
+    #[test]
+    fn test_params_deserialize_from_wire_json() {
+        let query = QueryParams {
+            rank_by: Some(RankBy::vector("vector", vec![0.5, 1.0])),
+            top_k: Some(10),
+            filters: Some(Filter::eq("lang", "en")),
+            include_attributes: Some(IncludeAttributes::List(vec!["title".to_string()])),
+            consistency: Some(Consistency { level: ConsistencyLevel::Eventual }),
+            aggregate_by: Some(HashMap::from([("n".to_string(), AggregateBy::Count)])),
+            ..Default::default()
+        };
+        let json = serde_json::to_value(&query).unwrap();
+        let parsed: QueryParams = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&parsed).unwrap(), json);
+
+        let write = WriteParams {
+            upsert_rows: rows(2),
+            patch_by_filter: Some(PatchByFilter { filters: Filter::eq("a", 1), patch: HashMap::from([("b".to_string(), 2.into())]) }),
+            distance_metric: Some(DistanceMetric::CosineDistance),
+            ..Default::default()
+        };
+        let json = serde_json::to_value(&write).unwrap();
+        let parsed: WriteParams = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&parsed).unwrap(), json);
+
+        assert!(serde_json::from_str::<QueryParams>(r#"{"top_k": 1, "rerank": true}"#).is_err());
+        assert!(serde_json::from_str::<AggregateBy>(r#"["Avg", "views"]"#).is_err());
+    }
+
+    #[test]
+    fn test_validate_rejects_empty_writes() {
+        assert!(WriteParams::default().validate().is_err());
+        assert!(WriteParams { upsert_rows: rows(0), deletes: Some(vec![]), ..Default::default() }.validate().is_err());
+        assert!(WriteParams { distance_metric: Some(DistanceMetric::CosineDistance), ..Default::default() }.validate().is_err());
+
+        assert!(WriteParams { upsert_rows: rows(1), ..Default::default() }.validate().is_ok());
+        assert!(WriteParams { schema: Some(HashMap::new()), ..Default::default() }.validate().is_ok());
+        assert!(WriteParams { copy_from_namespace: Some("src".into()), ..Default::default() }.validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_rejects_conflicting_fields() {
+        let err = WriteParams { upsert_rows: rows(1), copy_from_namespace: Some("src".into()), ..Default::default() }
+            .validate()
+            .unwrap_err();
+        assert_eq!(err.to_string(), "invalid params: copy_from_namespace can't be combined with other write operations");
+
+        let conditional_delete = WriteParams {
+            upsert_rows: rows(1),
+            delete_condition: Some(Filter::eq("status", "stale")),
+            ..Default::default()
+        };
+        assert!(conditional_delete.validate().is_err());
+
+        let partial = WriteParams { deletes: Some(vec![1.into()]), delete_by_filter_allow_partial: Some(true), ..Default::default() };
+        assert!(partial.validate().is_err());
+
+        let ragged = WriteParams {
+            upsert_columns: Some(HashMap::from([
+                ("id".to_string(), vec![1.into(), 2.into()]),
+                ("title".to_string(), vec!["a".into()]),
+            ])),
+            ..Default::default()
+        };
+        assert_eq!(ragged.validate().unwrap_err().to_string(), "invalid params: all upsert_columns must have the same length");
+    }
+}
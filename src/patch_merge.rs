@@ -0,0 +1,139 @@
+use crate::{Error, Filter, Id, IncludeAttributes, Namespace, QueryParams, Result, Row, WriteParams};
+
+// Reads and guarded writes before giving up on a row that keeps changing.
+const MAX_MERGE_ATTEMPTS: usize = 3;
+
+/// RFC 7396 JSON merge patch: objects merge recursively, `null` removes a
+/// key, anything else replaces the target.
+fn merge_json(target: &mut serde_json::Value, patch: &serde_json::Value) {
+    let serde_json::Value::Object(patch) = patch else {
+        *target = patch.clone();
+        return;
+    };
+    if !target.is_object() {
+        *target = serde_json::Value::Object(Default::default());
+    }
+    let target = target.as_object_mut().expect("just made an object");
+    for (key, value) in patch {
+        if value.is_null() {
+            target.remove(key);
+        } else {
+            merge_json(target.entry(key.clone()).or_insert(serde_json::Value::Null), value);
+        }
+    }
+}
+
+/// New value of one attribute. Strings holding a JSON object are merged as
+/// JSON and stored back as strings.
+fn merge_attribute(current: Option<&serde_json::Value>, patch: &serde_json::Value) -> serde_json::Value {
+    if let (Some(serde_json::Value::String(s)), serde_json::Value::Object(_)) = (current, patch)
+        && let Ok(mut blob @ serde_json::Value::Object(_)) = serde_json::from_str(s)
+    {
+        merge_json(&mut blob, patch);
+        return serde_json::Value::String(blob.to_string());
+    }
+    let mut value = current.cloned().unwrap_or_default();
+    merge_json(&mut value, patch);
+    value
+}
+
+/// Patch applying `patch` to `current`, conditioned on every patched
+/// attribute still holding the value it was merged from.
+fn merged_patch(id: &Id, current: &Row, patch: &serde_json::Map<String, serde_json::Value>) -> Result<(Row, WriteParams)> {
+    let mut merged = Row::new();
+    let mut unchanged = Vec::new();
+    for (attr, value) in patch {
+        let old = current.get(attr);
+        merged.insert(attr.clone(), merge_attribute(old, value));
+        unchanged.push(Filter::eq(attr.clone(), old.cloned().unwrap_or_default()));
+    }
+    let mut row = merged.clone();
+    row.insert("id".to_string(), serde_json::to_value(id)?);
+    let params = WriteParams {
+        patch_rows: Some(vec![row]),
+        patch_condition: Some(if unchanged.len() == 1 { unchanged.remove(0) } else { Filter::and(unchanged) }),
+        ..Default::default()
+    };
+    Ok((merged, params))
+}
+
+impl Namespace {
+    /// Deep-merge `patch`, a JSON object keyed by attribute, into row `id`.
+    ///
+    /// Nested objects merge as a JSON merge patch (RFC 7396), including
+    /// objects stored as JSON strings; `null` removes a key. The row is read,
+    /// merged and written back with a condition that the patched attributes
+    /// are unchanged, retrying if another writer got there first. Returns
+    /// the new values of the patched attributes, or `None` if the row
+    /// doesn't exist. Read hooks are not applied to the row that is read.
+    pub async fn patch_merge(&self, id: impl Into<Id>, patch: serde_json::Value) -> Result<Option<Row>> {
+        let id = id.into();
+        let serde_json::Value::Object(patch) = patch else {
+            return Err(Error::InvalidParams("patch_merge expects a JSON object".to_string()));
+        };
+        if patch.is_empty() {
+            return Err(Error::InvalidParams("patch_merge expects at least one attribute".to_string()));
+        }
+
+        let query = QueryParams {
+            top_k: Some(1),
+            filters: Some(Filter::eq("id", serde_json::to_value(&id)?)),
+            include_attributes: Some(IncludeAttributes::List(patch.keys().cloned().collect())),
+            ..Default::default()
+        };
+        for _ in 0..MAX_MERGE_ATTEMPTS {
+            // Bypass read hooks so masked values are never written back.
+            let resp = self.query_unhooked(&query).await?;
+            let Some(current) = resp.rows.into_iter().next() else {
+                return Ok(None);
+            };
+            let (merged, params) = merged_patch(&id, &current, &patch)?;
+            let resp = self.write(params).await?;
+            if resp.rows_patched.unwrap_or(resp.rows_affected) > 0 {
+                return Ok(Some(merged));
+            }
+        }
+        Err(Error::ConcurrentUpdate { id })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_merge_json_follows_merge_patch() {
+        let mut target = json!({"a": {"b": 1, "c": 2}, "d": [1, 2], "e": "x"});
+        merge_json(&mut target, &json!({"a": {"b": 10, "c": null, "n": {"m": true}}, "d": [3], "f": 1}));
+        assert_eq!(target, json!({"a": {"b": 10, "n": {"m": true}}, "d": [3], "e": "x", "f": 1}));
+    }
+
+    #[test]
+    fn test_merge_attribute_keeps_json_strings_as_strings() {
+        let current = json!(r#"{"theme": "dark", "tabs": {"home": true}}"#);
+        let merged = merge_attribute(Some(&current), &json!({"tabs": {"search": false}}));
+        let blob: serde_json::Value = serde_json::from_str(merged.as_str().unwrap()).unwrap();
+        assert_eq!(blob, json!({"theme": "dark", "tabs": {"home": true, "search": false}}));
+
+        assert_eq!(merge_attribute(Some(&json!("plain text")), &json!({"a": 1})), json!({"a": 1}));
+        assert_eq!(merge_attribute(None, &json!({"a": 1})), json!({"a": 1}));
+        assert_eq!(merge_attribute(Some(&json!(3)), &json!(4)), json!(4));
+    }
+
+    #[test]
+    fn test_merged_patch_guards_on_read_values() {
+        let current: Row = serde_json::from_value(json!({"id": 7, "settings": r#"{"a": 1}"#})).unwrap();
+        let patch = json!({"settings": {"b": 2}, "label": "new"});
+        let (merged, params) = merged_patch(&Id::from(7u64), &current, patch.as_object().unwrap()).unwrap();
+        assert_eq!(merged["label"], "new");
+
+        let body = serde_json::to_value(params).unwrap();
+        assert_eq!(body["patch_rows"][0]["id"], 7);
+        assert_eq!(body["patch_rows"][0]["settings"], r#"{"a":1,"b":2}"#);
+        assert_eq!(
+            body["patch_condition"],
+            json!(["And", [["label", "Eq", null], ["settings", "Eq", r#"{"a": 1}"#]]])
+        );
+    }
+}
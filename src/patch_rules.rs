@@ -0,0 +1,130 @@
+use crate::{Error, Namespace, PatchByFilter, Result, WriteParams};
+
+const DEFAULT_MAX_WRITES_PER_RULE: u64 = 100;
+
+/// Options for [`Namespace::patch_by_filters`].
+#[derive(Debug, Clone)]
+pub struct PatchRulesParams {
+    /// Send each rule with `patch_by_filter_allow_partial` and repeat it
+    /// while the server reports rows remaining, for rules matching more rows
+    /// than one write may patch.
+    ///
+    /// Each repeat patches the rows the filter matches next, so a rule's
+    /// filter must exclude rows it has already patched, e.g. `lang` is null
+    /// for a rule setting `lang`. Otherwise every repeat patches the same
+    /// rows until [`max_writes_per_rule`](Self::max_writes_per_rule) is hit.
+    pub allow_partial: bool,
+    /// Apply the remaining rules after one fails instead of stopping.
+    pub continue_on_error: bool,
+    /// Writes one rule may send with `allow_partial` before it fails with
+    /// [`Error::Verification`]. Defaults to 100.
+    pub max_writes_per_rule: u64,
+}
+
+impl Default for PatchRulesParams {
+    fn default() -> Self {
+        Self { allow_partial: false, continue_on_error: false, max_writes_per_rule: DEFAULT_MAX_WRITES_PER_RULE }
+    }
+}
+
+/// What one rule of [`Namespace::patch_by_filters`] did.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PatchRuleReport {
+    pub rows_patched: u64,
+    /// Writes sent for the rule; more than one when a partial patch was repeated.
+    pub writes: u64,
+}
+
+impl Namespace {
+    /// Apply several patch-by-filter rules one after another, e.g. backfills
+    /// such as "set `lang` to `en` where it is null", returning a result per
+    /// rule in order.
+    ///
+    /// Each rule is its own write, so later rules see the rows earlier ones
+    /// patched, and a failure doesn't undo rules already applied. After a
+    /// failed rule the rest are skipped, and missing from the results, unless
+    /// [`PatchRulesParams::continue_on_error`] is set. Patch-by-filter writes
+    /// aren't retried once they may have reached the server; see
+    /// [`Namespace::write_with_options`].
+    pub async fn patch_by_filters(
+        &self,
+        rules: impl IntoIterator<Item = PatchByFilter>,
+        params: &PatchRulesParams,
+    ) -> Vec<Result<PatchRuleReport>> {
+        let mut results = Vec::new();
+        for rule in rules {
+            let result = self.apply_patch_rule(rule, params).await;
+            let failed = result.is_err();
+            results.push(result);
+            if failed && !params.continue_on_error {
+                break;
+            }
+        }
+        results
+    }
+
+    async fn apply_patch_rule(&self, rule: PatchByFilter, params: &PatchRulesParams) -> Result<PatchRuleReport> {
+        let allow_partial = params.allow_partial;
+        let mut report = PatchRuleReport::default();
+        loop {
+            if report.writes >= params.max_writes_per_rule.max(1) {
+                return Err(Error::Verification(format!(
+                    "rows still remained after {} writes patching {} rows; does the filter exclude rows it patched?",
+                    report.writes, report.rows_patched
+                )));
+            }
+            let resp = self
+                .write(WriteParams {
+                    patch_by_filter: Some(rule.clone()),
+                    patch_by_filter_allow_partial: allow_partial.then_some(true),
+                    ..Default::default()
+                })
+                .await?;
+            let patched = resp.rows_patched.unwrap_or(resp.rows_affected);
+            report.rows_patched += patched;
+            report.writes += 1;
+            // A partial write that patched nothing would never finish.
+            if !(allow_partial && resp.rows_remaining == Some(true) && patched > 0) {
+                return Ok(report);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::{Client, Filter};
+
+    /// Patches a full chunk on every write and always reports rows remaining,
+    /// like a rule whose filter keeps matching the rows it patched.
+    #[derive(Default)]
+    struct NeverDone(AtomicUsize);
+
+    impl crate::HttpTransport for NeverDone {
+        fn send(&self, _req: reqwest::Request) -> crate::rt::BoxFuture<'_, Result<reqwest::Response>> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            let resp = http::Response::new(r#"{"rows_affected": 10, "rows_patched": 10, "rows_remaining": true}"#);
+            Box::pin(async move { Ok(reqwest::Response::from(resp)) })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_partial_rule_stops_at_write_cap() {
+        let transport = Arc::new(NeverDone::default());
+        let ns = Client::new("key").with_transport(transport.clone()).namespace("docs");
+        let rule = PatchByFilter {
+            filters: Filter::eq("status", "open"),
+            patch: HashMap::from([("priority".to_string(), 1.into())]),
+        };
+        let params = PatchRulesParams { allow_partial: true, max_writes_per_rule: 5, ..Default::default() };
+        let results = ns.patch_by_filters([rule.clone(), rule], &params).await;
+        assert_eq!(results.len(), 1);
+        assert!(matches!(&results[0], Err(Error::Verification(message)) if message.contains("after 5 writes")));
+        assert_eq!(transport.0.load(Ordering::SeqCst), 5);
+    }
+}
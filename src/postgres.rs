@@ -0,0 +1,185 @@
+use std::collections::{HashMap, HashSet};
+use std::pin::pin;
+
+use futures_util::StreamExt;
+
+use crate::{BulkWriter, Error, Result, Row};
+
+/// Maps Postgres columns to namespace attributes.
+#[derive(Debug, Clone)]
+pub struct ColumnMapping {
+    id_column: String,
+    renames: HashMap<String, String>,
+    vectors: HashSet<String>,
+    skipped: HashSet<String>,
+}
+
+impl Default for ColumnMapping {
+    fn default() -> Self {
+        Self {
+            id_column: "id".to_string(),
+            renames: HashMap::new(),
+            vectors: HashSet::new(),
+            skipped: HashSet::new(),
+        }
+    }
+}
+
+impl ColumnMapping {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Column whose value becomes the row `id` (default `id`).
+    pub fn id_column(mut self, column: impl Into<String>) -> Self {
+        self.id_column = column.into();
+        self
+    }
+
+    pub fn rename(mut self, column: impl Into<String>, attribute: impl Into<String>) -> Self {
+        self.renames.insert(column.into(), attribute.into());
+        self
+    }
+
+    /// Parse a pgvector column (text form `[1,2,3]`) into a float array.
+    pub fn vector(mut self, column: impl Into<String>) -> Self {
+        self.vectors.insert(column.into());
+        self
+    }
+
+    pub fn skip(mut self, column: impl Into<String>) -> Self {
+        self.skipped.insert(column.into());
+        self
+    }
+
+    fn apply(&self, columns: serde_json::Map<String, serde_json::Value>) -> Result<Row> {
+        let mut row = Row::new();
+        for (column, value) in columns {
+            if self.skipped.contains(&column) {
+                continue;
+            }
+            let value = match value {
+                serde_json::Value::String(s) if self.vectors.contains(&column) => serde_json::from_str(&s)?,
+                other => other,
+            };
+            let attribute = if column == self.id_column {
+                "id".to_string()
+            } else {
+                self.renames.get(&column).cloned().unwrap_or(column)
+            };
+            row.insert(attribute, value);
+        }
+        if !row.contains_key("id") {
+            return Err(Error::External(format!("query result has no `{}` column", self.id_column).into()));
+        }
+        Ok(row)
+    }
+}
+
+/// Stream the rows of `query` into `writer` using `COPY ... TO STDOUT`.
+///
+/// Each row is converted with `row_to_json`, so any type Postgres can render
+/// as JSON is supported. Returns the number of rows read; call
+/// [`BulkWriter::finish`] afterwards to write the last batch.
+pub async fn copy_query(
+    client: &tokio_postgres::Client,
+    query: &str,
+    mapping: &ColumnMapping,
+    writer: &mut BulkWriter<'_>,
+) -> Result<u64> {
+    let statement = format!("COPY (SELECT row_to_json(t) FROM ({}) t) TO STDOUT", query);
+    let mut stream = pin!(client.copy_out(statement.as_str()).await.map_err(external)?);
+    let mut pending = Vec::new();
+    let mut count = 0;
+
+    while let Some(chunk) = stream.next().await {
+        pending.extend_from_slice(&chunk.map_err(external)?);
+
+        let mut start = 0;
+        let mut rows = Vec::new();
+        while let Some(offset) = pending[start..].iter().position(|b| *b == b'\n') {
+            rows.push(parse_copy_line(&pending[start..start + offset], mapping)?);
+            start += offset + 1;
+        }
+        pending.drain(..start);
+
+        count += rows.len() as u64;
+        writer.extend(rows).await?;
+    }
+    if !pending.is_empty() {
+        writer.push(parse_copy_line(&pending, mapping)?).await?;
+        count += 1;
+    }
+
+    Ok(count)
+}
+
+fn external(e: tokio_postgres::Error) -> Error {
+    Error::External(Box::new(e))
+}
+
+fn parse_copy_line(line: &[u8], mapping: &ColumnMapping) -> Result<Row> {
+    let json = unescape_copy_text(line);
+    match serde_json::from_slice(&json)? {
+        serde_json::Value::Object(columns) => mapping.apply(columns),
+        other => Err(Error::External(format!("expected a JSON object from row_to_json, got {}", other).into())),
+    }
+}
+
+// COPY's text format backslash-escapes the JSON, e.g. `\"` arrives as `\\"`.
+fn unescape_copy_text(line: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(line.len());
+    let mut bytes = line.iter();
+    while let Some(&b) = bytes.next() {
+        if b != b'\\' {
+            out.push(b);
+            continue;
+        }
+        match bytes.next() {
+            Some(b'n') => out.push(b'\n'),
+            Some(b'r') => out.push(b'\r'),
+            Some(b't') => out.push(b'\t'),
+            Some(b'b') => out.push(0x08),
+            Some(b'f') => out.push(0x0c),
+            Some(b'v') => out.push(0x0b),
+            Some(&other) => out.push(other),
+            None => out.push(b'\\'),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unescape_copy_text() {
+        let line = br#"{"title":"say \\"hi\\"","path":"C:\\\\tmp"}"#;
+        let value: serde_json::Value = serde_json::from_slice(&unescape_copy_text(line)).unwrap();
+        assert_eq!(value["title"], r#"say "hi""#);
+        assert_eq!(value["path"], r"C:\tmp");
+    }
+
+    #[test]
+    fn test_mapping_renames_and_parses_vectors() {
+        let mapping = ColumnMapping::new()
+            .id_column("doc_id")
+            .rename("body", "text")
+            .vector("embedding")
+            .skip("internal");
+        let line = br#"{"doc_id":7,"body":"hello","embedding":"[0.5,1]","internal":true}"#;
+        let row = parse_copy_line(line, &mapping).unwrap();
+        assert_eq!(row["id"], 7);
+        assert_eq!(row["text"], "hello");
+        assert_eq!(row["embedding"], serde_json::json!([0.5, 1]));
+        assert!(!row.contains_key("internal"));
+        assert!(!row.contains_key("doc_id"));
+    }
+
+    #[test]
+    fn test_missing_id_column() {
+        let err = parse_copy_line(br#"{"name":"a"}"#, &ColumnMapping::new()).unwrap_err();
+        assert!(err.to_string().contains("`id`"));
+    }
+}
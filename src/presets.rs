@@ -0,0 +1,66 @@
+use crate::{Consistency, ConsistencyLevel, QueryParams, RankBy};
+
+/// Vector attribute searched and excluded from results by the RAG presets.
+pub const RAG_VECTOR_ATTRIBUTE: &str = "vector";
+
+impl QueryParams {
+    /// Retrieval for LLM context: the `k` nearest rows to `vector` with
+    /// every attribute except the vector itself, read with eventual
+    /// consistency for lower latency.
+    ///
+    /// Adjust the result like any other params, e.g. to add filters:
+    ///
+    /// ```
+    /// use rs_puff::{Filter, QueryParams};
+    ///
+    /// let params = QueryParams {
+    ///     filters: Some(Filter::eq("lang", "en")),
+    ///     ..QueryParams::rag_default(vec![0.1, 0.2], 8)
+    /// };
+    /// ```
+    pub fn rag_default(vector: Vec<f32>, k: u64) -> Self {
+        Self {
+            rank_by: Some(RankBy::vector(RAG_VECTOR_ATTRIBUTE, vector)),
+            ..Self::rag_base(k)
+        }
+    }
+
+    /// Like [`rag_default`](Self::rag_default), ranking by BM25 on the
+    /// full-text `attribute` instead.
+    pub fn rag_full_text(attribute: impl Into<String>, query: impl Into<String>, k: u64) -> Self {
+        Self {
+            rank_by: Some(RankBy::bm25(attribute, query)),
+            ..Self::rag_base(k)
+        }
+    }
+
+    fn rag_base(k: u64) -> Self {
+        Self {
+            top_k: Some(k),
+            exclude_attributes: Some(vec![RAG_VECTOR_ATTRIBUTE.to_string()]),
+            consistency: Some(Consistency { level: ConsistencyLevel::Eventual }),
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rag_presets() {
+        let body = serde_json::to_value(QueryParams::rag_default(vec![0.5, 0.25], 8)).unwrap();
+        assert_eq!(body, serde_json::json!({
+            "rank_by": ["vector", "ANN", [0.5, 0.25]],
+            "top_k": 8,
+            "exclude_attributes": ["vector"],
+            "consistency": {"level": "eventual"},
+        }));
+
+        let body = serde_json::to_value(QueryParams::rag_full_text("text", "vector databases", 5)).unwrap();
+        assert_eq!(body["rank_by"], serde_json::json!(["text", "BM25", "vector databases"]));
+        assert_eq!(body["top_k"], 5);
+        assert_eq!(body["exclude_attributes"], serde_json::json!(["vector"]));
+    }
+}
@@ -0,0 +1,217 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+
+use crate::rt::Instant;
+use crate::{Namespace, QueryParams, QueryResponse, Result};
+
+/// Wraps a namespace and aggregates the server-reported
+/// [`QueryPerformance`](crate::QueryPerformance) of every query sent through
+/// it, for load tests and tuning sessions.
+pub struct Profiler {
+    ns: Namespace,
+    samples: Mutex<Samples>,
+}
+
+#[derive(Default)]
+struct Samples {
+    errors: u64,
+    client_ms: Vec<f64>,
+    server_total_ms: Vec<f64>,
+    query_execution_ms: Vec<f64>,
+    cache_hit_ratios: Vec<f64>,
+    cache_temperatures: HashMap<String, u64>,
+    exhaustive_searches: u64,
+    exhaustive_search_count: u64,
+}
+
+impl Profiler {
+    pub fn new(ns: Namespace) -> Self {
+        Self { ns, samples: Mutex::new(Samples::default()) }
+    }
+
+    pub fn namespace(&self) -> &Namespace {
+        &self.ns
+    }
+
+    /// Run and record a query.
+    pub async fn query(&self, params: QueryParams) -> Result<QueryResponse> {
+        let start = Instant::now();
+        let result = self.ns.query(params).await;
+        match &result {
+            Ok(resp) => self.record(resp, start.elapsed()),
+            Err(_) => self.lock().errors += 1,
+        }
+        result
+    }
+
+    /// Record a response obtained some other way, e.g. via `query_with_options`.
+    pub fn record(&self, resp: &QueryResponse, latency: Duration) {
+        let mut samples = self.lock();
+        samples.client_ms.push(latency.as_secs_f64() * 1000.0);
+        let Some(perf) = &resp.performance else {
+            return;
+        };
+        samples.server_total_ms.extend(perf.server_total_ms.map(|ms| ms as f64));
+        samples.query_execution_ms.extend(perf.query_execution_ms.map(|ms| ms as f64));
+        samples.cache_hit_ratios.extend(perf.cache_hit_ratio);
+        if let Some(temperature) = &perf.cache_temperature {
+            *samples.cache_temperatures.entry(temperature.clone()).or_default() += 1;
+        }
+        if let Some(count) = perf.exhaustive_search_count.filter(|c| *c > 0) {
+            samples.exhaustive_searches += 1;
+            samples.exhaustive_search_count += count;
+        }
+    }
+
+    /// Aggregate everything recorded so far.
+    pub fn report(&self) -> ProfileReport {
+        let samples = self.lock();
+        let ratios = &samples.cache_hit_ratios;
+        ProfileReport {
+            queries: samples.client_ms.len() as u64,
+            errors: samples.errors,
+            client_ms: Percentiles::from_samples(&samples.client_ms),
+            server_total_ms: Percentiles::from_samples(&samples.server_total_ms),
+            query_execution_ms: Percentiles::from_samples(&samples.query_execution_ms),
+            mean_cache_hit_ratio: (!ratios.is_empty()).then(|| ratios.iter().sum::<f64>() / ratios.len() as f64),
+            cache_temperatures: samples.cache_temperatures.clone(),
+            exhaustive_searches: samples.exhaustive_searches,
+            exhaustive_search_count: samples.exhaustive_search_count,
+        }
+    }
+
+    /// Discard everything recorded so far, e.g. after a warm-up phase.
+    pub fn reset(&self) {
+        *self.lock() = Samples::default();
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Samples> {
+        self.samples.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Nearest-rank percentiles of a set of samples, in milliseconds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Percentiles {
+    pub p50: f64,
+    pub p95: f64,
+    pub p99: f64,
+    pub max: f64,
+}
+
+impl Percentiles {
+    fn from_samples(samples: &[f64]) -> Option<Self> {
+        let mut sorted = samples.to_vec();
+        sorted.sort_by(f64::total_cmp);
+        let at = |p: f64| {
+            let rank = (p * sorted.len() as f64).ceil() as usize;
+            sorted[rank.clamp(1, sorted.len()) - 1]
+        };
+        let max = *sorted.last()?;
+        Some(Self { p50: at(0.50), p95: at(0.95), p99: at(0.99), max })
+    }
+}
+
+impl fmt::Display for Percentiles {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "p50 {:.1}ms, p95 {:.1}ms, p99 {:.1}ms, max {:.1}ms", self.p50, self.p95, self.p99, self.max)
+    }
+}
+
+/// Output of [`Profiler::report`]. Percentiles are `None` when no query
+/// reported the metric.
+#[derive(Debug, Clone)]
+pub struct ProfileReport {
+    /// Successful queries.
+    pub queries: u64,
+    pub errors: u64,
+    /// Latency measured by the client, including network time.
+    pub client_ms: Option<Percentiles>,
+    pub server_total_ms: Option<Percentiles>,
+    pub query_execution_ms: Option<Percentiles>,
+    pub mean_cache_hit_ratio: Option<f64>,
+    /// Number of queries per reported cache temperature, e.g. `hot` or `cold`.
+    pub cache_temperatures: HashMap<String, u64>,
+    /// Queries that fell back to an exhaustive search.
+    pub exhaustive_searches: u64,
+    /// Sum of `exhaustive_search_count` across queries.
+    pub exhaustive_search_count: u64,
+}
+
+impl fmt::Display for ProfileReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "queries: {} ({} errors)", self.queries, self.errors)?;
+        for (name, percentiles) in [
+            ("client", &self.client_ms),
+            ("server total", &self.server_total_ms),
+            ("query execution", &self.query_execution_ms),
+        ] {
+            if let Some(percentiles) = percentiles {
+                writeln!(f, "{}: {}", name, percentiles)?;
+            }
+        }
+        if let Some(ratio) = self.mean_cache_hit_ratio {
+            writeln!(f, "mean cache hit ratio: {:.3}", ratio)?;
+        }
+        let mut temperatures: Vec<_> = self.cache_temperatures.iter().collect();
+        temperatures.sort();
+        for (temperature, count) in temperatures {
+            writeln!(f, "cache {}: {}", temperature, count)?;
+        }
+        write!(
+            f,
+            "exhaustive searches: {} queries, {} total",
+            self.exhaustive_searches, self.exhaustive_search_count
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Client;
+
+    fn response(perf: serde_json::Value) -> QueryResponse {
+        serde_json::from_value(serde_json::json!({"rows": [], "performance": perf})).unwrap()
+    }
+
+    #[test]
+    fn test_percentiles_nearest_rank() {
+        let samples: Vec<f64> = (1..=100).map(f64::from).collect();
+        let p = Percentiles::from_samples(&samples).unwrap();
+        assert_eq!((p.p50, p.p95, p.p99, p.max), (50.0, 95.0, 99.0, 100.0));
+        assert_eq!(Percentiles::from_samples(&[7.0]).unwrap().p50, 7.0);
+        assert!(Percentiles::from_samples(&[]).is_none());
+    }
+
+    #[test]
+    fn test_report_aggregates_performance() {
+        let client = Client::new("key");
+        let profiler = Profiler::new(client.namespace("docs"));
+        let latency = Duration::from_millis(20);
+        profiler.record(
+            &response(serde_json::json!({"server_total_ms": 10, "cache_hit_ratio": 1.0, "cache_temperature": "hot"})),
+            latency,
+        );
+        profiler.record(
+            &response(serde_json::json!({"server_total_ms": 30, "cache_hit_ratio": 0.5, "cache_temperature": "cold", "exhaustive_search_count": 4})),
+            latency,
+        );
+        profiler.record(&response(serde_json::Value::Null), latency);
+
+        let report = profiler.report();
+        assert_eq!(report.queries, 3);
+        assert_eq!(report.server_total_ms.unwrap().p50, 10.0);
+        assert_eq!(report.server_total_ms.unwrap().max, 30.0);
+        assert!(report.query_execution_ms.is_none());
+        assert_eq!(report.mean_cache_hit_ratio, Some(0.75));
+        assert_eq!(report.cache_temperatures["cold"], 1);
+        assert_eq!((report.exhaustive_searches, report.exhaustive_search_count), (1, 4));
+        assert!(report.to_string().contains("cache hot: 1"));
+
+        profiler.reset();
+        assert_eq!(profiler.report().queries, 0);
+    }
+}
@@ -0,0 +1,219 @@
+//! Compact encodings for embeddings.
+//!
+//! Half precision is supported natively: declare the vector attribute as
+//! [`AttributeType::vector_f16`](crate::AttributeType::vector_f16) in the
+//! schema and the server stores it at half the size, with
+//! [`to_f16`]/[`from_f16`] for local use. Int8 and binary vectors aren't
+//! indexable, but are useful as small attributes for re-scoring; they're
+//! stored as an integer array plus a companion attribute holding the scale
+//! (`<attr>_scale`) or dimension count (`<attr>_dims`).
+
+use serde::de::DeserializeOwned;
+
+use crate::{Error, Id, Result, Row};
+
+/// Convert to IEEE 754 half precision bit patterns, rounding to nearest even.
+pub fn to_f16(values: &[f32]) -> Vec<u16> {
+    values.iter().map(|v| f32_to_f16(*v)).collect()
+}
+
+pub fn from_f16(bits: &[u16]) -> Vec<f32> {
+    bits.iter().map(|b| f16_to_f32(*b)).collect()
+}
+
+/// Symmetric int8 quantization: `value ≈ values[i] as f32 * scale`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Int8Vector {
+    pub values: Vec<i8>,
+    pub scale: f32,
+}
+
+impl Int8Vector {
+    pub fn quantize(vector: &[f32]) -> Self {
+        let max = vector.iter().fold(0f32, |m, v| m.max(v.abs()));
+        let scale = if max > 0.0 { max / 127.0 } else { 1.0 };
+        let values = vector.iter().map(|v| (v / scale).round().clamp(-127.0, 127.0) as i8).collect();
+        Self { values, scale }
+    }
+
+    pub fn dequantize(&self) -> Vec<f32> {
+        self.values.iter().map(|v| *v as f32 * self.scale).collect()
+    }
+
+    /// Store as `attr` and `<attr>_scale`.
+    pub fn insert_into(&self, row: &mut Row, attr: &str) {
+        row.insert(attr.to_string(), serde_json::json!(self.values));
+        row.insert(format!("{}_scale", attr), serde_json::json!(self.scale));
+    }
+
+    /// Read back a vector stored with [`Int8Vector::insert_into`].
+    pub fn from_row(row: &Row, attr: &str) -> Result<Self> {
+        Ok(Self {
+            values: row_field(row, attr)?,
+            scale: row_field(row, &format!("{}_scale", attr))?,
+        })
+    }
+}
+
+/// One bit per dimension (set when the value is positive), most significant bit first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BinaryVector {
+    pub bits: Vec<u8>,
+    pub dims: usize,
+}
+
+impl BinaryVector {
+    pub fn quantize(vector: &[f32]) -> Self {
+        let mut bits = vec![0u8; vector.len().div_ceil(8)];
+        for (i, v) in vector.iter().enumerate() {
+            if *v > 0.0 {
+                bits[i / 8] |= 0x80 >> (i % 8);
+            }
+        }
+        Self { bits, dims: vector.len() }
+    }
+
+    /// Expand to `1.0`/`-1.0` per dimension.
+    pub fn dequantize(&self) -> Vec<f32> {
+        (0..self.dims)
+            .map(|i| if self.bits[i / 8] & (0x80 >> (i % 8)) != 0 { 1.0 } else { -1.0 })
+            .collect()
+    }
+
+    /// Number of differing bits; the usual distance for binary vectors.
+    pub fn hamming_distance(&self, other: &BinaryVector) -> u32 {
+        self.bits.iter().zip(&other.bits).map(|(a, b)| (a ^ b).count_ones()).sum()
+    }
+
+    /// Store as `attr` and `<attr>_dims`.
+    pub fn insert_into(&self, row: &mut Row, attr: &str) {
+        row.insert(attr.to_string(), serde_json::json!(self.bits));
+        row.insert(format!("{}_dims", attr), serde_json::json!(self.dims));
+    }
+
+    /// Read back a vector stored with [`BinaryVector::insert_into`].
+    pub fn from_row(row: &Row, attr: &str) -> Result<Self> {
+        Ok(Self {
+            bits: row_field(row, attr)?,
+            dims: row_field(row, &format!("{}_dims", attr))?,
+        })
+    }
+}
+
+fn row_field<T: DeserializeOwned>(row: &Row, attr: &str) -> Result<T> {
+    let value = row.get(attr).cloned().unwrap_or_default();
+    serde_json::from_value(value).map_err(|source| Error::RowConversion {
+        id: row.get("id").and_then(|id| serde_json::from_value::<Id>(id.clone()).ok()),
+        field: Some(attr.to_string()),
+        source,
+    })
+}
+
+fn f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exp = ((bits >> 23) & 0xff) as i32;
+    let mant = bits & 0x7f_ffff;
+
+    if exp == 0xff {
+        // Infinity, or NaN kept quiet.
+        return sign | 0x7c00 | if mant != 0 { 0x200 } else { 0 };
+    }
+    let half_exp = exp - 127 + 15;
+    if half_exp >= 0x1f {
+        return sign | 0x7c00;
+    }
+    if half_exp <= 0 {
+        if half_exp < -10 {
+            return sign;
+        }
+        // Subnormal: shift in the implicit leading bit.
+        let mant = mant | 0x80_0000;
+        let shift = (14 - half_exp) as u32;
+        let half = round_shift(mant, shift);
+        return sign | half as u16;
+    }
+    // A rounding carry into the exponent is still the correct result.
+    let half = ((half_exp as u32) << 10) + round_shift(mant, 13);
+    sign | half as u16
+}
+
+// `value >> shift`, rounded to nearest with ties to even.
+fn round_shift(value: u32, shift: u32) -> u32 {
+    let truncated = value >> shift;
+    let rem = value & ((1 << shift) - 1);
+    let halfway = 1 << (shift - 1);
+    if rem > halfway || (rem == halfway && truncated & 1 == 1) {
+        truncated + 1
+    } else {
+        truncated
+    }
+}
+
+fn f16_to_f32(bits: u16) -> f32 {
+    let sign = ((bits & 0x8000) as u32) << 16;
+    let exp = ((bits >> 10) & 0x1f) as u32;
+    let mant = (bits & 0x3ff) as u32;
+    match exp {
+        0 => {
+            let value = mant as f32 * 2f32.powi(-24);
+            if sign != 0 { -value } else { value }
+        }
+        0x1f => f32::from_bits(sign | 0x7f80_0000 | (mant << 13)),
+        _ => f32::from_bits(sign | ((exp + 112) << 23) | (mant << 13)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_f16_conversion() {
+        assert_eq!(to_f16(&[1.0, -2.0, 65504.0, 1e5, 1e-8]), vec![0x3c00, 0xc000, 0x7bff, 0x7c00, 0]);
+        // Smallest subnormal and a tie that rounds to even.
+        assert_eq!(to_f16(&[2f32.powi(-24), 1.0 + 2f32.powi(-11)]), vec![0x0001, 0x3c00]);
+        assert_eq!(from_f16(&[0x3c00, 0x0001, 0xfc00]), vec![1.0, 2f32.powi(-24), f32::NEG_INFINITY]);
+        assert!(from_f16(&to_f16(&[f32::NAN]))[0].is_nan());
+
+        let values = [0.1f32, -0.333, 12.5];
+        for (a, b) in values.iter().zip(from_f16(&to_f16(&values))) {
+            assert!((a - b).abs() <= a.abs() * 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_int8_roundtrip_through_row() {
+        let vector = [0.5f32, -1.0, 0.25];
+        let quantized = Int8Vector::quantize(&vector);
+        assert_eq!(quantized.values, vec![64, -127, 32]);
+
+        let mut row = Row::new();
+        quantized.insert_into(&mut row, "emb_i8");
+        let restored = Int8Vector::from_row(&row, "emb_i8").unwrap();
+        for (a, b) in vector.iter().zip(restored.dequantize()) {
+            assert!((a - b).abs() < 0.01);
+        }
+    }
+
+    #[test]
+    fn test_binary_quantization() {
+        let a = BinaryVector::quantize(&[0.3, -0.1, 0.0, 2.0, 1.0, -1.0, 1.0, 1.0, 0.5]);
+        assert_eq!(a.bits, vec![0b1001_1011, 0b1000_0000]);
+        assert_eq!(a.dequantize()[..3], [1.0, -1.0, -1.0]);
+
+        let b = BinaryVector::quantize(&[-0.3, -0.1, 0.0, 2.0, 1.0, -1.0, 1.0, 1.0, -0.5]);
+        assert_eq!(a.hamming_distance(&b), 2);
+
+        let mut row = Row::new();
+        a.insert_into(&mut row, "emb_bin");
+        assert_eq!(BinaryVector::from_row(&row, "emb_bin").unwrap(), a);
+    }
+
+    #[test]
+    fn test_from_row_reports_missing_attribute() {
+        let row: Row = serde_json::from_value(serde_json::json!({"id": 3, "emb_i8": [1]})).unwrap();
+        let err = Int8Vector::from_row(&row, "emb_i8").unwrap_err();
+        assert!(err.to_string().contains("with id 3 at `emb_i8_scale`"), "{}", err);
+    }
+}
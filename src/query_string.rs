@@ -0,0 +1,187 @@
+use crate::{Error, Filter, IncludeAttributes, Order, QueryParams, RankBy, Result};
+
+/// Limits for [`QueryParams::from_query_string`].
+#[derive(Debug, Clone)]
+pub struct QueryStringParams {
+    /// Largest `limit` accepted.
+    pub max_limit: u64,
+    /// `top_k` when the query string has no `limit`.
+    pub default_limit: Option<u64>,
+    /// Attributes that may be filtered, sorted, searched or selected; any
+    /// attribute if `None`. Set this when the query string comes from
+    /// untrusted clients.
+    pub allowed_attributes: Option<Vec<String>>,
+}
+
+impl Default for QueryStringParams {
+    fn default() -> Self {
+        Self { max_limit: 1000, default_limit: None, allowed_attributes: None }
+    }
+}
+
+impl QueryParams {
+    /// Build a query from REST-style URL parameters, for search endpoints
+    /// backed by a namespace.
+    ///
+    /// | Parameter | Meaning |
+    /// |---|---|
+    /// | `filter.status=active` | `status` equals `active` |
+    /// | `filter.price[gte]=10` | also `eq`, `ne`, `gt`, `lt`, `lte`, `glob`, `contains` |
+    /// | `filter.tag[in]=a,b` | `tag` is `a` or `b`; also `nin` |
+    /// | `sort=-created_at` | order by `created_at` descending (no `-`: ascending) |
+    /// | `search.title=rust` | rank by BM25 on `title` |
+    /// | `limit=20` | `top_k` |
+    /// | `fields=id,title` | `include_attributes` |
+    ///
+    /// Filters are combined with `And`. Values that parse as numbers or
+    /// booleans are sent as such; wrap them in double quotes to send a string.
+    /// Unknown parameters are rejected with [`Error::InvalidParams`].
+    pub fn from_query_string(query: &str, params: &QueryStringParams) -> Result<QueryParams> {
+        let mut url = reqwest::Url::parse("http://localhost/").expect("static URL parses");
+        url.set_query(Some(query.trim_start_matches('?')));
+
+        let allowed = |attr: &str| -> Result<()> {
+            match &params.allowed_attributes {
+                Some(allowed) if !allowed.iter().any(|a| a == attr) => {
+                    Err(Error::InvalidParams(format!("attribute {} is not allowed", attr)))
+                }
+                _ => Ok(()),
+            }
+        };
+        let mut query = QueryParams { top_k: params.default_limit, ..Default::default() };
+        let mut filters = Vec::new();
+
+        for (key, value) in url.query_pairs() {
+            if let Some(rest) = key.strip_prefix("filter.") {
+                let (attr, op) = match rest.strip_suffix(']').and_then(|r| r.split_once('[')) {
+                    Some((attr, op)) => (attr, op),
+                    None => (rest, "eq"),
+                };
+                allowed(attr)?;
+                filters.push(filter(attr, op, &value)?);
+            } else if let Some(attr) = key.strip_prefix("search.") {
+                allowed(attr)?;
+                set_rank_by(&mut query, RankBy::bm25(attr, value.into_owned()))?;
+            } else {
+                match &*key {
+                    "sort" => {
+                        let (attr, order) = match value.strip_prefix('-') {
+                            Some(attr) => (attr, Order::Desc),
+                            None => (&*value, Order::Asc),
+                        };
+                        allowed(attr)?;
+                        set_rank_by(&mut query, RankBy::attribute(attr, order))?;
+                    }
+                    "limit" => {
+                        let limit: u64 = value
+                            .parse()
+                            .map_err(|_| Error::InvalidParams(format!("limit must be a number, got {}", value)))?;
+                        if limit == 0 || limit > params.max_limit {
+                            return Err(Error::InvalidParams(format!("limit must be between 1 and {}", params.max_limit)));
+                        }
+                        query.top_k = Some(limit);
+                    }
+                    "fields" => {
+                        let fields: Vec<String> = value.split(',').filter(|f| !f.is_empty()).map(String::from).collect();
+                        for field in &fields {
+                            allowed(field)?;
+                        }
+                        query.include_attributes = Some(IncludeAttributes::List(fields));
+                    }
+                    other => return Err(Error::InvalidParams(format!("unknown query parameter {}", other))),
+                }
+            }
+        }
+
+        query.filters = match filters.len() {
+            0 => None,
+            1 => filters.pop(),
+            _ => Some(Filter::and(filters)),
+        };
+        Ok(query)
+    }
+}
+
+fn set_rank_by(query: &mut QueryParams, rank_by: RankBy) -> Result<()> {
+    if query.rank_by.replace(rank_by).is_some() {
+        return Err(Error::InvalidParams("only one of sort or search may be given".to_string()));
+    }
+    Ok(())
+}
+
+fn filter(attr: &str, op: &str, value: &str) -> Result<Filter> {
+    let list = || value.split(',').map(parse_value).collect();
+    Ok(match op {
+        "eq" => Filter::eq(attr, parse_value(value)),
+        "ne" => Filter::not_eq(attr, parse_value(value)),
+        "gt" => Filter::gt(attr, parse_value(value)),
+        "gte" => Filter::gte(attr, parse_value(value)),
+        "lt" => Filter::lt(attr, parse_value(value)),
+        "lte" => Filter::lte(attr, parse_value(value)),
+        "in" => Filter::r#in(attr, list()),
+        "nin" => Filter::not_in(attr, list()),
+        "glob" => Filter::glob(attr, value),
+        "contains" => Filter::contains(attr, parse_value(value)),
+        other => return Err(Error::InvalidParams(format!("unknown filter operator {}", other))),
+    })
+}
+
+fn parse_value(value: &str) -> serde_json::Value {
+    if let Some(quoted) = value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+        return quoted.into();
+    }
+    if let Ok(n) = value.parse::<i64>() {
+        return n.into();
+    }
+    if let Ok(n) = value.parse::<u64>() {
+        return n.into();
+    }
+    if let Ok(b) = value.parse::<bool>() {
+        return b.into();
+    }
+    match value.parse::<f64>() {
+        Ok(n) if n.is_finite() => n.into(),
+        _ => value.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn parse(query: &str) -> Result<serde_json::Value> {
+        let params = QueryParams::from_query_string(query, &QueryStringParams::default())?;
+        Ok(serde_json::to_value(params).unwrap())
+    }
+
+    #[test]
+    fn test_from_query_string() {
+        let query = parse("?filter.status=active&filter.price[gte]=10&filter.tag[in]=a,%222%22&sort=-created_at&limit=20&fields=id,title").unwrap();
+        assert_eq!(
+            query,
+            json!({
+                "rank_by": ["created_at", "desc"],
+                "top_k": 20,
+                "filters": ["And", [["status", "Eq", "active"], ["price", "Gte", 10], ["tag", "In", ["a", "2"]]]],
+                "include_attributes": ["id", "title"],
+            })
+        );
+        assert_eq!(parse("search.title=hello+world").unwrap()["rank_by"], json!(["title", "BM25", "hello world"]));
+        assert_eq!(parse("filter.done=true").unwrap()["filters"], json!(["done", "Eq", true]));
+    }
+
+    #[test]
+    fn test_from_query_string_rejects_bad_input() {
+        assert!(parse("limit=5000").is_err());
+        assert!(parse("limit=ten").is_err());
+        assert!(parse("sort=a&search.b=x").is_err());
+        assert!(parse("filter.a[regex]=x").is_err());
+        assert!(parse("page=2").is_err());
+
+        let params = QueryStringParams { allowed_attributes: Some(vec!["title".to_string()]), ..Default::default() };
+        assert!(QueryParams::from_query_string("filter.title=x&sort=title", &params).is_ok());
+        assert!(QueryParams::from_query_string("filter.secret=x", &params).is_err());
+        assert!(QueryParams::from_query_string("fields=title,secret", &params).is_err());
+    }
+}
@@ -0,0 +1,626 @@
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::pin::pin;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+
+use crate::bulk;
+use crate::rt;
+use crate::{BulkWriteReport, Client, DistanceMetric, Error, Result, Row, WriteParams};
+
+#[derive(Debug, Clone)]
+pub struct IngestQueueParams {
+    /// Rows upserted per write request.
+    pub batch_size: usize,
+    /// Rows held in memory before producers spill or wait.
+    pub max_buffered_rows: usize,
+    /// Append rows beyond `max_buffered_rows` to this file instead of making
+    /// producers wait. The file is truncated whenever it has been drained.
+    pub spill_path: Option<PathBuf>,
+    /// Write-ahead journal: `push` appends and syncs each row here before
+    /// returning, and rows are trimmed once their batch has been written.
+    /// Rows left over from a crash or failed write are queued again by the
+    /// next [`IngestQueue::new`] with the same path, so delivery is
+    /// at-least-once. Syncs run on blocking threads, one push at a time.
+    pub journal_path: Option<PathBuf>,
+    pub distance_metric: Option<DistanceMetric>,
+}
+
+impl Default for IngestQueueParams {
+    fn default() -> Self {
+        Self {
+            batch_size: 1000,
+            max_buffered_rows: 10_000,
+            spill_path: None,
+            journal_path: None,
+            distance_metric: None,
+        }
+    }
+}
+
+/// Decouples producers from writes: rows are queued by [`IngestQueue::push`]
+/// and upserted in batches by a background task.
+///
+/// Call [`IngestQueue::close`] to drain the queue and get the totals. If a
+/// write fails the background task stops; rows still queued in memory are
+/// lost unless a journal is configured, while spilled rows stay in the spill
+/// file.
+pub struct IngestQueue {
+    shared: Arc<Shared>,
+    worker: Option<JoinHandle<Result<BulkWriteReport>>>,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    /// Only locked on blocking threads, as every journal write is synced to
+    /// disk. `None` once the worker has removed it.
+    journal: Arc<Mutex<Option<Journal>>>,
+    /// Only locked on blocking threads, like the journal.
+    spill: Arc<Mutex<Option<Spill>>>,
+    journaled: bool,
+    /// Held by a producer from checking for room until its row is queued,
+    /// so rows are journaled in queue order and the room is still there.
+    journal_turn: tokio::sync::Mutex<()>,
+    /// Signals the worker that rows arrived or the queue closed.
+    rows_ready: Notify,
+    /// Signals waiting producers that the worker took a batch.
+    space_ready: Notify,
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn stopped() -> Error {
+    Error::External("ingest queue worker stopped".into())
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        lock(&self.state)
+    }
+
+    /// Run `f` on the journal on a blocking thread.
+    async fn with_journal<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Journal) -> Result<T> + Send + 'static,
+    {
+        on_blocking_thread(&self.journal, f).await
+    }
+
+    /// Run `f` on the spill file on a blocking thread.
+    async fn with_spill<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Spill) -> Result<T> + Send + 'static,
+    {
+        on_blocking_thread(&self.spill, f).await
+    }
+
+    /// Queue a row there is [room](State::has_room) for.
+    async fn enqueue(&self, row: Row) -> Result<()> {
+        let spilled = self.lock().enqueue(row);
+        self.spill(spilled).await
+    }
+
+    /// Append a row [`State::enqueue`] reserved a place in the spill file for.
+    async fn spill(&self, row: Option<Row>) -> Result<()> {
+        if let Some(row) = row
+            && let Err(e) = self.with_spill(move |spill| spill.append(&row)).await
+        {
+            self.lock().spilled -= 1;
+            return Err(e);
+        }
+        self.rows_ready.notify_one();
+        Ok(())
+    }
+}
+
+/// Run `f` on the value in `slot` on a blocking thread, failing once the
+/// worker has removed it.
+async fn on_blocking_thread<R, T, F>(slot: &Arc<Mutex<Option<R>>>, f: F) -> Result<T>
+where
+    R: Send + 'static,
+    T: Send + 'static,
+    F: FnOnce(&mut R) -> Result<T> + Send + 'static,
+{
+    let slot = slot.clone();
+    rt::blocking(move || match lock(&slot).as_mut() {
+        Some(value) => f(value),
+        None => Err(stopped()),
+    })
+    .await?
+}
+
+impl IngestQueue {
+    /// Start a queue writing to `namespace`; must be called within a Tokio runtime.
+    ///
+    /// Unacknowledged rows in an existing journal are queued ahead of new ones.
+    pub fn new(client: &Client, namespace: impl Into<String>, params: IngestQueueParams) -> Result<Self> {
+        let spill = params.spill_path.clone().map(Spill::create).transpose()?;
+        let mut state = State::new(params.max_buffered_rows, spill.is_some());
+        let mut journal = None;
+        if let Some(path) = &params.journal_path {
+            let (opened, replayed) = Journal::open(path.clone())?;
+            state.memory.extend(replayed);
+            journal = Some(opened);
+        }
+        let shared = Arc::new(Shared {
+            state: Mutex::new(state),
+            journaled: journal.is_some(),
+            journal: Arc::new(Mutex::new(journal)),
+            spill: Arc::new(Mutex::new(spill)),
+            journal_turn: tokio::sync::Mutex::new(()),
+            rows_ready: Notify::new(),
+            space_ready: Notify::new(),
+        });
+        let worker = tokio::spawn(run(shared.clone(), client.clone(), namespace.into(), params));
+        Ok(Self { shared, worker: Some(worker) })
+    }
+
+    /// Queue a row, waiting for room if the buffer is full and spilling is off.
+    pub async fn push(&self, row: Row) -> Result<()> {
+        let _turn = match self.shared.journaled {
+            true => Some(self.shared.journal_turn.lock().await),
+            false => None,
+        };
+        let mut row = Some(row);
+        let spilled = loop {
+            let mut space = pin!(self.shared.space_ready.notified());
+            space.as_mut().enable();
+            {
+                let mut state = self.shared.lock();
+                if state.stopped {
+                    return Err(stopped());
+                }
+                if state.has_room() {
+                    if self.shared.journaled {
+                        break None;
+                    }
+                    break Some(state.enqueue(row.take().expect("the row is queued once")));
+                }
+            }
+            space.await;
+        };
+        if let Some(spilled) = spilled {
+            return self.shared.spill(spilled).await;
+        }
+
+        // Journal outside the state lock, so the worker isn't held up by the sync.
+        let row = row.take().expect("the row is queued once");
+        let row = self.shared.with_journal(move |journal| journal.append(&row).map(|()| row)).await?;
+        self.shared.enqueue(row).await
+    }
+
+    /// Rows queued in memory or spilled to disk.
+    pub fn len(&self) -> usize {
+        let state = self.shared.lock();
+        state.memory.len() + state.spilled
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Write everything still queued and return totals for the queue's lifetime.
+    pub async fn close(mut self) -> Result<BulkWriteReport> {
+        self.shared.lock().closed = true;
+        self.shared.rows_ready.notify_one();
+        let worker = self.worker.take().expect("worker is only taken on close");
+        worker.await.map_err(|e| Error::External(Box::new(e)))?
+    }
+}
+
+impl Drop for IngestQueue {
+    // The worker keeps draining in the background after a drop.
+    fn drop(&mut self) {
+        self.shared.lock().closed = true;
+        self.shared.rows_ready.notify_one();
+    }
+}
+
+async fn run(shared: Arc<Shared>, client: Client, namespace: String, params: IngestQueueParams) -> Result<BulkWriteReport> {
+    let result = drain(&shared, &client, &namespace, &params).await;
+    shared.lock().stopped = true;
+    if result.is_ok() {
+        let (spill, journal) = (shared.spill.clone(), shared.journal.clone());
+        let _ = rt::blocking(move || {
+            if let Some(spill) = lock(&spill).take() {
+                spill.remove();
+            }
+            if let Some(journal) = lock(&journal).take() {
+                journal.remove();
+            }
+        })
+        .await;
+    }
+    shared.space_ready.notify_waiters();
+    result
+}
+
+async fn drain(shared: &Shared, client: &Client, namespace: &str, params: &IngestQueueParams) -> Result<BulkWriteReport> {
+    let ns = client.namespace(namespace);
+    let mut report = BulkWriteReport::default();
+    loop {
+        let (mut batch, from_spill, closed) = {
+            let mut state = shared.lock();
+            let (batch, from_spill) = state.take_batch(params.batch_size.max(1));
+            (batch, from_spill, state.closed)
+        };
+        if from_spill > 0 {
+            let spilled = shared.with_spill(move |spill| spill.read(from_spill)).await?;
+            shared.lock().spilled -= spilled.len();
+            batch.extend(spilled);
+        }
+        if batch.is_empty() {
+            if closed {
+                return Ok(report);
+            }
+            shared.rows_ready.notified().await;
+            continue;
+        }
+        shared.space_ready.notify_waiters();
+
+        let count = batch.len();
+        let write = WriteParams {
+            upsert_rows: Some(batch),
+            distance_metric: params.distance_metric,
+            ..Default::default()
+        };
+        bulk::write_reported(&ns, write, &mut report).await?;
+        if shared.journaled {
+            shared.with_journal(move |journal| journal.ack(count)).await?;
+        }
+    }
+}
+
+struct State {
+    memory: VecDeque<Row>,
+    max_buffered_rows: usize,
+    /// Whether rows that don't fit in memory go to the spill file.
+    spilling: bool,
+    /// Rows with a place in the spill file that haven't been read back.
+    spilled: usize,
+    closed: bool,
+    stopped: bool,
+}
+
+impl State {
+    fn new(max_buffered_rows: usize, spilling: bool) -> Self {
+        Self {
+            memory: VecDeque::new(),
+            max_buffered_rows: max_buffered_rows.max(1),
+            spilling,
+            spilled: 0,
+            closed: false,
+            stopped: false,
+        }
+    }
+
+    /// Whether a row fits in memory, or else in the spill file; the producer
+    /// must wait if not.
+    fn has_room(&self) -> bool {
+        self.spilling || self.fits_in_memory()
+    }
+
+    fn fits_in_memory(&self) -> bool {
+        // Once rows are spilled, later rows follow them so order is kept.
+        self.spilled == 0 && self.memory.len() < self.max_buffered_rows
+    }
+
+    /// Queue a row there is [room](Self::has_room) for, or return it to be
+    /// appended to the spill file outside the lock.
+    fn enqueue(&mut self, row: Row) -> Option<Row> {
+        if self.fits_in_memory() {
+            self.memory.push_back(row);
+            return None;
+        }
+        self.spilled += 1;
+        Some(row)
+    }
+
+    /// Up to `n` rows in queue order: memory first, then how many to read
+    /// from the spill file.
+    fn take_batch(&mut self, n: usize) -> (Vec<Row>, usize) {
+        let batch: Vec<Row> = self.memory.drain(..self.memory.len().min(n)).collect();
+        let from_spill = (n - batch.len()).min(self.spilled);
+        (batch, from_spill)
+    }
+}
+
+/// Append-only newline-delimited JSON file with a read cursor.
+struct Spill {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    reader: BufReader<File>,
+    written: usize,
+    read: usize,
+}
+
+impl Spill {
+    fn create(path: PathBuf) -> Result<Self> {
+        let file = OpenOptions::new().create(true).truncate(true).write(true).open(&path)?;
+        let reader = BufReader::new(File::open(&path)?);
+        Ok(Self { path, writer: BufWriter::new(file), reader, written: 0, read: 0 })
+    }
+
+    fn pending(&self) -> usize {
+        self.written - self.read
+    }
+
+    fn append(&mut self, row: &Row) -> Result<()> {
+        serde_json::to_writer(&mut self.writer, row)?;
+        self.writer.write_all(b"\n")?;
+        self.written += 1;
+        Ok(())
+    }
+
+    /// Read up to `n` of the rows appended so far.
+    fn read(&mut self, n: usize) -> Result<Vec<Row>> {
+        self.writer.flush()?;
+        let mut rows = Vec::new();
+        while rows.len() < n && self.pending() > 0 {
+            let mut line = String::new();
+            self.reader.read_line(&mut line)?;
+            rows.push(serde_json::from_str(&line)?);
+            self.read += 1;
+        }
+        if self.pending() == 0 {
+            // Drained: start over so the file doesn't grow without bound.
+            self.writer.get_ref().set_len(0)?;
+            self.writer.seek(SeekFrom::Start(0))?;
+            self.reader.seek(SeekFrom::Start(0))?;
+            self.written = 0;
+            self.read = 0;
+        }
+        Ok(rows)
+    }
+
+    fn remove(self) {
+        drop(self.writer);
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+// Acknowledged rows kept in the journal before it is rewritten without them.
+const JOURNAL_COMPACT_ROWS: usize = 10_000;
+
+/// Write-ahead log of queued rows. Each line is either a row object or a
+/// number acknowledging that many of the oldest rows as written.
+struct Journal {
+    path: PathBuf,
+    file: File,
+    pending: usize,
+    /// Acknowledged rows still present in the file.
+    acked: usize,
+}
+
+impl Journal {
+    /// Open or create the journal, returning the rows it still holds. The file
+    /// is rewritten to contain only those rows.
+    fn open(path: PathBuf) -> Result<(Self, Vec<Row>)> {
+        let rows = match File::open(&path) {
+            Ok(file) => replay(BufReader::new(file))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e.into()),
+        };
+        let file = rewrite(&path, &rows)?;
+        let journal = Self { path, file, pending: rows.len(), acked: 0 };
+        Ok((journal, rows))
+    }
+
+    fn append(&mut self, row: &Row) -> Result<()> {
+        let mut line = serde_json::to_vec(row)?;
+        line.push(b'\n');
+        self.file.write_all(&line)?;
+        self.file.sync_data()?;
+        self.pending += 1;
+        Ok(())
+    }
+
+    /// Mark the oldest `n` rows as written.
+    fn ack(&mut self, n: usize) -> Result<()> {
+        self.pending -= n;
+        self.acked += n;
+        if self.pending == 0 {
+            self.file.set_len(0)?;
+        } else if self.acked >= JOURNAL_COMPACT_ROWS && self.acked > self.pending {
+            let rows = replay(BufReader::new(File::open(&self.path)?))?;
+            self.file = rewrite(&self.path, &rows[n.min(rows.len())..])?;
+        } else {
+            writeln!(self.file, "{n}")?;
+            self.file.sync_data()?;
+            return Ok(());
+        }
+        self.file.sync_data()?;
+        self.acked = 0;
+        Ok(())
+    }
+
+    fn remove(self) {
+        drop(self.file);
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Rows in a journal that haven't been acknowledged. A torn final line from
+/// a crash mid-append is ignored.
+fn replay(reader: impl BufRead) -> Result<Vec<Row>> {
+    let lines = reader.lines().collect::<std::io::Result<Vec<_>>>()?;
+    let mut rows = VecDeque::new();
+    for (i, line) in lines.iter().enumerate() {
+        if line.is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<serde_json::Value>(line) {
+            Ok(serde_json::Value::Number(n)) => {
+                let n = n.as_u64().unwrap_or(0) as usize;
+                rows.drain(..n.min(rows.len()));
+            }
+            Ok(value) => rows.push_back(serde_json::from_value(value)?),
+            Err(_) if i + 1 == lines.len() => break,
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(rows.into())
+}
+
+/// Atomically replace the file at `path` with `rows`, returning it opened for appending.
+fn rewrite(path: &Path, rows: &[Row]) -> Result<File> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let mut writer = BufWriter::new(File::create(&tmp)?);
+    for row in rows {
+        serde_json::to_writer(&mut writer, row)?;
+        writer.write_all(b"\n")?;
+    }
+    writer.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+    std::fs::rename(&tmp, path)?;
+    Ok(OpenOptions::new().append(true).open(path)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(id: u64) -> Row {
+        serde_json::from_value(serde_json::json!({"id": id})).unwrap()
+    }
+
+    fn ids(rows: &[Row]) -> Vec<u64> {
+        rows.iter().map(|r| r["id"].as_u64().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_full_buffer_without_spill_rejects() {
+        let mut state = State::new(1, false);
+        assert!(state.has_room());
+        assert!(state.enqueue(row(1)).is_none());
+        assert!(!state.has_room());
+    }
+
+    /// Queue `ids` as `IngestQueue::push` does, minus the blocking threads.
+    fn enqueue(state: &mut State, spill: &mut Spill, ids: impl IntoIterator<Item = u64>) {
+        for id in ids {
+            assert!(state.has_room());
+            if let Some(row) = state.enqueue(row(id)) {
+                spill.append(&row).unwrap();
+            }
+        }
+    }
+
+    /// Take a batch as the worker does.
+    fn take(state: &mut State, spill: &mut Spill, n: usize) -> Vec<u64> {
+        let (mut batch, from_spill) = state.take_batch(n);
+        let spilled = spill.read(from_spill).unwrap();
+        state.spilled -= spilled.len();
+        batch.extend(spilled);
+        ids(&batch)
+    }
+
+    #[test]
+    fn test_spill_keeps_queue_order() {
+        let path = std::env::temp_dir().join(format!("rs_puff_spill_{}.ndjson", std::process::id()));
+        let mut spill = Spill::create(path.clone()).unwrap();
+        let mut state = State::new(2, true);
+        enqueue(&mut state, &mut spill, 1..=5);
+        assert_eq!((state.memory.len(), state.spilled, spill.pending()), (2, 3, 3));
+
+        assert_eq!(take(&mut state, &mut spill, 3), [1, 2, 3]);
+        // Memory has room again, but new rows queue behind the spilled ones.
+        enqueue(&mut state, &mut spill, [6]);
+        assert_eq!(take(&mut state, &mut spill, 10), [4, 5, 6]);
+
+        // Drained spill files are reset and reusable.
+        enqueue(&mut state, &mut spill, 7..=9);
+        assert_eq!(take(&mut state, &mut spill, 10), [7, 8, 9]);
+
+        spill.remove();
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_journal_replays_unacknowledged_rows() {
+        let path = std::env::temp_dir().join(format!("rs_puff_journal_{}.ndjson", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let (mut journal, replayed) = Journal::open(path.clone()).unwrap();
+        assert!(replayed.is_empty());
+        for id in 1..=4 {
+            journal.append(&row(id)).unwrap();
+        }
+        journal.ack(2).unwrap();
+        // Simulate a crash partway through appending a row.
+        journal.file.write_all(b"{\"id\": 5").unwrap();
+        drop(journal);
+
+        let (mut journal, replayed) = Journal::open(path.clone()).unwrap();
+        assert_eq!(ids(&replayed), [3, 4]);
+        journal.ack(2).unwrap();
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), 0);
+        journal.remove();
+        assert!(!path.exists());
+    }
+
+    /// Accepts requests and never answers them.
+    struct Hang;
+
+    impl crate::HttpTransport for Hang {
+        fn send(&self, _req: reqwest::Request) -> crate::rt::BoxFuture<'_, Result<reqwest::Response>> {
+            Box::pin(std::future::pending())
+        }
+    }
+
+    /// Accepts writes, recording the ids upserted.
+    #[derive(Default)]
+    struct Written(Mutex<Vec<u64>>);
+
+    impl crate::HttpTransport for Written {
+        fn send(&self, req: reqwest::Request) -> crate::rt::BoxFuture<'_, Result<reqwest::Response>> {
+            let body: serde_json::Value = serde_json::from_slice(req.body().unwrap().as_bytes().unwrap()).unwrap();
+            let rows = body["upsert_rows"].as_array().unwrap();
+            lock(&self.0).extend(rows.iter().map(|row| row["id"].as_u64().unwrap()));
+            let resp = http::Response::new(format!(r#"{{"rows_affected": {}}}"#, rows.len()));
+            Box::pin(async move { Ok(reqwest::Response::from(resp)) })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_spilled_rows_are_written_in_order() {
+        let path = std::env::temp_dir().join(format!("rs_puff_push_spill_{}.ndjson", std::process::id()));
+        let transport = Arc::new(Written::default());
+        let client = Client::new("key").with_transport(transport.clone());
+        let params = IngestQueueParams { batch_size: 2, max_buffered_rows: 1, spill_path: Some(path.clone()), ..Default::default() };
+        let queue = IngestQueue::new(&client, "docs", params).unwrap();
+        for id in 1..=7 {
+            queue.push(row(id)).await.unwrap();
+        }
+        let report = queue.close().await.unwrap();
+        assert_eq!(report.rows_upserted, 7);
+        assert_eq!(*lock(&transport.0), [1, 2, 3, 4, 5, 6, 7]);
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_push_appends_to_journal() {
+        let path = std::env::temp_dir().join(format!("rs_puff_push_journal_{}.ndjson", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let client = Client::new("key").with_transport(Hang);
+        let params = IngestQueueParams { max_buffered_rows: 1, journal_path: Some(path.clone()), ..Default::default() };
+        let queue = IngestQueue::new(&client, "docs", params).unwrap();
+        queue.push(row(1)).await.unwrap();
+        // Row 1 is stuck in the worker's write and row 2 fills the buffer.
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        queue.push(row(2)).await.unwrap();
+        // Rows the producer has to wait on aren't journaled.
+        let waiting = tokio::time::timeout(std::time::Duration::from_millis(50), queue.push(row(3))).await;
+        assert!(waiting.is_err());
+        assert_eq!(ids(&replay(BufReader::new(File::open(&path).unwrap())).unwrap()), [1, 2]);
+        drop(queue);
+        let _ = std::fs::remove_file(&path);
+    }
+}
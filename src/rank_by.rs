@@ -1,5 +1,9 @@
+use serde::de::{self, Deserializer};
 use serde::ser::{SerializeSeq, Serializer};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::wire::from_value;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -137,6 +141,50 @@ impl Serialize for RankBy {
     }
 }
 
+// Combinators lead with their name and an array or weight, so an attribute
+// named "Sum" ordered with `["Sum", "asc"]` still parses as an attribute.
+impl<'de> Deserialize<'de> for RankBy {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        rank_by(Vec::deserialize(deserializer)?).map_err(de::Error::custom)
+    }
+}
+
+fn rank_by(parts: Vec<Value>) -> Result<RankBy, String> {
+    let mut parts = parts.into_iter();
+    let (Some(Value::String(head)), Some(second), third, fourth, None) =
+        (parts.next(), parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Err("expected [attr, op, ...] or [combinator, ...]".to_string());
+    };
+    Ok(match (head.as_str(), second, third, fourth) {
+        ("Sum", subqueries @ Value::Array(_), None, None) => RankBy::Sum(from_value(subqueries)?),
+        ("Max", subqueries @ Value::Array(_), None, None) => RankBy::Max(from_value(subqueries)?),
+        ("Product", weight @ Value::Number(_), Some(subquery), None) => {
+            RankBy::Product { weight: from_value(weight)?, subquery: Box::new(from_value(subquery)?) }
+        }
+        (_, Value::String(op), query, params) => return ranking(head.clone(), &op, query, params),
+        _ => return Err(format!("malformed rank_by starting with {:?}", head)),
+    })
+}
+
+fn ranking(attr: String, op: &str, query: Option<Value>, params: Option<Value>) -> Result<RankBy, String> {
+    Ok(match (op, query, params) {
+        ("asc", None, None) => RankBy::Attribute { attr, order: Order::Asc },
+        ("desc", None, None) => RankBy::Attribute { attr, order: Order::Desc },
+        ("ANN", Some(query), None) => RankBy::Vector { attr, query: from_value(query)? },
+        ("kNN", Some(query), None) => RankBy::VectorKnn { attr, query: from_value(query)? },
+        ("BM25", Some(query), params) => RankBy::Bm25 {
+            attr,
+            query: from_value(query)?,
+            params: params.map(from_value).transpose()?,
+        },
+        (op, ..) => return Err(format!("unknown ranking {:?} or wrong number of arguments", op)),
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -232,6 +280,25 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_deserialize_roundtrips() {
+        let rankings = [
+            RankBy::sum(vec![
+                RankBy::product(2.0, RankBy::bm25_with_params("title", "qu", Bm25Params { last_as_prefix: Some(true) })),
+                RankBy::max(vec![RankBy::vector("vec", vec![0.1, 0.2]), RankBy::vector_knn("vec", vec![1.0])]),
+            ]),
+            RankBy::asc("Sum"),
+            RankBy::desc("views"),
+        ];
+        for rank_by in rankings {
+            let json = serde_json::to_value(&rank_by).unwrap();
+            assert_eq!(serde_json::from_value::<RankBy>(json).unwrap(), rank_by);
+        }
+        for json in [r#"["views","up"]"#, r#"["vec","ANN"]"#, r#"["Product","2",["a","asc"]]"#, r#"[]"#] {
+            assert!(serde_json::from_str::<RankBy>(json).is_err(), "{}", json);
+        }
+    }
+
     #[test]
     fn test_empty_vector() {
         let r = RankBy::vector("vec", vec![]);
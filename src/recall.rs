@@ -0,0 +1,67 @@
+use crate::{Error, Namespace, QueryParams, QueryResponse, RankBy, Result};
+
+/// Result of [`Namespace::compare_ann_knn`].
+#[derive(Debug, Clone)]
+pub struct AnnComparison {
+    pub ann: QueryResponse,
+    pub knn: QueryResponse,
+    /// Ids returned by both queries.
+    pub overlap: usize,
+    /// `overlap` as a fraction of the exact results; `1.0` when kNN returns nothing.
+    pub recall: f64,
+    /// Exact neighbours that ANN missed, in kNN rank order.
+    pub missed: Vec<serde_json::Value>,
+}
+
+impl Namespace {
+    /// Run a vector query with both `ANN` and exhaustive `kNN` ranking and
+    /// measure how many exact neighbours the approximate search found.
+    ///
+    /// `params.rank_by` must be a plain vector ranking (either kind); kNN
+    /// scans every row, so keep this to sampling and tuning.
+    pub async fn compare_ann_knn(&self, params: QueryParams) -> Result<AnnComparison> {
+        let (attr, query) = match &params.rank_by {
+            Some(RankBy::Vector { attr, query } | RankBy::VectorKnn { attr, query }) => (attr.clone(), query.clone()),
+            _ => return Err(Error::InvalidParams("compare_ann_knn requires a vector rank_by".to_string())),
+        };
+        let ann_params = QueryParams { rank_by: Some(RankBy::vector(attr.clone(), query.clone())), ..params.clone() };
+        let knn_params = QueryParams { rank_by: Some(RankBy::vector_knn(attr, query)), ..params };
+
+        let (ann, knn) = futures_util::future::try_join(self.query(ann_params), self.query(knn_params)).await?;
+        Ok(compare(ann, knn))
+    }
+}
+
+fn compare(ann: QueryResponse, knn: QueryResponse) -> AnnComparison {
+    let ann_ids: Vec<_> = ann.rows.iter().filter_map(|row| row.get("id")).collect();
+    let missed: Vec<_> = knn
+        .rows
+        .iter()
+        .filter_map(|row| row.get("id"))
+        .filter(|id| !ann_ids.contains(id))
+        .cloned()
+        .collect();
+    let overlap = knn.rows.len() - missed.len();
+    let recall = if knn.rows.is_empty() { 1.0 } else { overlap as f64 / knn.rows.len() as f64 };
+    AnnComparison { ann, knn, overlap, recall, missed }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(ids: &[u64]) -> QueryResponse {
+        let rows: Vec<_> = ids.iter().map(|id| serde_json::json!({"id": id})).collect();
+        serde_json::from_value(serde_json::json!({"rows": rows})).unwrap()
+    }
+
+    #[test]
+    fn test_compare_reports_recall_and_missed_ids() {
+        let result = compare(response(&[1, 2, 5, 6]), response(&[1, 2, 3, 4]));
+        assert_eq!(result.overlap, 2);
+        assert_eq!(result.recall, 0.5);
+        assert_eq!(result.missed, vec![serde_json::json!(3), serde_json::json!(4)]);
+
+        assert_eq!(compare(response(&[]), response(&[])).recall, 1.0);
+    }
+}
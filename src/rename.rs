@@ -0,0 +1,53 @@
+use crate::{Client, Error, Result, WriteParams};
+
+#[derive(Debug, Clone, Default)]
+pub struct RenameParams {
+    /// Check the rename can run and count the rows it would move, without
+    /// writing or deleting anything.
+    pub dry_run: bool,
+}
+
+/// Outcome of [`Client::rename_namespace`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenameReport {
+    pub from: String,
+    pub to: String,
+    /// Rows in the old namespace, and in the new one after the copy.
+    pub rows: u64,
+    pub dry_run: bool,
+}
+
+impl Client {
+    /// "Rename" a namespace: copy it to `to` with `copy_from_namespace`,
+    /// check the copy has as many rows as the original, then delete `from`.
+    ///
+    /// Fails without writing if `to` already exists. If the row counts
+    /// differ, `from` is kept and [`Error::Verification`] is returned; the
+    /// partial copy in `to` is left for inspection. Writes to `from` while
+    /// the rename runs can be lost.
+    pub async fn rename_namespace(&self, from: &str, to: &str, params: &RenameParams) -> Result<RenameReport> {
+        let source = self.namespace(from);
+        let target = self.namespace(to);
+        if target.exists().await? {
+            return Err(Error::InvalidParams(format!("namespace {} already exists", to)));
+        }
+        let rows = source.count_rows().await?;
+        let report = RenameReport { from: from.to_string(), to: to.to_string(), rows, dry_run: params.dry_run };
+        if params.dry_run {
+            return Ok(report);
+        }
+
+        target
+            .write(WriteParams { copy_from_namespace: Some(from.to_string()), ..Default::default() })
+            .await?;
+        let copied = target.count_rows().await?;
+        if copied != rows {
+            return Err(Error::Verification(format!(
+                "{} has {} rows after copying {} rows from {}; {} was not deleted",
+                to, copied, rows, from, from
+            )));
+        }
+        source.delete_all().await?;
+        Ok(report)
+    }
+}
@@ -0,0 +1,182 @@
+use std::marker::PhantomData;
+
+use serde::Serialize;
+
+use crate::rt::BoxFuture;
+use crate::{
+    DistanceMetric, Document, Error, Filter, Id, Namespace, QueryParams, RAG_VECTOR_ATTRIBUTE, RankBy, Result, Row,
+    WriteParams,
+};
+
+/// Typed storage for one kind of [`Document`], for application code that
+/// shouldn't build [`QueryParams`] or [`WriteParams`] itself.
+///
+/// Only [`namespace`](Self::namespace) is required; the other methods have
+/// defaults over it and can be overridden, e.g. to add a tenant filter or a
+/// cache. [`NamespaceRepository`] is the ready-made implementation.
+pub trait VectorRepository<T>: Sync
+where
+    T: Document + Serialize + Send + Sync,
+{
+    fn namespace(&self) -> &Namespace;
+
+    /// Attribute [`search`](Self::search) ranks by. Defaults to
+    /// [`RAG_VECTOR_ATTRIBUTE`].
+    fn vector_attribute(&self) -> &str {
+        RAG_VECTOR_ATTRIBUTE
+    }
+
+    /// Sent with every [`save`](Self::save), so the first one can create the
+    /// namespace. Defaults to cosine distance.
+    fn distance_metric(&self) -> Option<DistanceMetric> {
+        Some(DistanceMetric::CosineDistance)
+    }
+
+    /// Upsert `documents` in one write, replacing stored documents with the
+    /// same id. Each must serialize to an object with an `id`.
+    fn save<'a>(&'a self, documents: &'a [T]) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            if documents.is_empty() {
+                return Ok(());
+            }
+            let rows = documents.iter().map(to_row).collect::<Result<Vec<_>>>()?;
+            let params =
+                WriteParams { upsert_rows: Some(rows), distance_metric: self.distance_metric(), ..Default::default() };
+            self.namespace().write(params).await?;
+            Ok(())
+        })
+    }
+
+    /// Delete documents by id; ids that don't exist are ignored.
+    fn delete<I: Into<Id>>(&self, ids: impl IntoIterator<Item = I>) -> BoxFuture<'_, Result<()>> {
+        let ids: Vec<Id> = ids.into_iter().map(Into::into).collect();
+        Box::pin(async move {
+            if ids.is_empty() {
+                return Ok(());
+            }
+            let deletes = ids.iter().map(serde_json::to_value).collect::<serde_json::Result<_>>()?;
+            self.namespace().write(WriteParams { deletes: Some(deletes), ..Default::default() }).await?;
+            Ok(())
+        })
+    }
+
+    /// Fetch one document by id with [`Document::ATTRIBUTES`].
+    fn get(&self, id: impl Into<Id>) -> BoxFuture<'_, Result<Option<T>>> {
+        let id = id.into();
+        Box::pin(async move {
+            let params = QueryParams {
+                rank_by: Some(RankBy::asc("id")),
+                top_k: Some(1),
+                filters: Some(Filter::eq("id", serde_json::to_value(&id)?)),
+                ..Default::default()
+            };
+            Ok(self.namespace().query_documents(params).await?.pop())
+        })
+    }
+
+    /// The `top_k` documents nearest to `vector`, closest first, optionally
+    /// restricted by `filters`.
+    fn search(&self, vector: Vec<f32>, top_k: u64, filters: Option<Filter>) -> BoxFuture<'_, Result<Vec<T>>> {
+        Box::pin(async move {
+            let params = QueryParams {
+                rank_by: Some(RankBy::vector(self.vector_attribute(), vector)),
+                top_k: Some(top_k),
+                filters,
+                ..Default::default()
+            };
+            self.namespace().query_documents(params).await
+        })
+    }
+}
+
+fn to_row<T: Serialize>(document: &T) -> Result<Row> {
+    match serde_json::to_value(document)? {
+        serde_json::Value::Object(row) if row.contains_key("id") => Ok(row.into_iter().collect()),
+        _ => Err(Error::InvalidParams(format!(
+            "{} must serialize to an object with an id",
+            std::any::type_name::<T>()
+        ))),
+    }
+}
+
+/// [`VectorRepository`] over a namespace, with its defaults unless changed
+/// by the `with_*` methods.
+///
+/// ```no_run
+/// # async fn example(client: rs_puff::Client) -> rs_puff::Result<()> {
+/// # #[derive(serde::Serialize, serde::Deserialize)]
+/// # struct Article { id: u64, title: String, vector: Vec<f32> }
+/// # impl rs_puff::Document for Article { const ATTRIBUTES: &'static [&'static str] = &["id", "title"]; }
+/// use rs_puff::{NamespaceRepository, VectorRepository};
+///
+/// let articles = NamespaceRepository::<Article>::new(client.namespace("articles"));
+/// articles.save(&[Article { id: 1, title: "Hello".into(), vector: vec![0.1, 0.2] }]).await?;
+/// let nearest = articles.search(vec![0.1, 0.2], 10, None).await?;
+/// articles.delete([1]).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct NamespaceRepository<T> {
+    namespace: Namespace,
+    vector_attribute: String,
+    distance_metric: Option<DistanceMetric>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> NamespaceRepository<T> {
+    pub fn new(namespace: Namespace) -> Self {
+        Self {
+            namespace,
+            vector_attribute: RAG_VECTOR_ATTRIBUTE.to_string(),
+            distance_metric: Some(DistanceMetric::CosineDistance),
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn with_vector_attribute(mut self, attr: impl Into<String>) -> Self {
+        self.vector_attribute = attr.into();
+        self
+    }
+
+    pub fn with_distance_metric(mut self, metric: DistanceMetric) -> Self {
+        self.distance_metric = Some(metric);
+        self
+    }
+}
+
+impl<T> VectorRepository<T> for NamespaceRepository<T>
+where
+    T: Document + Serialize + Send + Sync,
+{
+    fn namespace(&self) -> &Namespace {
+        &self.namespace
+    }
+
+    fn vector_attribute(&self) -> &str {
+        &self.vector_attribute
+    }
+
+    fn distance_metric(&self) -> Option<DistanceMetric> {
+        self.distance_metric
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize)]
+    struct Note {
+        id: u64,
+        text: &'static str,
+    }
+
+    #[test]
+    fn test_to_row_requires_an_id() {
+        let row = to_row(&Note { id: 7, text: "hi" }).unwrap();
+        assert_eq!(row["id"], 7);
+        assert_eq!(row["text"], "hi");
+        assert!(to_row(&"not an object").is_err());
+        assert!(to_row(&serde_json::json!({"text": "no id"})).is_err());
+    }
+}
@@ -1,76 +1,139 @@
-use serde::Deserialize;
+//! Response types.
+//!
+//! Structs are `#[non_exhaustive]` and collect any field this crate doesn't
+//! model yet into `extra`, so new server fields are readable before a release
+//! adds them.
+
+use serde::de::{self, MapAccess};
+use serde::{Deserialize, Deserializer};
 use std::collections::HashMap;
+use std::fmt;
+use std::marker::PhantomData;
 
-use crate::Row;
+use crate::{AttrValue, Result, Row, Schema, from_row};
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone)]
+#[non_exhaustive]
 pub struct WriteResponse {
     pub rows_affected: u64,
-
-    #[serde(default)]
     pub rows_upserted: Option<u64>,
-
-    #[serde(default)]
     pub rows_patched: Option<u64>,
-
-    #[serde(default)]
     pub rows_deleted: Option<u64>,
-
-    #[serde(default)]
     pub rows_remaining: Option<bool>,
-
-    #[serde(default)]
     pub upserted_ids: Option<Vec<serde_json::Value>>,
-
-    #[serde(default)]
     pub patched_ids: Option<Vec<serde_json::Value>>,
-
-    #[serde(default)]
     pub deleted_ids: Option<Vec<serde_json::Value>>,
-
-    #[serde(default)]
     pub billing: Option<WriteBilling>,
+    pub extra: HashMap<String, serde_json::Value>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
 pub struct WriteBilling {
     pub billable_logical_bytes_written: u64,
 
     #[serde(default)]
     pub query: Option<QueryBillingInfo>,
+
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
 pub struct QueryBillingInfo {
     pub billable_logical_bytes_queried: u64,
     pub billable_logical_bytes_returned: u64,
+
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+/// A query response.
+///
+/// Rows are [`Row`]s by default. Any deserializable row type can be used
+/// instead, e.g. a map over a faster or smaller value type, or your own
+/// struct; see [`Namespace::query_rows`](crate::Namespace::query_rows).
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct QueryResponse<R = Row> {
+    pub rows: Vec<R>,
+    pub aggregations: Option<HashMap<String, serde_json::Value>>,
+    pub aggregation_groups: Option<Vec<AggregationGroup>>,
+    pub billing: Option<QueryBilling>,
+    pub performance: Option<QueryPerformance>,
+
+    /// Rows matching the query's filters, regardless of `top_k`. Only set by
+    /// [`Namespace::query_with_total`](crate::Namespace::query_with_total).
+    pub total_matches: Option<u64>,
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+impl QueryResponse {
+    /// Deserialize every row into `T`; see [`from_row`].
+    pub fn rows_as<T: serde::de::DeserializeOwned>(&self) -> Result<Vec<T>> {
+        self.rows.iter().map(from_row).collect()
+    }
+
+    /// Groups returned for a `group_by` query; empty otherwise.
+    pub fn groups(&self) -> &[AggregationGroup] {
+        self.aggregation_groups.as_deref().unwrap_or_default()
+    }
 }
 
+impl MultiQueryResponse {
+    /// Performance reported for subquery `index`, if the server broke it out.
+    pub fn subquery_performance(&self, index: usize) -> Option<&QueryPerformance> {
+        self.results.get(index)?.performance.as_ref()
+    }
+}
+
+/// One group of a `group_by` query: the group-by attribute values and the
+/// aggregations computed for the group, keyed by name.
 #[derive(Debug, Clone, Deserialize)]
-pub struct QueryResponse {
-    #[serde(default)]
-    pub rows: Vec<Row>,
+#[serde(transparent)]
+pub struct AggregationGroup(pub HashMap<String, serde_json::Value>);
 
-    #[serde(default)]
-    pub aggregations: Option<HashMap<String, serde_json::Value>>,
+impl AggregationGroup {
+    pub fn key(&self, attr: &str) -> Option<AttrValue> {
+        self.0.get(attr).and_then(AttrValue::from_json)
+    }
 
-    #[serde(default)]
-    pub aggregation_groups: Option<Vec<HashMap<String, serde_json::Value>>>,
+    pub fn key_str(&self, attr: &str) -> Option<&str> {
+        self.0.get(attr).and_then(|v| v.as_str())
+    }
 
-    #[serde(default)]
-    pub billing: Option<QueryBilling>,
+    pub fn key_i64(&self, attr: &str) -> Option<i64> {
+        self.0.get(attr).and_then(|v| v.as_i64())
+    }
 
-    #[serde(default)]
-    pub performance: Option<QueryPerformance>,
+    pub fn key_bool(&self, attr: &str) -> Option<bool> {
+        self.0.get(attr).and_then(|v| v.as_bool())
+    }
+
+    /// Value of a numeric aggregation such as `Sum`.
+    pub fn aggregation(&self, name: &str) -> Option<f64> {
+        self.0.get(name).and_then(|v| v.as_f64())
+    }
+
+    /// Value of a `Count` aggregation.
+    pub fn count(&self, name: &str) -> Option<u64> {
+        self.0.get(name).and_then(|v| v.as_u64())
+    }
 }
 
 #[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
 pub struct QueryBilling {
     pub billable_logical_bytes_queried: u64,
     pub billable_logical_bytes_returned: u64,
+
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
 pub struct QueryPerformance {
     #[serde(default)]
     pub cache_hit_ratio: Option<f64>,
@@ -89,19 +152,44 @@ pub struct QueryPerformance {
 
     #[serde(default)]
     pub approx_namespace_size: Option<u64>,
+
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone)]
+#[non_exhaustive]
 pub struct MultiQueryResponse {
     pub results: Vec<QueryResponse>,
+
+    /// Billing for the request as a whole.
+    pub billing: Option<QueryBilling>,
+
+    /// Performance of the request as a whole; see
+    /// [`subquery_performance`](Self::subquery_performance) for each subquery.
+    pub performance: Option<QueryPerformance>,
+    pub extra: HashMap<String, serde_json::Value>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
 pub struct DeleteAllResponse {
     pub status: String,
+
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+/// Outcome of [`Namespace::delete_all_if_exists`](crate::Namespace::delete_all_if_exists).
+#[derive(Debug, Clone)]
+pub enum DeleteAllStatus {
+    Deleted(DeleteAllResponse),
+    /// The namespace didn't exist, e.g. because it was never written to.
+    AlreadyAbsent,
 }
 
 #[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
 pub struct NamespaceMetadata {
     #[serde(default)]
     pub created_at: Option<String>,
@@ -123,45 +211,228 @@ pub struct NamespaceMetadata {
 
     #[serde(default)]
     pub schema: Option<HashMap<String, serde_json::Value>>,
+
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
 pub struct NamespaceEncryption {
     #[serde(default)]
     pub sse: Option<bool>,
 
     #[serde(default)]
     pub cmek: Option<serde_json::Value>,
+
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
 pub struct NamespaceIndex {
     #[serde(default)]
     pub status: Option<String>,
+
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct SchemaResponse(pub HashMap<String, serde_json::Value>);
 
+impl SchemaResponse {
+    /// Parse into the typed [`Schema`] model; attributes it can't represent stay raw.
+    pub fn to_schema(&self) -> Schema {
+        Schema::from_map(self.0.clone())
+    }
+}
+
 #[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
 pub struct HintCacheWarmResponse {
     pub status: String,
 
     #[serde(default)]
     pub message: Option<String>,
+
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+impl HintCacheWarmResponse {
+    /// Whether the cache was already warm (`OK`) rather than starting to warm (`ACCEPTED`).
+    pub fn is_already_warm(&self) -> bool {
+        self.status == "OK"
+    }
 }
 
 #[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
 pub struct NamespaceSummary {
     pub id: String,
+
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
 pub struct NamespacesResponse {
     pub namespaces: Vec<NamespaceSummary>,
 
     #[serde(default)]
     pub next_cursor: Option<String>,
+
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+// Responses that carry rows are read field by field: `#[serde(flatten)]`
+// would buffer the whole body, rows and all, before building any field.
+
+/// Read each entry of `map` with `field`, which returns false for keys it
+/// doesn't know; those are collected into `extra`.
+fn visit_fields<'de, A: MapAccess<'de>>(
+    mut map: A,
+    extra: &mut HashMap<String, serde_json::Value>,
+    mut field: impl FnMut(&str, &mut A) -> std::result::Result<bool, A::Error>,
+) -> std::result::Result<(), A::Error> {
+    while let Some(key) = map.next_key::<String>()? {
+        if !field(&key, &mut map)? {
+            extra.insert(key, map.next_value()?);
+        }
+    }
+    Ok(())
+}
+
+impl<'de> Deserialize<'de> for WriteResponse {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        struct Visitor;
+
+        impl<'de> de::Visitor<'de> for Visitor {
+            type Value = WriteResponse;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a write response")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, map: A) -> std::result::Result<WriteResponse, A::Error> {
+                let mut rows_affected = None;
+                let mut resp = WriteResponse {
+                    rows_affected: 0,
+                    rows_upserted: None,
+                    rows_patched: None,
+                    rows_deleted: None,
+                    rows_remaining: None,
+                    upserted_ids: None,
+                    patched_ids: None,
+                    deleted_ids: None,
+                    billing: None,
+                    extra: HashMap::new(),
+                };
+                let mut extra = HashMap::new();
+                visit_fields(map, &mut extra, |key, map| {
+                    match key {
+                        "rows_affected" => rows_affected = Some(map.next_value()?),
+                        "rows_upserted" => resp.rows_upserted = map.next_value()?,
+                        "rows_patched" => resp.rows_patched = map.next_value()?,
+                        "rows_deleted" => resp.rows_deleted = map.next_value()?,
+                        "rows_remaining" => resp.rows_remaining = map.next_value()?,
+                        "upserted_ids" => resp.upserted_ids = map.next_value()?,
+                        "patched_ids" => resp.patched_ids = map.next_value()?,
+                        "deleted_ids" => resp.deleted_ids = map.next_value()?,
+                        "billing" => resp.billing = map.next_value()?,
+                        _ => return Ok(false),
+                    }
+                    Ok(true)
+                })?;
+                resp.rows_affected = rows_affected.ok_or_else(|| de::Error::missing_field("rows_affected"))?;
+                resp.extra = extra;
+                Ok(resp)
+            }
+        }
+
+        deserializer.deserialize_map(Visitor)
+    }
+}
+
+impl<'de, R: Deserialize<'de>> Deserialize<'de> for QueryResponse<R> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        struct Visitor<R>(PhantomData<R>);
+
+        impl<'de, R: Deserialize<'de>> de::Visitor<'de> for Visitor<R> {
+            type Value = QueryResponse<R>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a query response")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, map: A) -> std::result::Result<QueryResponse<R>, A::Error> {
+                let mut resp = QueryResponse {
+                    rows: Vec::new(),
+                    aggregations: None,
+                    aggregation_groups: None,
+                    billing: None,
+                    performance: None,
+                    total_matches: None,
+                    extra: HashMap::new(),
+                };
+                let mut extra = HashMap::new();
+                visit_fields(map, &mut extra, |key, map| {
+                    match key {
+                        "rows" => resp.rows = map.next_value()?,
+                        "aggregations" => resp.aggregations = map.next_value()?,
+                        "aggregation_groups" => resp.aggregation_groups = map.next_value()?,
+                        "billing" => resp.billing = map.next_value()?,
+                        "performance" => resp.performance = map.next_value()?,
+                        _ => return Ok(false),
+                    }
+                    Ok(true)
+                })?;
+                resp.extra = extra;
+                Ok(resp)
+            }
+        }
+
+        deserializer.deserialize_map(Visitor(PhantomData))
+    }
+}
+
+impl<'de> Deserialize<'de> for MultiQueryResponse {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        struct Visitor;
+
+        impl<'de> de::Visitor<'de> for Visitor {
+            type Value = MultiQueryResponse;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a multi-query response")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, map: A) -> std::result::Result<MultiQueryResponse, A::Error> {
+                let mut results = None;
+                let mut resp =
+                    MultiQueryResponse { results: Vec::new(), billing: None, performance: None, extra: HashMap::new() };
+                let mut extra = HashMap::new();
+                visit_fields(map, &mut extra, |key, map| {
+                    match key {
+                        "results" => results = Some(map.next_value()?),
+                        "billing" => resp.billing = map.next_value()?,
+                        "performance" => resp.performance = map.next_value()?,
+                        _ => return Ok(false),
+                    }
+                    Ok(true)
+                })?;
+                resp.results = results.ok_or_else(|| de::Error::missing_field("results"))?;
+                resp.extra = extra;
+                Ok(resp)
+            }
+        }
+
+        deserializer.deserialize_map(Visitor)
+    }
 }
 
 #[cfg(test)]
@@ -176,6 +447,23 @@ mod tests {
         assert!(resp.rows_upserted.is_none());
     }
 
+    #[test]
+    fn test_unknown_fields_are_kept_in_extra() {
+        let json = r#"{"rows_affected": 1, "new_server_field": {"a": 1}}"#;
+        let resp: WriteResponse = serde_json::from_str(json).unwrap();
+        assert_eq!(resp.extra["new_server_field"]["a"], 1);
+        assert!(!resp.extra.contains_key("rows_affected"));
+
+        let json = r#"{"rows": [{"id": 1}], "next": true, "performance": {"server_total_ms": 3, "new": 1}}"#;
+        let resp: QueryResponse = serde_json::from_str(json).unwrap();
+        assert_eq!((resp.rows.len(), &resp.extra["next"]), (1, &serde_json::json!(true)));
+        assert_eq!(resp.performance.unwrap().extra["new"], 1);
+        let resp: MultiQueryResponse = serde_json::from_str(r#"{"results": [{}], "next": 1}"#).unwrap();
+        assert_eq!((resp.results.len(), resp.extra.len()), (1, 1));
+        assert!(serde_json::from_str::<WriteResponse>("{}").is_err());
+        assert!(serde_json::from_str::<MultiQueryResponse>("{}").is_err());
+    }
+
     #[test]
     fn test_write_response_full() {
         let json = r#"{
@@ -221,6 +509,27 @@ mod tests {
         assert_eq!(aggs.get("count").unwrap(), 42);
     }
 
+    #[test]
+    fn test_query_response_with_groups() {
+        let json = r#"{
+            "rows": [],
+            "aggregation_groups": [
+                {"category": "books", "in_stock": true, "count": 3, "total": 41.5},
+                {"category": "games", "in_stock": false, "count": 1, "total": 20}
+            ]
+        }"#;
+        let resp: QueryResponse = serde_json::from_str(json).unwrap();
+        let groups = resp.groups();
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].key_str("category"), Some("books"));
+        assert_eq!(groups[0].key_bool("in_stock"), Some(true));
+        assert_eq!(groups[0].count("count"), Some(3));
+        assert_eq!(groups[0].aggregation("total"), Some(41.5));
+        assert_eq!(groups[1].key("category"), Some(AttrValue::from("games")));
+        assert_eq!(groups[1].key("total"), Some(AttrValue::Int(20)));
+        assert!(groups[1].key("missing").is_none());
+    }
+
     #[test]
     fn test_query_response_with_performance() {
         let json = r#"{
@@ -290,5 +599,39 @@ mod tests {
         assert_eq!(resp.results.len(), 2);
         assert_eq!(resp.results[0].rows.len(), 1);
         assert_eq!(resp.results[1].rows.len(), 2);
+        assert!(resp.performance.is_none());
+    }
+
+    #[test]
+    fn test_multi_query_response_performance() {
+        let json = r#"{
+            "results": [
+                {"rows": [], "performance": {"query_execution_ms": 4}},
+                {"rows": []}
+            ],
+            "performance": {"server_total_ms": 12},
+            "billing": {"billable_logical_bytes_queried": 100, "billable_logical_bytes_returned": 10}
+        }"#;
+        let resp: MultiQueryResponse = serde_json::from_str(json).unwrap();
+        assert_eq!(resp.performance.as_ref().unwrap().server_total_ms, Some(12));
+        assert_eq!(resp.billing.as_ref().unwrap().billable_logical_bytes_queried, 100);
+        assert_eq!(resp.subquery_performance(0).unwrap().query_execution_ms, Some(4));
+        assert!(resp.subquery_performance(1).is_none());
+        assert!(resp.subquery_performance(2).is_none());
+    }
+
+    #[test]
+    fn test_query_response_with_custom_row_type() {
+        let json = r#"{"rows": [{"id": 1, "title": "a"}, {"id": 2, "title": null}], "billing": null}"#;
+        let resp: QueryResponse<Row<AttrValue>> = serde_json::from_str(json).unwrap();
+        assert_eq!(resp.rows[0]["title"], AttrValue::from("a"));
+        assert!(resp.rows[1]["title"].is_null());
+
+        #[derive(serde::Deserialize)]
+        struct Doc {
+            id: u64,
+        }
+        let resp: QueryResponse<Doc> = serde_json::from_str(json).unwrap();
+        assert_eq!(resp.rows.iter().map(|d| d.id).collect::<Vec<_>>(), [1, 2]);
     }
 }
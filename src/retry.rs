@@ -0,0 +1,185 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+use crate::rt::{SystemTime, UNIX_EPOCH};
+
+/// How failed requests are retried.
+///
+/// Transient failures (see [`Error::is_retryable`](crate::Error::is_retryable))
+/// are retried with exponential backoff; other errors are returned
+/// immediately. The default retries up to 3 times.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt; `0` disables retrying.
+    pub max_retries: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// Randomize each delay to between half and all of its backoff, so
+    /// clients that failed together don't retry in lockstep.
+    pub jitter: bool,
+    /// Longest `Retry-After` on a 429 that is waited out. A rate-limited
+    /// request asking for a longer wait fails with
+    /// [`Error::RateLimited`](crate::Error::RateLimited) instead.
+    pub max_retry_after: Duration,
+}
+
+impl RetryPolicy {
+    pub fn new(max_retries: u32) -> Self {
+        Self {
+            max_retries,
+            initial_backoff: Duration::from_millis(200),
+            max_backoff: Duration::from_secs(10),
+            jitter: true,
+            max_retry_after: Duration::from_secs(30),
+        }
+    }
+
+    pub fn none() -> Self {
+        Self::new(0)
+    }
+
+    pub fn initial_backoff(mut self, backoff: Duration) -> Self {
+        self.initial_backoff = backoff;
+        self
+    }
+
+    pub fn max_backoff(mut self, backoff: Duration) -> Self {
+        self.max_backoff = backoff;
+        self
+    }
+
+    pub fn jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    pub fn max_retry_after(mut self, max: Duration) -> Self {
+        self.max_retry_after = max;
+        self
+    }
+
+    /// Delay before retrying after `error`: the server's `Retry-After` if
+    /// it sent one, otherwise [`backoff`](Self::backoff). `None` if the
+    /// server asked for a longer wait than `max_retry_after`.
+    pub(crate) fn delay(&self, retry: u32, error: &crate::Error) -> Option<Duration> {
+        match error {
+            crate::Error::RateLimited(limit) => match limit.retry_after {
+                Some(wait) if wait > self.max_retry_after => None,
+                Some(wait) => Some(wait),
+                None => Some(self.backoff(retry)),
+            },
+            _ => Some(self.backoff(retry)),
+        }
+    }
+
+    /// Delay before retry number `retry` (starting at 0).
+    pub(crate) fn backoff(&self, retry: u32) -> Duration {
+        let factor = 2u32.saturating_pow(retry);
+        let backoff = self.initial_backoff.saturating_mul(factor).min(self.max_backoff);
+        if self.jitter {
+            backoff.mul_f64(0.5 + random_fraction() / 2.0)
+        } else {
+            backoff
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::new(3)
+    }
+}
+
+/// Parse a `Retry-After` header: either seconds or an HTTP date.
+pub(crate) fn parse_retry_after(value: &str, now: SystemTime) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let at = UNIX_EPOCH + Duration::from_secs(parse_http_date(value)?);
+    Some(at.duration_since(now).unwrap_or_default())
+}
+
+/// Seconds since the Unix epoch for an IMF-fixdate, e.g.
+/// `Wed, 21 Oct 2015 07:28:00 GMT`.
+fn parse_http_date(value: &str) -> Option<u64> {
+    let parts: Vec<&str> = value.split_whitespace().collect();
+    let [_, day, month, year, time, "GMT"] = parts[..] else {
+        return None;
+    };
+    const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+    let month = MONTHS.iter().position(|m| *m == month)? as i64 + 1;
+    let (day, year): (i64, i64) = (day.parse().ok()?, year.parse().ok()?);
+    let mut hms = time.splitn(3, ':').map(|n| n.parse::<i64>().ok());
+    let (h, m, s) = (hms.next()??, hms.next()??, hms.next()??);
+
+    // Days from civil, after Howard Hinnant's algorithm.
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+    u64::try_from(days * 86_400 + h * 3600 + m * 60 + s).ok()
+}
+
+/// Uniform in `[0, 1)`; `RandomState` is randomly seeded per instance.
+fn random_fraction() -> f64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u8(0);
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_doubles_up_to_max() {
+        let policy = RetryPolicy::new(5)
+            .initial_backoff(Duration::from_millis(100))
+            .max_backoff(Duration::from_millis(350))
+            .jitter(false);
+        assert_eq!(policy.backoff(0), Duration::from_millis(100));
+        assert_eq!(policy.backoff(1), Duration::from_millis(200));
+        assert_eq!(policy.backoff(2), Duration::from_millis(350));
+        assert_eq!(policy.backoff(40), Duration::from_millis(350));
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        let now = UNIX_EPOCH + Duration::from_secs(1_445_412_470);
+        assert_eq!(parse_retry_after(" 120 ", now), Some(Duration::from_secs(120)));
+        // 1445412480 is 2015-10-21 07:28:00 UTC.
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT", now), Some(Duration::from_secs(10)));
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:27:00 GMT", now), Some(Duration::ZERO));
+        assert_eq!(parse_retry_after("soon", now), None);
+        assert_eq!(parse_http_date("Thu, 01 Jan 1970 00:00:00 GMT"), Some(0));
+        assert_eq!(parse_http_date("Tue, 29 Feb 2000 12:00:00 GMT"), Some(951_825_600));
+    }
+
+    #[test]
+    fn test_delay_honors_retry_after_up_to_max() {
+        let policy = RetryPolicy::new(3).jitter(false).max_retry_after(Duration::from_secs(10));
+        let limited = |secs: Option<u64>| {
+            crate::Error::RateLimited(crate::RateLimit {
+                retry_after: secs.map(Duration::from_secs),
+                message: String::new(),
+            })
+        };
+        assert_eq!(policy.delay(0, &limited(Some(4))), Some(Duration::from_secs(4)));
+        assert_eq!(policy.delay(0, &limited(Some(60))), None);
+        assert_eq!(policy.delay(1, &limited(None)), Some(Duration::from_millis(400)));
+    }
+
+    #[test]
+    fn test_jitter_stays_within_half_to_full_backoff() {
+        let policy = RetryPolicy::new(5).initial_backoff(Duration::from_millis(100));
+        for retry in 0..4 {
+            let full = Duration::from_millis(100 << retry);
+            let delay = policy.backoff(retry);
+            assert!(delay >= full / 2 && delay <= full, "{delay:?} outside {full:?}");
+        }
+    }
+}
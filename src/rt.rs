@@ -0,0 +1,106 @@
+//! Timers, clocks and task spawning: tokio and `std::time` natively, the
+//! JavaScript event loop on `wasm32`, where there is no tokio runtime and
+//! `std::time::Instant::now` panics.
+
+#[cfg(feature = "client")]
+use std::future::Future;
+#[cfg(feature = "client")]
+use std::pin::Pin;
+#[cfg(feature = "client")]
+use std::time::Duration;
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg_attr(not(feature = "client"), allow(unused_imports))]
+pub(crate) use std::time::{Instant, SystemTime, UNIX_EPOCH};
+#[cfg(target_arch = "wasm32")]
+#[cfg_attr(not(feature = "client"), allow(unused_imports))]
+pub(crate) use web_time::{Instant, SystemTime, UNIX_EPOCH};
+
+// Everything below runs futures, so it needs the client's runtime.
+
+/// A boxed future that is `Send` except on `wasm32`, where HTTP requests are
+/// JavaScript promises tied to the thread that made them.
+#[cfg(all(feature = "client", not(target_arch = "wasm32")))]
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+#[cfg(all(feature = "client", target_arch = "wasm32"))]
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + 'a>>;
+
+/// The future passed to [`timeout`] didn't finish in time.
+#[cfg(feature = "client")]
+#[derive(Debug)]
+pub(crate) struct Elapsed;
+
+#[cfg(all(feature = "client", not(target_arch = "wasm32")))]
+pub(crate) async fn sleep(duration: Duration) {
+    tokio::time::sleep(duration).await
+}
+
+#[cfg(all(feature = "client", target_arch = "wasm32"))]
+pub(crate) async fn sleep(duration: Duration) {
+    use wasm_bindgen::prelude::*;
+
+    // `setTimeout` is a global in browsers, workers and Cloudflare Workers
+    // alike, unlike `window.setTimeout`.
+    #[wasm_bindgen]
+    extern "C" {
+        #[wasm_bindgen(js_name = setTimeout)]
+        fn set_timeout(callback: &js_sys::Function, millis: f64) -> JsValue;
+    }
+
+    let millis = duration.as_secs_f64() * 1000.0;
+    let promise = js_sys::Promise::new(&mut |resolve, _reject| {
+        set_timeout(&resolve, millis);
+    });
+    let _ = wasm_bindgen_futures::JsFuture::from(promise).await;
+}
+
+#[cfg(feature = "client")]
+pub(crate) async fn timeout<F: Future>(duration: Duration, future: F) -> Result<F::Output, Elapsed> {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        tokio::time::timeout(duration, future).await.map_err(|_| Elapsed)
+    }
+    #[cfg(target_arch = "wasm32")]
+    {
+        use futures_util::future::{Either, select};
+
+        let future = std::pin::pin!(future);
+        let timer = std::pin::pin!(sleep(duration));
+        match select(future, timer).await {
+            Either::Left((output, _)) => Ok(output),
+            Either::Right(_) => Err(Elapsed),
+        }
+    }
+}
+
+/// Like [`timeout`], until `deadline`.
+#[cfg(feature = "client")]
+pub(crate) async fn timeout_at<F: Future>(deadline: Instant, future: F) -> Result<F::Output, Elapsed> {
+    timeout(deadline.saturating_duration_since(Instant::now()), future).await
+}
+
+/// Run `future` in the background if there is a runtime to run it on:
+/// the current tokio runtime natively, the JavaScript event loop on `wasm32`.
+#[cfg(all(feature = "client", not(target_arch = "wasm32")))]
+pub(crate) fn spawn_detached(future: impl Future<Output = ()> + Send + 'static) {
+    if let Ok(handle) = tokio::runtime::Handle::try_current() {
+        handle.spawn(future);
+    }
+}
+
+#[cfg(all(feature = "client", target_arch = "wasm32"))]
+pub(crate) fn spawn_detached(future: impl Future<Output = ()> + 'static) {
+    wasm_bindgen_futures::spawn_local(future);
+}
+
+/// Run blocking work such as file I/O off the executor: on tokio's blocking
+/// pool natively, inline on `wasm32`, which has no threads to move it to.
+#[cfg(all(feature = "client", not(target_arch = "wasm32")))]
+pub(crate) async fn blocking<T: Send + 'static>(f: impl FnOnce() -> T + Send + 'static) -> crate::Result<T> {
+    tokio::task::spawn_blocking(f).await.map_err(|e| crate::Error::External(Box::new(e)))
+}
+
+#[cfg(all(feature = "client", target_arch = "wasm32"))]
+pub(crate) async fn blocking<T>(f: impl FnOnce() -> T) -> crate::Result<T> {
+    Ok(f())
+}
@@ -0,0 +1,345 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Error, Result};
+
+/// Attribute value types, e.g. `string`, `[]uint` or `[1536]f32`.
+///
+/// Vector types are easiest built with [`AttributeType::vector`] and the
+/// `vector_*` helpers rather than written out as strings:
+///
+/// ```
+/// use rs_puff::{AttributeType, VectorDtype};
+///
+/// assert_eq!(AttributeType::vector_f16(768).to_string(), "[768]f16");
+/// assert_eq!("[8]u8".parse(), Ok(AttributeType::vector(8, VectorDtype::U8)));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum AttributeType {
+    String,
+    Int,
+    Uint,
+    Float,
+    Uuid,
+    Bool,
+    Datetime,
+    Array(Box<AttributeType>),
+    Vector { dims: usize, dtype: VectorDtype },
+}
+
+/// Element type of a vector attribute.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum VectorDtype {
+    #[default]
+    F32,
+    /// Half precision; see [`quantize::to_f16`](crate::quantize::to_f16).
+    F16,
+    U8,
+}
+
+impl VectorDtype {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            VectorDtype::F32 => "f32",
+            VectorDtype::F16 => "f16",
+            VectorDtype::U8 => "u8",
+        }
+    }
+}
+
+impl fmt::Display for VectorDtype {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for VectorDtype {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "f32" => Ok(VectorDtype::F32),
+            "f16" => Ok(VectorDtype::F16),
+            "u8" => Ok(VectorDtype::U8),
+            _ => Err(format!("unknown vector element type {:?}", s)),
+        }
+    }
+}
+
+impl AttributeType {
+    /// `[dims]<dtype>`.
+    pub fn vector(dims: usize, dtype: VectorDtype) -> Self {
+        AttributeType::Vector { dims, dtype }
+    }
+
+    /// `[dims]f32`.
+    pub fn vector_f32(dims: usize) -> Self {
+        Self::vector(dims, VectorDtype::F32)
+    }
+
+    /// `[dims]f16`.
+    pub fn vector_f16(dims: usize) -> Self {
+        Self::vector(dims, VectorDtype::F16)
+    }
+
+    /// `[dims]u8`.
+    pub fn vector_u8(dims: usize) -> Self {
+        Self::vector(dims, VectorDtype::U8)
+    }
+}
+
+impl fmt::Display for AttributeType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AttributeType::String => f.write_str("string"),
+            AttributeType::Int => f.write_str("int"),
+            AttributeType::Uint => f.write_str("uint"),
+            AttributeType::Float => f.write_str("float"),
+            AttributeType::Uuid => f.write_str("uuid"),
+            AttributeType::Bool => f.write_str("bool"),
+            AttributeType::Datetime => f.write_str("datetime"),
+            AttributeType::Array(inner) => write!(f, "[]{}", inner),
+            AttributeType::Vector { dims, dtype } => write!(f, "[{}]{}", dims, dtype),
+        }
+    }
+}
+
+impl FromStr for AttributeType {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Ok(match s {
+            "string" => AttributeType::String,
+            "int" => AttributeType::Int,
+            "uint" => AttributeType::Uint,
+            "float" => AttributeType::Float,
+            "uuid" => AttributeType::Uuid,
+            "bool" => AttributeType::Bool,
+            "datetime" => AttributeType::Datetime,
+            _ => {
+                if let Some(inner) = s.strip_prefix("[]") {
+                    AttributeType::Array(Box::new(inner.parse()?))
+                } else if let Some((dims, elem)) = s.strip_prefix('[').and_then(|rest| rest.split_once(']'))
+                    && let Ok(dims) = dims.parse()
+                    && let Ok(dtype) = elem.parse()
+                {
+                    AttributeType::Vector { dims, dtype }
+                } else {
+                    return Err(format!("unknown attribute type {:?}", s));
+                }
+            }
+        })
+    }
+}
+
+impl TryFrom<String> for AttributeType {
+    type Error = String;
+
+    fn try_from(s: String) -> std::result::Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<AttributeType> for String {
+    fn from(t: AttributeType) -> Self {
+        t.to_string()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum FullTextSearch {
+    Enabled(bool),
+    Config(FullTextSearchConfig),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FullTextSearchConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stemming: Option<bool>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remove_stopwords: Option<bool>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub case_sensitive: Option<bool>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tokenizer: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AttributeSchemaConfig {
+    #[serde(rename = "type")]
+    pub attribute_type: AttributeType,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filterable: Option<bool>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub full_text_search: Option<FullTextSearch>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub regex: Option<bool>,
+}
+
+/// Schema for one attribute.
+///
+/// Anything the typed model doesn't cover, such as options newer than this
+/// crate, round-trips through [`AttributeSchema::Raw`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum AttributeSchema {
+    Config(AttributeSchemaConfig),
+    Raw(serde_json::Value),
+}
+
+impl AttributeSchema {
+    pub fn new(attribute_type: AttributeType) -> Self {
+        AttributeSchema::Config(AttributeSchemaConfig {
+            attribute_type,
+            filterable: None,
+            full_text_search: None,
+            regex: None,
+        })
+    }
+
+    /// Send `value` as-is.
+    pub fn raw(value: serde_json::Value) -> Self {
+        AttributeSchema::Raw(value)
+    }
+
+    /// Set `filterable`; has no effect on raw schemas.
+    pub fn filterable(mut self, filterable: bool) -> Self {
+        if let AttributeSchema::Config(config) = &mut self {
+            config.filterable = Some(filterable);
+        }
+        self
+    }
+
+    /// Set `full_text_search`; has no effect on raw schemas.
+    pub fn full_text_search(mut self, fts: FullTextSearch) -> Self {
+        if let AttributeSchema::Config(config) = &mut self {
+            config.full_text_search = Some(fts);
+        }
+        self
+    }
+}
+
+/// A namespace schema: attribute name to [`AttributeSchema`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Schema(pub HashMap<String, AttributeSchema>);
+
+impl Schema {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Build from a JSON object, keeping every attribute raw.
+    pub fn raw(value: serde_json::Value) -> Result<Self> {
+        match value {
+            serde_json::Value::Object(map) => Ok(Schema(
+                map.into_iter().map(|(name, v)| (name, AttributeSchema::Raw(v))).collect(),
+            )),
+            other => Err(Error::InvalidParams(format!("schema must be a JSON object, got {}", other))),
+        }
+    }
+
+    pub fn attribute(mut self, name: impl Into<String>, schema: AttributeSchema) -> Self {
+        self.0.insert(name.into(), schema);
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<&AttributeSchema> {
+        self.0.get(name)
+    }
+
+    /// The JSON form used by `WriteParams::schema`.
+    pub fn into_map(self) -> HashMap<String, serde_json::Value> {
+        self.0
+            .into_iter()
+            .map(|(name, schema)| {
+                let value = serde_json::to_value(schema).expect("attribute schemas always serialize");
+                (name, value)
+            })
+            .collect()
+    }
+
+    /// Parse a schema returned by the API; unrecognized attributes stay raw.
+    pub fn from_map(map: HashMap<String, serde_json::Value>) -> Self {
+        Schema(
+            map.into_iter()
+                .map(|(name, value)| {
+                    let schema = serde_json::from_value(value.clone()).unwrap_or(AttributeSchema::Raw(value));
+                    (name, schema)
+                })
+                .collect(),
+        )
+    }
+}
+
+impl From<Schema> for HashMap<String, serde_json::Value> {
+    fn from(schema: Schema) -> Self {
+        schema.into_map()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_attribute_type_roundtrip() {
+        for s in ["string", "[]uint", "[1536]f32", "[768]f16", "[64]u8", "datetime"] {
+            assert_eq!(s.parse::<AttributeType>().unwrap().to_string(), s);
+        }
+        assert!("[12]i8".parse::<AttributeType>().is_err());
+        assert!("[]f16".parse::<AttributeType>().is_err());
+    }
+
+    #[test]
+    fn test_vector_helpers() {
+        assert_eq!(AttributeType::vector_f32(3).to_string(), "[3]f32");
+        assert_eq!(AttributeType::vector_f16(2).to_string(), "[2]f16");
+        assert_eq!(AttributeType::vector_u8(16).to_string(), "[16]u8");
+        assert_eq!(
+            serde_json::to_value(AttributeSchema::new(AttributeType::vector(4, VectorDtype::F16))).unwrap(),
+            serde_json::json!({"type": "[4]f16"})
+        );
+    }
+
+    #[test]
+    fn test_typed_and_raw_attributes_serialize() {
+        let schema = Schema::new()
+            .attribute("title", AttributeSchema::new(AttributeType::String).full_text_search(FullTextSearch::Enabled(true)))
+            .attribute("tags", AttributeSchema::raw(serde_json::json!({"type": "[]string", "brand_new_option": 1})));
+        let map = schema.into_map();
+        assert_eq!(map["title"], serde_json::json!({"type": "string", "full_text_search": true}));
+        assert_eq!(map["tags"]["brand_new_option"], 1);
+    }
+
+    #[test]
+    fn test_from_map_keeps_unknown_options_raw() {
+        let schema = Schema::from_map(
+            serde_json::from_value(serde_json::json!({
+                "id": {"type": "uint"},
+                "vec": {"type": "[3]f32", "ann": {"distance_metric": "cosine_distance"}},
+            }))
+            .unwrap(),
+        );
+        assert_eq!(schema.get("id"), Some(&AttributeSchema::new(AttributeType::Uint)));
+        assert!(matches!(schema.get("vec"), Some(AttributeSchema::Raw(_))));
+
+        assert!(Schema::raw(serde_json::json!([1])).is_err());
+    }
+}
@@ -0,0 +1,86 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::fanout::{FanoutResult, fanout};
+use crate::{Client, FanoutParams, Result, Schema, SchemaResponse};
+
+/// Schemas of several namespaces as one document, e.g. to keep in version
+/// control and provision another environment from.
+///
+/// [`to_json`](Self::to_json) and [`from_json`](Self::from_json) cover JSON;
+/// the type works with any serde format, so YAML or TOML only need the
+/// matching crate.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SchemaDocument {
+    pub namespaces: BTreeMap<String, Schema>,
+}
+
+impl SchemaDocument {
+    /// Pretty-printed JSON with keys sorted, so exports diff cleanly.
+    pub fn to_json(&self) -> String {
+        let value = serde_json::to_value(self).expect("schemas always serialize");
+        serde_json::to_string_pretty(&value).expect("JSON values always serialize")
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+}
+
+impl Client {
+    /// Fetch the schema of every namespace whose name starts with `prefix`.
+    ///
+    /// Fails if any schema can't be fetched.
+    pub async fn export_schemas(&self, prefix: &str, params: &FanoutParams) -> Result<SchemaDocument> {
+        let names = self.namespace_ids(prefix).await?;
+        let tasks = names
+            .iter()
+            .map(|name| {
+                let ns = self.namespace(name);
+                async move { ns.schema().await }
+            })
+            .collect();
+        let schemas = fanout(tasks, params).await.into_result()?;
+        Ok(SchemaDocument {
+            namespaces: names.into_iter().zip(schemas.iter().map(SchemaResponse::to_schema)).collect(),
+        })
+    }
+
+    /// Apply every schema in `document` to the namespace of the same name,
+    /// e.g. to provision a new environment.
+    ///
+    /// Results are in the document's (sorted) namespace order; one failure
+    /// doesn't stop the others.
+    pub async fn apply_schemas(&self, document: &SchemaDocument, params: &FanoutParams) -> FanoutResult<SchemaResponse> {
+        let tasks = document
+            .namespaces
+            .iter()
+            .map(|(name, schema)| {
+                let ns = self.namespace(name);
+                let schema = schema.clone();
+                async move { ns.update_schema(schema).await }
+            })
+            .collect();
+        fanout(tasks, params).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AttributeSchema, AttributeType};
+
+    #[test]
+    fn test_document_json_roundtrip() {
+        let document = SchemaDocument {
+            namespaces: BTreeMap::from([
+                ("b".to_string(), Schema::new().attribute("title", AttributeSchema::new(AttributeType::String))),
+                ("a".to_string(), Schema::raw(serde_json::json!({"vec": {"type": AttributeType::vector_f32(3), "ann": true}})).unwrap()),
+            ]),
+        };
+        let json = document.to_json();
+        assert!(json.find("\"a\"").unwrap() < json.find("\"b\"").unwrap());
+        assert_eq!(SchemaDocument::from_json(&json).unwrap(), document);
+    }
+}
@@ -0,0 +1,198 @@
+use std::collections::HashMap;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+use crate::rt::Instant;
+use crate::{
+    AttributeType, Consistency, ConsistencyLevel, Error, Filter, Id, Namespace, QueryParams, RankBy, Result, Row,
+    WriteParams,
+};
+
+const VECTOR_ATTRIBUTE: &str = "vector";
+
+// Largest `$dist` accepted for the sentinel's own vector; quantized
+// indexes don't return exactly zero.
+const MAX_SELF_DISTANCE: f64 = 1e-3;
+
+/// Result of [`Namespace::self_test`].
+#[derive(Debug, Clone)]
+pub struct SelfTestReport {
+    pub namespace: String,
+    /// Id of the sentinel row written and deleted by the test.
+    pub sentinel_id: Id,
+    /// Every step attempted, in order; steps after a failed write are skipped.
+    pub steps: Vec<SelfTestStep>,
+}
+
+impl SelfTestReport {
+    pub fn passed(&self) -> bool {
+        self.steps.iter().all(|step| step.error.is_none())
+    }
+
+    /// The first failed step.
+    pub fn failure(&self) -> Option<&SelfTestStep> {
+        self.steps.iter().find(|step| step.error.is_some())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SelfTestStep {
+    /// `write`, `vector_query`, `filter_query`, `delete` or `verify_deleted`.
+    pub name: &'static str,
+    pub latency: Duration,
+    /// Why the step failed; `None` if it passed.
+    pub error: Option<String>,
+}
+
+impl Namespace {
+    /// Check that the namespace accepts writes and serves queries, e.g. as a
+    /// post-deploy verification step.
+    ///
+    /// Writes a sentinel row, queries it back by vector (checking that its
+    /// distance to itself is zero) and by an id filter, then deletes it and
+    /// checks it is gone. The namespace must exist; its schema decides the
+    /// sentinel's id type and vector dimensions, and the vector query is
+    /// skipped if it has no `vector` attribute. Failed steps are reported in
+    /// the [`SelfTestReport`] rather than as an error.
+    pub async fn self_test(&self) -> Result<SelfTestReport> {
+        let schema = self.metadata().await?.schema.unwrap_or_default();
+        let sentinel_id = self.unused_sentinel_id(&schema).await?;
+        let vector = vector_dims(&schema).map(|dims| vec![1.0f32; dims]);
+
+        let mut report = SelfTestReport { namespace: self.name().to_string(), sentinel_id, steps: Vec::new() };
+        let id = serde_json::to_value(&report.sentinel_id)?;
+        let mut row = Row::new();
+        row.insert("id".to_string(), id.clone());
+        if let Some(vector) = &vector {
+            row.insert(VECTOR_ATTRIBUTE.to_string(), serde_json::json!(vector));
+        }
+
+        let write = WriteParams { upsert_rows: Some(vec![row]), ..Default::default() };
+        if !step(&mut report, "write", async { self.write(write).await.map(|_| ()) }).await {
+            return Ok(report);
+        }
+        let by_id = Filter::eq("id", id.clone());
+        if let Some(vector) = vector {
+            let params = QueryParams {
+                rank_by: Some(RankBy::vector(VECTOR_ATTRIBUTE, vector)),
+                filters: Some(by_id.clone()),
+                ..strong_query()
+            };
+            step(&mut report, "vector_query", async {
+                let rows = self.query(params).await?.rows;
+                let dist = rows.first().and_then(|row| row.get("$dist")).and_then(|d| d.as_f64());
+                match dist {
+                    None => Err(failed("sentinel row not returned by a vector query")),
+                    Some(dist) if dist.abs() > MAX_SELF_DISTANCE => {
+                        Err(failed(&format!("sentinel row's distance to itself is {}, expected 0", dist)))
+                    }
+                    Some(_) => Ok(()),
+                }
+            })
+            .await;
+        }
+        let params = QueryParams { rank_by: Some(RankBy::asc("id")), filters: Some(by_id.clone()), ..strong_query() };
+        step(&mut report, "filter_query", async {
+            match self.query(params.clone()).await?.rows.as_slice() {
+                [row] if row.get("id") == Some(&id) => Ok(()),
+                rows => Err(failed(&format!("id filter returned {} rows, expected the sentinel", rows.len()))),
+            }
+        })
+        .await;
+        let delete = WriteParams { deletes: Some(vec![id.clone()]), ..Default::default() };
+        if step(&mut report, "delete", async { self.write(delete).await.map(|_| ()) }).await {
+            step(&mut report, "verify_deleted", async {
+                match self.query(params).await?.rows.len() {
+                    0 => Ok(()),
+                    _ => Err(failed("sentinel row still returned after delete")),
+                }
+            })
+            .await;
+        }
+        Ok(report)
+    }
+
+    /// A random id of the namespace's id type that no row has.
+    async fn unused_sentinel_id(&self, schema: &HashMap<String, serde_json::Value>) -> Result<Id> {
+        let id_type = schema.get("id").and_then(|id| id["type"].as_str()).unwrap_or("string");
+        for _ in 0..3 {
+            let id = sentinel_id(id_type, random());
+            if self.get(id.clone()).await?.is_none() {
+                return Ok(id);
+            }
+        }
+        Err(failed("couldn't find an unused sentinel id"))
+    }
+}
+
+/// Run one step, recording its outcome; returns whether it passed.
+async fn step(report: &mut SelfTestReport, name: &'static str, run: impl Future<Output = Result<()>>) -> bool {
+    let start = Instant::now();
+    let error = run.await.err().map(|e| e.to_string());
+    let passed = error.is_none();
+    report.steps.push(SelfTestStep { name, latency: start.elapsed(), error });
+    passed
+}
+
+fn strong_query() -> QueryParams {
+    QueryParams {
+        top_k: Some(1),
+        consistency: Some(Consistency { level: ConsistencyLevel::Strong }),
+        ..Default::default()
+    }
+}
+
+fn failed(message: &str) -> Error {
+    Error::Verification(message.to_string())
+}
+
+fn random() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
+
+fn sentinel_id(id_type: &str, random: u64) -> Id {
+    match id_type {
+        // Top half of the range, away from sequential ids.
+        "uint" => Id::Uint(random | 1 << 63),
+        "uuid" => {
+            let hex = format!("{:016x}{:016x}", random, random.rotate_left(32) ^ 0x5e1f_7e57);
+            Id::String(format!("{}-{}-4{}-8{}-{}", &hex[..8], &hex[8..12], &hex[13..16], &hex[17..20], &hex[20..]))
+        }
+        _ => Id::String(format!("__rs_puff_self_test_{:016x}", random)),
+    }
+}
+
+fn vector_dims(schema: &HashMap<String, serde_json::Value>) -> Option<usize> {
+    let attribute_type = schema.get(VECTOR_ATTRIBUTE)?["type"].as_str()?.parse().ok()?;
+    match attribute_type {
+        AttributeType::Vector { dims, .. } => Some(dims),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sentinel_id_matches_id_type() {
+        assert_eq!(sentinel_id("uint", 5), Id::Uint((1 << 63) | 5));
+        assert_eq!(sentinel_id("string", 255), Id::String("__rs_puff_self_test_00000000000000ff".to_string()));
+        let Id::String(uuid) = sentinel_id("uuid", 0x0123_4567_89ab_cdef) else { panic!("expected a string id") };
+        let groups: Vec<usize> = uuid.split('-').map(str::len).collect();
+        assert_eq!(groups, [8, 4, 4, 4, 12]);
+        assert!(uuid.as_bytes()[14] == b'4' && uuid.as_bytes()[19] == b'8');
+    }
+
+    #[test]
+    fn test_vector_dims_from_schema() {
+        let schema: HashMap<String, serde_json::Value> = serde_json::from_value(serde_json::json!({
+            "id": {"type": "uint"},
+            "vector": {"type": AttributeType::vector_f32(3), "ann": {"distance_metric": "cosine_distance"}},
+        }))
+        .unwrap();
+        assert_eq!(vector_dims(&schema), Some(3));
+        assert_eq!(vector_dims(&HashMap::new()), None);
+    }
+}
@@ -0,0 +1,114 @@
+use crate::middleware::sha256_hex;
+use crate::{Filter, Order, QueryParams, RankBy};
+
+impl QueryParams {
+    /// The structure of `filters` and `rank_by` with every literal value
+    /// stripped, e.g. `filters=And(Eq(lang),In(id)) rank_by=ANN(vector)`.
+    ///
+    /// Queries that differ only in the values they filter or rank by share a
+    /// shape, so latency metrics can be aggregated per shape rather than per
+    /// query. Operands of `And`, `Or`, `Sum` and `Max` are sorted, so their
+    /// order doesn't matter.
+    pub fn shape(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(filters) = &self.filters {
+            parts.push(format!("filters={}", filter_shape(filters)));
+        }
+        if let Some(rank_by) = &self.rank_by {
+            parts.push(format!("rank_by={}", rank_by_shape(rank_by)));
+        }
+        parts.join(" ")
+    }
+
+    /// A short, stable hash of [`shape`](Self::shape), for use as a metric
+    /// label.
+    pub fn shape_hash(&self) -> String {
+        let mut hash = sha256_hex(self.shape().as_bytes());
+        hash.truncate(16);
+        hash
+    }
+}
+
+fn filter_shape(filter: &Filter) -> String {
+    let (op, attr) = match filter {
+        Filter::And(filters) => return combinator("And", filters.iter().map(filter_shape)),
+        Filter::Or(filters) => return combinator("Or", filters.iter().map(filter_shape)),
+        Filter::Not(filter) => return format!("Not({})", filter_shape(filter)),
+        Filter::Eq { attr, .. } => ("Eq", attr),
+        Filter::NotEq { attr, .. } => ("NotEq", attr),
+        Filter::Lt { attr, .. } => ("Lt", attr),
+        Filter::Lte { attr, .. } => ("Lte", attr),
+        Filter::Gt { attr, .. } => ("Gt", attr),
+        Filter::Gte { attr, .. } => ("Gte", attr),
+        Filter::AnyLt { attr, .. } => ("AnyLt", attr),
+        Filter::AnyLte { attr, .. } => ("AnyLte", attr),
+        Filter::AnyGt { attr, .. } => ("AnyGt", attr),
+        Filter::AnyGte { attr, .. } => ("AnyGte", attr),
+        Filter::In { attr, .. } => ("In", attr),
+        Filter::NotIn { attr, .. } => ("NotIn", attr),
+        Filter::Contains { attr, .. } => ("Contains", attr),
+        Filter::NotContains { attr, .. } => ("NotContains", attr),
+        Filter::ContainsAny { attr, .. } => ("ContainsAny", attr),
+        Filter::NotContainsAny { attr, .. } => ("NotContainsAny", attr),
+        Filter::Glob { attr, .. } => ("Glob", attr),
+        Filter::NotGlob { attr, .. } => ("NotGlob", attr),
+        Filter::IGlob { attr, .. } => ("IGlob", attr),
+        Filter::NotIGlob { attr, .. } => ("NotIGlob", attr),
+        Filter::Regex { attr, .. } => ("Regex", attr),
+        Filter::ContainsAllTokens { attr, .. } => ("ContainsAllTokens", attr),
+        Filter::ContainsTokenSequence { attr, .. } => ("ContainsTokenSequence", attr),
+    };
+    format!("{}({})", op, attr)
+}
+
+fn rank_by_shape(rank_by: &RankBy) -> String {
+    match rank_by {
+        RankBy::Vector { attr, .. } => format!("ANN({})", attr),
+        RankBy::VectorKnn { attr, .. } => format!("kNN({})", attr),
+        RankBy::Bm25 { attr, .. } => format!("BM25({})", attr),
+        RankBy::Attribute { attr, order: Order::Asc } => format!("asc({})", attr),
+        RankBy::Attribute { attr, order: Order::Desc } => format!("desc({})", attr),
+        RankBy::Sum(subqueries) => combinator("Sum", subqueries.iter().map(rank_by_shape)),
+        RankBy::Max(subqueries) => combinator("Max", subqueries.iter().map(rank_by_shape)),
+        RankBy::Product { subquery, .. } => format!("Product({})", rank_by_shape(subquery)),
+    }
+}
+
+fn combinator(op: &str, operands: impl Iterator<Item = String>) -> String {
+    let mut operands: Vec<String> = operands.collect();
+    operands.sort();
+    format!("{}({})", op, operands.join(","))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shape_strips_literals() {
+        let params = QueryParams {
+            rank_by: Some(RankBy::sum(vec![RankBy::bm25("title", "rust"), RankBy::product(2.0, RankBy::bm25("body", "rust"))])),
+            filters: Some(Filter::and(vec![Filter::r#in("id", vec![1.into(), 2.into()]), Filter::eq("lang", "en")])),
+            top_k: Some(10),
+            ..Default::default()
+        };
+        assert_eq!(params.shape(), "filters=And(Eq(lang),In(id)) rank_by=Sum(BM25(title),Product(BM25(body)))");
+
+        let other = QueryParams {
+            rank_by: Some(RankBy::sum(vec![RankBy::product(0.5, RankBy::bm25("body", "go")), RankBy::bm25("title", "go")])),
+            filters: Some(Filter::and(vec![Filter::eq("lang", "de"), Filter::r#in("id", vec![3.into()])])),
+            ..Default::default()
+        };
+        assert_eq!(other.shape_hash(), params.shape_hash());
+        assert_eq!(params.shape_hash().len(), 16);
+    }
+
+    #[test]
+    fn test_shape_keeps_structure() {
+        let by = |rank_by| QueryParams { rank_by: Some(rank_by), ..Default::default() };
+        assert_eq!(by(RankBy::desc("created_at")).shape(), "rank_by=desc(created_at)");
+        assert_ne!(by(RankBy::desc("created_at")).shape_hash(), by(RankBy::asc("created_at")).shape_hash());
+        assert_ne!(by(RankBy::vector("a", vec![1.0])).shape_hash(), by(RankBy::vector_knn("a", vec![1.0])).shape_hash());
+        assert_eq!(QueryParams::default().shape(), "");
+    }
+}
@@ -0,0 +1,157 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{Client, DistanceMetric, Encryption, Error, Result, Schema, WriteParams};
+
+/// Desired configuration of a namespace, for keeping namespaces in code or
+/// config files and reconciling them with [`Client::ensure`].
+///
+/// Any serde format works; in TOML:
+///
+/// ```toml
+/// name = "articles"
+/// distance_metric = "cosine_distance"
+///
+/// [schema.title]
+/// type = "string"
+/// full_text_search = true
+///
+/// [encryption.cmek]
+/// key_name = "projects/p/locations/l/keyRings/r/cryptoKeys/k"
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NamespaceSpec {
+    pub name: String,
+    #[serde(default)]
+    pub schema: Schema,
+    /// Only applied when the namespace is created.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub distance_metric: Option<DistanceMetric>,
+    /// Only applied when the namespace is created; a mismatch on an existing
+    /// namespace is reported in [`EnsureReport::drift`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encryption: Option<Encryption>,
+}
+
+/// Changes [`Client::ensure`] applied, with attribute names sorted.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EnsureReport {
+    pub created: bool,
+    /// Attributes added to the schema.
+    pub added: Vec<String>,
+    /// Attributes whose schema was updated to match the spec.
+    pub changed: Vec<String>,
+    /// Differences that can't be changed on an existing namespace, such as
+    /// its encryption key. Nothing is applied for these.
+    pub drift: Vec<String>,
+}
+
+impl EnsureReport {
+    /// Whether the namespace already matched the spec.
+    pub fn is_unchanged(&self) -> bool {
+        !self.created && self.added.is_empty() && self.changed.is_empty()
+    }
+}
+
+impl Client {
+    /// Create the namespace described by `spec`, or update an existing one's
+    /// schema to match it.
+    ///
+    /// Attributes the namespace has but the spec doesn't mention are left
+    /// alone, as are options the spec doesn't set.
+    pub async fn ensure(&self, spec: &NamespaceSpec) -> Result<EnsureReport> {
+        let ns = self.namespace(&spec.name);
+        let metadata = match ns.metadata().await {
+            Ok(metadata) => metadata,
+            Err(Error::Api { status: 404, .. }) => {
+                ns.write(WriteParams {
+                    schema: Some(spec.schema.clone().into_map()),
+                    distance_metric: spec.distance_metric,
+                    encryption: spec.encryption.clone(),
+                    ..Default::default()
+                })
+                .await?;
+                let mut added: Vec<String> = spec.schema.0.keys().cloned().collect();
+                added.sort();
+                return Ok(EnsureReport { created: true, added, ..Default::default() });
+            }
+            Err(e) => return Err(e),
+        };
+
+        let current = ns.schema().await?.0;
+        let mut report = EnsureReport::default();
+        let mut update = Schema::new();
+        for (name, attribute) in &spec.schema.0 {
+            let want = serde_json::to_value(attribute)?;
+            match current.get(name) {
+                None => report.added.push(name.clone()),
+                Some(have) if !satisfies(&want, have) => report.changed.push(name.clone()),
+                Some(_) => continue,
+            }
+            update = update.attribute(name.clone(), attribute.clone());
+        }
+        report.added.sort();
+        report.changed.sort();
+
+        if let Some(Encryption { cmek }) = &spec.encryption {
+            let key = metadata.encryption.as_ref().and_then(|e| e.cmek.as_ref()).and_then(|c| c.get("key_name"));
+            if key.and_then(|k| k.as_str()) != Some(cmek.key_name.as_str()) {
+                let actual = key.map_or("none".to_string(), |k| k.to_string());
+                report.drift.push(format!("encryption: want CMEK key {}, namespace has {}", cmek.key_name, actual));
+            }
+        }
+
+        if !update.0.is_empty() {
+            ns.update_schema(update).await?;
+        }
+        Ok(report)
+    }
+}
+
+/// Whether the server's attribute schema `have` has every option `want`
+/// sets. The server reports options in full, e.g. `full_text_search: true`
+/// comes back as its expanded configuration.
+fn satisfies(want: &serde_json::Value, have: &serde_json::Value) -> bool {
+    use serde_json::Value;
+
+    match (want, have) {
+        (Value::Object(want), Value::Object(have)) => {
+            want.iter().all(|(key, want)| have.get(key).is_some_and(|have| satisfies(want, have)))
+        }
+        (Value::Bool(true), Value::Object(_)) => true,
+        _ => want == have,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_satisfies_ignores_server_defaults() {
+        let have = json!({"type": "string", "filterable": true, "full_text_search": {"language": "english", "stemming": false}});
+        assert!(satisfies(&json!({"type": "string"}), &have));
+        assert!(satisfies(&json!({"type": "string", "full_text_search": true}), &have));
+        assert!(satisfies(&json!({"full_text_search": {"language": "english"}}), &have));
+        assert!(!satisfies(&json!({"full_text_search": {"stemming": true}}), &have));
+        assert!(!satisfies(&json!({"type": "string", "filterable": false}), &have));
+        assert!(!satisfies(&json!({"type": "string", "regex": true}), &have));
+    }
+
+    #[test]
+    fn test_spec_deserializes() {
+        let spec: NamespaceSpec = serde_json::from_value(json!({
+            "name": "articles",
+            "distance_metric": "cosine_distance",
+            "schema": {"title": {"type": "string", "full_text_search": true}},
+            "encryption": {"cmek": {"key_name": "k1"}},
+        }))
+        .unwrap();
+        assert_eq!(spec.distance_metric, Some(DistanceMetric::CosineDistance));
+        assert_eq!(spec.encryption, Some(Encryption::cmek("k1")));
+        assert!(spec.schema.get("title").is_some());
+
+        assert!(serde_json::from_value::<NamespaceSpec>(json!({"name": "a", "distance": "x"})).is_err());
+    }
+}
@@ -0,0 +1,126 @@
+use std::sync::Arc;
+
+use bytes::Bytes;
+
+use crate::client::{Payload, RequestStats};
+use crate::{CallOptions, Namespace, Result, Row, WriteParams, WriteResponse};
+
+// Rows are serialized into chunks of about this size as the body is sent.
+const CHUNK_BYTES: usize = 64 * 1024;
+
+impl Namespace {
+    /// Like [`write`](Self::write), but `upsert_rows` are serialized into the
+    /// request while it is sent, using chunked transfer encoding, instead of
+    /// into one buffer first. For very large batches this avoids holding a
+    /// second, serialized copy of every row in memory.
+    ///
+    /// Middleware sees the request without a body, and the body is
+    /// re-serialized for each retry.
+    pub async fn write_streaming(&self, params: WriteParams) -> Result<WriteResponse> {
+        self.write_streaming_with_options(params, &CallOptions::default()).await
+    }
+
+    pub async fn write_streaming_with_options(&self, params: WriteParams, opts: &CallOptions) -> Result<WriteResponse> {
+        params.validate()?;
+        let idempotent = params.is_idempotent();
+        let verifiable = crate::verify::upserts_only(&params);
+        let (rows, head, tail) = split_rows(params)?;
+        let payload = Payload::Stream(Arc::new({
+            let rows = rows.clone();
+            move || {
+                let chunks = RowChunks { rows: rows.clone(), next: 0, head: head.clone(), tail: Some(tail.clone()) };
+                reqwest::Body::wrap_stream(futures_util::stream::iter(chunks))
+            }
+        }));
+        let upsert_rows = verifiable.then_some(rows.as_slice());
+        self.send_write(payload, upsert_rows, idempotent, opts, &mut RequestStats::default()).await
+    }
+}
+
+/// The upserted rows, and the JSON before and after them. A write without
+/// `upsert_rows` is all head, so the body is exactly what
+/// [`Namespace::write`] would send.
+fn split_rows(mut params: WriteParams) -> Result<(Arc<Vec<Row>>, Bytes, Bytes)> {
+    let Some(rows) = params.upsert_rows.take() else {
+        return Ok((Arc::default(), Bytes::from(serde_json::to_vec(&params)?), Bytes::new()));
+    };
+    let rest = serde_json::to_vec(&params)?;
+    // `rest` is `{}` or `{"key":...}`; continue the object after the rows.
+    let tail = if rest.len() > 2 { [b"],".as_slice(), &rest[1..]].concat() } else { b"]}".to_vec() };
+    Ok((Arc::new(rows), Bytes::from_static(br#"{"upsert_rows":["#), Bytes::from(tail)))
+}
+
+/// `{"upsert_rows":[...],...}` in chunks of about [`CHUNK_BYTES`].
+struct RowChunks {
+    rows: Arc<Vec<Row>>,
+    next: usize,
+    /// Sent before the first row.
+    head: Bytes,
+    /// `None` once the whole body has been produced.
+    tail: Option<Bytes>,
+}
+
+impl Iterator for RowChunks {
+    type Item = std::result::Result<Bytes, serde_json::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let tail = self.tail.as_ref()?;
+        let mut chunk = Vec::with_capacity(CHUNK_BYTES + 1024);
+        if self.next == 0 {
+            chunk.extend_from_slice(&self.head);
+        }
+        while self.next < self.rows.len() && chunk.len() < CHUNK_BYTES {
+            if self.next > 0 {
+                chunk.push(b',');
+            }
+            if let Err(e) = serde_json::to_writer(&mut chunk, &self.rows[self.next]) {
+                self.tail = None;
+                return Some(Err(e));
+            }
+            self.next += 1;
+        }
+        if self.next == self.rows.len() {
+            chunk.extend_from_slice(tail);
+            self.tail = None;
+        }
+        Some(Ok(Bytes::from(chunk)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DistanceMetric, Filter};
+
+    fn body(params: WriteParams) -> (usize, serde_json::Value) {
+        let (rows, head, tail) = split_rows(params).unwrap();
+        let chunks: Vec<Bytes> =
+            RowChunks { rows, next: 0, head, tail: Some(tail) }.map(|chunk| chunk.unwrap()).collect();
+        (chunks.len(), serde_json::from_slice(&chunks.concat()).unwrap())
+    }
+
+    #[test]
+    fn test_chunked_body_matches_serialized_params() {
+        let rows: Vec<Row> = (0..200)
+            .map(|id| serde_json::from_value(serde_json::json!({"id": id, "text": "x".repeat(1000)})).unwrap())
+            .collect();
+        let params = WriteParams {
+            upsert_rows: Some(rows),
+            upsert_condition: Some(Filter::eq("v", 1)),
+            distance_metric: Some(DistanceMetric::CosineDistance),
+            ..Default::default()
+        };
+        let expected = serde_json::to_value(&params).unwrap();
+        let (chunks, actual) = body(params);
+        assert!(chunks > 1);
+        assert_eq!(actual, expected);
+
+        let (chunks, actual) = body(WriteParams { upsert_rows: Some(vec![]), ..Default::default() });
+        assert_eq!((chunks, actual), (1, serde_json::json!({"upsert_rows": []})));
+
+        // Without upserts the body is the serialized write, with no upsert_rows key.
+        let copy = WriteParams { copy_from_namespace: Some("docs-v1".into()), ..Default::default() };
+        let expected = serde_json::to_value(&copy).unwrap();
+        assert_eq!(body(copy), (1, expected));
+    }
+}
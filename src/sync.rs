@@ -0,0 +1,206 @@
+use std::future::Future;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use crate::bulk;
+use crate::rt;
+use crate::{BulkWriteReport, DistanceMetric, Id, Namespace, Result, Row, WriteParams};
+
+/// Changes pulled from a source since a checkpoint.
+#[derive(Debug, Clone)]
+pub struct ChangeBatch<C> {
+    pub upserts: Vec<Row>,
+    pub deletes: Vec<Id>,
+    /// Checkpoint to resume from once this batch has been applied.
+    pub checkpoint: C,
+    /// Whether the source has more changes ready without waiting.
+    pub has_more: bool,
+}
+
+/// A primary data source that can report changed records since a checkpoint.
+pub trait SourceAdapter {
+    type Checkpoint: Clone + Send + Sync;
+
+    /// Pull changes after `checkpoint`, or from the beginning when `None`.
+    fn changes_since(
+        &mut self,
+        checkpoint: Option<&Self::Checkpoint>,
+    ) -> impl Future<Output = Result<ChangeBatch<Self::Checkpoint>>> + Send;
+}
+
+/// Persists the last applied checkpoint between runs.
+pub trait CheckpointStore<C> {
+    fn load(&mut self) -> impl Future<Output = Result<Option<C>>> + Send;
+    fn save(&mut self, checkpoint: &C) -> impl Future<Output = Result<()>> + Send;
+}
+
+/// Keeps the checkpoint in memory; progress is lost on restart.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryCheckpointStore<C> {
+    checkpoint: Option<C>,
+}
+
+impl<C: Clone + Send + Sync> CheckpointStore<C> for MemoryCheckpointStore<C> {
+    async fn load(&mut self) -> Result<Option<C>> {
+        Ok(self.checkpoint.clone())
+    }
+
+    async fn save(&mut self, checkpoint: &C) -> Result<()> {
+        self.checkpoint = Some(checkpoint.clone());
+        Ok(())
+    }
+}
+
+/// Stores the checkpoint as JSON in a local file, replaced atomically on save.
+#[derive(Debug, Clone)]
+pub struct FileCheckpointStore {
+    path: PathBuf,
+}
+
+impl FileCheckpointStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl<C: Serialize + DeserializeOwned + Sync> CheckpointStore<C> for FileCheckpointStore {
+    async fn load(&mut self) -> Result<Option<C>> {
+        let path = self.path.clone();
+        match rt::blocking(move || std::fs::read(path)).await? {
+            Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn save(&mut self, checkpoint: &C) -> Result<()> {
+        let bytes = serde_json::to_vec(checkpoint)?;
+        let path = self.path.clone();
+        rt::blocking(move || {
+            let tmp = path.with_extension("tmp");
+            std::fs::write(&tmp, bytes)?;
+            std::fs::rename(&tmp, &path)
+        })
+        .await??;
+        Ok(())
+    }
+}
+
+/// Mirrors a [`SourceAdapter`] into a namespace.
+///
+/// Each batch is written before its checkpoint is saved, so a crash replays
+/// the last batch rather than skipping it; upserts and deletes are idempotent.
+pub struct SyncEngine<'a, S, K> {
+    ns: &'a Namespace,
+    source: S,
+    checkpoints: K,
+    distance_metric: Option<DistanceMetric>,
+}
+
+impl<'a, S, K> SyncEngine<'a, S, K>
+where
+    S: SourceAdapter,
+    K: CheckpointStore<S::Checkpoint>,
+{
+    pub fn new(ns: &'a Namespace, source: S, checkpoints: K) -> Self {
+        Self { ns, source, checkpoints, distance_metric: None }
+    }
+
+    /// Distance metric sent with upserts; required if the namespace may not exist yet.
+    pub fn distance_metric(mut self, metric: DistanceMetric) -> Self {
+        self.distance_metric = Some(metric);
+        self
+    }
+
+    /// Apply changes until the source reports no more are ready.
+    pub async fn sync_once(&mut self) -> Result<BulkWriteReport> {
+        let mut report = BulkWriteReport::default();
+        let mut checkpoint = self.checkpoints.load().await?;
+
+        loop {
+            let batch = self.source.changes_since(checkpoint.as_ref()).await?;
+            let has_more = batch.has_more;
+            if let Some(params) = write_params(&batch, self.distance_metric) {
+                bulk::write_reported(self.ns, params, &mut report).await?;
+            }
+            self.checkpoints.save(&batch.checkpoint).await?;
+            checkpoint = Some(batch.checkpoint);
+
+            if !has_more {
+                return Ok(report);
+            }
+        }
+    }
+
+    /// Run [`SyncEngine::sync_once`] forever, sleeping `interval` between rounds.
+    pub async fn run(&mut self, interval: Duration) -> Result<()> {
+        loop {
+            self.sync_once().await?;
+            rt::sleep(interval).await;
+        }
+    }
+}
+
+fn write_params<C>(batch: &ChangeBatch<C>, distance_metric: Option<DistanceMetric>) -> Option<WriteParams> {
+    if batch.upserts.is_empty() && batch.deletes.is_empty() {
+        return None;
+    }
+    let deletes = batch
+        .deletes
+        .iter()
+        .map(|id| serde_json::to_value(id).expect("ids always serialize"))
+        .collect::<Vec<_>>();
+    Some(WriteParams {
+        upsert_rows: (!batch.upserts.is_empty()).then(|| batch.upserts.clone()),
+        deletes: (!deletes.is_empty()).then_some(deletes),
+        distance_metric: distance_metric.filter(|_| !batch.upserts.is_empty()),
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn batch(upserts: Vec<Row>, deletes: Vec<Id>) -> ChangeBatch<u64> {
+        ChangeBatch { upserts, deletes, checkpoint: 1, has_more: false }
+    }
+
+    #[test]
+    fn test_write_params_skips_empty_batches() {
+        assert!(write_params(&batch(vec![], vec![]), Some(DistanceMetric::CosineDistance)).is_none());
+    }
+
+    #[test]
+    fn test_write_params_combines_upserts_and_deletes() {
+        let row: Row = serde_json::from_value(serde_json::json!({"id": 1, "name": "a"})).unwrap();
+        let params = write_params(&batch(vec![row], vec![Id::from(2), Id::from("x")]), Some(DistanceMetric::CosineDistance)).unwrap();
+        let json = serde_json::to_value(&params).unwrap();
+        assert_eq!(json["upsert_rows"][0]["name"], "a");
+        assert_eq!(json["deletes"], serde_json::json!([2, "x"]));
+        assert_eq!(json["distance_metric"], "cosine_distance");
+    }
+
+    #[test]
+    fn test_deletes_only_omit_distance_metric() {
+        let params = write_params(&batch(vec![], vec![Id::from(2)]), Some(DistanceMetric::CosineDistance)).unwrap();
+        assert!(params.distance_metric.is_none());
+        assert!(params.upsert_rows.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_file_checkpoint_store_roundtrip() {
+        let path = std::env::temp_dir().join(format!("rs_puff_checkpoint_{}.json", std::process::id()));
+        let mut store = FileCheckpointStore::new(&path);
+        assert_eq!(CheckpointStore::<String>::load(&mut store).await.unwrap(), None);
+
+        store.save(&"2024-01-01T00:00:00Z".to_string()).await.unwrap();
+        let loaded: Option<String> = store.load().await.unwrap();
+        assert_eq!(loaded.as_deref(), Some("2024-01-01T00:00:00Z"));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
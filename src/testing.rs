@@ -0,0 +1,209 @@
+use std::cmp::Ordering;
+use std::ops::Deref;
+use std::sync::atomic::{AtomicU64, Ordering as AtomicOrdering};
+
+use serde_json::{Map, Value};
+
+use crate::rt::{self, SystemTime, UNIX_EPOCH};
+use crate::{Client, Namespace, QueryResponse, Result};
+
+// Row attributes that vary between runs and are stripped from snapshots.
+const VOLATILE_ATTRIBUTES: &[&str] = &["$dist", "_dist"];
+
+const DEFAULT_TEMP_PREFIX: &str = "rs_puff_tmp_";
+
+/// A uniquely-named namespace that is deleted when closed or dropped.
+///
+/// Prefer awaiting [`TempNamespace::close`]: dropping only schedules a
+/// best-effort delete on the current tokio runtime, which may be shut down
+/// before it runs (e.g. at the end of a `#[tokio::test]`).
+pub struct TempNamespace {
+    ns: Namespace,
+    closed: bool,
+}
+
+impl TempNamespace {
+    pub fn new(client: &Client) -> Self {
+        Self::with_prefix(client, DEFAULT_TEMP_PREFIX)
+    }
+
+    pub fn with_prefix(client: &Client, prefix: &str) -> Self {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+        let nonce = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        let count = COUNTER.fetch_add(1, AtomicOrdering::SeqCst);
+        Self {
+            ns: client.namespace(format!("{}{}_{}", prefix, nonce, count)),
+            closed: false,
+        }
+    }
+
+    /// Delete the namespace. A namespace that was never written to is not an error.
+    pub async fn close(mut self) -> Result<()> {
+        self.closed = true;
+        self.ns.delete_all_if_exists().await.map(drop)
+    }
+}
+
+impl Deref for TempNamespace {
+    type Target = Namespace;
+
+    fn deref(&self) -> &Self::Target {
+        &self.ns
+    }
+}
+
+impl Drop for TempNamespace {
+    fn drop(&mut self) {
+        if self.closed {
+            return;
+        }
+        let client = Client::clone(&self.ns.client);
+        let name = self.ns.name().to_string();
+        rt::spawn_detached(async move {
+            let _ = client.namespace(name).delete_all_if_exists().await;
+        });
+    }
+}
+
+/// Normalize a query response for snapshot assertions.
+///
+/// Distances, performance and billing info are dropped, rows are sorted by
+/// `id`, and object keys are sorted so the rendered JSON is stable.
+pub fn normalize_query_response(resp: &QueryResponse) -> Value {
+    let mut rows: Vec<Value> = resp
+        .rows
+        .iter()
+        .map(|row| {
+            let attrs = row
+                .iter()
+                .filter(|(k, _)| !VOLATILE_ATTRIBUTES.contains(&k.as_str()))
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect::<Map<_, _>>();
+            Value::Object(attrs)
+        })
+        .collect();
+    rows.sort_by(|a, b| compare_ids(a.get("id"), b.get("id")));
+
+    let mut out = Map::new();
+    out.insert("rows".to_string(), Value::Array(rows));
+    if let Some(aggs) = &resp.aggregations {
+        let aggs = aggs.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+        out.insert("aggregations".to_string(), Value::Object(aggs));
+    }
+    if let Some(groups) = &resp.aggregation_groups {
+        let groups = groups
+            .iter()
+            .map(|g| Value::Object(g.0.iter().map(|(k, v)| (k.clone(), v.clone())).collect()))
+            .collect();
+        out.insert("aggregation_groups".to_string(), Value::Array(groups));
+    }
+    canonicalize(Value::Object(out))
+}
+
+/// Render a normalized query response as pretty, stable JSON.
+pub fn snapshot(resp: &QueryResponse) -> String {
+    serde_json::to_string_pretty(&normalize_query_response(resp))
+        .expect("normalized response is always serializable")
+}
+
+/// Assert that a query response matches an expected snapshot.
+///
+/// Leading/trailing whitespace is ignored on both sides so snapshots can be
+/// written as indented raw string literals.
+#[track_caller]
+pub fn assert_snapshot(resp: &QueryResponse, expected: &str) {
+    let actual = snapshot(resp);
+    let expected = serde_json::from_str::<Value>(expected)
+        .map(|v| serde_json::to_string_pretty(&canonicalize(v)).unwrap())
+        .unwrap_or_else(|_| expected.trim().to_string());
+    assert_eq!(actual.trim(), expected.trim(), "query response snapshot mismatch");
+}
+
+// Rebuild objects with sorted keys, independent of serde_json's map backend.
+pub(crate) fn canonicalize(value: Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<_> = map.into_iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(&b.0));
+            Value::Object(entries.into_iter().map(|(k, v)| (k, canonicalize(v))).collect())
+        }
+        Value::Array(items) => Value::Array(items.into_iter().map(canonicalize).collect()),
+        other => other,
+    }
+}
+
+// Numeric ids sort before string ids; missing ids sort last.
+fn compare_ids(a: Option<&Value>, b: Option<&Value>) -> Ordering {
+    match (a, b) {
+        (Some(Value::Number(a)), Some(Value::Number(b))) => a
+            .as_f64()
+            .partial_cmp(&b.as_f64())
+            .unwrap_or(Ordering::Equal),
+        (Some(Value::Number(_)), Some(_)) => Ordering::Less,
+        (Some(_), Some(Value::Number(_))) => Ordering::Greater,
+        (Some(Value::String(a)), Some(Value::String(b))) => a.cmp(b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        _ => Ordering::Equal,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(json: &str) -> QueryResponse {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_temp_namespace_names_are_unique() {
+        let client = Client::new("test");
+        let a = TempNamespace::new(&client);
+        let b = TempNamespace::with_prefix(&client, "custom_");
+        assert!(a.name().starts_with(DEFAULT_TEMP_PREFIX));
+        assert!(b.name().starts_with("custom_"));
+        assert_ne!(a.name(), b.name());
+    }
+
+    #[test]
+    fn test_normalize_strips_volatile_fields_and_sorts() {
+        let resp = response(
+            r#"{
+                "rows": [
+                    {"id": 2, "name": "bob", "$dist": 0.2},
+                    {"id": 1, "name": "alice", "$dist": 0.1}
+                ],
+                "billing": {"billable_logical_bytes_queried": 1, "billable_logical_bytes_returned": 1},
+                "performance": {"server_total_ms": 10}
+            }"#,
+        );
+        let normalized = normalize_query_response(&resp);
+        assert_eq!(
+            normalized,
+            serde_json::json!({"rows": [{"id": 1, "name": "alice"}, {"id": 2, "name": "bob"}]})
+        );
+    }
+
+    #[test]
+    fn test_string_ids_sort_after_numeric() {
+        let resp = response(r#"{"rows": [{"id": "b"}, {"id": 3}, {"id": "a"}]}"#);
+        let normalized = normalize_query_response(&resp);
+        assert_eq!(normalized, serde_json::json!({"rows": [{"id": 3}, {"id": "a"}, {"id": "b"}]}));
+    }
+
+    #[test]
+    fn test_assert_snapshot_ignores_formatting() {
+        let resp = response(r#"{"rows": [{"name": "alice", "id": 1, "_dist": 0.5}], "aggregations": {"count": 1}}"#);
+        assert_snapshot(
+            &resp,
+            r#"
+            {"aggregations": {"count": 1}, "rows": [{"id": 1, "name": "alice"}]}
+            "#,
+        );
+    }
+}
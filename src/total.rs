@@ -0,0 +1,74 @@
+use std::collections::HashMap;
+
+use crate::{AggregateBy, Error, Filter, MultiQueryParams, Namespace, QueryParams, QueryResponse, Result};
+
+const TOTAL_AGGREGATION: &str = "total";
+
+/// The query plus a `Count` over the same filters, as one multi-query.
+fn with_count(mut params: QueryParams) -> MultiQueryParams {
+    // Multi-queries take these once, for every sub-query.
+    let consistency = params.consistency.take();
+    let vector_encoding = params.vector_encoding.take();
+    let count = count_query(params.filters.clone());
+    MultiQueryParams { queries: vec![params, count], vector_encoding, consistency }
+}
+
+fn count_query(filters: Option<Filter>) -> QueryParams {
+    QueryParams {
+        filters,
+        aggregate_by: Some(HashMap::from([(TOTAL_AGGREGATION.to_string(), AggregateBy::Count)])),
+        ..Default::default()
+    }
+}
+
+fn total(resp: &QueryResponse) -> Option<u64> {
+    resp.aggregations.as_ref()?.get(TOTAL_AGGREGATION)?.as_u64()
+}
+
+impl Namespace {
+    /// Run a query and count every row matching its filters, ignoring
+    /// `top_k`, in the same round trip. The count is in
+    /// [`QueryResponse::total_matches`].
+    pub async fn query_with_total(&self, params: QueryParams) -> Result<QueryResponse> {
+        let results = self.multi_query(with_count(params)).await?.results;
+        let Ok([mut resp, count]) = <[QueryResponse; 2]>::try_from(results) else {
+            return Err(Error::Json(serde::de::Error::custom("expected 2 multi-query results")));
+        };
+        resp.total_matches = total(&count);
+        Ok(resp)
+    }
+
+    /// Exact number of rows in the namespace.
+    pub(crate) async fn count_rows(&self) -> Result<u64> {
+        let resp = self.query(count_query(None)).await?;
+        Ok(total(&resp).unwrap_or(0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Consistency, ConsistencyLevel, RankBy};
+
+    #[test]
+    fn test_with_count_shares_filters() {
+        let params = QueryParams {
+            rank_by: Some(RankBy::desc("created_at")),
+            top_k: Some(20),
+            filters: Some(Filter::eq("lang", "en")),
+            consistency: Some(Consistency { level: ConsistencyLevel::Eventual }),
+            ..Default::default()
+        };
+        let body = serde_json::to_value(with_count(params)).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "queries": [
+                    {"rank_by": ["created_at", "desc"], "top_k": 20, "filters": ["lang", "Eq", "en"]},
+                    {"filters": ["lang", "Eq", "en"], "aggregate_by": {"total": ["Count"]}},
+                ],
+                "consistency": {"level": "eventual"},
+            })
+        );
+    }
+}
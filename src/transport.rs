@@ -0,0 +1,369 @@
+use std::fmt;
+use std::net::SocketAddr;
+#[cfg(not(target_arch = "wasm32"))]
+use std::net::ToSocketAddrs;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[cfg(not(target_arch = "wasm32"))]
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use thiserror::Error;
+
+use crate::rt::BoxFuture;
+
+// How long a failed request waits to report where its host resolves.
+#[cfg(not(target_arch = "wasm32"))]
+const DIAGNOSE_RESOLVE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Sends a [`Client`](crate::Client)'s HTTP requests; replace the built-in
+/// one with [`Client::with_transport`](crate::Client::with_transport).
+///
+/// `reqwest::Client` implements this, so a pre-configured one can be passed
+/// directly. Other backends build a `reqwest::Response` from an
+/// `http::Response`. On `wasm32` the returned future needn't be `Send`.
+pub trait HttpTransport: Send + Sync {
+    /// Send `request` and return the response whatever its status; the
+    /// client handles errors and retries. Should honor
+    /// [`reqwest::Request::timeout`], which carries the per-attempt timeout.
+    fn send(&self, request: reqwest::Request) -> BoxFuture<'_, crate::Result<reqwest::Response>>;
+}
+
+impl HttpTransport for reqwest::Client {
+    fn send(&self, request: reqwest::Request) -> BoxFuture<'_, crate::Result<reqwest::Response>> {
+        Box::pin(async move { Ok(self.execute(request).await?) })
+    }
+}
+
+impl<T: HttpTransport + ?Sized> HttpTransport for Arc<T> {
+    fn send(&self, request: reqwest::Request) -> BoxFuture<'_, crate::Result<reqwest::Response>> {
+        (**self).send(request)
+    }
+}
+
+/// Which IP versions connections to the API use; see
+/// [`Client::with_ip_preference`](crate::Client::with_ip_preference).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum IpPreference {
+    /// Use addresses in the order the system resolver returns them, racing
+    /// IPv6 and IPv4 ("happy eyeballs").
+    #[default]
+    Any,
+    /// Try IPv4 addresses first, falling back to IPv6.
+    PreferIpv4,
+    /// Never connect over IPv6.
+    Ipv4Only,
+}
+
+/// Settings baked into the underlying HTTP client, which is rebuilt when
+/// any of them change.
+#[derive(Debug, Clone)]
+pub(crate) struct HttpConfig {
+    pub ip_preference: IpPreference,
+    pub connect_timeout: Option<Duration>,
+    #[cfg(not(target_arch = "wasm32"))]
+    pub proxy: Option<reqwest::Proxy>,
+    /// Without an explicit proxy, follow `HTTPS_PROXY`, `HTTP_PROXY`,
+    /// `ALL_PROXY` and `NO_PROXY` from the environment.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub system_proxy: bool,
+    /// Trusted on top of the TLS backend's built-in roots.
+    #[cfg(all(any(feature = "native-tls", feature = "rustls"), not(target_arch = "wasm32")))]
+    pub root_certificates: Vec<reqwest::Certificate>,
+    /// Send `Accept-Encoding: gzip` and decompress responses.
+    #[cfg(feature = "gzip")]
+    pub gzip: bool,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            ip_preference: IpPreference::Any,
+            connect_timeout: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            proxy: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            system_proxy: true,
+            #[cfg(all(any(feature = "native-tls", feature = "rustls"), not(target_arch = "wasm32")))]
+            root_certificates: Vec::new(),
+            #[cfg(feature = "gzip")]
+            gzip: true,
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn http_client(config: &HttpConfig) -> reqwest::Client {
+    let mut builder = reqwest::Client::builder();
+    if let Some(timeout) = config.connect_timeout {
+        builder = builder.connect_timeout(timeout);
+    }
+    // An explicit proxy replaces the system one; reqwest reads the
+    // environment in `build`.
+    match &config.proxy {
+        Some(proxy) => builder = builder.proxy(proxy.clone()),
+        None if !config.system_proxy => builder = builder.no_proxy(),
+        None => {}
+    }
+    #[cfg(all(any(feature = "native-tls", feature = "rustls"), not(target_arch = "wasm32")))]
+    for certificate in &config.root_certificates {
+        builder = builder.add_root_certificate(certificate.clone());
+    }
+    #[cfg(feature = "gzip")]
+    {
+        builder = builder.gzip(config.gzip);
+    }
+    let builder = match config.ip_preference {
+        IpPreference::Any => builder,
+        preference => builder.dns_resolver(Arc::new(PreferenceResolver(preference))),
+    };
+    builder.build().expect("TLS backend failed to initialize")
+}
+
+// `fetch` owns connections, compression and TLS on wasm32.
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn http_client(_config: &HttpConfig) -> reqwest::Client {
+    reqwest::Client::new()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+struct PreferenceResolver(IpPreference);
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Resolve for PreferenceResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let preference = self.0;
+        let host = name.as_str().to_string();
+        Box::pin(async move {
+            let lookup = host.clone();
+            let addrs = tokio::task::spawn_blocking(move || (lookup.as_str(), 0).to_socket_addrs()).await??;
+            let addrs = order_addrs(addrs.collect(), preference);
+            if addrs.is_empty() {
+                return Err(format!("no IPv4 address for {}", host).into());
+            }
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+fn order_addrs(mut addrs: Vec<SocketAddr>, preference: IpPreference) -> Vec<SocketAddr> {
+    match preference {
+        IpPreference::Any => {}
+        // Stable, so the resolver's order holds within each family.
+        IpPreference::PreferIpv4 => addrs.sort_by_key(|addr| addr.is_ipv6()),
+        IpPreference::Ipv4Only => addrs.retain(|addr| addr.is_ipv4()),
+    }
+    addrs
+}
+
+/// Where a request failed before any response was received.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransportStage {
+    /// Resolving the API host failed.
+    Dns,
+    /// Opening a TCP connection failed.
+    Connect,
+    /// The TLS handshake failed.
+    Tls,
+    /// The request timed out.
+    Timeout,
+    /// The connection failed after it was established, e.g. it was reset by
+    /// the peer. The server may have received the request.
+    Request,
+}
+
+impl fmt::Display for TransportStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            TransportStage::Dns => "DNS resolution",
+            TransportStage::Connect => "TCP connect",
+            TransportStage::Tls => "TLS handshake",
+            TransportStage::Timeout => "timeout",
+            TransportStage::Request => "connection",
+        })
+    }
+}
+
+/// A request that failed at the transport layer, with what the client knew
+/// about the connection; see [`Error::Transport`](crate::Error::Transport).
+#[derive(Error, Debug)]
+#[error("{stage} failed for {host}{} after {elapsed:?}: {source}", resolved_context(.resolved))]
+pub struct TransportError {
+    pub stage: TransportStage,
+    /// API host, from the client's base URL.
+    pub host: String,
+    /// Addresses the host resolved to when the failure was diagnosed; empty
+    /// if it didn't resolve.
+    pub resolved: Vec<SocketAddr>,
+    /// Time spent on the failed attempt.
+    pub elapsed: Duration,
+    #[source]
+    pub source: reqwest::Error,
+}
+
+fn resolved_context(resolved: &[SocketAddr]) -> String {
+    if resolved.is_empty() {
+        return String::new();
+    }
+    let addrs: Vec<String> = resolved.iter().map(|addr| addr.ip().to_string()).collect();
+    format!(" ({})", addrs.join(", "))
+}
+
+impl TransportError {
+    /// Whether the request can't have reached the server.
+    pub fn before_request_sent(&self) -> bool {
+        matches!(self.stage, TransportStage::Dns | TransportStage::Connect | TransportStage::Tls)
+    }
+}
+
+/// Diagnose a failed send to `base_url`, resolving its host again to report
+/// where it points.
+pub(crate) async fn diagnose(source: reqwest::Error, base_url: &str, elapsed: Duration) -> TransportError {
+    let messages = error_chain(&source);
+    let stage = classify(is_connect(&source), source.is_timeout(), &messages);
+    let url = reqwest::Url::parse(base_url).ok();
+    let host = url.as_ref().and_then(|url| url.host_str()).unwrap_or(base_url).to_string();
+    let port = url.as_ref().and_then(|url| url.port_or_known_default()).unwrap_or(443);
+
+    let resolved = resolve(host.clone(), port).await;
+    TransportError { stage, host, resolved, elapsed, source }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+async fn resolve(host: String, port: u16) -> Vec<SocketAddr> {
+    // The lookup can't be cancelled, so a slow resolver is left to finish on
+    // its blocking thread rather than delaying the error.
+    let lookup = tokio::task::spawn_blocking(move || (host, port).to_socket_addrs().map(Vec::from_iter));
+    crate::rt::timeout(DIAGNOSE_RESOLVE_TIMEOUT, lookup)
+        .await
+        .ok()
+        .and_then(|joined| joined.ok())
+        .and_then(|addrs| addrs.ok())
+        .unwrap_or_default()
+}
+
+// There is no resolver to ask on wasm32.
+#[cfg(target_arch = "wasm32")]
+async fn resolve(_host: String, _port: u16) -> Vec<SocketAddr> {
+    Vec::new()
+}
+
+/// Whether connecting failed; `fetch` doesn't say on wasm32.
+pub(crate) fn is_connect(error: &reqwest::Error) -> bool {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        error.is_connect()
+    }
+    #[cfg(target_arch = "wasm32")]
+    {
+        let _ = error;
+        false
+    }
+}
+
+fn error_chain(error: &(dyn std::error::Error + 'static)) -> Vec<String> {
+    std::iter::successors(Some(error), |e| e.source()).map(|e| e.to_string().to_lowercase()).collect()
+}
+
+// reqwest only says whether connecting failed; the connector's messages
+// say which step.
+fn classify(is_connect: bool, is_timeout: bool, messages: &[String]) -> TransportStage {
+    let mentions = |needles: &[&str]| messages.iter().any(|m| needles.iter().any(|n| m.contains(n)));
+    if is_connect {
+        if mentions(&["dns error", "failed to lookup address", "name or service not known", "no such host"]) {
+            TransportStage::Dns
+        } else if mentions(&["tls", "ssl", "certificate", "handshake"]) {
+            TransportStage::Tls
+        } else {
+            TransportStage::Connect
+        }
+    } else if is_timeout {
+        TransportStage::Timeout
+    } else {
+        TransportStage::Request
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(chain: &[&str]) -> Vec<String> {
+        chain.iter().map(|m| m.to_lowercase()).collect()
+    }
+
+    #[test]
+    fn test_classify_connect_failures() {
+        let dns = messages(&["error sending request", "client error (Connect)", "dns error: failed to lookup address information"]);
+        assert_eq!(classify(true, false, &dns), TransportStage::Dns);
+
+        let tcp = messages(&["error sending request", "tcp connect error: Connection refused (os error 111)"]);
+        assert_eq!(classify(true, false, &tcp), TransportStage::Connect);
+
+        let tls = messages(&["error sending request", "invalid peer certificate: UnknownIssuer"]);
+        assert_eq!(classify(true, false, &tls), TransportStage::Tls);
+    }
+
+    #[test]
+    fn test_classify_after_connecting() {
+        assert_eq!(classify(false, true, &messages(&["operation timed out"])), TransportStage::Timeout);
+        let reset = messages(&["error sending request", "connection reset by peer (os error 104)"]);
+        assert_eq!(classify(false, false, &reset), TransportStage::Request);
+    }
+
+    #[test]
+    fn test_order_addrs() {
+        let addrs: Vec<SocketAddr> = ["[2001:db8::1]:0", "10.0.0.1:0", "[2001:db8::2]:0", "10.0.0.2:0"]
+            .iter()
+            .map(|a| a.parse().unwrap())
+            .collect();
+        let ips = |addrs: Vec<SocketAddr>| addrs.iter().map(|a| a.ip().to_string()).collect::<Vec<_>>();
+        assert_eq!(ips(order_addrs(addrs.clone(), IpPreference::Any)), ["2001:db8::1", "10.0.0.1", "2001:db8::2", "10.0.0.2"]);
+        assert_eq!(ips(order_addrs(addrs.clone(), IpPreference::PreferIpv4)), ["10.0.0.1", "10.0.0.2", "2001:db8::1", "2001:db8::2"]);
+        assert_eq!(ips(order_addrs(addrs, IpPreference::Ipv4Only)), ["10.0.0.1", "10.0.0.2"]);
+    }
+
+    #[tokio::test]
+    async fn test_ipv4_only_client_connects_over_ipv4() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            use std::io::{Read, Write};
+            let (mut stream, _) = listener.accept().unwrap();
+            let _ = stream.read(&mut [0; 1024]);
+            stream.write_all(b"HTTP/1.1 204 No Content\r\ncontent-length: 0\r\n\r\n").unwrap();
+        });
+        let resp = http_client(&HttpConfig { ip_preference: IpPreference::Ipv4Only, ..Default::default() })
+            .get(format!("http://localhost:{}/", port)).send().await.unwrap();
+        assert_eq!(resp.status(), 204);
+    }
+
+    #[tokio::test]
+    async fn test_call_timeout_overrides_client() {
+        // Accepts connections but never answers.
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            let _streams: Vec<_> = listener.incoming().collect();
+        });
+        let client = crate::Client::with_base_url("key", format!("http://127.0.0.1:{}", port))
+            .with_timeout(Duration::from_secs(60));
+        let opts = crate::CallOptions::no_retry().timeout(Duration::from_millis(50));
+        let err = client.namespace("ns").query_with_options(Default::default(), &opts).await.unwrap_err();
+        let crate::Error::Transport(err) = err else { panic!("expected a transport error, got {:?}", err) };
+        assert_eq!(err.stage, TransportStage::Timeout);
+        assert!(err.elapsed < Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn test_diagnose_reports_host_and_stage() {
+        // Nothing listens on port 1.
+        let source = reqwest::get("http://127.0.0.1:1/").await.unwrap_err();
+        let err = diagnose(source, "http://127.0.0.1:1", Duration::from_millis(3)).await;
+        assert_eq!(err.stage, TransportStage::Connect);
+        assert_eq!(err.host, "127.0.0.1");
+        assert_eq!(err.resolved, ["127.0.0.1:1".parse().unwrap()]);
+        assert!(err.before_request_sent());
+        assert!(err.to_string().starts_with("TCP connect failed for 127.0.0.1 (127.0.0.1) after 3ms: "), "{}", err);
+    }
+}
@@ -0,0 +1,108 @@
+use std::time::Duration;
+
+use crate::rt::{SystemTime, UNIX_EPOCH};
+use crate::{AttributeSchema, AttributeType, Filter, QueryParams, Schema};
+
+/// Datetime attribute after which a row counts as expired; see
+/// [`QueryParams::exclude_expired`]. Rows without it never expire.
+pub const EXPIRES_AT_ATTRIBUTE: &str = "expires_at";
+
+/// A value for [`EXPIRES_AT_ATTRIBUTE`] `ttl` from now, e.g.
+/// `row.insert(EXPIRES_AT_ATTRIBUTE.into(), expires_in(Duration::from_secs(3600)))`.
+pub fn expires_in(ttl: Duration) -> serde_json::Value {
+    datetime(SystemTime::now() + ttl).into()
+}
+
+impl Schema {
+    /// Declare [`EXPIRES_AT_ATTRIBUTE`] as a filterable `datetime`, so
+    /// [`QueryParams::exclude_expired`] can filter on it.
+    pub fn expires_at(self) -> Self {
+        self.attribute(EXPIRES_AT_ATTRIBUTE, AttributeSchema::new(AttributeType::Datetime).filterable(true))
+    }
+}
+
+impl QueryParams {
+    /// Leave out rows whose [`EXPIRES_AT_ATTRIBUTE`] has passed, combined
+    /// with any filters already set. The cutoff is the time of this call.
+    pub fn exclude_expired(self) -> Self {
+        self.exclude_expired_at(SystemTime::now())
+    }
+
+    fn exclude_expired_at(mut self, now: SystemTime) -> Self {
+        let unexpired = Filter::or(vec![
+            Filter::eq(EXPIRES_AT_ATTRIBUTE, serde_json::Value::Null),
+            Filter::gt(EXPIRES_AT_ATTRIBUTE, datetime(now)),
+        ]);
+        self.filters = Some(match self.filters.take() {
+            Some(Filter::And(mut filters)) => {
+                filters.push(unexpired);
+                Filter::And(filters)
+            }
+            Some(filters) => Filter::and(vec![filters, unexpired]),
+            None => unexpired,
+        });
+        self
+    }
+}
+
+/// RFC 3339 in UTC with millisecond precision, e.g. `2024-03-15T10:30:45.123Z`.
+fn datetime(at: SystemTime) -> String {
+    let since_epoch = at.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs() as i64;
+    let (days, secs_of_day) = (secs.div_euclid(86_400), secs.rem_euclid(86_400));
+
+    // Civil from days, after Howard Hinnant's algorithm.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        secs_of_day / 3600,
+        secs_of_day % 3600 / 60,
+        secs_of_day % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_datetime_formats_rfc3339() {
+        assert_eq!(datetime(UNIX_EPOCH), "1970-01-01T00:00:00.000Z");
+        let at = UNIX_EPOCH + Duration::from_millis(1_709_202_645_123);
+        assert_eq!(datetime(at), "2024-02-29T10:30:45.123Z");
+    }
+
+    #[test]
+    fn test_exclude_expired_appends_filter() {
+        let now = UNIX_EPOCH + Duration::from_secs(1_445_412_480);
+        let params = QueryParams { filters: Some(Filter::eq("lang", "en")), ..Default::default() }.exclude_expired_at(now);
+        assert_eq!(
+            serde_json::to_value(&params.filters).unwrap(),
+            serde_json::json!(["And", [
+                ["lang", "Eq", "en"],
+                ["Or", [["expires_at", "Eq", null], ["expires_at", "Gt", "2015-10-21T07:28:00.000Z"]]],
+            ]])
+        );
+
+        let params = QueryParams { filters: Some(Filter::and(vec![Filter::eq("a", 1)])), ..Default::default() }
+            .exclude_expired_at(now);
+        let Some(Filter::And(filters)) = params.filters else { panic!("expected an And filter") };
+        assert_eq!(filters.len(), 2);
+
+        let schema = serde_json::to_value(Schema::new().expires_at().into_map()).unwrap();
+        assert_eq!(schema, serde_json::json!({"expires_at": {"type": "datetime", "filterable": true}}));
+    }
+}
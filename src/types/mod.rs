@@ -1,9 +1,11 @@
+mod attr_value;
 mod distance_metric;
 mod id;
 mod row;
 mod vector_encoding;
 
+pub use attr_value::AttrValue;
 pub use distance_metric::DistanceMetric;
 pub use id::Id;
-pub use row::Row;
+pub use row::{Row, from_row};
 pub use vector_encoding::VectorEncoding;
@@ -1,3 +1,73 @@
 use std::collections::HashMap;
 
-pub type Row = HashMap<String, serde_json::Value>;
+use serde::de::DeserializeOwned;
+
+use crate::{Error, Id, Result};
+
+/// A row of attributes. `V` defaults to [`serde_json::Value`]; other
+/// attribute value types can be used with
+/// [`Namespace::query_rows`](crate::Namespace::query_rows).
+pub type Row<V = serde_json::Value> = HashMap<String, V>;
+
+/// Deserialize a row into `T`, reporting the row id and offending field on failure.
+pub fn from_row<T: DeserializeOwned>(row: &Row) -> Result<T> {
+    let value = serde_json::Value::Object(row.iter().map(|(k, v)| (k.clone(), v.clone())).collect());
+    serde_path_to_error::deserialize(value).map_err(|err| {
+        let path = err.path().to_string();
+        Error::RowConversion {
+            id: row.get("id").and_then(|v| serde_json::from_value::<Id>(v.clone()).ok()),
+            field: (path != ".").then_some(path),
+            source: err.into_inner(),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, serde::Deserialize)]
+    #[allow(dead_code)]
+    struct Doc {
+        id: Id,
+        title: String,
+        tags: Vec<String>,
+    }
+
+    fn row(json: serde_json::Value) -> Row {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn test_from_row() {
+        let doc: Doc = from_row(&row(serde_json::json!({"id": 1, "title": "a", "tags": ["x"]}))).unwrap();
+        assert_eq!(doc.title, "a");
+    }
+
+    #[test]
+    fn test_from_row_error_has_context() {
+        let err = from_row::<Doc>(&row(serde_json::json!({"id": 7, "title": null, "tags": []}))).unwrap_err();
+        match &err {
+            Error::RowConversion { id, field, .. } => {
+                assert_eq!(id, &Some(Id::Uint(7)));
+                assert_eq!(field.as_deref(), Some("title"));
+            }
+            other => panic!("unexpected error: {other:?}"),
+        }
+        let message = err.to_string();
+        assert!(message.contains("id 7"), "{message}");
+        assert!(message.contains("`title`"), "{message}");
+    }
+
+    #[test]
+    fn test_from_row_nested_field_path() {
+        let err = from_row::<Doc>(&row(serde_json::json!({"id": "doc-1", "title": "a", "tags": ["x", 3]}))).unwrap_err();
+        match err {
+            Error::RowConversion { id, field, .. } => {
+                assert_eq!(id, Some(Id::String("doc-1".to_string())));
+                assert_eq!(field.as_deref(), Some("tags[1]"));
+            }
+            other => panic!("unexpected error: {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,339 @@
+use std::collections::{HashMap, VecDeque};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::rt::{self, SystemTime, UNIX_EPOCH};
+use crate::{Client, Error, QueryBilling, Result};
+
+const SECS_PER_DAY: f64 = 86_400.0;
+
+/// Running query billing totals per namespace.
+///
+/// Register it with [`Client::with_usage_meter`]; every query made through
+/// that client (or its clones) is added to the totals, which a
+/// [`UsageRecorder`] on the same client includes in its snapshots. Clones
+/// share the totals.
+#[derive(Debug, Clone, Default)]
+pub struct UsageMeter {
+    totals: Arc<Mutex<HashMap<String, QueryUsage>>>,
+}
+
+impl UsageMeter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Totals for `namespace` since the meter was created.
+    pub fn query_usage(&self, namespace: &str) -> QueryUsage {
+        self.lock().get(namespace).copied().unwrap_or_default()
+    }
+
+    pub(crate) fn record(&self, namespace: &str, billing: Option<&QueryBilling>) {
+        let mut totals = self.lock();
+        let usage = totals.entry(namespace.to_string()).or_default();
+        usage.queries += 1;
+        if let Some(billing) = billing {
+            usage.billable_logical_bytes_queried += billing.billable_logical_bytes_queried;
+            usage.billable_logical_bytes_returned += billing.billable_logical_bytes_returned;
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, QueryUsage>> {
+        self.totals.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueryUsage {
+    pub queries: u64,
+    pub billable_logical_bytes_queried: u64,
+    pub billable_logical_bytes_returned: u64,
+}
+
+impl QueryUsage {
+    fn since(self, earlier: QueryUsage) -> QueryUsage {
+        QueryUsage {
+            queries: self.queries.saturating_sub(earlier.queries),
+            billable_logical_bytes_queried: self
+                .billable_logical_bytes_queried
+                .saturating_sub(earlier.billable_logical_bytes_queried),
+            billable_logical_bytes_returned: self
+                .billable_logical_bytes_returned
+                .saturating_sub(earlier.billable_logical_bytes_returned),
+        }
+    }
+}
+
+/// One namespace's size and query billing at a point in time, as recorded by
+/// [`UsageRecorder`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UsageSnapshot {
+    pub namespace: String,
+    /// Seconds since the Unix epoch.
+    pub taken_at: u64,
+    pub approx_row_count: Option<u64>,
+    pub approx_logical_bytes: Option<u64>,
+    /// Query billing since this namespace's previous snapshot; `None` unless
+    /// the client has a [`UsageMeter`].
+    pub queries: Option<QueryUsage>,
+    /// `None` until there are at least two snapshots to compare.
+    pub growth: Option<GrowthEstimate>,
+}
+
+impl UsageSnapshot {
+    /// Days until the namespace reaches `limit` logical bytes at the
+    /// estimated growth rate; `None` if it isn't growing.
+    pub fn days_until_bytes(&self, limit: u64) -> Option<f64> {
+        let bytes_per_day = self.growth?.bytes_per_day.filter(|rate| *rate > 0.0)?;
+        let remaining = limit.saturating_sub(self.approx_logical_bytes?);
+        Some(remaining as f64 / bytes_per_day)
+    }
+}
+
+/// Growth rates fitted by least squares over recent snapshots.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GrowthEstimate {
+    pub rows_per_day: Option<f64>,
+    pub bytes_per_day: Option<f64>,
+    /// Seconds between the oldest and newest snapshot used.
+    pub window_secs: u64,
+}
+
+/// Receives snapshots from a [`UsageRecorder`].
+///
+/// Closures taking `&UsageSnapshot` are sinks, and [`JsonLinesSink`] appends
+/// snapshots to a file.
+pub trait UsageSink: Send + Sync {
+    /// An error stops [`UsageRecorder::run`].
+    fn record(&self, snapshot: &UsageSnapshot) -> Result<()>;
+
+    /// Called when a namespace's metadata couldn't be fetched; it is skipped
+    /// for that round.
+    fn on_error(&self, _namespace: &str, _error: &Error) {}
+}
+
+impl<F> UsageSink for F
+where
+    F: Fn(&UsageSnapshot) + Send + Sync,
+{
+    fn record(&self, snapshot: &UsageSnapshot) -> Result<()> {
+        self(snapshot);
+        Ok(())
+    }
+}
+
+/// Appends each snapshot to a file as one line of JSON.
+pub struct JsonLinesSink {
+    file: Mutex<File>,
+}
+
+impl JsonLinesSink {
+    /// Open `path` for appending, creating it if needed.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self { file: Mutex::new(file) })
+    }
+}
+
+impl UsageSink for JsonLinesSink {
+    fn record(&self, snapshot: &UsageSnapshot) -> Result<()> {
+        let mut line = serde_json::to_vec(snapshot)?;
+        line.push(b'\n');
+        self.file.lock().unwrap_or_else(|e| e.into_inner()).write_all(&line)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Sample {
+    at: u64,
+    rows: Option<u64>,
+    bytes: Option<u64>,
+}
+
+/// Periodically snapshots namespace size and query billing into a
+/// [`UsageSink`] for capacity planning.
+///
+/// ```no_run
+/// # async fn example(client: rs_puff::Client) -> rs_puff::Result<()> {
+/// use rs_puff::usage::{JsonLinesSink, UsageRecorder};
+///
+/// let recorder = UsageRecorder::new(&client, ["products"], JsonLinesSink::open("usage.ndjson")?);
+/// tokio::spawn(recorder.run());
+/// # Ok(())
+/// # }
+/// ```
+pub struct UsageRecorder {
+    client: Client,
+    namespaces: Vec<String>,
+    sink: Box<dyn UsageSink>,
+    interval: Duration,
+    history: usize,
+    samples: HashMap<String, VecDeque<Sample>>,
+    billed: HashMap<String, QueryUsage>,
+}
+
+impl UsageRecorder {
+    pub fn new<S: Into<String>>(
+        client: &Client,
+        namespaces: impl IntoIterator<Item = S>,
+        sink: impl UsageSink + 'static,
+    ) -> Self {
+        Self {
+            client: client.clone(),
+            namespaces: namespaces.into_iter().map(Into::into).collect(),
+            sink: Box::new(sink),
+            interval: Duration::from_secs(3600),
+            history: 24,
+            samples: HashMap::new(),
+            billed: HashMap::new(),
+        }
+    }
+
+    /// Time between snapshots in [`UsageRecorder::run`]. Defaults to one hour.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Snapshots per namespace used for growth estimates. Defaults to 24.
+    pub fn history(mut self, snapshots: usize) -> Self {
+        self.history = snapshots.max(2);
+        self
+    }
+
+    /// Snapshot every namespace once and pass the results to the sink.
+    pub async fn record_once(&mut self) -> Result<Vec<UsageSnapshot>> {
+        let mut snapshots = Vec::with_capacity(self.namespaces.len());
+        for name in &self.namespaces {
+            let metadata = match self.client.namespace(name.as_str()).metadata().await {
+                Ok(metadata) => metadata,
+                Err(e) => {
+                    self.sink.on_error(name, &e);
+                    continue;
+                }
+            };
+            let taken_at = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+            let samples = self.samples.entry(name.clone()).or_default();
+            samples.push_back(Sample {
+                at: taken_at,
+                rows: metadata.approx_row_count,
+                bytes: metadata.approx_logical_bytes,
+            });
+            while samples.len() > self.history {
+                samples.pop_front();
+            }
+            let queries = self.client.inner.usage_meter.as_ref().map(|meter| {
+                let total = meter.query_usage(name);
+                let previous = self.billed.insert(name.clone(), total).unwrap_or_default();
+                total.since(previous)
+            });
+            let snapshot = UsageSnapshot {
+                namespace: name.clone(),
+                taken_at,
+                approx_row_count: metadata.approx_row_count,
+                approx_logical_bytes: metadata.approx_logical_bytes,
+                queries,
+                growth: estimate(samples),
+            };
+            self.sink.record(&snapshot)?;
+            snapshots.push(snapshot);
+        }
+        Ok(snapshots)
+    }
+
+    /// Snapshot every interval until the sink returns an error.
+    pub async fn run(mut self) -> Result<()> {
+        loop {
+            self.record_once().await?;
+            rt::sleep(self.interval).await;
+        }
+    }
+}
+
+fn estimate(samples: &VecDeque<Sample>) -> Option<GrowthEstimate> {
+    let first = samples.front()?;
+    let last = samples.back()?;
+    let window_secs = last.at.saturating_sub(first.at);
+    if window_secs == 0 {
+        return None;
+    }
+    let rows_per_day = slope(samples.iter().filter_map(|s| Some((s.at, s.rows?))));
+    let bytes_per_day = slope(samples.iter().filter_map(|s| Some((s.at, s.bytes?))));
+    Some(GrowthEstimate { rows_per_day, bytes_per_day, window_secs })
+}
+
+/// Least-squares slope of `(seconds, value)` points, per day.
+fn slope(points: impl Iterator<Item = (u64, u64)>) -> Option<f64> {
+    let points: Vec<(f64, f64)> = points.map(|(t, v)| (t as f64 / SECS_PER_DAY, v as f64)).collect();
+    if points.len() < 2 {
+        return None;
+    }
+    let n = points.len() as f64;
+    let mean_t = points.iter().map(|p| p.0).sum::<f64>() / n;
+    let mean_v = points.iter().map(|p| p.1).sum::<f64>() / n;
+    let covariance: f64 = points.iter().map(|(t, v)| (t - mean_t) * (v - mean_v)).sum();
+    let variance: f64 = points.iter().map(|(t, _)| (t - mean_t).powi(2)).sum();
+    (variance > 0.0).then(|| covariance / variance)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(hours: u64, rows: Option<u64>, bytes: u64) -> Sample {
+        Sample { at: 1_700_000_000 + hours * 3600, rows, bytes: Some(bytes) }
+    }
+
+    #[test]
+    fn test_estimate_fits_daily_growth() {
+        let samples: VecDeque<_> =
+            [sample(0, Some(100), 1000), sample(12, None, 1600), sample(24, Some(300), 2000)].into();
+        let growth = estimate(&samples).unwrap();
+        assert_eq!(growth.window_secs, 86_400);
+        assert!((growth.rows_per_day.unwrap() - 200.0).abs() < 1e-9);
+        assert!((growth.bytes_per_day.unwrap() - 1000.0).abs() < 1e-9);
+
+        assert!(estimate(&[sample(0, Some(1), 1)].into()).is_none());
+    }
+
+    #[test]
+    fn test_days_until_bytes() {
+        let mut snapshot = UsageSnapshot {
+            namespace: "ns".into(),
+            taken_at: 0,
+            approx_row_count: None,
+            approx_logical_bytes: Some(1000),
+            queries: None,
+            growth: Some(GrowthEstimate { rows_per_day: None, bytes_per_day: Some(500.0), window_secs: 86_400 }),
+        };
+        assert_eq!(snapshot.days_until_bytes(3000), Some(4.0));
+        snapshot.growth = Some(GrowthEstimate { rows_per_day: None, bytes_per_day: Some(-1.0), window_secs: 86_400 });
+        assert_eq!(snapshot.days_until_bytes(3000), None);
+    }
+
+    #[test]
+    fn test_meter_reports_usage_since_previous_total() {
+        let meter = UsageMeter::new();
+        let billing: QueryBilling = serde_json::from_value(serde_json::json!({
+            "billable_logical_bytes_queried": 100, "billable_logical_bytes_returned": 10
+        }))
+        .unwrap();
+        meter.record("a", Some(&billing));
+        let first = meter.query_usage("a");
+        meter.record("a", Some(&billing));
+        meter.record("a", None);
+        let usage = meter.query_usage("a").since(first);
+        assert_eq!(usage, QueryUsage {
+            queries: 2,
+            billable_logical_bytes_queried: 100,
+            billable_logical_bytes_returned: 10
+        });
+        assert_eq!(meter.query_usage("b"), QueryUsage::default());
+    }
+}
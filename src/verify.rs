@@ -0,0 +1,149 @@
+use crate::{
+    Consistency, ConsistencyLevel, Filter, IncludeAttributes, QueryParams, RankBy, Row, WriteParams, WriteResponse,
+};
+
+// Vectors are not compared: they may come back re-encoded or at lower precision.
+const VECTOR_ATTRIBUTE: &str = "vector";
+
+/// Strongly consistent query for the rows `rows` were upserted as, or `None`
+/// if any row has no id.
+pub(crate) fn read_back_query(rows: &[Row]) -> Option<QueryParams> {
+    let ids = rows.iter().map(|row| row.get("id").cloned()).collect::<Option<Vec<_>>>()?;
+    let mut attributes: Vec<String> = rows
+        .iter()
+        .flat_map(|row| row.keys())
+        .filter(|k| *k != "id" && *k != VECTOR_ATTRIBUTE)
+        .cloned()
+        .collect();
+    attributes.sort();
+    attributes.dedup();
+
+    Some(QueryParams {
+        rank_by: Some(RankBy::asc("id")),
+        top_k: Some(ids.len() as u64),
+        filters: Some(Filter::r#in("id", ids)),
+        include_attributes: Some(IncludeAttributes::List(attributes)),
+        consistency: Some(Consistency { level: ConsistencyLevel::Strong }),
+        ..Default::default()
+    })
+}
+
+/// Whether `params`, apart from its `upsert_rows`, does nothing a read-back of
+/// the upserted rows can't confirm.
+pub(crate) fn upserts_only(params: &WriteParams) -> bool {
+    let WriteParams {
+        upsert_rows: _,
+        upsert_condition: _,
+        distance_metric: _,
+        schema: _,
+        disable_backpressure: _,
+        return_affected_ids: _,
+        encryption: _,
+        upsert_columns,
+        patch_rows,
+        patch_columns,
+        deletes,
+        delete_by_filter,
+        patch_by_filter,
+        patch_condition,
+        delete_condition,
+        delete_by_filter_allow_partial,
+        patch_by_filter_allow_partial,
+        copy_from_namespace,
+    } = params;
+    upsert_columns.is_none()
+        && patch_rows.is_none()
+        && patch_columns.is_none()
+        && deletes.is_none()
+        && delete_by_filter.is_none()
+        && patch_by_filter.is_none()
+        && patch_condition.is_none()
+        && delete_condition.is_none()
+        && delete_by_filter_allow_partial.is_none()
+        && patch_by_filter_allow_partial.is_none()
+        && copy_from_namespace.is_none()
+}
+
+/// Whether every expected row is present in `found` with the same attribute values.
+pub(crate) fn rows_match(expected: &[Row], found: &[Row]) -> bool {
+    expected.iter().all(|row| {
+        found.iter().find(|f| f.get("id") == row.get("id")).is_some_and(|f| {
+            row.iter()
+                .filter(|(k, _)| *k != VECTOR_ATTRIBUTE)
+                .all(|(k, v)| f.get(k).is_some_and(|actual| values_match(v, actual)))
+        })
+    })
+}
+
+fn values_match(expected: &serde_json::Value, actual: &serde_json::Value) -> bool {
+    match (expected, actual) {
+        // 1 and 1.0 are the same stored value.
+        (serde_json::Value::Number(a), serde_json::Value::Number(b)) => a.as_f64() == b.as_f64(),
+        (serde_json::Value::Array(a), serde_json::Value::Array(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| values_match(a, b))
+        }
+        _ => expected == actual,
+    }
+}
+
+/// Response reported for a write confirmed by read-back.
+pub(crate) fn applied_response(rows: &[Row]) -> WriteResponse {
+    let count = rows.len() as u64;
+    WriteResponse {
+        rows_affected: count,
+        rows_upserted: Some(count),
+        rows_patched: None,
+        rows_deleted: None,
+        rows_remaining: None,
+        upserted_ids: None,
+        patched_ids: None,
+        deleted_ids: None,
+        billing: None,
+        extra: Default::default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(value: serde_json::Value) -> Row {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_read_back_query_requests_written_attributes() {
+        let rows = vec![
+            row(serde_json::json!({"id": 1, "title": "a", "vector": [0.1]})),
+            row(serde_json::json!({"id": 2, "score": 3})),
+        ];
+        let query = serde_json::to_value(read_back_query(&rows).unwrap()).unwrap();
+        assert_eq!(query["filters"], serde_json::json!(["id", "In", [1, 2]]));
+        assert_eq!(query["include_attributes"], serde_json::json!(["score", "title"]));
+        assert_eq!(query["consistency"]["level"], "strong");
+
+        assert!(read_back_query(&[row(serde_json::json!({"title": "no id"}))]).is_none());
+    }
+
+    #[test]
+    fn test_rows_match() {
+        let expected = vec![row(serde_json::json!({"id": 1, "score": 2, "vector": [0.1]}))];
+        assert!(rows_match(&expected, &[row(serde_json::json!({"id": 1, "score": 2.0}))]));
+        assert!(!rows_match(&expected, &[row(serde_json::json!({"id": 1, "score": 3}))]));
+        assert!(!rows_match(&expected, &[]));
+    }
+
+    #[test]
+    fn test_only_upserts_are_verifiable() {
+        let upsert = WriteParams {
+            upsert_rows: Some(vec![row(serde_json::json!({"id": 1}))]),
+            upsert_condition: Some(Filter::eq("v", 1)),
+            ..Default::default()
+        };
+        assert!(upserts_only(&upsert));
+        let with_delete = WriteParams { delete_by_filter: Some(Filter::eq("v", 0)), ..upsert.clone() };
+        assert!(!upserts_only(&with_delete));
+        let with_deletes = WriteParams { deletes: Some(vec![2.into()]), ..upsert };
+        assert!(!upserts_only(&with_deletes));
+    }
+}
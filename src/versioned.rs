@@ -0,0 +1,53 @@
+use crate::{Error, Filter, Id, Namespace, Result, Row, WriteParams};
+
+/// Attribute holding a row's version for [`Namespace::patch_versioned`].
+pub const VERSION_ATTRIBUTE: &str = "version";
+
+fn versioned_patch(id: &Id, mut patch: Row, expected_version: u64) -> Result<WriteParams> {
+    patch.insert("id".to_string(), serde_json::to_value(id)?);
+    patch.insert(VERSION_ATTRIBUTE.to_string(), (expected_version + 1).into());
+    Ok(WriteParams {
+        patch_rows: Some(vec![patch]),
+        patch_condition: Some(Filter::eq(VERSION_ATTRIBUTE, expected_version)),
+        ..Default::default()
+    })
+}
+
+impl Namespace {
+    /// Optimistic concurrency: apply `patch` to row `id` only if its
+    /// [`VERSION_ATTRIBUTE`] still equals `expected_version`, bumping it by one.
+    ///
+    /// Returns the new version. If another writer got there first, or the row
+    /// doesn't exist, nothing is written and [`Error::Conflict`] is returned;
+    /// re-read the row and try again. Rows need an initial version, e.g. by
+    /// upserting them with `"version": 1`.
+    pub async fn patch_versioned(&self, id: impl Into<Id>, patch: Row, expected_version: u64) -> Result<u64> {
+        let id = id.into();
+        let resp = self.write(versioned_patch(&id, patch, expected_version)?).await?;
+        if resp.rows_patched.unwrap_or(resp.rows_affected) == 0 {
+            return Err(Error::Conflict { id, expected_version });
+        }
+        Ok(expected_version + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_versioned_patch_conditions_on_expected_version() {
+        let patch: Row = serde_json::from_value(serde_json::json!({"title": "new", "version": 99})).unwrap();
+        let params = versioned_patch(&Id::from("doc-1"), patch, 3).unwrap();
+        assert!(!params.is_idempotent());
+        let body = serde_json::to_value(params).unwrap();
+        assert_eq!(body["patch_rows"], serde_json::json!([{"id": "doc-1", "title": "new", "version": 4}]));
+        assert_eq!(body["patch_condition"], serde_json::json!(["version", "Eq", 3]));
+    }
+
+    #[test]
+    fn test_conflict_message() {
+        let err = Error::Conflict { id: Id::from(7u64), expected_version: 3 };
+        assert_eq!(err.to_string(), "version conflict on row with id 7: expected version 3");
+    }
+}
@@ -0,0 +1,105 @@
+use std::time::Duration;
+
+use futures_util::Stream;
+
+use crate::rt;
+use crate::{Namespace, NamespaceMetadata, Result};
+
+/// A change observed by [`Namespace::watch_metadata`].
+#[derive(Debug, Clone)]
+pub struct MetadataDiff {
+    /// `None` for the first poll.
+    pub previous: Option<NamespaceMetadata>,
+    pub current: NamespaceMetadata,
+}
+
+impl MetadataDiff {
+    pub fn bytes_delta(&self) -> Option<i64> {
+        let previous = self.previous.as_ref()?.approx_logical_bytes?;
+        Some(self.current.approx_logical_bytes? as i64 - previous as i64)
+    }
+
+    pub fn row_count_delta(&self) -> Option<i64> {
+        let previous = self.previous.as_ref()?.approx_row_count?;
+        Some(self.current.approx_row_count? as i64 - previous as i64)
+    }
+
+    pub fn index_status_changed(&self) -> bool {
+        self.previous.as_ref().is_some_and(|p| index_status(p) != index_status(&self.current))
+    }
+
+    pub fn schema_changed(&self) -> bool {
+        self.previous.as_ref().is_some_and(|p| p.schema != self.current.schema)
+    }
+}
+
+fn index_status(metadata: &NamespaceMetadata) -> Option<&str> {
+    metadata.index.as_ref()?.status.as_deref()
+}
+
+fn changed(previous: &NamespaceMetadata, current: &NamespaceMetadata) -> bool {
+    previous.approx_logical_bytes != current.approx_logical_bytes
+        || previous.approx_row_count != current.approx_row_count
+        || index_status(previous) != index_status(current)
+        || previous.schema != current.schema
+}
+
+impl Namespace {
+    /// Poll metadata every `interval`, yielding the first result and then
+    /// only polls where size, row count, index status or schema changed.
+    ///
+    /// Failed polls are yielded as errors and polling continues; drop the
+    /// stream to stop.
+    pub fn watch_metadata(&self, interval: Duration) -> impl Stream<Item = Result<MetadataDiff>> + '_ {
+        futures_util::stream::unfold((None, true), move |(mut previous, first)| async move {
+            if !first {
+                rt::sleep(interval).await;
+            }
+            loop {
+                match self.metadata().await {
+                    Err(e) => return Some((Err(e), (previous, false))),
+                    Ok(current) => {
+                        if previous.as_ref().is_none_or(|p| changed(p, &current)) {
+                            let diff = MetadataDiff { previous: previous.replace(current.clone()), current };
+                            return Some((Ok(diff), (previous, false)));
+                        }
+                    }
+                }
+                rt::sleep(interval).await;
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(value: serde_json::Value) -> NamespaceMetadata {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_diff_reports_growth_and_index_changes() {
+        let previous = metadata(serde_json::json!({
+            "approx_logical_bytes": 1000, "approx_row_count": 10, "index": {"status": "updating"}
+        }));
+        let current = metadata(serde_json::json!({
+            "approx_logical_bytes": 1500, "approx_row_count": 8, "index": {"status": "up-to-date"}
+        }));
+        assert!(changed(&previous, &current));
+
+        let diff = MetadataDiff { previous: Some(previous), current };
+        assert_eq!(diff.bytes_delta(), Some(500));
+        assert_eq!(diff.row_count_delta(), Some(-2));
+        assert!(diff.index_status_changed());
+        assert!(!diff.schema_changed());
+    }
+
+    #[test]
+    fn test_updated_at_alone_is_not_a_change() {
+        let a = metadata(serde_json::json!({"approx_row_count": 1, "updated_at": "2024-01-01T00:00:00Z"}));
+        let b = metadata(serde_json::json!({"approx_row_count": 1, "updated_at": "2024-01-02T00:00:00Z"}));
+        assert!(!changed(&a, &b));
+    }
+}
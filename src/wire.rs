@@ -0,0 +1,172 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{AggregateBy, Error, Filter, MultiQueryParams, QueryParams, RankBy, Result, WriteParams};
+
+/// The JSON the turbopuffer API sends and receives, for passing requests
+/// through job queues or other languages without an HTTP call.
+///
+/// Parsing accepts exactly what the API accepts for these types: unknown
+/// operators and fields are errors rather than being dropped, so a request
+/// that parses is sent unchanged. Available without the `client` feature.
+///
+/// ```
+/// use rs_puff::{Filter, QueryParams, RankBy, WireFormat};
+///
+/// let query = QueryParams {
+///     rank_by: Some(RankBy::desc("views")),
+///     filters: Some(Filter::eq("lang", "en")),
+///     ..Default::default()
+/// };
+/// let json = query.to_wire_json();
+/// assert_eq!(json, r#"{"rank_by":["views","desc"],"filters":["lang","Eq","en"]}"#);
+/// let query = QueryParams::from_wire_json(&json)?;
+/// # Ok::<(), rs_puff::Error>(())
+/// ```
+pub trait WireFormat: Serialize + DeserializeOwned {
+    /// Compact JSON, exactly as it would be sent.
+    fn to_wire_json(&self) -> String {
+        serde_json::to_string(self).expect("wire types always serialize")
+    }
+
+    fn from_wire_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+}
+
+impl WireFormat for Filter {}
+impl WireFormat for RankBy {}
+impl WireFormat for AggregateBy {}
+impl WireFormat for QueryParams {}
+impl WireFormat for MultiQueryParams {}
+impl WireFormat for WriteParams {}
+
+/// Deserialize part of a hand-parsed wire value, keeping serde's message.
+pub(crate) fn from_value<T: DeserializeOwned>(value: serde_json::Value) -> std::result::Result<T, String> {
+    serde_json::from_value(value).map_err(|e| e.to_string())
+}
+
+/// Check that every case in a wire-format fixture parses and prints back as
+/// the same JSON, returning the number of cases checked.
+///
+/// This lets systems that also use the official TypeScript or Python SDKs
+/// assert that every SDK sends the same requests: dump what the other SDK
+/// serializes into a fixture of named cases, grouped by type, and verify it
+/// in a Rust test. The sections are `filters`, `rank_by`, `aggregate_by`,
+/// `queries`, `multi_queries` and `writes`, each optional:
+///
+/// ```json
+/// {
+///   "filters": {"english": ["And", [["lang", "Eq", "en"], ["views", "Gte", 10]]]},
+///   "rank_by": {"nearest": ["vector", "ANN", [0.1, 0.2]]},
+///   "aggregate_by": {"count": ["Count"]},
+///   "queries": {"top": {"rank_by": ["views", "desc"], "top_k": 10}},
+///   "writes": {"delete": {"deletes": [1, 2]}}
+/// }
+/// ```
+///
+/// Numbers compare by value, so `1` matches `1.0`, and vector components
+/// match at `f32` precision. Fields set to `null` don't match, as rs-puff
+/// omits them. All failing cases are reported in one [`Error::Verification`].
+pub fn verify_fixture(path: impl AsRef<Path>) -> Result<usize> {
+    verify_fixture_json(&std::fs::read_to_string(path)?)
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Fixture {
+    #[serde(default)]
+    filters: BTreeMap<String, Value>,
+    #[serde(default)]
+    rank_by: BTreeMap<String, Value>,
+    #[serde(default)]
+    aggregate_by: BTreeMap<String, Value>,
+    #[serde(default)]
+    queries: BTreeMap<String, Value>,
+    #[serde(default)]
+    multi_queries: BTreeMap<String, Value>,
+    #[serde(default)]
+    writes: BTreeMap<String, Value>,
+}
+
+type Check = fn(&Value) -> std::result::Result<(), String>;
+
+fn verify_fixture_json(json: &str) -> Result<usize> {
+    let fixture: Fixture = serde_json::from_str(json)?;
+    let sections: [(&str, BTreeMap<String, Value>, Check); 6] = [
+        ("filters", fixture.filters, roundtrip::<Filter>),
+        ("rank_by", fixture.rank_by, roundtrip::<RankBy>),
+        ("aggregate_by", fixture.aggregate_by, roundtrip::<AggregateBy>),
+        ("queries", fixture.queries, roundtrip::<QueryParams>),
+        ("multi_queries", fixture.multi_queries, roundtrip::<MultiQueryParams>),
+        ("writes", fixture.writes, roundtrip::<WriteParams>),
+    ];
+
+    let mut checked = 0;
+    let mut failures = Vec::new();
+    for (section, cases, check) in sections {
+        for (name, wire) in cases {
+            checked += 1;
+            if let Err(e) = check(&wire) {
+                failures.push(format!("{}.{}: {}", section, name, e));
+            }
+        }
+    }
+    if failures.is_empty() {
+        Ok(checked)
+    } else {
+        Err(Error::Verification(failures.join("; ")))
+    }
+}
+
+fn roundtrip<T: WireFormat>(wire: &Value) -> std::result::Result<(), String> {
+    let parsed: T = from_value(wire.clone())?;
+    let printed: Value = serde_json::from_str(&parsed.to_wire_json()).expect("wire JSON always parses");
+    if same_json(wire, &printed) { Ok(()) } else { Err(format!("printed as {}", printed)) }
+}
+
+fn same_json(expected: &Value, actual: &Value) -> bool {
+    match (expected, actual) {
+        // JavaScript has a single number type, and vectors are sent as f32.
+        (Value::Number(a), Value::Number(b)) => {
+            a.as_f64() == b.as_f64() || a.as_f64().map(|a| a as f32) == b.as_f64().map(|b| b as f32)
+        }
+        (Value::Array(a), Value::Array(b)) => a.len() == b.len() && a.iter().zip(b).all(|(a, b)| same_json(a, b)),
+        (Value::Object(a), Value::Object(b)) => {
+            a.len() == b.len() && a.iter().all(|(k, v)| b.get(k).is_some_and(|actual| same_json(v, actual)))
+        }
+        _ => expected == actual,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_golden_fixture_roundtrips() {
+        let checked = verify_fixture(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/wire.json")).unwrap();
+        assert!(checked > 50, "only {} cases", checked);
+    }
+
+    #[test]
+    fn test_fixture_reports_every_failing_case() {
+        let fixture = serde_json::json!({
+            "filters": {"ok": ["a", "Eq", 1], "unknown_op": ["a", "Near", 1]},
+            "queries": {"null_field": {"top_k": 10, "filters": null}},
+        });
+        let Err(Error::Verification(message)) = verify_fixture_json(&fixture.to_string()) else {
+            panic!("fixture should fail");
+        };
+        assert!(message.contains("filters.unknown_op: unknown filter operator \"Near\""), "{}", message);
+        assert!(message.contains(r#"queries.null_field: printed as {"top_k":10}"#), "{}", message);
+        assert!(!message.contains("filters.ok"));
+
+        assert!(verify_fixture_json(r#"{"filter": {}}"#).is_err());
+        assert!(matches!(verify_fixture("/nonexistent/wire.json"), Err(Error::Io(_))));
+    }
+}
@@ -0,0 +1,369 @@
+use rs_puff::emulator::Emulator;
+use rs_puff::{
+    AggregateBy, AttributeMerge, AttributeType, BlueGreenIndex, BulkWriter, CallOptions, DefaultAttributes, DistanceMetric, Document,
+    Error, ExportParams, Filter, IncludeAttributes, Mask, MultiQueryParams, NamespaceRepository, NamespacesParams, PatchByFilter, PatchRuleReport,
+    PatchRulesParams, QueryParams, RankBy, StandbyChecks, VectorRepository, WriteParams,
+};
+use std::collections::HashMap;
+
+fn row(id: i64, vector: Vec<f64>, attrs: Vec<(&str, serde_json::Value)>) -> HashMap<String, serde_json::Value> {
+    let mut map = HashMap::new();
+    map.insert("id".to_string(), serde_json::json!(id));
+    map.insert("vector".to_string(), serde_json::json!(vector));
+    for (k, v) in attrs {
+        map.insert(k.to_string(), v);
+    }
+    map
+}
+
+fn docs() -> Vec<HashMap<String, serde_json::Value>> {
+    vec![
+        row(1, vec![1.0, 0.0], vec![("lang", "en".into()), ("title", "fast vector search".into()), ("views", 10.into())]),
+        row(2, vec![0.0, 1.0], vec![("lang", "de".into()), ("title", "schnelle suche".into()), ("views", 20.into())]),
+        row(3, vec![0.7, 0.7], vec![("lang", "en".into()), ("title", "search search search".into()), ("views", 30.into())]),
+    ]
+}
+
+async fn seeded(emulator: &Emulator) -> rs_puff::Namespace {
+    let ns = emulator.client().namespace("docs");
+    ns.write(WriteParams {
+        upsert_rows: Some(docs()),
+        distance_metric: Some(DistanceMetric::CosineDistance),
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+    ns
+}
+
+fn ids(rows: &[HashMap<String, serde_json::Value>]) -> Vec<i64> {
+    rows.iter().map(|row| row["id"].as_i64().unwrap()).collect()
+}
+
+#[tokio::test]
+async fn test_write_query_roundtrip() {
+    let emulator = Emulator::start().unwrap();
+    let ns = seeded(&emulator).await;
+
+    let results = ns
+        .query(QueryParams {
+            rank_by: Some(RankBy::vector("vector", vec![1.0, 0.1])),
+            top_k: Some(2),
+            include_attributes: Some(IncludeAttributes::List(vec!["lang".to_string()])),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(ids(&results.rows), [1, 3]);
+    assert_eq!(results.rows[0]["lang"], "en");
+    assert!(!results.rows[0].contains_key("title"));
+    assert!(results.rows[0]["$dist"].as_f64().unwrap() < results.rows[1]["$dist"].as_f64().unwrap());
+
+    let results = ns
+        .query(QueryParams {
+            rank_by: Some(RankBy::bm25("title", "search")),
+            filters: Some(Filter::eq("lang", "en")),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(ids(&results.rows), [3, 1]);
+
+    let results = ns
+        .query(QueryParams { rank_by: Some(RankBy::desc("views")), top_k: Some(10), ..Default::default() })
+        .await
+        .unwrap();
+    assert_eq!(ids(&results.rows), [3, 2, 1]);
+}
+
+#[tokio::test]
+async fn test_patches_deletes_and_aggregations() {
+    let emulator = Emulator::start().unwrap();
+    let ns = seeded(&emulator).await;
+
+    let write = ns
+        .write(WriteParams {
+            patch_rows: Some(vec![row(2, vec![0.0, 1.0], vec![("lang", "en".into())])]),
+            deletes: Some(vec![1.into()]),
+            return_affected_ids: Some(true),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(write.rows_affected, 2);
+
+    let results = ns
+        .query(QueryParams {
+            aggregate_by: Some(HashMap::from([
+                ("docs".to_string(), AggregateBy::Count),
+                ("views".to_string(), AggregateBy::Sum("views".to_string())),
+            ])),
+            group_by: Some(vec!["lang".to_string()]),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    let groups = results.groups();
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0].key_str("lang"), Some("en"));
+    assert_eq!(groups[0].count("docs"), Some(2));
+    assert_eq!(groups[0].aggregation("views"), Some(50.0));
+
+    let multi = ns
+        .multi_query(MultiQueryParams {
+            queries: vec![
+                QueryParams { filters: Some(Filter::eq("id", 1)), ..Default::default() },
+                QueryParams { filters: Some(Filter::eq("id", 2)), ..Default::default() },
+            ],
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(multi.results.len(), 2);
+    assert!(multi.results[0].rows.is_empty());
+    assert_eq!(ids(&multi.results[1].rows), [2]);
+}
+
+#[tokio::test]
+async fn test_metadata_listing_and_delete_all() {
+    let emulator = Emulator::start().unwrap();
+    let ns = seeded(&emulator).await;
+
+    let metadata = ns.metadata().await.unwrap();
+    assert_eq!(metadata.approx_row_count, Some(3));
+    assert_eq!(metadata.schema.unwrap()["vector"]["type"], AttributeType::vector_f32(2).to_string());
+
+    let listed = emulator.client().namespaces(NamespacesParams::default()).await.unwrap();
+    assert_eq!(listed.namespaces.iter().map(|ns| ns.id.as_str()).collect::<Vec<_>>(), ["docs"]);
+    emulator.client().ping().await.unwrap();
+
+    ns.delete_all().await.unwrap();
+    match ns.metadata().await.unwrap_err() {
+        Error::Api { status, .. } => assert_eq!(status, 404),
+        err => panic!("expected a 404, got {:?}", err),
+    }
+}
+
+#[tokio::test]
+async fn test_default_attributes_merge_with_queries() {
+    let emulator = Emulator::start().unwrap();
+    seeded(&emulator).await;
+    let ns = emulator
+        .client()
+        .namespace("docs")
+        .with_default_attributes(DefaultAttributes::include(["title", "lang"]).merge(AttributeMerge::Union));
+    let attributes = |rows: &[HashMap<String, serde_json::Value>]| {
+        let mut attrs: Vec<_> = rows[0].keys().cloned().collect();
+        attrs.sort();
+        attrs
+    };
+    let by_id = || QueryParams { filters: Some(Filter::eq("id", 1)), ..Default::default() };
+
+    let rows = ns.query(by_id()).await.unwrap().rows;
+    assert_eq!(attributes(&rows), ["id", "lang", "title"]);
+
+    let own = QueryParams { include_attributes: Some(IncludeAttributes::List(vec!["views".to_string()])), ..by_id() };
+    let rows = ns.query(own.clone()).await.unwrap().rows;
+    assert_eq!(attributes(&rows), ["id", "lang", "title", "views"]);
+
+    let opts = CallOptions::default().attribute_merge(AttributeMerge::PerCall);
+    let rows = ns.query_with_options(own.clone(), &opts).await.unwrap().rows;
+    assert_eq!(attributes(&rows), ["id", "views"]);
+
+    let multi = ns.multi_query(MultiQueryParams { queries: vec![by_id(), own], ..Default::default() }).await.unwrap();
+    assert_eq!(attributes(&multi.results[1].rows), ["id", "lang", "title", "views"]);
+}
+
+#[tokio::test]
+async fn test_unsupported_params_are_rejected() {
+    let emulator = Emulator::start().unwrap();
+    let ns = seeded(&emulator).await;
+
+    let err = ns
+        .query(QueryParams { filters: Some(Filter::regex("title", "^fast")), ..Default::default() })
+        .await
+        .unwrap_err();
+    match err {
+        Error::Api { status, message } => {
+            assert_eq!(status, 400);
+            assert!(message.contains("Regex"), "{}", message);
+        }
+        err => panic!("expected a 400, got {:?}", err),
+    }
+}
+
+#[tokio::test]
+async fn test_self_test_passes_against_emulator() {
+    let emulator = Emulator::start().unwrap();
+    let ns = seeded(&emulator).await;
+
+    let report = ns.self_test().await.unwrap();
+    assert!(report.passed(), "{:?}", report.failure());
+    assert!(ns.get(report.sentinel_id).await.unwrap().is_none());
+    assert!(ns.get(1).await.unwrap().is_some());
+}
+
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+struct Note {
+    id: u64,
+    text: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    vector: Vec<f32>,
+}
+
+impl Document for Note {
+    const ATTRIBUTES: &'static [&'static str] = &["id", "text"];
+}
+
+#[tokio::test]
+async fn test_repository_saves_searches_and_deletes() {
+    let emulator = Emulator::start().unwrap();
+    let notes = NamespaceRepository::<Note>::new(emulator.client().namespace("notes"));
+    let note = |id: u64, text: &str, vector: Vec<f32>| Note { id, text: text.to_string(), vector };
+    notes
+        .save(&[note(1, "east", vec![1.0, 0.0]), note(2, "north", vec![0.0, 1.0]), note(3, "north east", vec![0.7, 0.7])])
+        .await
+        .unwrap();
+
+    assert_eq!(notes.get(2).await.unwrap(), Some(note(2, "north", vec![])));
+    assert_eq!(notes.get(9).await.unwrap(), None);
+
+    // Repositories over owned namespaces can move into spawned tasks.
+    let (notes, nearest) = tokio::spawn(async move {
+        let nearest = notes.search(vec![1.0, 0.1], 2, None).await.unwrap();
+        (notes, nearest)
+    })
+    .await
+    .unwrap();
+    assert_eq!(nearest.iter().map(|note| note.id).collect::<Vec<_>>(), [1, 3]);
+    let filtered = notes.search(vec![1.0, 0.1], 2, Some(Filter::glob("text", "north*"))).await.unwrap();
+    assert_eq!(filtered.iter().map(|note| note.id).collect::<Vec<_>>(), [3, 2]);
+
+    notes.delete([1, 3]).await.unwrap();
+    let remaining = notes.search(vec![1.0, 0.0], 10, None).await.unwrap();
+    assert_eq!(remaining, [note(2, "north", vec![])]);
+}
+
+#[tokio::test]
+async fn test_blue_green_index_promotes_validated_standby() {
+    let emulator = Emulator::start().unwrap();
+    let client = emulator.client();
+    let index = BlueGreenIndex::new(client.namespace("docs-blue"), client.namespace("docs-green"))
+        .distance_metric(DistanceMetric::CosineDistance);
+    let rows = || futures_util::stream::iter(docs().into_iter().map(Ok));
+
+    let report = index.rebuild(rows()).await.unwrap();
+    assert_eq!(report.rows_upserted, 3);
+    assert_eq!(index.active_name(), "docs-blue");
+    assert!(!index.active().exists().await.unwrap());
+
+    // A failed check leaves the active namespace in place.
+    let err = index.promote(&StandbyChecks { expected_rows: Some(4), ..Default::default() }).await.unwrap_err();
+    assert!(matches!(&err, Error::Verification(message) if message.contains("docs-green has 3 rows, expected 4")));
+    assert_eq!(index.active_name(), "docs-blue");
+
+    let checks = StandbyChecks {
+        expected_rows: Some(3),
+        recall_queries: vec![QueryParams {
+            rank_by: Some(RankBy::vector("vector", vec![1.0, 0.1])),
+            top_k: Some(2),
+            ..Default::default()
+        }],
+        ..Default::default()
+    };
+    let report = index.promote(&checks).await.unwrap();
+    assert_eq!((report.rows, report.recall), (3, Some(1.0)));
+    assert_eq!(index.active_name(), "docs-green");
+    assert_eq!(index.active().query(QueryParams { top_k: Some(10), ..Default::default() }).await.unwrap().rows.len(), 3);
+
+    // The next rebuild goes to the old namespace; flipping back rolls a promotion back.
+    index.rebuild(rows()).await.unwrap();
+    assert_eq!(index.standby().name(), "docs-blue");
+    assert_eq!(index.flip().name(), "docs-blue");
+    assert_eq!(index.flip().name(), "docs-green");
+
+    let restored = BlueGreenIndex::new(client.namespace("docs-blue"), client.namespace("docs-green"))
+        .with_active("docs-green")
+        .unwrap();
+    assert_eq!(restored.standby().name(), "docs-blue");
+    assert!(BlueGreenIndex::new(client.namespace("a"), client.namespace("b")).with_active("c").is_err());
+}
+
+#[tokio::test]
+async fn test_patch_by_filters_applies_rules_in_order() {
+    let emulator = Emulator::start().unwrap();
+    let ns = seeded(&emulator).await;
+    let rule = |filters: Filter, attr: &str, value: serde_json::Value| PatchByFilter {
+        filters,
+        patch: HashMap::from([(attr.to_string(), value)]),
+    };
+    let rules = vec![
+        rule(Filter::eq("tier", serde_json::Value::Null), "tier", "standard".into()),
+        // Sees the rows the first rule patched.
+        rule(Filter::and(vec![Filter::eq("tier", "standard"), Filter::gte("views", 20)]), "tier", "top".into()),
+        rule(Filter::regex("title", "^fast"), "tier", "fast".into()),
+        rule(Filter::eq("lang", "de"), "region", "eu".into()),
+    ];
+
+    let results = ns.patch_by_filters(rules.clone(), &PatchRulesParams::default()).await;
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[0].as_ref().unwrap(), &PatchRuleReport { rows_patched: 3, writes: 1 });
+    assert_eq!(results[1].as_ref().unwrap().rows_patched, 2);
+    assert!(matches!(results[2], Err(Error::Api { status: 400, .. })), "{:?}", results[2]);
+
+    let params = PatchRulesParams { allow_partial: true, continue_on_error: true, ..Default::default() };
+    let results = ns.patch_by_filters(rules, &params).await;
+    assert_eq!(results.len(), 4);
+    assert_eq!(results[0].as_ref().unwrap().rows_patched, 0);
+    assert_eq!(results[3].as_ref().unwrap().rows_patched, 1);
+
+    let rows = ns
+        .query(QueryParams {
+            rank_by: Some(RankBy::asc("id")),
+            top_k: Some(10),
+            include_attributes: Some(IncludeAttributes::All(true)),
+            ..Default::default()
+        })
+        .await
+        .unwrap()
+        .rows;
+    assert_eq!(rows.iter().map(|row| row["tier"].as_str().unwrap()).collect::<Vec<_>>(), ["standard", "top", "top"]);
+    assert_eq!(rows[1]["region"], "eu");
+}
+
+#[tokio::test]
+async fn test_internal_reads_ignore_read_hooks() {
+    let emulator = Emulator::start().unwrap();
+    let ns = seeded(&emulator).await.with_read_hook(Mask::new(["title", "_hash"], "***"));
+
+    let mut exporter = ns.exporter(ExportParams::default());
+    let page = exporter.next_page().await.unwrap().unwrap();
+    assert_eq!(page[0]["title"], "fast vector search");
+
+    for expected_skipped in [0, 3] {
+        let mut writer = BulkWriter::new(&ns).content_hash("_hash");
+        for row in docs() {
+            writer.push(row).await.unwrap();
+        }
+        assert_eq!(writer.finish().await.unwrap().rows_skipped, expected_skipped);
+    }
+}
+
+#[tokio::test]
+async fn test_internal_reads_ignore_default_attributes() {
+    let emulator = Emulator::start().unwrap();
+    let defaults = DefaultAttributes::exclude(["vector", "_hash"]).merge(AttributeMerge::Intersection);
+    let ns = seeded(&emulator).await.with_default_attributes(defaults);
+
+    let mut exporter = ns.exporter(ExportParams::default());
+    let page = exporter.next_page().await.unwrap().unwrap();
+    assert_eq!(page[0]["vector"], serde_json::json!([1.0, 0.0]));
+
+    for expected_skipped in [0, 3] {
+        let mut writer = BulkWriter::new(&ns).content_hash("_hash");
+        for row in docs() {
+            writer.push(row).await.unwrap();
+        }
+        assert_eq!(writer.finish().await.unwrap().rows_skipped, expected_skipped);
+    }
+}
@@ -1,22 +1,10 @@
 use rs_puff::{
-    Client, DistanceMetric, Filter, IncludeAttributes, NamespacesParams, QueryParams, RankBy,
+    AttributeType, Client, DistanceMetric, Filter, IncludeAttributes, Method, NamespacesParams, QueryParams, RankBy,
     WriteParams,
 };
+use rs_puff::testing::TempNamespace;
 use serial_test::serial;
 use std::collections::HashMap;
-use std::time::{SystemTime, UNIX_EPOCH};
-
-fn test_prefix() -> String {
-    use std::sync::atomic::{AtomicU64, Ordering};
-    static COUNTER: AtomicU64 = AtomicU64::new(0);
-
-    let nonce = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_nanos();
-    let count = COUNTER.fetch_add(1, Ordering::SeqCst);
-    format!("rust_sdk_{}_{}_", nonce, count)
-}
 
 fn setup() -> Client {
     dotenvy::dotenv().ok();
@@ -26,6 +14,11 @@ fn setup() -> Client {
     )
 }
 
+// Shares the `rust_sdk_` prefix so `test_zz_cleanup_ephemeral_namespaces` sweeps orphans.
+fn temp_namespace(client: &Client) -> TempNamespace {
+    TempNamespace::with_prefix(client, "rust_sdk_")
+}
+
 fn row(id: i64, vector: Vec<f64>, attrs: Vec<(&str, serde_json::Value)>) -> HashMap<String, serde_json::Value> {
     let mut map = HashMap::new();
     map.insert("id".to_string(), serde_json::json!(id));
@@ -40,11 +33,7 @@ fn row(id: i64, vector: Vec<f64>, attrs: Vec<(&str, serde_json::Value)>) -> Hash
 #[serial]
 async fn test_sanity() {
     let client = setup();
-    let prefix = test_prefix();
-    let ns = client.namespace(format!("{}sanity", prefix));
-
-    // Clean up if exists
-    let _ = ns.delete_all().await;
+    let ns = temp_namespace(&client);
 
     // Write some rows
     let write_result = ns
@@ -146,17 +135,14 @@ async fn test_sanity() {
     assert_eq!(results3.rows[0].get("id").unwrap(), 2);
 
     // Clean up
-    ns.delete_all().await.unwrap();
+    ns.close().await.unwrap();
 }
 
 #[tokio::test]
 #[serial]
 async fn test_order_by_attribute() {
     let client = setup();
-    let prefix = test_prefix();
-    let ns = client.namespace(format!("{}order_by_attribute", prefix));
-
-    let _ = ns.delete_all().await;
+    let ns = temp_namespace(&client);
 
     ns.write(WriteParams {
         upsert_rows: Some(vec![
@@ -206,17 +192,14 @@ async fn test_order_by_attribute() {
     assert_eq!(results_desc.rows[3].get("id").unwrap(), 4);
     assert_eq!(results_desc.rows[4].get("id").unwrap(), 5);
 
-    ns.delete_all().await.unwrap();
+    ns.close().await.unwrap();
 }
 
 #[tokio::test]
 #[serial]
 async fn test_contains_and_contains_any() {
     let client = setup();
-    let prefix = test_prefix();
-    let ns = client.namespace(format!("{}contains_and_contains_any", prefix));
-
-    let _ = ns.delete_all().await;
+    let ns = temp_namespace(&client);
 
     ns.write(WriteParams {
         upsert_rows: Some(vec![
@@ -303,17 +286,14 @@ async fn test_contains_and_contains_any() {
     ids.sort();
     assert_eq!(ids, vec![1, 4]);
 
-    ns.delete_all().await.unwrap();
+    ns.close().await.unwrap();
 }
 
 #[tokio::test]
 #[serial]
 async fn test_delete_by_filter() {
     let client = setup();
-    let prefix = test_prefix();
-    let ns = client.namespace(format!("{}delete_by_filter", prefix));
-
-    let _ = ns.delete_all().await;
+    let ns = temp_namespace(&client);
 
     ns.write(WriteParams {
         upsert_rows: Some(vec![
@@ -359,17 +339,16 @@ async fn test_delete_by_filter() {
     assert_eq!(results2.rows.len(), 1);
     assert_eq!(results2.rows[0].get("id").unwrap(), 1);
 
-    ns.delete_all().await.unwrap();
+    ns.close().await.unwrap();
 }
 
 #[tokio::test]
 #[serial]
 async fn test_namespaces_listing() {
     let client = setup();
-    let prefix = test_prefix();
 
     // Create a test namespace to ensure there's at least one to list
-    let test_ns = client.namespace(format!("{}ns_listing_test", prefix));
+    let test_ns = temp_namespace(&client);
     test_ns
         .write(WriteParams {
             upsert_rows: Some(vec![row(1, vec![0.1, 0.1], vec![])]),
@@ -408,18 +387,14 @@ async fn test_namespaces_listing() {
     }
 
     // Cleanup
-    test_ns.delete_all().await.unwrap();
+    test_ns.close().await.unwrap();
 }
 
 #[tokio::test]
 #[serial]
 async fn test_hint_cache_warm() {
     let client = setup();
-    let prefix = test_prefix();
-
-    // Create a test namespace
-    let ns = client.namespace(format!("{}hint_cache_warm", prefix));
-    let _ = ns.delete_all().await;
+    let ns = temp_namespace(&client);
 
     ns.write(WriteParams {
         upsert_rows: Some(vec![row(1, vec![0.1, 0.1], vec![])]),
@@ -432,17 +407,14 @@ async fn test_hint_cache_warm() {
     let result = ns.hint_cache_warm().await.unwrap();
     assert!(result.status == "ACCEPTED" || result.status == "OK");
 
-    ns.delete_all().await.unwrap();
+    ns.close().await.unwrap();
 }
 
 #[tokio::test]
 #[serial]
 async fn test_schema_and_metadata() {
     let client = setup();
-    let prefix = test_prefix();
-    let ns = client.namespace(format!("{}schema", prefix));
-
-    let _ = ns.delete_all().await;
+    let ns = temp_namespace(&client);
 
     let mut schema = HashMap::new();
     schema.insert(
@@ -459,7 +431,7 @@ async fn test_schema_and_metadata() {
     schema.insert(
         "vector".to_string(),
         serde_json::json!({
-            "type": "[2]f16",
+            "type": AttributeType::vector_f16(2),
             "ann": true
         }),
     );
@@ -494,17 +466,14 @@ async fn test_schema_and_metadata() {
         metadata
     );
 
-    ns.delete_all().await.unwrap();
+    ns.close().await.unwrap();
 }
 
 #[tokio::test]
 #[serial]
 async fn test_bm25_with_sum_query() {
     let client = setup();
-    let prefix = test_prefix();
-    let ns = client.namespace(format!("{}bm25_sum", prefix));
-
-    let _ = ns.delete_all().await;
+    let ns = temp_namespace(&client);
 
     let mut schema = HashMap::new();
     schema.insert(
@@ -562,17 +531,14 @@ async fn test_bm25_with_sum_query() {
     assert_eq!(results.rows[1].get("id").unwrap(), 1);
     assert_eq!(results.rows[2].get("id").unwrap(), 3);
 
-    ns.delete_all().await.unwrap();
+    ns.close().await.unwrap();
 }
 
 #[tokio::test]
 #[serial]
 async fn test_not_filter() {
     let client = setup();
-    let prefix = test_prefix();
-    let ns = client.namespace(format!("{}not_filter", prefix));
-
-    let _ = ns.delete_all().await;
+    let ns = temp_namespace(&client);
 
     ns.write(WriteParams {
         upsert_rows: Some(vec![
@@ -620,17 +586,14 @@ async fn test_not_filter() {
     assert_eq!(results2.rows.len(), 1);
     assert_eq!(results2.rows[0].get("id").unwrap(), 2);
 
-    ns.delete_all().await.unwrap();
+    ns.close().await.unwrap();
 }
 
 #[tokio::test]
 #[serial]
 async fn test_patch() {
     let client = setup();
-    let prefix = test_prefix();
-    let ns = client.namespace(format!("{}patch", prefix));
-
-    let _ = ns.delete_all().await;
+    let ns = temp_namespace(&client);
 
     ns.write(WriteParams {
         upsert_rows: Some(vec![
@@ -697,17 +660,14 @@ async fn test_patch() {
     assert_eq!(results.rows[1].get("a").unwrap(), 2);
     assert_eq!(results.rows[1].get("b").unwrap(), 2);
 
-    ns.delete_all().await.unwrap();
+    ns.close().await.unwrap();
 }
 
 #[tokio::test]
 #[serial]
 async fn test_product_operator() {
     let client = setup();
-    let prefix = test_prefix();
-    let ns = client.namespace(format!("{}product_operator", prefix));
-
-    let _ = ns.delete_all().await;
+    let ns = temp_namespace(&client);
 
     let mut schema = HashMap::new();
     schema.insert(
@@ -789,15 +749,14 @@ async fn test_product_operator() {
         .unwrap();
     assert!(!results3.rows.is_empty());
 
-    ns.delete_all().await.unwrap();
+    ns.close().await.unwrap();
 }
 
 #[tokio::test]
 #[serial]
 async fn test_empty_namespace_query() {
     let client = setup();
-    let prefix = test_prefix();
-    let ns = client.namespace(format!("{}empty_ns", prefix));
+    let ns = temp_namespace(&client);
 
     // Write then delete
     ns.write(WriteParams {
@@ -827,18 +786,14 @@ async fn test_empty_namespace_query() {
 
     assert_eq!(results.rows.len(), 0);
 
-    ns.delete_all().await.unwrap();
+    ns.close().await.unwrap();
 }
 
 #[tokio::test]
 #[serial]
 async fn test_exists() {
     let client = setup();
-    let prefix = test_prefix();
-    let ns = client.namespace(format!("{}exists", prefix));
-
-    // Clean up if exists
-    let _ = ns.delete_all().await;
+    let ns = temp_namespace(&client);
 
     // Verify namespace doesn't exist yet
     assert!(!ns.exists().await.unwrap());
@@ -860,18 +815,17 @@ async fn test_exists() {
 
     // Verify namespace doesn't exist anymore
     assert!(!ns.exists().await.unwrap());
+    assert!(matches!(ns.delete_all_if_exists().await.unwrap(), rs_puff::DeleteAllStatus::AlreadyAbsent));
+
+    ns.close().await.unwrap();
 }
 
 #[tokio::test]
 #[serial]
 async fn test_copy_from_namespace() {
     let client = setup();
-    let prefix = test_prefix();
-    let ns1 = client.namespace(format!("{}copy_from_1", prefix));
-    let ns2 = client.namespace(format!("{}copy_from_2", prefix));
-
-    let _ = ns1.delete_all().await;
-    let _ = ns2.delete_all().await;
+    let ns1 = temp_namespace(&client);
+    let ns2 = temp_namespace(&client);
 
     // Create source namespace with data
     ns1.write(WriteParams {
@@ -908,18 +862,15 @@ async fn test_copy_from_namespace() {
     assert_eq!(results.rows.len(), 3);
 
     // Cleanup
-    ns1.delete_all().await.unwrap();
-    ns2.delete_all().await.unwrap();
+    ns1.close().await.unwrap();
+    ns2.close().await.unwrap();
 }
 
 #[tokio::test]
 #[serial]
 async fn test_bm25_with_default_schema() {
     let client = setup();
-    let prefix = test_prefix();
-    let ns = client.namespace(format!("{}bm25_default_schema", prefix));
-
-    let _ = ns.delete_all().await;
+    let ns = temp_namespace(&client);
 
     let mut schema = HashMap::new();
     schema.insert(
@@ -958,17 +909,14 @@ async fn test_bm25_with_default_schema() {
     assert_eq!(results.rows.len(), 1);
     assert_eq!(results.rows[0].get("id").unwrap(), 2);
 
-    ns.delete_all().await.unwrap();
+    ns.close().await.unwrap();
 }
 
 #[tokio::test]
 #[serial]
 async fn test_contains_all_tokens() {
     let client = setup();
-    let prefix = test_prefix();
-    let ns = client.namespace(format!("{}contains_all_tokens", prefix));
-
-    let _ = ns.delete_all().await;
+    let ns = temp_namespace(&client);
 
     let mut schema = HashMap::new();
     schema.insert(
@@ -1018,7 +966,520 @@ async fn test_contains_all_tokens() {
         .unwrap();
     assert_eq!(missing.rows.len(), 0);
 
-    ns.delete_all().await.unwrap();
+    ns.close().await.unwrap();
+}
+
+#[tokio::test]
+#[serial]
+async fn test_query_raw() {
+    let client = setup();
+    let ns = temp_namespace(&client);
+
+    ns.write(WriteParams {
+        upsert_rows: Some(vec![row(1, vec![0.1, 0.1], vec![("name", serde_json::json!("one"))])]),
+        distance_metric: Some(DistanceMetric::CosineDistance),
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+
+    let params = QueryParams {
+        rank_by: Some(RankBy::asc("id")),
+        include_attributes: Some(IncludeAttributes::List(vec!["name".to_string()])),
+        top_k: Some(10),
+        ..Default::default()
+    };
+
+    let value = ns.query_raw_value(params.clone()).await.unwrap();
+    assert_eq!(value["rows"][0]["name"], "one");
+
+    let bytes = ns.query_raw_bytes(params).await.unwrap();
+    let reparsed: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(reparsed["rows"], value["rows"]);
+
+    ns.close().await.unwrap();
+}
+
+#[tokio::test]
+#[serial]
+async fn test_raw_request() {
+    let client = setup();
+    let ns = temp_namespace(&client);
+
+    let body = serde_json::json!({
+        "upsert_rows": [{"id": 1, "vector": [0.1, 0.1]}],
+        "distance_metric": "cosine_distance"
+    });
+    let write = client
+        .raw_request(Method::POST, &format!("/v2/namespaces/{}", ns.name()), Some(&body))
+        .await
+        .unwrap();
+    assert_eq!(write["rows_affected"], 1);
+
+    let metadata = client
+        .raw_request(Method::GET, &format!("/v1/namespaces/{}/metadata", ns.name()), None)
+        .await
+        .unwrap();
+    assert!(metadata.get("created_at").is_some());
+
+    ns.close().await.unwrap();
+}
+
+#[tokio::test]
+#[serial]
+async fn test_owned_namespace_moves_into_task() {
+    let client = setup();
+    let ns = temp_namespace(&client);
+    let owned = client.namespace(ns.name());
+
+    let rows = tokio::spawn(async move {
+        owned
+            .write(WriteParams {
+                upsert_rows: Some(vec![row(1, vec![0.1, 0.1], vec![])]),
+                distance_metric: Some(DistanceMetric::CosineDistance),
+                ..Default::default()
+            })
+            .await?;
+        owned
+            .query(QueryParams {
+                rank_by: Some(RankBy::asc("id")),
+                top_k: Some(10),
+                ..Default::default()
+            })
+            .await
+    })
+    .await
+    .unwrap()
+    .unwrap()
+    .rows;
+    assert_eq!(rows.len(), 1);
+
+    ns.close().await.unwrap();
+}
+
+#[tokio::test]
+#[serial]
+async fn test_get_many_reports_missing_ids() {
+    let client = setup();
+    let ns = temp_namespace(&client);
+
+    ns.write(WriteParams {
+        upsert_rows: Some(vec![
+            row(1, vec![0.1, 0.1], vec![("name", serde_json::json!("one"))]),
+            row(3, vec![0.3, 0.3], vec![("name", serde_json::json!("three"))]),
+        ]),
+        distance_metric: Some(DistanceMetric::CosineDistance),
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+
+    let result = ns.get_many([3u64, 2, 1]).await.unwrap();
+    let names: Vec<_> = result.found.iter().map(|r| r["name"].clone()).collect();
+    assert_eq!(names, [serde_json::json!("three"), serde_json::json!("one")]);
+    assert_eq!(result.missing, [rs_puff::Id::from(2u64)]);
+
+    ns.close().await.unwrap();
+}
+
+#[tokio::test]
+#[serial]
+async fn test_query_with_total_counts_past_top_k() {
+    let client = setup();
+    let ns = temp_namespace(&client);
+
+    ns.write(WriteParams {
+        upsert_rows: Some((1..=5).map(|i| row(i, vec![0.1 * i as f64, 0.1], vec![])).collect()),
+        distance_metric: Some(DistanceMetric::CosineDistance),
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+
+    let resp = ns
+        .query_with_total(QueryParams {
+            rank_by: Some(RankBy::asc("id")),
+            top_k: Some(2),
+            filters: Some(Filter::gt("id", 1)),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(resp.rows.len(), 2);
+    assert_eq!(resp.total_matches, Some(4));
+
+    ns.close().await.unwrap();
+}
+
+#[tokio::test]
+#[serial]
+async fn test_patch_merge_deep_merges_json_strings() {
+    let client = setup();
+    let ns = temp_namespace(&client);
+
+    ns.write(WriteParams {
+        upsert_rows: Some(vec![row(1, vec![0.1, 0.1], vec![("settings", serde_json::json!(r#"{"theme":"light","tabs":{"home":true}}"#))])]),
+        distance_metric: Some(DistanceMetric::CosineDistance),
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+
+    let merged = ns
+        .patch_merge(1u64, serde_json::json!({"settings": {"theme": "dark", "tabs": {"search": true}}}))
+        .await
+        .unwrap()
+        .unwrap();
+    let settings: serde_json::Value = serde_json::from_str(merged["settings"].as_str().unwrap()).unwrap();
+    assert_eq!(settings, serde_json::json!({"theme": "dark", "tabs": {"home": true, "search": true}}));
+    assert!(ns.patch_merge(2u64, serde_json::json!({"settings": {}})).await.unwrap().is_none());
+
+    ns.close().await.unwrap();
+}
+
+#[tokio::test]
+#[serial]
+async fn test_rename_namespace() {
+    use rs_puff::RenameParams;
+
+    let client = setup();
+    let from = temp_namespace(&client);
+    let to = temp_namespace(&client);
+
+    from.write(WriteParams {
+        upsert_rows: Some((1..=3).map(|i| row(i, vec![0.1 * i as f64, 0.1], vec![])).collect()),
+        distance_metric: Some(DistanceMetric::CosineDistance),
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+
+    let dry_run = client
+        .rename_namespace(from.name(), to.name(), &RenameParams { dry_run: true })
+        .await
+        .unwrap();
+    assert_eq!(dry_run.rows, 3);
+    assert!(!to.exists().await.unwrap());
+
+    let report = client.rename_namespace(from.name(), to.name(), &RenameParams::default()).await.unwrap();
+    assert_eq!(report.rows, 3);
+    assert!(!from.exists().await.unwrap());
+    assert_eq!(to.get_many([1u64, 2, 3]).await.unwrap().found.len(), 3);
+
+    from.close().await.unwrap();
+    to.close().await.unwrap();
+}
+
+#[tokio::test]
+#[serial]
+async fn test_ensure_creates_then_reconciles() {
+    use rs_puff::{AttributeSchema, NamespaceSpec, Schema};
+
+    let client = setup();
+    let ns = temp_namespace(&client);
+    let mut spec = NamespaceSpec {
+        name: ns.name().to_string(),
+        schema: Schema::new().attribute("title", AttributeSchema::new(AttributeType::String)),
+        distance_metric: Some(DistanceMetric::CosineDistance),
+        encryption: None,
+    };
+
+    let report = client.ensure(&spec).await.unwrap();
+    assert!(report.created);
+    assert!(client.ensure(&spec).await.unwrap().is_unchanged());
+
+    spec.schema = spec.schema.attribute("tags", AttributeSchema::new(AttributeType::Array(Box::new(AttributeType::String))));
+    let report = client.ensure(&spec).await.unwrap();
+    assert_eq!(report.added, ["tags"]);
+    assert!(report.changed.is_empty());
+
+    ns.close().await.unwrap();
+}
+
+#[tokio::test]
+#[serial]
+async fn test_top_k_per_group() {
+    let client = setup();
+    let ns = temp_namespace(&client);
+    let doc = |d: &str| vec![("doc", serde_json::json!(d))];
+    ns.write(WriteParams {
+        upsert_rows: Some(vec![
+            row(1, vec![1.0, 0.0], doc("a")),
+            row(2, vec![0.9, 0.1], doc("a")),
+            row(3, vec![0.8, 0.2], doc("a")),
+            row(4, vec![0.7, 0.3], doc("b")),
+        ]),
+        distance_metric: Some(DistanceMetric::CosineDistance),
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+
+    let params = QueryParams { rank_by: Some(RankBy::vector_knn("vector", vec![1.0, 0.0])), ..Default::default() };
+    let groups = ns.top_k_per_group("doc", 2, params).await.unwrap();
+    assert_eq!(groups.len(), 2);
+    assert_eq!(groups[0].key, "a");
+    assert_eq!(groups[0].rows.iter().map(|r| r["id"].as_u64().unwrap()).collect::<Vec<_>>(), [1, 2]);
+    assert_eq!(groups[1].rows.len(), 1);
+
+    ns.close().await.unwrap();
+}
+
+#[cfg(feature = "stream-writes")]
+#[tokio::test]
+#[serial]
+async fn test_write_streaming() {
+    let client = setup();
+    let ns = temp_namespace(&client);
+    let rows: Vec<_> = (0..500).map(|i| row(i, vec![i as f64, 1.0], vec![("text", serde_json::json!("x".repeat(500)))])).collect();
+    let resp = ns
+        .write_streaming(WriteParams {
+            upsert_rows: Some(rows),
+            distance_metric: Some(DistanceMetric::CosineDistance),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(resp.rows_affected, 500);
+
+    ns.close().await.unwrap();
+}
+
+#[tokio::test]
+#[serial]
+async fn test_self_test_passes_on_healthy_namespace() {
+    let client = setup();
+    let ns = temp_namespace(&client);
+    ns.write(WriteParams {
+        upsert_rows: Some(vec![row(1, vec![0.6, 0.8], vec![])]),
+        distance_metric: Some(DistanceMetric::CosineDistance),
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+
+    let report = ns.self_test().await.unwrap();
+    assert!(report.passed(), "{:?}", report.failure());
+    let steps: Vec<_> = report.steps.iter().map(|s| s.name).collect();
+    assert_eq!(steps, ["write", "vector_query", "filter_query", "delete", "verify_deleted"]);
+    assert!(ns.get(report.sentinel_id).await.unwrap().is_none());
+    assert!(ns.get(1).await.unwrap().is_some());
+
+    ns.close().await.unwrap();
+}
+
+#[tokio::test]
+#[serial]
+async fn test_usage_recorder_snapshots_namespace() {
+    use rs_puff::{UsageMeter, UsageRecorder, UsageSnapshot};
+    use std::sync::{Arc, Mutex};
+
+    let client = setup().with_usage_meter(UsageMeter::new());
+    let ns = temp_namespace(&client);
+    ns.write(WriteParams {
+        upsert_rows: Some(vec![row(1, vec![0.1, 0.2], vec![]), row(2, vec![0.2, 0.1], vec![])]),
+        distance_metric: Some(DistanceMetric::CosineDistance),
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+    ns.query(QueryParams { rank_by: Some(RankBy::asc("id")), top_k: Some(10), ..Default::default() })
+        .await
+        .unwrap();
+
+    let recorded = Arc::new(Mutex::new(Vec::new()));
+    let sink = recorded.clone();
+    let mut recorder = UsageRecorder::new(&client, [ns.name()], move |s: &UsageSnapshot| {
+        sink.lock().unwrap().push(s.clone());
+    });
+    let snapshots = recorder.record_once().await.unwrap();
+    assert_eq!(snapshots.len(), 1);
+    assert_eq!(snapshots[0].queries.unwrap().queries, 1);
+    assert!(snapshots[0].growth.is_none());
+    assert_eq!(*recorded.lock().unwrap(), snapshots);
+
+    // Billing is reported per snapshot, not cumulatively.
+    let snapshots = recorder.record_once().await.unwrap();
+    assert_eq!(snapshots[0].queries.unwrap().queries, 0);
+
+    ns.close().await.unwrap();
+}
+
+#[tokio::test]
+#[serial]
+async fn test_ingest_queue_spills_and_drains() {
+    use rs_puff::{IngestQueue, IngestQueueParams};
+
+    let client = setup();
+    let ns = temp_namespace(&client);
+    let spill_path = std::env::temp_dir().join(format!("{}.ndjson", ns.name()));
+
+    let queue = IngestQueue::new(&client, ns.name(), IngestQueueParams {
+        batch_size: 2,
+        max_buffered_rows: 1,
+        spill_path: Some(spill_path.clone()),
+        distance_metric: Some(DistanceMetric::CosineDistance),
+        ..Default::default()
+    })
+    .unwrap();
+    for i in 1..=5 {
+        queue.push(row(i, vec![0.1 * i as f64, 0.1], vec![])).await.unwrap();
+    }
+    let report = queue.close().await.unwrap();
+    assert_eq!(report.rows_upserted, 5);
+    assert!(!spill_path.exists());
+
+    let result = ns
+        .query(QueryParams {
+            rank_by: Some(RankBy::asc("id")),
+            top_k: Some(10),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(result.rows.len(), 5);
+
+    ns.close().await.unwrap();
+}
+
+#[tokio::test]
+#[serial]
+async fn test_ingest_queue_journal_replays_rows() {
+    use rs_puff::{IngestQueue, IngestQueueParams};
+
+    let client = setup();
+    let ns = temp_namespace(&client);
+    let journal_path = std::env::temp_dir().join(format!("{}.journal", ns.name()));
+    // Rows journaled by a previous process that never got written.
+    std::fs::write(&journal_path, "{\"id\":1,\"vector\":[0.1,0.1]}\n{\"id\":2,\"vector\":[0.2,0.1]}\n").unwrap();
+
+    let queue = IngestQueue::new(&client, ns.name(), IngestQueueParams {
+        journal_path: Some(journal_path.clone()),
+        distance_metric: Some(DistanceMetric::CosineDistance),
+        ..Default::default()
+    })
+    .unwrap();
+    assert_eq!(queue.len(), 2);
+    queue.push(row(3, vec![0.3, 0.1], vec![])).await.unwrap();
+    let report = queue.close().await.unwrap();
+    assert_eq!(report.rows_upserted, 3);
+    assert!(!journal_path.exists());
+
+    let result = ns
+        .query(QueryParams {
+            rank_by: Some(RankBy::asc("id")),
+            top_k: Some(10),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(result.rows.len(), 3);
+
+    ns.close().await.unwrap();
+}
+
+#[cfg(feature = "object_store")]
+#[tokio::test]
+#[serial]
+async fn test_object_store_export_import() {
+    use object_store::{memory::InMemory, path::Path};
+    use rs_puff::ExportParams;
+    use rs_puff::object_storage::ImportParams;
+
+    let client = setup();
+    let source = temp_namespace(&client);
+    let target = temp_namespace(&client);
+
+    source
+        .write(WriteParams {
+            upsert_rows: Some((1..=5).map(|i| row(i, vec![0.1 * i as f64, 0.1], vec![])).collect()),
+            distance_metric: Some(DistanceMetric::CosineDistance),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+    let store = InMemory::new();
+    let path = Path::from("backups/source.ndjson");
+    let exported = source
+        .export_to_object_store(&store, &path, ExportParams { page_size: 2, ..Default::default() })
+        .await
+        .unwrap();
+    assert_eq!(exported.rows, 5);
+
+    let imported = target
+        .import_from_object_store(&store, &path, ImportParams {
+            batch_size: 2,
+            distance_metric: Some(DistanceMetric::CosineDistance),
+        })
+        .await
+        .unwrap();
+    assert_eq!(imported.rows, 5);
+    assert_eq!(imported.bytes, exported.bytes);
+
+    source.close().await.unwrap();
+    target.close().await.unwrap();
+}
+
+#[cfg(feature = "object_store")]
+#[tokio::test]
+#[serial]
+async fn test_object_store_parted_export_resumes() {
+    use object_store::{memory::InMemory, path::Path};
+    use rs_puff::ExportParams;
+    use rs_puff::object_storage::{ImportParams, PartedExportParams};
+    use rs_puff::sync::MemoryCheckpointStore;
+
+    let client = setup();
+    let source = temp_namespace(&client);
+    let target = temp_namespace(&client);
+
+    source
+        .write(WriteParams {
+            upsert_rows: Some((1..=5).map(|i| row(i, vec![0.1 * i as f64, 0.1], vec![])).collect()),
+            distance_metric: Some(DistanceMetric::CosineDistance),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+    let store = InMemory::new();
+    let prefix = Path::from("backups/parts");
+    let mut checkpoints = MemoryCheckpointStore::default();
+    let params = PartedExportParams {
+        export: ExportParams { page_size: 2, ..Default::default() },
+        rows_per_part: 2,
+        deadline: Some(std::time::Duration::ZERO),
+    };
+
+    // A zero deadline stops after the first page; the second run resumes.
+    let first = source
+        .export_parts_to_object_store(&store, &prefix, params.clone(), &mut checkpoints)
+        .await
+        .unwrap();
+    assert!(!first.complete);
+    assert_eq!(first.checkpoint.rows, 2);
+
+    let params = PartedExportParams { deadline: None, ..params };
+    let done = source
+        .export_parts_to_object_store(&store, &prefix, params, &mut checkpoints)
+        .await
+        .unwrap();
+    assert!(done.complete);
+    assert_eq!(done.checkpoint.rows, 5);
+    assert_eq!(done.checkpoint.parts, 3);
+
+    let imported = target
+        .import_parts_from_object_store(&store, &prefix, ImportParams {
+            batch_size: 2,
+            distance_metric: Some(DistanceMetric::CosineDistance),
+        })
+        .await
+        .unwrap();
+    assert_eq!(imported.rows, 5);
+
+    source.close().await.unwrap();
+    target.close().await.unwrap();
 }
 
 /// Cleanup test that deletes all ephemeral test namespaces with the `rust_sdk_` prefix.